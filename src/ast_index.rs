@@ -0,0 +1,457 @@
+use crate::grammar::expressions::*;
+use crate::grammar::statements::*;
+
+/// Identifies a node of an [`AstIndex`]. Ids are handed out in pre-order, so
+/// the same source always gets the same ids and a parent's id is smaller than
+/// the ones of its children.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// Node of the AST. Methods and constructors of classes and functions of
+/// tables aren't wrapped in a statement or expression, so they get a variant
+/// of their own.
+#[derive(Debug, Clone, Copy)]
+pub enum Node<'a> {
+    Statement(&'a Statement),
+    Expression(&'a Expression),
+    Function(&'a FunctionDefinition),
+}
+
+impl<'a> Node<'a> {
+    /// The function this node defines, if any.
+    pub fn as_function(self) -> Option<&'a FunctionDefinition> {
+        match self {
+            Node::Statement(Statement::FunctionDefinition(function))
+            | Node::Expression(Expression::Function(function)) => Some(function),
+            Node::Function(function) => Some(function),
+            _ => None,
+        }
+    }
+
+    /// The class this node defines, if any.
+    pub fn as_class(self) -> Option<&'a ClassDefinition> {
+        match self {
+            Node::Statement(Statement::Class(class))
+            | Node::Expression(Expression::Class(class)) => Some(class),
+            _ => None,
+        }
+    }
+
+    fn span(self) -> (usize, usize) {
+        match self {
+            Node::Statement(statement) => (statement.get_from().index, statement.get_to().index),
+            Node::Expression(expression) => {
+                (expression.get_from().index, expression.get_to().index)
+            }
+            Node::Function(function) => (function.from.index, function.to.index),
+        }
+    }
+
+    /// Child nodes in source order.
+    pub fn children(self) -> Vec<Node<'a>> {
+        let mut children = Vec::new();
+
+        match self {
+            Node::Statement(statement) => statement_children(statement, &mut children),
+            Node::Expression(expression) => expression_children(expression, &mut children),
+            Node::Function(function) => function_children(function, &mut children),
+        }
+
+        children
+    }
+}
+
+fn statement_children<'a>(statement: &'a Statement, children: &mut Vec<Node<'a>>) {
+    let statements = |statements: &'a Statements| statements.statements.iter().map(Node::Statement);
+
+    match statement {
+        Statement::Block(block) => children.extend(statements(&block.statements)),
+        Statement::If(statement) => {
+            children.push(Node::Expression(&statement.condition));
+            children.push(Node::Statement(&statement.if_true));
+            children.extend(statement.if_false.iter().map(Node::Statement));
+        }
+        Statement::While(statement) => {
+            children.push(Node::Expression(&statement.condition));
+            children.push(Node::Statement(&statement.statement));
+        }
+        Statement::DoWhile(statement) => {
+            children.push(Node::Statement(&statement.statement));
+            children.push(Node::Expression(&statement.condition));
+        }
+        Statement::Switch(statement) => {
+            children.push(Node::Expression(&statement.expression));
+            for case in &statement.cases {
+                children.push(Node::Expression(&case.expression));
+                children.extend(statements(&case.statements));
+            }
+            if let Some(default) = &statement.default {
+                children.extend(statements(default));
+            }
+        }
+        Statement::For(statement) => {
+            children.extend(statement.initialization.iter().map(Node::Statement));
+            children.extend(statement.condition.iter().map(Node::Expression));
+            children.extend(statement.increment.iter().map(Node::Expression));
+            children.push(Node::Statement(&statement.statement));
+        }
+        Statement::ForEach(statement) => {
+            children.extend(statement.key.iter().map(Node::Expression));
+            children.push(Node::Expression(&statement.value));
+            children.push(Node::Expression(&statement.iterable));
+            children.push(Node::Statement(&statement.statement));
+        }
+        Statement::TryCatch(statement) => {
+            children.push(Node::Statement(&statement.try_statement));
+            children.push(Node::Expression(&statement.catch_variable));
+            children.push(Node::Statement(&statement.catch_statement));
+        }
+        Statement::Return(statement) => {
+            children.extend(statement.expression.iter().map(Node::Expression))
+        }
+        Statement::Yield(statement) => {
+            children.extend(statement.expression.iter().map(Node::Expression))
+        }
+        Statement::Throw(statement) => children.push(Node::Expression(&statement.expression)),
+        Statement::Expression(statement) => children.push(Node::Expression(&statement.expression)),
+        Statement::Const(statement) => {
+            children.push(Node::Expression(&statement.name));
+            children.push(Node::Expression(&statement.expression));
+        }
+        Statement::Local(statement) => children.extend(
+            statement
+                .initializations
+                .iter()
+                .filter_map(|initialization| initialization.expression.as_ref())
+                .map(Node::Expression),
+        ),
+        Statement::Destructuring(statement) => {
+            children.extend(
+                statement
+                    .bindings
+                    .iter()
+                    .filter_map(|binding| binding.expression.as_ref())
+                    .map(Node::Expression),
+            );
+            children.push(Node::Expression(&statement.expression));
+        }
+        Statement::Import(statement) => {
+            children.extend(statement.alias.iter().map(Node::Expression))
+        }
+        Statement::FunctionDefinition(function) => function_children(function, children),
+        Statement::Class(class) => class_children(class, children),
+        Statement::Enum(statement) => {
+            children.push(Node::Expression(&statement.name));
+            for enumeration in &statement.enumerations {
+                children.push(Node::Expression(&enumeration.name));
+                children.extend(enumeration.value.iter().map(Node::Expression));
+            }
+        }
+        Statement::Break(_) | Statement::Continue(_) => {}
+    }
+}
+
+fn expression_children<'a>(expression: &'a Expression, children: &mut Vec<Node<'a>>) {
+    match expression {
+        Expression::UnaryOperator(expression) => {
+            children.push(Node::Expression(&expression.expression))
+        }
+        Expression::PostfixUnaryOperator(expression) => {
+            children.push(Node::Expression(&expression.expression))
+        }
+        Expression::BinaryOperator(expression) => {
+            children.push(Node::Expression(&expression.left));
+            children.push(Node::Expression(&expression.right));
+        }
+        Expression::Table(expression) => table_children(expression, children),
+        Expression::Clone(expression) => children.push(Node::Expression(&expression.expression)),
+        Expression::Resume(expression) => children.push(Node::Expression(&expression.expression)),
+        Expression::Delete(expression) => children.push(Node::Expression(&expression.expression)),
+        Expression::Array(expression) => {
+            children.extend(expression.elements.iter().map(Node::Expression))
+        }
+        Expression::ArrayAccess(expression) => {
+            children.push(Node::Expression(&expression.array));
+            children.push(Node::Expression(&expression.index));
+        }
+        Expression::Grouping(expression) => {
+            children.extend(expression.expression.iter().map(Node::Expression))
+        }
+        Expression::FunctionCall(expression) => {
+            children.push(Node::Expression(&expression.function));
+            children.extend(expression.arguments.iter().map(Node::Expression));
+        }
+        Expression::RawCall(expression) => {
+            children.extend(expression.arguments.iter().map(Node::Expression))
+        }
+        Expression::MemberAccess(expression) => {
+            children.push(Node::Expression(&expression.expression))
+        }
+        Expression::TernaryOperator(expression) => {
+            children.push(Node::Expression(&expression.condition));
+            children.push(Node::Expression(&expression.if_true));
+            children.push(Node::Expression(&expression.if_false));
+        }
+        Expression::Class(class) => class_children(class, children),
+        Expression::Function(function) => function_children(function, children),
+        Expression::Spread(_)
+        | Expression::StringLiteral(_)
+        | Expression::MutliLineStringLiteral(_)
+        | Expression::FloatLiteral(_)
+        | Expression::IntegerLiteral(_)
+        | Expression::CharacterLiteral(_)
+        | Expression::Identifier(_)
+        | Expression::NullLiteral(_)
+        | Expression::BooleanLiteral(_)
+        | Expression::LineLiteral(_)
+        | Expression::FileLiteral(_)
+        | Expression::ScopeResolution(_) => {}
+    }
+}
+
+fn table_children<'a>(table: &'a TableExpression, children: &mut Vec<Node<'a>>) {
+    for entry in &table.entries {
+        match entry {
+            TableEntry::Field(field) => {
+                children.push(Node::Expression(&field.name));
+                children.push(Node::Expression(&field.expression));
+            }
+            TableEntry::Function(function) => children.push(Node::Function(&function.function)),
+            TableEntry::FieldWithExpressionKey(field) => {
+                children.push(Node::Expression(&field.key));
+                children.push(Node::Expression(&field.expression));
+            }
+            TableEntry::FieldWithStringKey(field) => {
+                children.push(Node::Expression(&field.key));
+                children.push(Node::Expression(&field.expression));
+            }
+        }
+    }
+}
+
+fn function_children<'a>(function: &'a FunctionDefinition, children: &mut Vec<Node<'a>>) {
+    children.extend(function.name.iter().map(Node::Expression));
+    children.extend(function.parameters.iter().map(Node::Expression));
+    children.push(Node::Statement(&function.statement));
+}
+
+fn class_children<'a>(class: &'a ClassDefinition, children: &mut Vec<Node<'a>>) {
+    children.extend(class.name.iter().map(Node::Expression));
+    children.extend(class.extends.iter().map(Node::Expression));
+
+    // Attributes have no node of their own, their slots are children of the class
+    if let Some(attributes) = &class.attributes {
+        table_children(attributes, children);
+    }
+
+    for member in &class.members {
+        match member {
+            ClassMemberDefinition::Field(field) => {
+                if let Some(attributes) = &field.attributes {
+                    table_children(attributes, children);
+                }
+                children.push(Node::Expression(&field.name));
+                children.push(Node::Expression(&field.expression));
+            }
+            ClassMemberDefinition::Method(function)
+            | ClassMemberDefinition::Constructor(function) => {
+                if let Some(attributes) = &function.attributes {
+                    table_children(attributes, children);
+                }
+                children.push(Node::Function(function))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Entry<'a> {
+    node: Node<'a>,
+    parent: Option<NodeId>,
+    from: usize,
+    to: usize,
+}
+
+/// Side table giving every statement and expression of an AST a [`NodeId`],
+/// with links to their parents and an index of their spans to find the node
+/// at a position without walking the whole tree.
+#[derive(Debug)]
+pub struct AstIndex<'a> {
+    nodes: Vec<Entry<'a>>,
+    /// Ids ordered by the start of their node, parents before their children
+    by_start: Vec<NodeId>,
+}
+
+impl<'a> AstIndex<'a> {
+    pub fn new(statements: &'a Statements) -> Self {
+        let mut nodes = Vec::new();
+        let mut stack = statements
+            .statements
+            .iter()
+            .rev()
+            .map(|statement| (Node::Statement(statement), None))
+            .collect::<Vec<_>>();
+
+        while let Some((node, parent)) = stack.pop() {
+            let id = NodeId(nodes.len());
+            let (from, to) = node.span();
+            nodes.push(Entry {
+                node,
+                parent,
+                from,
+                to,
+            });
+
+            stack.extend(
+                node.children()
+                    .into_iter()
+                    .rev()
+                    .map(|child| (child, Some(id))),
+            );
+        }
+
+        let mut by_start = (0..nodes.len()).map(NodeId).collect::<Vec<_>>();
+        by_start.sort_by_key(|id| nodes[id.0].from);
+
+        Self { nodes, by_start }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn node(&self, id: NodeId) -> Node<'a> {
+        self.nodes[id.0].node
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].parent
+    }
+
+    /// The node and its ancestors, innermost first.
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(Some(id), |id| self.parent(*id))
+    }
+
+    /// Innermost node whose span contains the byte offset.
+    pub fn node_at(&self, offset: usize) -> Option<NodeId> {
+        let started = self
+            .by_start
+            .partition_point(|id| self.nodes[id.0].from <= offset);
+        let last = *self.by_start.get(started.checked_sub(1)?)?;
+
+        // Nodes starting between the innermost one and the offset are nested
+        // in it, so it's found among the ancestors of the last one to start
+        self.ancestors(last).find(|id| {
+            let entry = &self.nodes[id.0];
+            entry.from <= offset && offset < entry.to
+        })
+    }
+
+    /// Closest function around the node, the node itself excluded.
+    pub fn enclosing_function(&self, id: NodeId) -> Option<NodeId> {
+        self.ancestors(id)
+            .skip(1)
+            .find(|id| self.node(*id).as_function().is_some())
+    }
+
+    /// Closest class around the node, the node itself excluded.
+    pub fn enclosing_class(&self, id: NodeId) -> Option<NodeId> {
+        self.ancestors(id)
+            .skip(1)
+            .find(|id| self.node(*id).as_class().is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{squirrel_lexer::Token, squirrel_parser::Parser};
+
+    use super::*;
+
+    fn identifier(node: Node) -> Option<String> {
+        match node {
+            Node::Expression(Expression::Identifier(identifier)) => match &identifier.token {
+                Token::Identifier(name) => Some(name.clone()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_ids_follow_source_order_with_parent_links() {
+        let statements = Parser::new("local a = b + c\nfoo(a)").parse().unwrap();
+        let index = AstIndex::new(&statements);
+
+        // local, b + c, b, c, the statement and call foo(a), foo, a
+        assert_eq!(index.len(), 8);
+        assert!(matches!(
+            index.node(NodeId(0)),
+            Node::Statement(Statement::Local(_))
+        ));
+        assert_eq!(identifier(index.node(NodeId(2))), Some("b".to_string()));
+        assert_eq!(index.parent(NodeId(2)), Some(NodeId(1)));
+        assert_eq!(
+            index.ancestors(NodeId(3)).collect::<Vec<_>>(),
+            vec![NodeId(3), NodeId(1), NodeId(0)]
+        );
+        assert_eq!(index.parent(NodeId(0)), None);
+    }
+
+    #[test]
+    fn test_node_at_finds_innermost_node() {
+        let text = "local a = b + c\nfoo(a)";
+        let statements = Parser::new(text).parse().unwrap();
+        let index = AstIndex::new(&statements);
+
+        let at = |offset| index.node_at(offset).map(|id| index.node(id));
+
+        assert_eq!(
+            identifier(at(text.find("c\n").unwrap()).unwrap()),
+            Some("c".to_string())
+        );
+        assert!(matches!(
+            at(text.find('+').unwrap()),
+            Some(Node::Expression(Expression::BinaryOperator(_)))
+        ));
+        assert!(matches!(
+            at(text.find('(').unwrap()),
+            Some(Node::Expression(Expression::FunctionCall(_)))
+        ));
+        assert!(at(text.len()).is_none());
+    }
+
+    #[test]
+    fn test_enclosing_function_and_class() {
+        let text = "class A {\n  function f() {\n    return x\n  }\n}\nlocal g = function() { y }";
+        let statements = Parser::new(text).parse().unwrap();
+        let index = AstIndex::new(&statements);
+
+        let x = index.node_at(text.find('x').unwrap()).unwrap();
+        let method = index.enclosing_function(x).unwrap();
+        assert!(matches!(index.node(method), Node::Function(_)));
+        assert!(index
+            .node(index.enclosing_class(x).unwrap())
+            .as_class()
+            .is_some());
+        assert_eq!(index.enclosing_class(method), index.enclosing_class(x));
+
+        let y = index.node_at(text.find('y').unwrap()).unwrap();
+        assert!(matches!(
+            index.node(index.enclosing_function(y).unwrap()),
+            Node::Expression(Expression::Function(_))
+        ));
+        assert_eq!(index.enclosing_class(y), None);
+    }
+}
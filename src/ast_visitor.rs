@@ -5,8 +5,15 @@ use crate::grammar::statements::*;
 pub enum AstVisitorResult {
     Continue,
     Break,
+    /// Returned from an `enter_*` method, the children of the node aren't
+    /// visited while its `leave_*` method still is. Skipping from
+    /// `enter_statement` or `enter_expression` leaves out the whole node.
+    /// Anywhere else it's the same as `Continue`.
+    SkipChildren,
 }
 
+/// Callbacks for [`visit`], called when entering and leaving each statement and
+/// expression. Returning [`AstVisitorResult::Break`] stops the walk.
 pub trait AstVisitor {
     fn enter_block_statement(&mut self, _statement: &BlockStatement) -> AstVisitorResult {
         AstVisitorResult::Continue
@@ -152,6 +159,28 @@ pub trait AstVisitor {
         AstVisitorResult::Continue
     }
 
+    fn enter_destructuring_statement(
+        &mut self,
+        _statement: &DestructuringStatement,
+    ) -> AstVisitorResult {
+        AstVisitorResult::Continue
+    }
+
+    fn leave_destructuring_statement(
+        &mut self,
+        _statement: &DestructuringStatement,
+    ) -> AstVisitorResult {
+        AstVisitorResult::Continue
+    }
+
+    fn enter_import_statement(&mut self, _statement: &ImportStatement) -> AstVisitorResult {
+        AstVisitorResult::Continue
+    }
+
+    fn leave_import_statement(&mut self, _statement: &ImportStatement) -> AstVisitorResult {
+        AstVisitorResult::Continue
+    }
+
     fn enter_function_declaration(&mut self, _statement: &FunctionDefinition) -> AstVisitorResult {
         AstVisitorResult::Continue
     }
@@ -282,6 +311,20 @@ pub trait AstVisitor {
         AstVisitorResult::Continue
     }
 
+    fn enter_character_literal_expression(
+        &mut self,
+        _expression: &CharacterLiteralExpression,
+    ) -> AstVisitorResult {
+        AstVisitorResult::Continue
+    }
+
+    fn leave_character_literal_expression(
+        &mut self,
+        _expression: &CharacterLiteralExpression,
+    ) -> AstVisitorResult {
+        AstVisitorResult::Continue
+    }
+
     fn enter_table_expression(&mut self, _expression: &TableExpression) -> AstVisitorResult {
         AstVisitorResult::Continue
     }
@@ -372,6 +415,34 @@ pub trait AstVisitor {
         AstVisitorResult::Continue
     }
 
+    fn enter_line_literal_expression(
+        &mut self,
+        _expression: &LineLiteralExpression,
+    ) -> AstVisitorResult {
+        AstVisitorResult::Continue
+    }
+
+    fn leave_line_literal_expression(
+        &mut self,
+        _expression: &LineLiteralExpression,
+    ) -> AstVisitorResult {
+        AstVisitorResult::Continue
+    }
+
+    fn enter_file_literal_expression(
+        &mut self,
+        _expression: &FileLiteralExpression,
+    ) -> AstVisitorResult {
+        AstVisitorResult::Continue
+    }
+
+    fn leave_file_literal_expression(
+        &mut self,
+        _expression: &FileLiteralExpression,
+    ) -> AstVisitorResult {
+        AstVisitorResult::Continue
+    }
+
     fn enter_boolean_literal_expression(
         &mut self,
         _expression: &BooleanLiteralExpression,
@@ -400,6 +471,14 @@ pub trait AstVisitor {
         AstVisitorResult::Continue
     }
 
+    fn enter_raw_call_expression(&mut self, _expression: &RawCallExpression) -> AstVisitorResult {
+        AstVisitorResult::Continue
+    }
+
+    fn leave_raw_call_expression(&mut self, _expression: &RawCallExpression) -> AstVisitorResult {
+        AstVisitorResult::Continue
+    }
+
     fn enter_scope_resolution_expression(
         &mut self,
         _expression: &ScopeResolutionExpression,
@@ -449,6 +528,16 @@ pub trait AstVisitor {
     fn leave_function_expression(&mut self, _expression: &FunctionDefinition) -> AstVisitorResult {
         AstVisitorResult::Continue
     }
+
+    /// Called for every statement before the method specific to its kind.
+    fn enter_statement(&mut self, _statement: &Statement) -> AstVisitorResult {
+        AstVisitorResult::Continue
+    }
+
+    /// Called for every expression before the method specific to its kind.
+    fn enter_expression(&mut self, _expression: &Expression) -> AstVisitorResult {
+        AstVisitorResult::Continue
+    }
 }
 
 pub fn visit<'a>(statements: &'a Statements, visitor: &mut impl AstVisitor) -> AstVisitorResult {
@@ -472,6 +561,12 @@ fn visit_statement<'a>(
     statement: &'a Statement,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
+    match visitor.enter_statement(statement) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return AstVisitorResult::Continue,
+    }
+
     match statement {
         Statement::Block(stat) => visit_block_statement(stat, visitor),
         Statement::If(stat) => visit_if_statement(stat, visitor),
@@ -489,6 +584,8 @@ fn visit_statement<'a>(
         Statement::Expression(stat) => visit_expression_statement(stat, visitor),
         Statement::Const(stat) => visit_const_statement(stat, visitor),
         Statement::Local(stat) => visit_local_statement(stat, visitor),
+        Statement::Destructuring(stat) => visit_destructuring_statement(stat, visitor),
+        Statement::Import(stat) => visit_import_statement(stat, visitor),
         Statement::FunctionDefinition(stat) => visit_function_declaration(stat, visitor),
         Statement::Class(stat) => visit_class_definition(stat, visitor),
         Statement::Enum(stat) => visit_enum_statement(stat, visitor),
@@ -499,8 +596,10 @@ fn visit_block_statement<'a>(
     statement: &'a BlockStatement,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_block_statement(statement) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_block_statement(statement) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_block_statement(statement),
     }
     if visit_statements(&statement.statements, visitor) == AstVisitorResult::Break {
         return AstVisitorResult::Break;
@@ -517,8 +616,10 @@ fn visit_if_statement<'a>(
     statement: &'a IfStatement,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_if_statement(statement) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_if_statement(statement) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_if_statement(statement),
     }
 
     if visit_expression(&statement.condition, visitor) == AstVisitorResult::Break {
@@ -546,8 +647,10 @@ fn visit_while_statement<'a>(
     statement: &'a WhileStatement,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_while_statement(statement) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_while_statement(statement) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_while_statement(statement),
     }
 
     if visit_expression(&statement.condition, visitor) == AstVisitorResult::Break {
@@ -569,8 +672,10 @@ fn visit_do_while_statement<'a>(
     statement: &'a DoWhileStatement,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_do_while_statement(statement) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_do_while_statement(statement) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_do_while_statement(statement),
     }
 
     if visit_expression(&statement.condition, visitor) == AstVisitorResult::Break {
@@ -592,8 +697,10 @@ fn visit_switch_statement<'a>(
     statement: &'a SwitchStatement,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_switch_statement(statement) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_switch_statement(statement) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_switch_statement(statement),
     }
 
     if visit_expression(&statement.expression, visitor) == AstVisitorResult::Break {
@@ -627,8 +734,10 @@ fn visit_for_statement<'a>(
     statement: &'a ForStatement,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_for_statement(statement) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_for_statement(statement) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_for_statement(statement),
     }
 
     if let Some(initializer) = &statement.initialization {
@@ -664,8 +773,10 @@ fn visit_foreach_statement<'a>(
     statement: &'a ForEachStatement,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_for_each_statement(statement) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_for_each_statement(statement) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_for_each_statement(statement),
     }
 
     if let Some(key) = &statement.key {
@@ -697,26 +808,30 @@ fn visit_try_catch_statement<'a>(
     statement: &'a TryCatchStatement,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_try_catch_statement(statement) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_try_catch_statement(statement) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_try_catch_statement(statement),
     }
 
     if visit_statement(&statement.try_statement, visitor) == AstVisitorResult::Break {
         return AstVisitorResult::Break;
     }
 
-    if visitor.enter_catch_clause(&statement.catch_variable, &statement.catch_statement)
-        == AstVisitorResult::Break
-    {
+    let catch_clause =
+        visitor.enter_catch_clause(&statement.catch_variable, &statement.catch_statement);
+    if catch_clause == AstVisitorResult::Break {
         return AstVisitorResult::Break;
     }
 
-    if visit_expression(&statement.catch_variable, visitor) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
-    }
+    if catch_clause == AstVisitorResult::Continue {
+        if visit_expression(&statement.catch_variable, visitor) == AstVisitorResult::Break {
+            return AstVisitorResult::Break;
+        }
 
-    if visit_statement(&statement.catch_statement, visitor) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+        if visit_statement(&statement.catch_statement, visitor) == AstVisitorResult::Break {
+            return AstVisitorResult::Break;
+        }
     }
 
     if visitor.leave_catch_clause(&statement.catch_variable, &statement.catch_statement)
@@ -766,8 +881,10 @@ fn visit_return_statement<'a>(
     statement: &'a ReturnStatement,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_return_statement(statement) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_return_statement(statement) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_return_statement(statement),
     }
 
     if let Some(expression) = &statement.expression {
@@ -787,8 +904,10 @@ fn visit_yield_statement<'a>(
     statement: &'a YieldStatement,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_yield_statement(statement) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_yield_statement(statement) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_yield_statement(statement),
     }
 
     if let Some(expression) = &statement.expression {
@@ -808,8 +927,10 @@ fn visit_throw_statement<'a>(
     statement: &'a ThrowStatement,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_throw_statement(statement) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_throw_statement(statement) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_throw_statement(statement),
     }
 
     if visit_expression(&statement.expression, visitor) == AstVisitorResult::Break {
@@ -827,8 +948,10 @@ fn visit_expression_statement<'a>(
     statement: &'a ExpressionStatement,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_expression_statement(statement) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_expression_statement(statement) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_expression_statement(statement),
     }
 
     if visit_expression(&statement.expression, visitor) == AstVisitorResult::Break {
@@ -846,8 +969,10 @@ fn visit_const_statement<'a>(
     statement: &'a ConstStatement,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_const_statement(statement) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_const_statement(statement) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_const_statement(statement),
     }
 
     if visit_expression(&statement.name, visitor) == AstVisitorResult::Break {
@@ -869,8 +994,10 @@ fn visit_local_statement<'a>(
     statement: &'a LocalStatement,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_local_statement(statement) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_local_statement(statement) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_local_statement(statement),
     }
 
     for initialization in statement.initializations.iter() {
@@ -888,12 +1015,66 @@ fn visit_local_statement<'a>(
     AstVisitorResult::Continue
 }
 
+fn visit_destructuring_statement<'a>(
+    statement: &'a DestructuringStatement,
+    visitor: &mut impl AstVisitor,
+) -> AstVisitorResult {
+    match visitor.enter_destructuring_statement(statement) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_destructuring_statement(statement),
+    }
+
+    for binding in statement.bindings.iter() {
+        if let Some(expression) = &binding.expression {
+            if visit_expression(expression, visitor) == AstVisitorResult::Break {
+                return AstVisitorResult::Break;
+            }
+        }
+    }
+
+    if visit_expression(&statement.expression, visitor) == AstVisitorResult::Break {
+        return AstVisitorResult::Break;
+    }
+
+    if visitor.leave_destructuring_statement(statement) == AstVisitorResult::Break {
+        return AstVisitorResult::Break;
+    }
+
+    AstVisitorResult::Continue
+}
+
+fn visit_import_statement<'a>(
+    statement: &'a ImportStatement,
+    visitor: &mut impl AstVisitor,
+) -> AstVisitorResult {
+    match visitor.enter_import_statement(statement) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_import_statement(statement),
+    }
+
+    if let Some(alias) = &statement.alias {
+        if visit_expression(alias, visitor) == AstVisitorResult::Break {
+            return AstVisitorResult::Break;
+        }
+    }
+
+    if visitor.leave_import_statement(statement) == AstVisitorResult::Break {
+        return AstVisitorResult::Break;
+    }
+
+    AstVisitorResult::Continue
+}
+
 fn visit_function_declaration<'a>(
     declaration: &'a FunctionDefinition,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_function_declaration(declaration) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_function_declaration(declaration) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_function_declaration(declaration),
     }
 
     if let Some(name) = &declaration.name {
@@ -923,8 +1104,10 @@ fn visit_class_definition<'a>(
     definition: &'a ClassDefinition,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_class_definition(definition) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_class_definition(definition) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_class_definition(definition),
     }
 
     if let Some(name) = &definition.name {
@@ -939,19 +1122,35 @@ fn visit_class_definition<'a>(
         }
     }
 
+    if visit_attributes(&definition.attributes, visitor) == AstVisitorResult::Break {
+        return AstVisitorResult::Break;
+    }
+
     for member in &definition.members {
         match member {
             ClassMemberDefinition::Method(declaration) => {
+                if visit_attributes(&declaration.attributes, visitor) == AstVisitorResult::Break {
+                    return AstVisitorResult::Break;
+                }
+
                 if visit_function_declaration(declaration, visitor) == AstVisitorResult::Break {
                     return AstVisitorResult::Break;
                 }
             }
             ClassMemberDefinition::Constructor(declaration) => {
+                if visit_attributes(&declaration.attributes, visitor) == AstVisitorResult::Break {
+                    return AstVisitorResult::Break;
+                }
+
                 if visit_function_declaration(declaration, visitor) == AstVisitorResult::Break {
                     return AstVisitorResult::Break;
                 }
             }
             ClassMemberDefinition::Field(field) => {
+                if visit_attributes(&field.attributes, visitor) == AstVisitorResult::Break {
+                    return AstVisitorResult::Break;
+                }
+
                 if visit_expression(&field.name, visitor) == AstVisitorResult::Break {
                     return AstVisitorResult::Break;
                 }
@@ -970,12 +1169,25 @@ fn visit_class_definition<'a>(
     AstVisitorResult::Continue
 }
 
+/// Attributes of a class or class member, visited like a table.
+fn visit_attributes(
+    attributes: &Option<TableExpression>,
+    visitor: &mut impl AstVisitor,
+) -> AstVisitorResult {
+    match attributes {
+        Some(attributes) => visit_table_expression(attributes, visitor),
+        None => AstVisitorResult::Continue,
+    }
+}
+
 fn visit_enum_statement<'a>(
     statement: &'a EnumStatement,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_enum_statement(statement) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_enum_statement(statement) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_enum_statement(statement),
     }
 
     if visit_expression(&statement.name, visitor) == AstVisitorResult::Break {
@@ -1005,6 +1217,12 @@ fn visit_expression<'a>(
     expression: &'a Expression,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
+    match visitor.enter_expression(expression) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return AstVisitorResult::Continue,
+    }
+
     match expression {
         Expression::UnaryOperator(unary_operator) => {
             visit_unary_operator_expression(unary_operator, visitor)
@@ -1028,6 +1246,9 @@ fn visit_expression<'a>(
         Expression::IntegerLiteral(integer_literal) => {
             visit_integer_literal_expression(integer_literal, visitor)
         }
+        Expression::CharacterLiteral(character_literal) => {
+            visit_character_literal_expression(character_literal, visitor)
+        }
         Expression::Table(table) => visit_table_expression(table, visitor),
         Expression::Clone(clone) => visit_clone_expression(clone, visitor),
         Expression::Resume(resume) => visit_resume_expression(resume, visitor),
@@ -1044,9 +1265,16 @@ fn visit_expression<'a>(
         Expression::BooleanLiteral(boolean_literal) => {
             visit_boolean_literal_expression(boolean_literal, visitor)
         }
+        Expression::LineLiteral(line_literal) => {
+            visit_line_literal_expression(line_literal, visitor)
+        }
+        Expression::FileLiteral(file_literal) => {
+            visit_file_literal_expression(file_literal, visitor)
+        }
         Expression::FunctionCall(function_call) => {
             visit_function_call_expression(function_call, visitor)
         }
+        Expression::RawCall(raw_call) => visit_raw_call_expression(raw_call, visitor),
         Expression::ScopeResolution(scope_resolution) => {
             visit_scope_resolution_expression(scope_resolution, visitor)
         }
@@ -1065,8 +1293,12 @@ fn visit_unary_operator_expression<'a>(
     expression: &'a UnaryOperatorExpression,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_unary_operator_expression(expression) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_unary_operator_expression(expression) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => {
+            return visitor.leave_unary_operator_expression(expression)
+        }
     }
 
     if visit_expression(&expression.expression, visitor) == AstVisitorResult::Break {
@@ -1084,8 +1316,12 @@ fn visit_postfix_unary_operator_expression<'a>(
     expression: &'a PostfixUnaryOperatorExpression,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_postfix_unary_operator_expression(expression) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_postfix_unary_operator_expression(expression) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => {
+            return visitor.leave_postfix_unary_operator_expression(expression)
+        }
     }
 
     if visit_expression(&expression.expression, visitor) == AstVisitorResult::Break {
@@ -1103,8 +1339,12 @@ fn visit_binary_operator_expression<'a>(
     expression: &'a BinaryOperatorExpression,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_binary_operator_expression(expression) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_binary_operator_expression(expression) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => {
+            return visitor.leave_binary_operator_expression(expression)
+        }
     }
 
     if visit_expression(&expression.left, visitor) == AstVisitorResult::Break {
@@ -1197,12 +1437,29 @@ fn visit_integer_literal_expression<'a>(
     AstVisitorResult::Continue
 }
 
+fn visit_character_literal_expression(
+    expression: &CharacterLiteralExpression,
+    visitor: &mut impl AstVisitor,
+) -> AstVisitorResult {
+    if visitor.enter_character_literal_expression(expression) == AstVisitorResult::Break {
+        return AstVisitorResult::Break;
+    }
+
+    if visitor.leave_character_literal_expression(expression) == AstVisitorResult::Break {
+        return AstVisitorResult::Break;
+    }
+
+    AstVisitorResult::Continue
+}
+
 fn visit_table_expression<'a>(
     expression: &'a TableExpression,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_table_expression(expression) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_table_expression(expression) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_table_expression(expression),
     }
 
     for entry in &expression.entries {
@@ -1226,6 +1483,15 @@ fn visit_table_expression<'a>(
                     return AstVisitorResult::Break;
                 }
 
+                if visit_expression(&f.expression, visitor) == AstVisitorResult::Break {
+                    return AstVisitorResult::Break;
+                }
+            }
+            TableEntry::FieldWithStringKey(f) => {
+                if visit_expression(&f.key, visitor) == AstVisitorResult::Break {
+                    return AstVisitorResult::Break;
+                }
+
                 if visit_expression(&f.expression, visitor) == AstVisitorResult::Break {
                     return AstVisitorResult::Break;
                 }
@@ -1244,8 +1510,10 @@ fn visit_clone_expression<'a>(
     expression: &'a CloneExpression,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_clone_expression(expression) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_clone_expression(expression) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_clone_expression(expression),
     }
 
     if visit_expression(&expression.expression, visitor) == AstVisitorResult::Break {
@@ -1263,8 +1531,10 @@ fn visit_array_expression<'a>(
     expression: &'a ArrayExpression,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_array_expression(expression) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_array_expression(expression) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_array_expression(expression),
     }
 
     for element in &expression.elements {
@@ -1284,8 +1554,10 @@ fn visit_resume_expression<'a>(
     expression: &'a ResumeExpression,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_resume_expression(expression) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_resume_expression(expression) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_resume_expression(expression),
     }
 
     if visit_expression(&expression.expression, visitor) == AstVisitorResult::Break {
@@ -1303,8 +1575,10 @@ fn visit_delete_expression<'a>(
     expression: &'a DeleteExpression,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_delete_expression(expression) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_delete_expression(expression) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_delete_expression(expression),
     }
 
     if visit_expression(&expression.expression, visitor) == AstVisitorResult::Break {
@@ -1337,8 +1611,10 @@ fn visit_array_access_expression<'a>(
     expression: &'a ArrayAccessExpression,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_array_access_expression(expression) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_array_access_expression(expression) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_array_access_expression(expression),
     }
 
     if visit_expression(&expression.array, visitor) == AstVisitorResult::Break {
@@ -1360,8 +1636,10 @@ fn visit_grouping_expression<'a>(
     expression: &'a GroupingExpression,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_grouping_expression(expression) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_grouping_expression(expression) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_grouping_expression(expression),
     }
 
     if let Some(expr) = &expression.expression {
@@ -1392,6 +1670,36 @@ fn visit_null_literal_expression<'a>(
     AstVisitorResult::Continue
 }
 
+fn visit_line_literal_expression(
+    expression: &LineLiteralExpression,
+    visitor: &mut impl AstVisitor,
+) -> AstVisitorResult {
+    if visitor.enter_line_literal_expression(expression) == AstVisitorResult::Break {
+        return AstVisitorResult::Break;
+    }
+
+    if visitor.leave_line_literal_expression(expression) == AstVisitorResult::Break {
+        return AstVisitorResult::Break;
+    }
+
+    AstVisitorResult::Continue
+}
+
+fn visit_file_literal_expression(
+    expression: &FileLiteralExpression,
+    visitor: &mut impl AstVisitor,
+) -> AstVisitorResult {
+    if visitor.enter_file_literal_expression(expression) == AstVisitorResult::Break {
+        return AstVisitorResult::Break;
+    }
+
+    if visitor.leave_file_literal_expression(expression) == AstVisitorResult::Break {
+        return AstVisitorResult::Break;
+    }
+
+    AstVisitorResult::Continue
+}
+
 fn visit_boolean_literal_expression<'a>(
     expression: &'a BooleanLiteralExpression,
     visitor: &mut impl AstVisitor,
@@ -1411,8 +1719,12 @@ fn visit_function_call_expression<'a>(
     expression: &'a FunctionCallExpression,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_function_call_expression(expression) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_function_call_expression(expression) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => {
+            return visitor.leave_function_call_expression(expression)
+        }
     }
 
     if visit_expression(&expression.function, visitor) == AstVisitorResult::Break {
@@ -1432,6 +1744,29 @@ fn visit_function_call_expression<'a>(
     AstVisitorResult::Continue
 }
 
+fn visit_raw_call_expression(
+    expression: &RawCallExpression,
+    visitor: &mut impl AstVisitor,
+) -> AstVisitorResult {
+    match visitor.enter_raw_call_expression(expression) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => return visitor.leave_raw_call_expression(expression),
+    }
+
+    for arg in &expression.arguments {
+        if visit_expression(arg, visitor) == AstVisitorResult::Break {
+            return AstVisitorResult::Break;
+        }
+    }
+
+    if visitor.leave_raw_call_expression(expression) == AstVisitorResult::Break {
+        return AstVisitorResult::Break;
+    }
+
+    AstVisitorResult::Continue
+}
+
 fn visit_scope_resolution_expression<'a>(
     expression: &'a ScopeResolutionExpression,
     visitor: &mut impl AstVisitor,
@@ -1451,8 +1786,12 @@ fn visit_member_access_expression<'a>(
     expression: &'a MemberAccessExpression,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_member_access_expression(expression) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_member_access_expression(expression) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => {
+            return visitor.leave_member_access_expression(expression)
+        }
     }
 
     if visit_expression(&expression.expression, visitor) == AstVisitorResult::Break {
@@ -1470,8 +1809,12 @@ fn visit_ternary_operator_expression<'a>(
     expression: &'a TernaryOperatorExpression,
     visitor: &mut impl AstVisitor,
 ) -> AstVisitorResult {
-    if visitor.enter_ternary_operator_expression(expression) == AstVisitorResult::Break {
-        return AstVisitorResult::Break;
+    match visitor.enter_ternary_operator_expression(expression) {
+        AstVisitorResult::Continue => {}
+        AstVisitorResult::Break => return AstVisitorResult::Break,
+        AstVisitorResult::SkipChildren => {
+            return visitor.leave_ternary_operator_expression(expression)
+        }
     }
 
     if visit_expression(&expression.condition, visitor) == AstVisitorResult::Break {
@@ -1497,7 +1840,9 @@ fn visit_ternary_operator_expression<'a>(
 mod test {
     use crate::{squirrel_lexer::Token, squirrel_parser::Parser};
 
-    use super::{visit, AstVisitor, AstVisitorResult, IdentifierExpression};
+    use super::{
+        visit, AstVisitor, AstVisitorResult, FunctionCallExpression, IdentifierExpression,
+    };
 
     struct IdentifierVisitor {
         identifiers: Vec<Token>,
@@ -1529,4 +1874,59 @@ mod test {
         println!("{:?}", visitor.identifiers);
         assert_eq!(visitor.identifiers.len(), 2);
     }
+
+    struct SkipCallsVisitor {
+        identifiers: Vec<Token>,
+        calls: usize,
+    }
+
+    impl AstVisitor for SkipCallsVisitor {
+        fn enter_function_call_expression(
+            &mut self,
+            _expression: &FunctionCallExpression,
+        ) -> AstVisitorResult {
+            AstVisitorResult::SkipChildren
+        }
+
+        fn leave_function_call_expression(
+            &mut self,
+            _expression: &FunctionCallExpression,
+        ) -> AstVisitorResult {
+            self.calls += 1;
+            AstVisitorResult::Continue
+        }
+
+        fn enter_identifier_expression(
+            &mut self,
+            expression: &IdentifierExpression,
+        ) -> AstVisitorResult {
+            self.identifiers.push(expression.token.clone());
+            AstVisitorResult::Continue
+        }
+    }
+
+    #[test]
+    fn test_skip_children() {
+        let statements = Parser::new(
+            "local a = b + foo(c)
+d",
+        )
+        .parse()
+        .unwrap();
+
+        let mut visitor = SkipCallsVisitor {
+            identifiers: Vec::new(),
+            calls: 0,
+        };
+
+        assert_eq!(visit(&statements, &mut visitor), AstVisitorResult::Continue);
+        assert_eq!(visitor.calls, 1);
+        assert_eq!(
+            visitor.identifiers,
+            vec![
+                Token::Identifier("b".to_string()),
+                Token::Identifier("d".to_string())
+            ]
+        );
+    }
 }
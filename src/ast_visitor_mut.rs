@@ -0,0 +1,567 @@
+use std::ops::ControlFlow;
+
+use crate::ast_visitor::AstVisitorResult;
+use crate::grammar::expressions::*;
+use crate::grammar::statements::*;
+
+/// Callbacks for [`visit_mut`], which can rewrite the AST while walking it.
+///
+/// Nodes are replaced in place from the `enter_*` and `leave_*` methods, e.g.
+/// `*expression = Expression::null_literal(..)`. Statements of a list (the
+/// script, blocks and switch cases) and expressions of a list (call arguments
+/// and array elements) are also passed through `fold_*` once visited, which
+/// can remove them or insert others next to them.
+///
+/// Returning [`AstVisitorResult::SkipChildren`] from `enter_statement` or
+/// `enter_expression` leaves the node as it is, without calling `leave_*` or
+/// `fold_*` for it.
+pub trait AstVisitorMut {
+    /// Called before the children of the statement are visited.
+    fn enter_statement(&mut self, _statement: &mut Statement) -> AstVisitorResult {
+        AstVisitorResult::Continue
+    }
+
+    /// Called after the children of the statement were visited.
+    fn leave_statement(&mut self, _statement: &mut Statement) -> AstVisitorResult {
+        AstVisitorResult::Continue
+    }
+
+    /// Called before the children of the expression are visited.
+    fn enter_expression(&mut self, _expression: &mut Expression) -> AstVisitorResult {
+        AstVisitorResult::Continue
+    }
+
+    /// Called after the children of the expression were visited.
+    fn leave_expression(&mut self, _expression: &mut Expression) -> AstVisitorResult {
+        AstVisitorResult::Continue
+    }
+
+    /// Statements taking the place of a visited statement in its list.
+    fn fold_statement(&mut self, statement: Statement) -> Vec<Statement> {
+        vec![statement]
+    }
+
+    /// Expressions taking the place of a visited expression in its list.
+    fn fold_expression(&mut self, expression: Expression) -> Vec<Expression> {
+        vec![expression]
+    }
+}
+
+/// Walks the statements depth first, letting the visitor rewrite them. Once a
+/// callback returns [`AstVisitorResult::Break`] the rest of the AST is left
+/// untouched.
+pub fn visit_mut(
+    statements: &mut Statements,
+    visitor: &mut impl AstVisitorMut,
+) -> AstVisitorResult {
+    match visit_statements(statements, visitor) {
+        ControlFlow::Continue(()) => AstVisitorResult::Continue,
+        ControlFlow::Break(()) => AstVisitorResult::Break,
+    }
+}
+
+/// Whether to go on walking the AST, along with the outcome of visiting a node
+type Walk<T = ()> = ControlFlow<(), T>;
+
+fn check(result: AstVisitorResult) -> Walk {
+    match result {
+        AstVisitorResult::Break => ControlFlow::Break(()),
+        _ => ControlFlow::Continue(()),
+    }
+}
+
+/// Visits and folds each item, keeping the items that weren't reached yet
+/// when the walk is stopped and the ones skipped as a whole.
+fn visit_list<T, V: AstVisitorMut>(
+    items: &mut Vec<T>,
+    visitor: &mut V,
+    visit: fn(&mut T, &mut V) -> Walk<bool>,
+    fold: fn(&mut V, T) -> Vec<T>,
+) -> Walk {
+    let mut remaining = std::mem::take(items).into_iter();
+
+    while let Some(mut item) = remaining.next() {
+        match visit(&mut item, visitor) {
+            ControlFlow::Continue(true) => items.extend(fold(visitor, item)),
+            ControlFlow::Continue(false) => items.push(item),
+            ControlFlow::Break(()) => {
+                items.push(item);
+                items.extend(remaining);
+                return ControlFlow::Break(());
+            }
+        }
+    }
+
+    ControlFlow::Continue(())
+}
+
+fn visit_statements(statements: &mut Statements, visitor: &mut impl AstVisitorMut) -> Walk {
+    visit_list(
+        &mut statements.statements,
+        visitor,
+        walk_statement,
+        AstVisitorMut::fold_statement,
+    )
+}
+
+fn visit_expressions(expressions: &mut Vec<Expression>, visitor: &mut impl AstVisitorMut) -> Walk {
+    visit_list(
+        expressions,
+        visitor,
+        walk_expression,
+        AstVisitorMut::fold_expression,
+    )
+}
+
+fn visit_statement(statement: &mut Statement, visitor: &mut impl AstVisitorMut) -> Walk {
+    walk_statement(statement, visitor)?;
+
+    ControlFlow::Continue(())
+}
+
+/// Visits the statement, returning `false` when `enter_statement` skipped it as a
+/// whole, in which case it isn't left or folded either.
+fn walk_statement(statement: &mut Statement, visitor: &mut impl AstVisitorMut) -> Walk<bool> {
+    match visitor.enter_statement(statement) {
+        AstVisitorResult::Continue => visit_statement_children(statement, visitor)?,
+        AstVisitorResult::Break => return ControlFlow::Break(()),
+        AstVisitorResult::SkipChildren => return ControlFlow::Continue(false),
+    }
+
+    check(visitor.leave_statement(statement))?;
+
+    ControlFlow::Continue(true)
+}
+
+fn visit_statement_children(statement: &mut Statement, visitor: &mut impl AstVisitorMut) -> Walk {
+    match statement {
+        Statement::Block(statement) => visit_statements(&mut statement.statements, visitor)?,
+        Statement::If(statement) => {
+            visit_expression(&mut statement.condition, visitor)?;
+            visit_statement(&mut statement.if_true, visitor)?;
+            if let Some(if_false) = &mut statement.if_false {
+                visit_statement(if_false, visitor)?;
+            }
+        }
+        Statement::While(statement) => {
+            visit_expression(&mut statement.condition, visitor)?;
+            visit_statement(&mut statement.statement, visitor)?;
+        }
+        Statement::DoWhile(statement) => {
+            visit_statement(&mut statement.statement, visitor)?;
+            visit_expression(&mut statement.condition, visitor)?;
+        }
+        Statement::Switch(statement) => {
+            visit_expression(&mut statement.expression, visitor)?;
+            for case in &mut statement.cases {
+                visit_expression(&mut case.expression, visitor)?;
+                visit_statements(&mut case.statements, visitor)?;
+            }
+            if let Some(default) = &mut statement.default {
+                visit_statements(default, visitor)?;
+            }
+        }
+        Statement::For(statement) => {
+            if let Some(initialization) = &mut statement.initialization {
+                visit_statement(initialization, visitor)?;
+            }
+            if let Some(condition) = &mut statement.condition {
+                visit_expression(condition, visitor)?;
+            }
+            if let Some(increment) = &mut statement.increment {
+                visit_expression(increment, visitor)?;
+            }
+            visit_statement(&mut statement.statement, visitor)?;
+        }
+        Statement::ForEach(statement) => {
+            if let Some(key) = &mut statement.key {
+                visit_expression(key, visitor)?;
+            }
+            visit_expression(&mut statement.value, visitor)?;
+            visit_expression(&mut statement.iterable, visitor)?;
+            visit_statement(&mut statement.statement, visitor)?;
+        }
+        Statement::TryCatch(statement) => {
+            visit_statement(&mut statement.try_statement, visitor)?;
+            visit_expression(&mut statement.catch_variable, visitor)?;
+            visit_statement(&mut statement.catch_statement, visitor)?;
+        }
+        Statement::Return(statement) => {
+            if let Some(expression) = &mut statement.expression {
+                visit_expression(expression, visitor)?;
+            }
+        }
+        Statement::Yield(statement) => {
+            if let Some(expression) = &mut statement.expression {
+                visit_expression(expression, visitor)?;
+            }
+        }
+        Statement::Throw(statement) => visit_expression(&mut statement.expression, visitor)?,
+        Statement::Expression(statement) => visit_expression(&mut statement.expression, visitor)?,
+        Statement::Const(statement) => {
+            visit_expression(&mut statement.name, visitor)?;
+            visit_expression(&mut statement.expression, visitor)?;
+        }
+        Statement::Local(statement) => {
+            for initialization in &mut statement.initializations {
+                if let Some(expression) = &mut initialization.expression {
+                    visit_expression(expression, visitor)?;
+                }
+            }
+        }
+        Statement::Destructuring(statement) => {
+            for binding in &mut statement.bindings {
+                if let Some(expression) = &mut binding.expression {
+                    visit_expression(expression, visitor)?;
+                }
+            }
+            visit_expression(&mut statement.expression, visitor)?;
+        }
+        Statement::Import(statement) => {
+            if let Some(alias) = &mut statement.alias {
+                visit_expression(alias, visitor)?;
+            }
+        }
+        Statement::FunctionDefinition(definition) => visit_function(definition, visitor)?,
+        Statement::Class(definition) => visit_class(definition, visitor)?,
+        Statement::Enum(statement) => {
+            visit_expression(&mut statement.name, visitor)?;
+            for enumeration in &mut statement.enumerations {
+                visit_expression(&mut enumeration.name, visitor)?;
+                if let Some(value) = &mut enumeration.value {
+                    visit_expression(value, visitor)?;
+                }
+            }
+        }
+        Statement::Break(_) | Statement::Continue(_) => {}
+    }
+
+    ControlFlow::Continue(())
+}
+
+fn visit_function(definition: &mut FunctionDefinition, visitor: &mut impl AstVisitorMut) -> Walk {
+    if let Some(name) = &mut definition.name {
+        visit_expression(name, visitor)?;
+    }
+    for parameter in &mut definition.parameters {
+        visit_expression(parameter, visitor)?;
+    }
+
+    visit_statement(&mut definition.statement, visitor)
+}
+
+fn visit_class(definition: &mut ClassDefinition, visitor: &mut impl AstVisitorMut) -> Walk {
+    if let Some(name) = &mut definition.name {
+        visit_expression(name, visitor)?;
+    }
+    if let Some(extends) = &mut definition.extends {
+        visit_expression(extends, visitor)?;
+    }
+    if let Some(attributes) = &mut definition.attributes {
+        visit_table(attributes, visitor)?;
+    }
+
+    for member in &mut definition.members {
+        match member {
+            ClassMemberDefinition::Field(field) => {
+                if let Some(attributes) = &mut field.attributes {
+                    visit_table(attributes, visitor)?;
+                }
+                visit_expression(&mut field.name, visitor)?;
+                visit_expression(&mut field.expression, visitor)?;
+            }
+            ClassMemberDefinition::Method(definition)
+            | ClassMemberDefinition::Constructor(definition) => {
+                if let Some(attributes) = &mut definition.attributes {
+                    visit_table(attributes, visitor)?;
+                }
+                visit_function(definition, visitor)?
+            }
+        }
+    }
+
+    ControlFlow::Continue(())
+}
+
+fn visit_table(table: &mut TableExpression, visitor: &mut impl AstVisitorMut) -> Walk {
+    for entry in &mut table.entries {
+        match entry {
+            TableEntry::Field(field) => {
+                visit_expression(&mut field.name, visitor)?;
+                visit_expression(&mut field.expression, visitor)?;
+            }
+            TableEntry::Function(function) => visit_function(&mut function.function, visitor)?,
+            TableEntry::FieldWithExpressionKey(field) => {
+                visit_expression(&mut field.key, visitor)?;
+                visit_expression(&mut field.expression, visitor)?;
+            }
+            TableEntry::FieldWithStringKey(field) => {
+                visit_expression(&mut field.key, visitor)?;
+                visit_expression(&mut field.expression, visitor)?;
+            }
+        }
+    }
+
+    ControlFlow::Continue(())
+}
+
+fn visit_expression(expression: &mut Expression, visitor: &mut impl AstVisitorMut) -> Walk {
+    walk_expression(expression, visitor)?;
+
+    ControlFlow::Continue(())
+}
+
+/// Visits the expression, returning `false` when `enter_expression` skipped it as a
+/// whole, in which case it isn't left or folded either.
+fn walk_expression(expression: &mut Expression, visitor: &mut impl AstVisitorMut) -> Walk<bool> {
+    match visitor.enter_expression(expression) {
+        AstVisitorResult::Continue => visit_expression_children(expression, visitor)?,
+        AstVisitorResult::Break => return ControlFlow::Break(()),
+        AstVisitorResult::SkipChildren => return ControlFlow::Continue(false),
+    }
+
+    check(visitor.leave_expression(expression))?;
+
+    ControlFlow::Continue(true)
+}
+
+fn visit_expression_children(
+    expression: &mut Expression,
+    visitor: &mut impl AstVisitorMut,
+) -> Walk {
+    match expression {
+        Expression::UnaryOperator(expression) => {
+            visit_expression(&mut expression.expression, visitor)
+        }
+        Expression::PostfixUnaryOperator(expression) => {
+            visit_expression(&mut expression.expression, visitor)
+        }
+        Expression::BinaryOperator(expression) => {
+            visit_expression(&mut expression.left, visitor)?;
+            visit_expression(&mut expression.right, visitor)
+        }
+        Expression::Table(expression) => visit_table(expression, visitor),
+        Expression::Clone(expression) => visit_expression(&mut expression.expression, visitor),
+        Expression::Resume(expression) => visit_expression(&mut expression.expression, visitor),
+        Expression::Delete(expression) => visit_expression(&mut expression.expression, visitor),
+        Expression::Array(expression) => visit_expressions(&mut expression.elements, visitor),
+        Expression::ArrayAccess(expression) => {
+            visit_expression(&mut expression.array, visitor)?;
+            visit_expression(&mut expression.index, visitor)
+        }
+        Expression::Grouping(expression) => match &mut expression.expression {
+            Some(expression) => visit_expression(expression, visitor),
+            None => ControlFlow::Continue(()),
+        },
+        Expression::FunctionCall(expression) => {
+            visit_expression(&mut expression.function, visitor)?;
+            visit_expressions(&mut expression.arguments, visitor)
+        }
+        Expression::RawCall(expression) => visit_expressions(&mut expression.arguments, visitor),
+        Expression::MemberAccess(expression) => {
+            visit_expression(&mut expression.expression, visitor)
+        }
+        Expression::TernaryOperator(expression) => {
+            visit_expression(&mut expression.condition, visitor)?;
+            visit_expression(&mut expression.if_true, visitor)?;
+            visit_expression(&mut expression.if_false, visitor)
+        }
+        Expression::Class(definition) => visit_class(definition, visitor),
+        Expression::Function(definition) => visit_function(definition, visitor),
+        Expression::Spread(_)
+        | Expression::StringLiteral(_)
+        | Expression::MutliLineStringLiteral(_)
+        | Expression::FloatLiteral(_)
+        | Expression::IntegerLiteral(_)
+        | Expression::CharacterLiteral(_)
+        | Expression::Identifier(_)
+        | Expression::NullLiteral(_)
+        | Expression::BooleanLiteral(_)
+        | Expression::LineLiteral(_)
+        | Expression::FileLiteral(_)
+        | Expression::ScopeResolution(_) => ControlFlow::Continue(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        squirrel_formatter::FormatterOptions, squirrel_lexer::Token, squirrel_parser::Parser,
+        squirrel_printer::Printer,
+    };
+
+    use super::*;
+
+    fn rewrite(input: &str, visitor: &mut impl AstVisitorMut) -> String {
+        let mut statements = Parser::new(input).parse().unwrap();
+        visit_mut(&mut statements, visitor);
+
+        Printer::new(input.len(), &FormatterOptions::default()).print(&statements)
+    }
+
+    /// Renames every identifier `a` to `b`.
+    struct Rename;
+
+    impl AstVisitorMut for Rename {
+        fn enter_expression(&mut self, expression: &mut Expression) -> AstVisitorResult {
+            if let Expression::Identifier(identifier) = expression {
+                if identifier.token == Token::Identifier("a".to_string()) {
+                    *expression = Expression::identifier(
+                        Token::Identifier("b".to_string()),
+                        identifier.from.clone(),
+                        identifier.to.clone(),
+                    );
+                }
+            }
+
+            AstVisitorResult::Continue
+        }
+    }
+
+    /// Drops `print` calls, logs the other calls and doubles identifier
+    /// arguments, leaving the arguments of `debug` calls alone.
+    struct Logging;
+
+    impl AstVisitorMut for Logging {
+        fn enter_expression(&mut self, expression: &mut Expression) -> AstVisitorResult {
+            match expression {
+                Expression::FunctionCall(call) if is_call_to(call, "debug") => {
+                    AstVisitorResult::SkipChildren
+                }
+                _ => AstVisitorResult::Continue,
+            }
+        }
+
+        fn fold_statement(&mut self, statement: Statement) -> Vec<Statement> {
+            let Statement::Expression(expression) = &statement else {
+                return vec![statement];
+            };
+            let Expression::FunctionCall(call) = &expression.expression else {
+                return vec![statement];
+            };
+            if is_call_to(call, "print") {
+                return vec![];
+            }
+
+            let log = Parser::new("log()").parse().unwrap().statements.remove(0);
+            vec![log, statement]
+        }
+
+        fn fold_expression(&mut self, expression: Expression) -> Vec<Expression> {
+            match expression {
+                Expression::Identifier(_) => vec![expression.clone(), expression],
+                _ => vec![expression],
+            }
+        }
+    }
+
+    fn is_call_to(call: &FunctionCallExpression, name: &str) -> bool {
+        matches!(
+            &call.function,
+            Expression::Identifier(identifier) if identifier.token == Token::Identifier(name.to_string())
+        )
+    }
+
+    #[test]
+    fn test_replace_expressions_in_place() {
+        assert_eq!(
+            rewrite("local x = a\nfunction f(y) { return a + y }\n", &mut Rename),
+            "local x = b\nfunction f(y) {\n  return b + y\n}"
+        );
+    }
+
+    #[test]
+    fn test_fold_removes_and_inserts() {
+        // Inserted statements keep the locations they were parsed at, which
+        // the printer takes for a blank line before the next statement
+        assert_eq!(
+            rewrite("print(1)\nif (x) {\n  foo(y)\n}\ndebug(z)\n", &mut Logging),
+            "if (x) {\n  log()\n\n  foo(y, y)\n}\nlog()\n\ndebug(z)"
+        );
+    }
+
+    #[test]
+    fn test_skip_children_leaves_out_the_whole_node() {
+        /// Skips `return` statements and `debug` calls, duplicating and
+        /// counting everything else.
+        #[derive(Default)]
+        struct Duplicate {
+            left: usize,
+        }
+
+        impl AstVisitorMut for Duplicate {
+            fn enter_statement(&mut self, statement: &mut Statement) -> AstVisitorResult {
+                match statement {
+                    Statement::Return(_) => AstVisitorResult::SkipChildren,
+                    _ => AstVisitorResult::Continue,
+                }
+            }
+
+            fn leave_statement(&mut self, _statement: &mut Statement) -> AstVisitorResult {
+                self.left += 1;
+                AstVisitorResult::Continue
+            }
+
+            fn enter_expression(&mut self, expression: &mut Expression) -> AstVisitorResult {
+                match expression {
+                    Expression::FunctionCall(call) if is_call_to(call, "debug") => {
+                        AstVisitorResult::SkipChildren
+                    }
+                    _ => AstVisitorResult::Continue,
+                }
+            }
+
+            fn leave_expression(&mut self, _expression: &mut Expression) -> AstVisitorResult {
+                self.left += 1;
+                AstVisitorResult::Continue
+            }
+
+            fn fold_statement(&mut self, statement: Statement) -> Vec<Statement> {
+                vec![statement.clone(), statement]
+            }
+
+            fn fold_expression(&mut self, expression: Expression) -> Vec<Expression> {
+                vec![expression.clone(), expression]
+            }
+        }
+
+        let mut visitor = Duplicate::default();
+
+        assert_eq!(
+            rewrite("foo(debug(a), b)\nreturn c", &mut visitor),
+            "foo(debug(a), b, b)\nfoo(debug(a), b, b)\nreturn c"
+        );
+        // `foo`, `b`, the call and its statement
+        assert_eq!(visitor.left, 4);
+    }
+
+    #[test]
+    fn test_break_keeps_remaining_statements() {
+        struct RemoveUntilReturn;
+
+        impl AstVisitorMut for RemoveUntilReturn {
+            fn enter_statement(&mut self, statement: &mut Statement) -> AstVisitorResult {
+                match statement {
+                    Statement::Return(_) => AstVisitorResult::Break,
+                    _ => AstVisitorResult::Continue,
+                }
+            }
+
+            fn fold_statement(&mut self, _statement: Statement) -> Vec<Statement> {
+                vec![]
+            }
+        }
+
+        assert_eq!(
+            rewrite(
+                "a()
+b()
+return c
+d()",
+                &mut RemoveUntilReturn
+            ),
+            "return c
+d()"
+        );
+    }
+}
@@ -0,0 +1,348 @@
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use glob::Pattern;
+use serde::Deserialize;
+
+use crate::{codemod::Rule, dialect::Dialect, squirrel_formatter::FormatterOptions};
+
+use super::{
+    collect_files, fmt::unified_diff, formatter_options, option_value, EXIT_ERROR, EXIT_FAILURE,
+    EXIT_SUCCESS,
+};
+
+const USAGE: &str =
+    "Usage: squirrel_lsp codemod (--search <pattern> --replace <template> | --rules <file>)
+                             [--check | --diff] [--dialect <dialect>] [--exclude <glob>]...
+                             [<path>...]
+
+Rewrites code matching a pattern in place, searching directories for `.nut`
+files. Reads from stdin and writes to stdout when no path or `-` is given.
+Patterns and templates are Squirrel snippets where `$name` stands for any
+expression, e.g. --search '$obj.bindenv(this)' --replace '$obj.bindenv(this.env)'.
+
+Options:
+    --search <pattern>   Code to look for
+    --replace <template> Code to replace it with
+    --rules <file>       JSON list of {\"search\": ..., \"replace\": ...} applied in order
+    --check              Only list the files that would change
+    --diff               Only print a unified diff of the changes
+    --dialect <dialect>  Squirrel variant: squirrel2, squirrel3 (default) or quirrel
+    --exclude <glob>     Skip files and directories matching the pattern
+    -h, --help           Print this help";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Write,
+    Check,
+    Diff,
+}
+
+#[derive(Debug, Deserialize)]
+struct RuleDefinition {
+    search: String,
+    replace: String,
+}
+
+#[derive(Debug)]
+struct Args {
+    mode: Mode,
+    dialect: Dialect,
+    rules: Vec<RuleDefinition>,
+    paths: Vec<PathBuf>,
+    excludes: Vec<Pattern>,
+}
+
+fn parse_args(args: &[String]) -> Result<Option<Args>, String> {
+    let mut parsed = Args {
+        mode: Mode::Write,
+        dialect: Dialect::default(),
+        rules: Vec::new(),
+        paths: Vec::new(),
+        excludes: Vec::new(),
+    };
+    let mut search = None;
+    let mut replace = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if let Some(dialect) = option_value(arg, "--dialect", &mut args) {
+            parsed.dialect = dialect?.parse()?;
+            continue;
+        }
+        if let Some(pattern) = option_value(arg, "--exclude", &mut args) {
+            let pattern = pattern?;
+            let pattern = Pattern::new(pattern)
+                .map_err(|error| format!("Invalid exclude pattern {}: {}", pattern, error))?;
+            parsed.excludes.push(pattern);
+            continue;
+        }
+        if let Some(value) = option_value(arg, "--search", &mut args) {
+            search = Some(value?.to_string());
+            continue;
+        }
+        if let Some(value) = option_value(arg, "--replace", &mut args) {
+            replace = Some(value?.to_string());
+            continue;
+        }
+        if let Some(file) = option_value(arg, "--rules", &mut args) {
+            let file = file?;
+            let rules = std::fs::read_to_string(file).map_err(|error| error.to_string());
+            let rules = rules.and_then(|rules| {
+                serde_json::from_str::<Vec<RuleDefinition>>(&rules)
+                    .map_err(|error| error.to_string())
+            });
+            parsed
+                .rules
+                .extend(rules.map_err(|error| format!("{}: {}", file, error))?);
+            continue;
+        }
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--check" => parsed.mode = Mode::Check,
+            "--diff" => parsed.mode = Mode::Diff,
+            "-" => parsed.paths.push(PathBuf::from("-")),
+            option if option.starts_with('-') => return Err(format!("Unknown option {}", option)),
+            path => parsed.paths.push(PathBuf::from(path)),
+        }
+    }
+
+    match (search, replace) {
+        (Some(search), Some(replace)) => parsed.rules.push(RuleDefinition { search, replace }),
+        (None, None) if !parsed.rules.is_empty() => {}
+        (None, None) => return Err("Missing --search and --replace or --rules".to_string()),
+        _ => return Err("--search and --replace go together".to_string()),
+    }
+
+    if parsed.paths.len() > 1 && parsed.paths.iter().any(|path| path.as_os_str() == "-") {
+        return Err("- can't be combined with other paths".to_string());
+    }
+
+    Ok(Some(parsed))
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let args = match parse_args(args) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return EXIT_SUCCESS;
+        }
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, USAGE);
+            return EXIT_ERROR;
+        }
+    };
+
+    let mut rules = Vec::new();
+    for definition in &args.rules {
+        match Rule::new(&definition.search, &definition.replace, args.dialect) {
+            Ok(rule) => rules.push(rule),
+            Err(error) => {
+                eprintln!("Invalid rule {}: {}", definition.search, error);
+                return EXIT_ERROR;
+            }
+        }
+    }
+
+    if args.paths.is_empty() || args.paths.iter().any(|path| path.as_os_str() == "-") {
+        return rewrite_stdin(&rules, args.mode);
+    }
+
+    let mut code = EXIT_SUCCESS;
+    for file in collect_files(&args.paths, &args.excludes) {
+        let result = rewrite_file(&file, &rules, args.mode);
+        code = code.max(result);
+    }
+
+    code
+}
+
+fn rewrite_stdin(rules: &[Rule], mode: Mode) -> i32 {
+    let mut input = String::new();
+    if let Err(error) = std::io::stdin().read_to_string(&mut input) {
+        eprintln!("<stdin>: {}", error);
+        return EXIT_ERROR;
+    }
+
+    let Ok(directory) = std::env::current_dir() else {
+        return EXIT_ERROR;
+    };
+    let options = match formatter_options(&directory) {
+        Ok(options) => options,
+        Err(code) => return code,
+    };
+
+    let rewritten = match rewrite(Path::new("<stdin>"), &input, rules, &options) {
+        Ok(rewritten) => rewritten,
+        Err(code) => return code,
+    };
+
+    match mode {
+        Mode::Write => {
+            print!("{}", rewritten);
+            let _ = std::io::stdout().flush();
+            EXIT_SUCCESS
+        }
+        Mode::Check | Mode::Diff => report(Path::new("<stdin>"), &input, &rewritten, mode),
+    }
+}
+
+fn rewrite_file(path: &Path, rules: &[Rule], mode: Mode) -> i32 {
+    let input = match std::fs::read_to_string(path) {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("{}: {}", path.display(), error);
+            return EXIT_ERROR;
+        }
+    };
+
+    let options = match formatter_options(path) {
+        Ok(options) => options,
+        Err(code) => return code,
+    };
+    let rewritten = match rewrite(path, &input, rules, &options) {
+        Ok(rewritten) => rewritten,
+        Err(code) => return code,
+    };
+
+    match mode {
+        Mode::Write if rewritten != input => match std::fs::write(path, rewritten) {
+            Ok(()) => EXIT_SUCCESS,
+            Err(error) => {
+                eprintln!("{}: {}", path.display(), error);
+                EXIT_ERROR
+            }
+        },
+        Mode::Write => EXIT_SUCCESS,
+        Mode::Check | Mode::Diff => report(path, &input, &rewritten, mode),
+    }
+}
+
+/// Applies the rules one after the other, each to the result of the previous.
+fn rewrite(
+    path: &Path,
+    input: &str,
+    rules: &[Rule],
+    options: &FormatterOptions,
+) -> Result<String, i32> {
+    let mut rewritten = input.to_string();
+
+    for rule in rules {
+        let (output, skipped) = rule.apply(&rewritten, options).map_err(|error| {
+            eprintln!(
+                "{}:{}:{}: {}",
+                path.display(),
+                error.from.line + 1,
+                error.from.linechar + 1,
+                error
+            );
+            EXIT_ERROR
+        })?;
+        rewritten = output;
+
+        for location in skipped {
+            eprintln!(
+                "{}:{}:{}: match left alone, replacing it would drop its comments",
+                path.display(),
+                location.line + 1,
+                location.linechar + 1
+            );
+        }
+    }
+
+    Ok(rewritten)
+}
+
+/// Prints the file name or the diff of a file that would change.
+fn report(path: &Path, input: &str, rewritten: &str, mode: Mode) -> i32 {
+    if input == rewritten {
+        return EXIT_SUCCESS;
+    }
+
+    match mode {
+        Mode::Diff => print!(
+            "{}",
+            unified_diff(&path.display().to_string(), input, rewritten)
+        ),
+        _ => println!("{}", path.display()),
+    }
+
+    EXIT_FAILURE
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_args() {
+        let args = [
+            "--search",
+            "a($x)",
+            "--replace=b($x)",
+            "--diff",
+            "--dialect=quirrel",
+            "src",
+        ]
+        .map(String::from)
+        .to_vec();
+        let parsed = parse_args(&args).unwrap().unwrap();
+
+        assert_eq!(parsed.mode, Mode::Diff);
+        assert_eq!(parsed.dialect, Dialect::Quirrel);
+        assert_eq!(parsed.rules.len(), 1);
+        assert_eq!(parsed.rules[0].search, "a($x)");
+        assert_eq!(parsed.rules[0].replace, "b($x)");
+        assert_eq!(parsed.paths, vec![PathBuf::from("src")]);
+
+        assert!(parse_args(&["src".to_string()]).is_err());
+        assert!(parse_args(&["--search".to_string(), "a".to_string()]).is_err());
+
+        let args = ["--search=a", "--replace=b", "u.nut", "-"].map(String::from);
+        assert!(parse_args(&args).is_err());
+    }
+
+    #[test]
+    fn test_rules_file_applied_in_order() {
+        let root = std::env::temp_dir().join("squirrel_lsp_codemod");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+
+        let rules = root.join("rules.json");
+        fs::write(
+            &rules,
+            r#"[
+                {"search": "old($x)", "replace": "middle($x)"},
+                {"search": "middle($x)", "replace": "new($x, true)"}
+            ]"#,
+        )
+        .unwrap();
+        let file = root.join("test.nut");
+        fs::write(&file, "old(1)\nother(2)\n").unwrap();
+
+        let args = parse_args(&["--rules".to_string(), rules.display().to_string()])
+            .unwrap()
+            .unwrap();
+        let rules = args
+            .rules
+            .iter()
+            .map(|rule| Rule::new(&rule.search, &rule.replace, args.dialect).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(rewrite_file(&file, &rules, Mode::Check), EXIT_FAILURE);
+        assert_eq!(rewrite_file(&file, &rules, Mode::Write), EXIT_SUCCESS);
+        assert_eq!(
+            fs::read_to_string(&file).unwrap(),
+            "new(1, true)\nother(2)\n"
+        );
+        assert_eq!(rewrite_file(&file, &rules, Mode::Check), EXIT_SUCCESS);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,265 @@
+use std::{io::Read, path::PathBuf};
+
+use serde::Serialize;
+
+use crate::{
+    dialect::Dialect,
+    scope_table::{Scope, ScopeTable},
+    squirrel_lexer::{location_at, Lexer, Location},
+    squirrel_parser::Parser,
+    syntax::{SyntaxElement, SyntaxNode, SyntaxTree, SyntaxTreeError},
+};
+
+use super::{option_value, EXIT_ERROR, EXIT_SUCCESS};
+
+const USAGE: &str =
+    "Usage: squirrel_lsp dump [--tokens | --ast | --scopes | --sexp] [--dialect <dialect>] [<file>]
+
+Prints how a Squirrel file is lexed and parsed, reading from stdin when no file
+or `-` is given.
+
+Options:
+    --tokens             Tokens as JSON, comments and newlines included
+    --ast                Syntax tree as JSON (default)
+    --scopes             Scopes with their declarations and usages as JSON
+    --sexp               Syntax tree as an S-expression
+    --dialect <dialect>  Squirrel variant: squirrel2, squirrel3 (default) or quirrel
+    -h, --help           Print this help";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Output {
+    Tokens,
+    Ast,
+    Scopes,
+    SExpression,
+}
+
+#[derive(Debug)]
+struct Args {
+    output: Output,
+    dialect: Dialect,
+    file: Option<PathBuf>,
+}
+
+fn parse_args(args: &[String]) -> Result<Option<Args>, String> {
+    let mut parsed = Args {
+        output: Output::Ast,
+        dialect: Dialect::default(),
+        file: None,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if let Some(dialect) = option_value(arg, "--dialect", &mut args) {
+            parsed.dialect = dialect?.parse()?;
+            continue;
+        }
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--tokens" => parsed.output = Output::Tokens,
+            "--ast" => parsed.output = Output::Ast,
+            "--scopes" => parsed.output = Output::Scopes,
+            "--sexp" => parsed.output = Output::SExpression,
+            "-" => parsed.file = None,
+            option if option.starts_with('-') => return Err(format!("Unknown option {}", option)),
+            file if parsed.file.is_none() => parsed.file = Some(PathBuf::from(file)),
+            _ => return Err("Only one file can be dumped at a time".to_string()),
+        }
+    }
+
+    Ok(Some(parsed))
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let args = match parse_args(args) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return EXIT_SUCCESS;
+        }
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, USAGE);
+            return EXIT_ERROR;
+        }
+    };
+
+    let (name, text) = match &args.file {
+        Some(file) => match std::fs::read_to_string(file) {
+            Ok(text) => (file.display().to_string(), text),
+            Err(error) => {
+                eprintln!("{}: {}", file.display(), error);
+                return EXIT_ERROR;
+            }
+        },
+        None => {
+            let mut text = String::new();
+            if let Err(error) = std::io::stdin().read_to_string(&mut text) {
+                eprintln!("<stdin>: {}", error);
+                return EXIT_ERROR;
+            }
+            ("<stdin>".to_string(), text)
+        }
+    };
+
+    match dump(&text, args.output, args.dialect) {
+        Ok(output) => {
+            println!("{}", output);
+            EXIT_SUCCESS
+        }
+        Err((location, message)) => {
+            eprintln!(
+                "{}:{}:{}: {}",
+                name,
+                location.line + 1,
+                location.linechar + 1,
+                message
+            );
+            EXIT_ERROR
+        }
+    }
+}
+
+/// Scope as printed by `--scopes`, along with its position in the scope table
+/// which declarations refer to.
+#[derive(Serialize)]
+struct ScopeView<'a> {
+    index: usize,
+    #[serde(flatten)]
+    scope: &'a Scope,
+}
+
+fn dump(text: &str, output: Output, dialect: Dialect) -> Result<String, (Location, String)> {
+    if output == Output::Tokens {
+        let tokens = Lexer::new(text, false)
+            .with_dialect(dialect)
+            .tokens()
+            .map_err(|error| (error.from, format!("{:?}", error.error)))?;
+
+        return Ok(serde_json::to_string_pretty(&tokens).unwrap_or_default());
+    }
+
+    let ast = Parser::new(text)
+        .with_dialect(dialect)
+        .parse()
+        .map_err(|error| (error.from.clone(), error.to_string()))?;
+
+    let output = match output {
+        Output::Scopes => {
+            let scope_table = ScopeTable::new(&ast);
+            let scopes = scope_table
+                .scopes()
+                .iter()
+                .enumerate()
+                .map(|(index, scope)| ScopeView { index, scope })
+                .collect::<Vec<_>>();
+
+            serde_json::to_string_pretty(&scopes).unwrap_or_default()
+        }
+        Output::SExpression => {
+            let tree = SyntaxTree::parse(text, dialect).map_err(|error| {
+                let location = match &error {
+                    SyntaxTreeError::Syntax(error) => error.from.clone(),
+                    SyntaxTreeError::CrossingNode(_, offset) => location_at(text, *offset),
+                };
+                (location, error.to_string())
+            })?;
+
+            s_expression(&tree.root(), 0)
+        }
+        _ => serde_json::to_string_pretty(&ast).unwrap_or_default(),
+    };
+
+    Ok(output)
+}
+
+/// Prints a node with its significant tokens inline and each child node on its
+/// own line, e.g. `(LocalStatement "local" "a" "=" (IntegerLiteralExpression "1"))`.
+fn s_expression(node: &SyntaxNode, depth: usize) -> String {
+    let mut output = format!("({:?}", node.kind());
+
+    for element in node.children_with_tokens() {
+        match element {
+            SyntaxElement::Node(child) => {
+                output.push('\n');
+                output.push_str(&"  ".repeat(depth + 1));
+                output.push_str(&s_expression(&child, depth + 1));
+            }
+            SyntaxElement::Token(token) if !token.kind().is_trivia() => {
+                output.push_str(&format!(" {:?}", token.text()));
+            }
+            SyntaxElement::Token(_) => {}
+        }
+    }
+
+    output.push(')');
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_args() {
+        let args = ["--scopes", "test.nut"].map(String::from).to_vec();
+        let parsed = parse_args(&args).unwrap().unwrap();
+
+        assert_eq!(parsed.output, Output::Scopes);
+        assert_eq!(parsed.file, Some(PathBuf::from("test.nut")));
+
+        assert!(parse_args(&["a.nut".to_string(), "b.nut".to_string()]).is_err());
+        assert!(parse_args(&["--json".to_string()]).is_err());
+
+        let args = ["--dialect", "quirrel"].map(String::from).to_vec();
+        assert_eq!(
+            parse_args(&args).unwrap().unwrap().dialect,
+            Dialect::Quirrel
+        );
+        assert!(parse_args(&["--dialect=lua".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_dump_s_expression() {
+        let text = "local a = 1 // one\nif (a) {\n  foo(a)\n}\n";
+
+        assert_eq!(
+            dump(text, Output::SExpression, Dialect::default()).unwrap(),
+            "(Root
+  (LocalStatement \"local\" \"a\" \"=\"
+    (IntegerLiteralExpression \"1\"))
+  (IfStatement \"if\" \"(\"
+    (IdentifierExpression \"a\") \")\"
+    (BlockStatement \"{\"
+      (ExpressionStatement
+        (FunctionCallExpression
+          (IdentifierExpression \"foo\") \"(\"
+          (IdentifierExpression \"a\") \")\")) \"}\")))"
+        );
+    }
+
+    #[test]
+    fn test_dump_tokens_scopes_and_errors() {
+        let tokens: serde_json::Value = serde_json::from_str(
+            &dump("local a // one", Output::Tokens, Dialect::default()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(tokens.as_array().unwrap().len(), 3);
+        assert_eq!(tokens[2]["token"]["Comment"][0], " one");
+
+        let scopes: serde_json::Value = serde_json::from_str(
+            &dump("local a = 1\nfoo(a)", Output::Scopes, Dialect::default()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(scopes[0]["index"], 0);
+        assert_eq!(scopes[0]["variables"][0]["name"], "a");
+        assert_eq!(scopes[0]["variable_usage"][0]["name"], "foo");
+
+        let (location, _) =
+            dump("local a = 1\nlocal = 2", Output::Ast, Dialect::default()).unwrap_err();
+        assert_eq!(location.line, 1);
+
+        assert!(dump("a ?? b", Output::Ast, Dialect::default()).is_err());
+        assert!(dump("a ?? b", Output::Ast, Dialect::Quirrel).is_ok());
+    }
+}
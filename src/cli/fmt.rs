@@ -0,0 +1,384 @@
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use difference::{Changeset, Difference};
+use glob::Pattern;
+
+use crate::{
+    dialect::Dialect,
+    squirrel_formatter::{Formatter, FormatterOptions},
+};
+
+use super::{
+    collect_files, formatter_options, option_value, EXIT_ERROR, EXIT_FAILURE, EXIT_SUCCESS,
+};
+
+const USAGE: &str =
+    "Usage: squirrel_lsp fmt [--check | --diff] [--dialect <dialect>] [--exclude <glob>]...
+                       [<path>...]
+
+Formats Squirrel files in place, searching directories for `.nut` files. Reads
+from stdin and writes to stdout when no path or `-` is given. Options are read
+from the closest `.squirrelfmt.json`.
+
+Options:
+    --check              Only list the files that aren't formatted
+    --diff               Only print a unified diff of the changes
+    --dialect <dialect>  Squirrel variant: squirrel2, squirrel3 (default) or quirrel
+    --exclude <glob>     Skip files and directories matching the pattern
+    -h, --help           Print this help";
+
+/// Number of unchanged lines shown around changes in diffs
+const DIFF_CONTEXT: usize = 3;
+
+/// Appended to the last line of a text missing the final newline, so that
+/// adding or removing only that newline still changes a line
+const MISSING_NEWLINE: char = '\0';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Write,
+    Check,
+    Diff,
+}
+
+#[derive(Debug)]
+struct Args {
+    mode: Mode,
+    dialect: Dialect,
+    paths: Vec<PathBuf>,
+    excludes: Vec<Pattern>,
+}
+
+fn parse_args(args: &[String]) -> Result<Option<Args>, String> {
+    let mut parsed = Args {
+        mode: Mode::Write,
+        dialect: Dialect::default(),
+        paths: Vec::new(),
+        excludes: Vec::new(),
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if let Some(dialect) = option_value(arg, "--dialect", &mut args) {
+            parsed.dialect = dialect?.parse()?;
+            continue;
+        }
+
+        if let Some(pattern) = option_value(arg, "--exclude", &mut args) {
+            let pattern = pattern?;
+            let pattern = Pattern::new(pattern)
+                .map_err(|error| format!("Invalid exclude pattern {}: {}", pattern, error))?;
+            parsed.excludes.push(pattern);
+            continue;
+        }
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--check" => parsed.mode = Mode::Check,
+            "--diff" => parsed.mode = Mode::Diff,
+            "-" => parsed.paths.push(PathBuf::from("-")),
+            option if option.starts_with('-') => return Err(format!("Unknown option {}", option)),
+            path => parsed.paths.push(PathBuf::from(path)),
+        }
+    }
+
+    if parsed.paths.len() > 1 && parsed.paths.iter().any(|path| path.as_os_str() == "-") {
+        return Err("- can't be combined with other paths".to_string());
+    }
+
+    Ok(Some(parsed))
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let args = match parse_args(args) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return EXIT_SUCCESS;
+        }
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, USAGE);
+            return EXIT_ERROR;
+        }
+    };
+
+    if args.paths.is_empty() || args.paths.iter().any(|path| path.as_os_str() == "-") {
+        return format_stdin(args.mode, args.dialect);
+    }
+
+    let mut code = EXIT_SUCCESS;
+    for file in collect_files(&args.paths, &args.excludes) {
+        let result = format_file(&file, args.mode, args.dialect);
+        code = code.max(result);
+    }
+
+    code
+}
+
+fn format_stdin(mode: Mode, dialect: Dialect) -> i32 {
+    let mut input = String::new();
+    if let Err(error) = std::io::stdin().read_to_string(&mut input) {
+        eprintln!("<stdin>: {}", error);
+        return EXIT_ERROR;
+    }
+
+    let Ok(directory) = std::env::current_dir() else {
+        return EXIT_ERROR;
+    };
+    let options = match formatter_options(&directory) {
+        Ok(options) => options,
+        Err(code) => return code,
+    };
+
+    let formatted = match format(Path::new("<stdin>"), &input, &options, dialect) {
+        Ok(formatted) => formatted,
+        Err(code) => return code,
+    };
+
+    match mode {
+        Mode::Write => {
+            print!("{}", formatted);
+            let _ = std::io::stdout().flush();
+            EXIT_SUCCESS
+        }
+        Mode::Check | Mode::Diff => report(Path::new("<stdin>"), &input, &formatted, mode),
+    }
+}
+
+fn format_file(path: &Path, mode: Mode, dialect: Dialect) -> i32 {
+    let input = match std::fs::read_to_string(path) {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("{}: {}", path.display(), error);
+            return EXIT_ERROR;
+        }
+    };
+
+    let options = match formatter_options(path) {
+        Ok(options) => options,
+        Err(code) => return code,
+    };
+    let formatted = match format(path, &input, &options, dialect) {
+        Ok(formatted) => formatted,
+        Err(code) => return code,
+    };
+
+    match mode {
+        Mode::Write if formatted != input => match std::fs::write(path, formatted) {
+            Ok(()) => EXIT_SUCCESS,
+            Err(error) => {
+                eprintln!("{}: {}", path.display(), error);
+                EXIT_ERROR
+            }
+        },
+        Mode::Write => EXIT_SUCCESS,
+        Mode::Check | Mode::Diff => report(path, &input, &formatted, mode),
+    }
+}
+
+fn format(
+    path: &Path,
+    input: &str,
+    options: &FormatterOptions,
+    dialect: Dialect,
+) -> Result<String, i32> {
+    Formatter::new(input)
+        .with_dialect(dialect)
+        .format(options)
+        .map_err(|error| {
+            eprintln!(
+                "{}:{}:{}: {}",
+                path.display(),
+                error.from.line + 1,
+                error.from.linechar + 1,
+                error
+            );
+            EXIT_ERROR
+        })
+}
+
+/// Prints the file name or the diff of an unformatted file.
+fn report(path: &Path, input: &str, formatted: &str, mode: Mode) -> i32 {
+    if input == formatted {
+        return EXIT_SUCCESS;
+    }
+
+    match mode {
+        Mode::Diff => print!(
+            "{}",
+            unified_diff(&path.display().to_string(), input, formatted)
+        ),
+        _ => println!("{}", path.display()),
+    }
+
+    EXIT_FAILURE
+}
+
+/// Line based diff in the unified format understood by `patch` and `git apply`.
+pub(super) fn unified_diff(path: &str, original: &str, formatted: &str) -> String {
+    let lines_of = |text: &str| {
+        let mut lines = text.lines().collect::<Vec<_>>().join("\n");
+        if !text.is_empty() && !text.ends_with('\n') {
+            lines.push(MISSING_NEWLINE);
+        }
+        lines
+    };
+
+    let original = lines_of(original);
+    let formatted = lines_of(formatted);
+    let changeset = Changeset::new(&original, &formatted, "\n");
+
+    let lines = changeset
+        .diffs
+        .iter()
+        .flat_map(|difference| {
+            let (tag, text) = match difference {
+                Difference::Same(text) => (' ', text),
+                Difference::Rem(text) => ('-', text),
+                Difference::Add(text) => ('+', text),
+            };
+            text.split('\n').map(move |line| (tag, line))
+        })
+        .collect::<Vec<_>>();
+
+    // Changes close enough for their context to overlap share a hunk
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (index, _) in lines.iter().enumerate().filter(|(_, (tag, _))| *tag != ' ') {
+        match hunks.last_mut() {
+            Some((_, last)) if index <= *last + 2 * DIFF_CONTEXT => *last = index,
+            _ => hunks.push((index, index)),
+        }
+    }
+
+    let mut diff = format!("--- a/{}\n+++ b/{}\n", path, path);
+
+    let count = |lines: &[(char, &str)], skipped: char| {
+        lines.iter().filter(|(tag, _)| *tag != skipped).count()
+    };
+
+    for (first, last) in hunks {
+        let start = first.saturating_sub(DIFF_CONTEXT);
+        let end = (last + DIFF_CONTEXT + 1).min(lines.len());
+        let hunk = &lines[start..end];
+
+        diff.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            count(&lines[..start], '+') + 1,
+            count(hunk, '+'),
+            count(&lines[..start], '-') + 1,
+            count(hunk, '-'),
+        ));
+
+        for (tag, line) in hunk {
+            match line.strip_suffix(MISSING_NEWLINE) {
+                Some(line) => {
+                    diff.push_str(&format!("{}{}\n\\ No newline at end of file\n", tag, line))
+                }
+                None => diff.push_str(&format!("{}{}\n", tag, line)),
+            }
+        }
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_args() {
+        let args = [
+            "--check",
+            "--exclude=**/vendor",
+            "--dialect",
+            "quirrel",
+            "src",
+            "lib",
+        ]
+        .map(String::from)
+        .to_vec();
+        let parsed = parse_args(&args).unwrap().unwrap();
+
+        assert_eq!(parsed.mode, Mode::Check);
+        assert_eq!(parsed.dialect, Dialect::Quirrel);
+        assert_eq!(
+            parsed.paths,
+            vec![PathBuf::from("src"), PathBuf::from("lib")]
+        );
+        assert_eq!(parsed.excludes, vec![Pattern::new("**/vendor").unwrap()]);
+
+        assert!(parse_args(&["--exclude".to_string()]).is_err());
+        assert!(parse_args(&["--dialect=lua".to_string()]).is_err());
+        assert!(parse_args(&["--unknown".to_string()]).is_err());
+        assert!(parse_args(&["--help".to_string()]).unwrap().is_none());
+        assert!(parse_args(&["u.nut".to_string(), "-".to_string()]).is_err());
+        assert!(parse_args(&["-".to_string()]).is_ok());
+    }
+
+    #[test]
+    fn test_unified_diff_groups_changes_with_context() {
+        let original = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let formatted = "A\nb\nc\nd\ne\nf\ng\nh\ni\nJ\n";
+
+        assert_eq!(
+            unified_diff("test.nut", original, formatted),
+            "--- a/test.nut\n+++ b/test.nut\n\
+             @@ -1,4 +1,4 @@\n-a\n+A\n b\n c\n d\n\
+             @@ -7,4 +7,4 @@\n g\n h\n i\n-j\n+J\n"
+        );
+
+        assert_eq!(
+            unified_diff("test.nut", "a\nb", "a\nb\n"),
+            "--- a/test.nut\n+++ b/test.nut\n\
+             @@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+b\n"
+        );
+    }
+
+    #[test]
+    fn test_check_and_write_files() {
+        let root = std::env::temp_dir().join("squirrel_lsp_fmt");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+
+        let file = root.join("test.nut");
+        fs::write(&file, "local a   =  1\n").unwrap();
+
+        assert_eq!(
+            format_file(&file, Mode::Check, Dialect::default()),
+            EXIT_FAILURE
+        );
+        assert_eq!(fs::read_to_string(&file).unwrap(), "local a   =  1\n");
+
+        assert_eq!(
+            format_file(&file, Mode::Write, Dialect::default()),
+            EXIT_SUCCESS
+        );
+        assert_eq!(fs::read_to_string(&file).unwrap(), "local a = 1\n");
+        assert_eq!(
+            format_file(&file, Mode::Check, Dialect::default()),
+            EXIT_SUCCESS
+        );
+
+        fs::write(&file, "local a = \n").unwrap();
+        assert_eq!(
+            format_file(&file, Mode::Check, Dialect::default()),
+            EXIT_ERROR
+        );
+
+        // Broken options are an error rather than falling back to the defaults
+        fs::write(&file, "local a = 1\n").unwrap();
+        fs::write(root.join(".squirrelfmt.json"), "{ \"maxWidth\": \"wide\" }").unwrap();
+        assert_eq!(
+            format_file(&file, Mode::Check, Dialect::default()),
+            EXIT_ERROR
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,576 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    config::Config,
+    diagnostics::{self, Diagnostic, Severity},
+    dialect::Dialect,
+    squirrel_lexer::Location,
+};
+use glob::Pattern;
+use serde_json::json;
+
+use super::{collect_files, option_value, EXIT_ERROR, EXIT_FAILURE, EXIT_SUCCESS};
+
+const USAGE: &str =
+    "Usage: squirrel_lsp lint [--format <format>] [--config <file>] [--dialect <dialect>]
+                        [--exclude <glob>]... [<path>...]
+
+Reports the diagnostics of Squirrel files, searching directories for `.nut` files
+and the current directory when no path is given. Exits with 1 when there are
+warnings, with 3 when there are errors and with 2 for invalid arguments.
+
+Options:
+    --format <format>    Output format: human (default), json, sarif, checkstyle or junit
+    --config <file>      JSON file with the language server settings, e.g. `diagnostics`
+                         and `apiDefinitions`
+    --dialect <dialect>  Squirrel variant: squirrel2, squirrel3 or quirrel, overriding
+                         the configured one
+    --exclude <glob>     Skip files and directories matching the pattern
+    -h, --help           Print this help";
+
+/// Exit code when there are errors, told apart from the invalid arguments
+/// [`EXIT_ERROR`] stands for
+const EXIT_LINT_ERRORS: i32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Human,
+    Json,
+    Sarif,
+    Checkstyle,
+    Junit,
+}
+
+#[derive(Debug)]
+struct Args {
+    format: Format,
+    config: Option<PathBuf>,
+    dialect: Option<Dialect>,
+    paths: Vec<PathBuf>,
+    excludes: Vec<Pattern>,
+}
+
+/// Diagnostics of one linted file
+struct FileReport {
+    path: PathBuf,
+    diagnostics: Vec<Diagnostic>,
+}
+
+fn parse_args(args: &[String]) -> Result<Option<Args>, String> {
+    let mut parsed = Args {
+        format: Format::Human,
+        config: None,
+        dialect: None,
+        paths: Vec::new(),
+        excludes: Vec::new(),
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if let Some(format) = option_value(arg, "--format", &mut args) {
+            parsed.format = match format? {
+                "human" => Format::Human,
+                "json" => Format::Json,
+                "sarif" => Format::Sarif,
+                "checkstyle" => Format::Checkstyle,
+                "junit" => Format::Junit,
+                format => return Err(format!("Unknown format {}", format)),
+            };
+            continue;
+        }
+
+        if let Some(config) = option_value(arg, "--config", &mut args) {
+            parsed.config = Some(PathBuf::from(config?));
+            continue;
+        }
+
+        if let Some(dialect) = option_value(arg, "--dialect", &mut args) {
+            parsed.dialect = Some(dialect?.parse()?);
+            continue;
+        }
+
+        if let Some(pattern) = option_value(arg, "--exclude", &mut args) {
+            let pattern = pattern?;
+            let pattern = Pattern::new(pattern)
+                .map_err(|error| format!("Invalid exclude pattern {}: {}", pattern, error))?;
+            parsed.excludes.push(pattern);
+            continue;
+        }
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            option if option.starts_with('-') => return Err(format!("Unknown option {}", option)),
+            path => parsed.paths.push(PathBuf::from(path)),
+        }
+    }
+
+    if parsed.paths.is_empty() {
+        parsed.paths.push(PathBuf::from("."));
+    }
+
+    Ok(Some(parsed))
+}
+
+fn read_config(path: &Path) -> Result<Config, String> {
+    let contents =
+        std::fs::read_to_string(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+    let value = serde_json::from_str(&contents)
+        .map_err(|error| format!("{}: {}", path.display(), error))?;
+
+    Config::from_json(value).ok_or_else(|| format!("{}: Invalid configuration", path.display()))
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let args = match parse_args(args) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return EXIT_SUCCESS;
+        }
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, USAGE);
+            return EXIT_ERROR;
+        }
+    };
+
+    let mut config = match &args.config {
+        Some(path) => match read_config(path) {
+            Ok(config) => config,
+            Err(error) => {
+                eprintln!("{}", error);
+                return EXIT_ERROR;
+            }
+        },
+        None => Config::default(),
+    };
+
+    if let Some(dialect) = args.dialect {
+        config.dialect = dialect;
+    }
+
+    let reports = collect_files(&args.paths, &args.excludes)
+        .into_iter()
+        .map(|path| lint(path, &config))
+        .collect::<Vec<_>>();
+
+    let output = match args.format {
+        Format::Human => human(&reports),
+        Format::Json => json(&reports),
+        Format::Sarif => sarif(&reports),
+        Format::Checkstyle => checkstyle(&reports),
+        Format::Junit => junit(&reports),
+    };
+    println!("{}", output);
+
+    exit_code(&reports)
+}
+
+/// Files that can't be read are reported with a `read-error` diagnostic
+fn lint(path: PathBuf, config: &Config) -> FileReport {
+    let diagnostics = match std::fs::read_to_string(&path) {
+        Ok(text) => diagnostics::check(&text, config),
+        Err(error) => {
+            let start = Location {
+                line: 0,
+                linechar: 0,
+                index: 0,
+            };
+            vec![Diagnostic::new(
+                Severity::Error,
+                "read-error",
+                &start,
+                &start,
+                error.to_string(),
+            )]
+        }
+    };
+
+    FileReport { path, diagnostics }
+}
+
+fn severity_name(diagnostic: &Diagnostic) -> &'static str {
+    match diagnostic.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Information => "info",
+        Severity::Hint => "hint",
+    }
+}
+
+fn is_failure(diagnostic: &Diagnostic) -> bool {
+    matches!(diagnostic.severity, Severity::Error | Severity::Warning)
+}
+
+/// 3 when there are errors, 1 when there are warnings and 0 otherwise.
+fn exit_code(reports: &[FileReport]) -> i32 {
+    let severities = reports
+        .iter()
+        .flat_map(|report| &report.diagnostics)
+        .map(|diagnostic| diagnostic.severity)
+        .collect::<Vec<_>>();
+
+    if severities.contains(&Severity::Error) {
+        EXIT_LINT_ERRORS
+    } else if severities.contains(&Severity::Warning) {
+        EXIT_FAILURE
+    } else {
+        EXIT_SUCCESS
+    }
+}
+
+/// Lines and columns are 1-based in every output format
+fn start(diagnostic: &Diagnostic) -> (usize, usize) {
+    (diagnostic.from.line + 1, diagnostic.from.linechar + 1)
+}
+
+fn end(diagnostic: &Diagnostic) -> (usize, usize) {
+    (diagnostic.to.line + 1, diagnostic.to.linechar + 1)
+}
+
+fn human(reports: &[FileReport]) -> String {
+    let mut lines = Vec::new();
+
+    for report in reports {
+        for diagnostic in &report.diagnostics {
+            let (line, column) = start(diagnostic);
+            lines.push(format!(
+                "{}:{}:{}: {}[{}]: {}",
+                report.path.display(),
+                line,
+                column,
+                severity_name(diagnostic),
+                diagnostic.code,
+                diagnostic.message
+            ));
+        }
+    }
+
+    let diagnostics = reports.iter().flat_map(|report| &report.diagnostics);
+    let count = |severity_filter: Severity| {
+        diagnostics
+            .clone()
+            .filter(|diagnostic| diagnostic.severity == severity_filter)
+            .count()
+    };
+    lines.push(format!(
+        "{} files checked: {} errors, {} warnings",
+        reports.len(),
+        count(Severity::Error),
+        count(Severity::Warning)
+    ));
+
+    lines.join("\n")
+}
+
+fn json(reports: &[FileReport]) -> String {
+    let diagnostics = reports
+        .iter()
+        .flat_map(|report| {
+            report.diagnostics.iter().map(|diagnostic| {
+                let (line, column) = start(diagnostic);
+                let (end_line, end_column) = end(diagnostic);
+
+                json!({
+                    "file": report.path.display().to_string(),
+                    "line": line,
+                    "column": column,
+                    "endLine": end_line,
+                    "endColumn": end_column,
+                    "severity": severity_name(diagnostic),
+                    "code": diagnostic.code,
+                    "message": diagnostic.message,
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+
+    serde_json::to_string_pretty(&diagnostics).unwrap_or_default()
+}
+
+/// Static Analysis Results Interchange Format 2.1.0, as read by code scanning services
+fn sarif(reports: &[FileReport]) -> String {
+    let mut rules = reports
+        .iter()
+        .flat_map(|report| report.diagnostics.iter().map(|diagnostic| &diagnostic.code))
+        .collect::<Vec<_>>();
+    rules.sort();
+    rules.dedup();
+
+    let results = reports
+        .iter()
+        .flat_map(|report| {
+            report.diagnostics.iter().map(|diagnostic| {
+                let (line, column) = start(diagnostic);
+                let (end_line, end_column) = end(diagnostic);
+                let level = match diagnostic.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                    _ => "note",
+                };
+
+                json!({
+                    "ruleId": diagnostic.code,
+                    "level": level,
+                    "message": { "text": diagnostic.message },
+                    "locations": [{
+                        "physicalLocation": {
+                            "artifactLocation": {
+                                "uri": report.path.to_string_lossy().replace('\\', "/"),
+                            },
+                            "region": {
+                                "startLine": line,
+                                "startColumn": column,
+                                "endLine": end_line,
+                                "endColumn": end_column,
+                            },
+                        },
+                    }],
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let sarif = json!({
+        "version": "2.1.0",
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules.iter().map(|rule| json!({ "id": rule })).collect::<Vec<_>>(),
+                },
+            },
+            "results": results,
+        }],
+    });
+
+    serde_json::to_string_pretty(&sarif).unwrap_or_default()
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn checkstyle(reports: &[FileReport]) -> String {
+    let mut xml = vec![
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>".to_string(),
+        "<checkstyle version=\"4.3\">".to_string(),
+    ];
+
+    for report in reports {
+        xml.push(format!(
+            "  <file name=\"{}\">",
+            escape_xml(&report.path.display().to_string())
+        ));
+
+        for diagnostic in &report.diagnostics {
+            let (line, column) = start(diagnostic);
+            let severity = match severity_name(diagnostic) {
+                "hint" => "info",
+                severity => severity,
+            };
+
+            xml.push(format!(
+                "    <error line=\"{}\" column=\"{}\" severity=\"{}\" message=\"{}\" source=\"squirrel.{}\"/>",
+                line,
+                column,
+                severity,
+                escape_xml(&diagnostic.message),
+                escape_xml(&diagnostic.code)
+            ));
+        }
+
+        xml.push("  </file>".to_string());
+    }
+
+    xml.push("</checkstyle>".to_string());
+    xml.join("\n")
+}
+
+/// One test case per file, failing when it has warnings or errors
+fn junit(reports: &[FileReport]) -> String {
+    let failures = reports
+        .iter()
+        .filter(|report| report.diagnostics.iter().any(is_failure))
+        .count();
+
+    let mut xml = vec![
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>".to_string(),
+        format!(
+            "<testsuites name=\"{0}\" tests=\"{1}\" failures=\"{2}\">\n  <testsuite name=\"{0}\" tests=\"{1}\" failures=\"{2}\">",
+            env!("CARGO_PKG_NAME"),
+            reports.len(),
+            failures
+        ),
+    ];
+
+    for report in reports {
+        let path = escape_xml(&report.path.display().to_string());
+        let failed = report
+            .diagnostics
+            .iter()
+            .filter(|diagnostic| is_failure(diagnostic))
+            .collect::<Vec<_>>();
+
+        if failed.is_empty() {
+            xml.push(format!(
+                "    <testcase name=\"{}\" classname=\"{}\"/>",
+                path,
+                env!("CARGO_PKG_NAME")
+            ));
+            continue;
+        }
+
+        let details = failed
+            .iter()
+            .map(|diagnostic| {
+                let (line, column) = start(diagnostic);
+                escape_xml(&format!(
+                    "{}:{}: {}[{}]: {}",
+                    line,
+                    column,
+                    severity_name(diagnostic),
+                    diagnostic.code,
+                    diagnostic.message
+                ))
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        xml.push(format!(
+            "    <testcase name=\"{}\" classname=\"{}\">\n      <failure message=\"{} problems\">{}</failure>\n    </testcase>",
+            path,
+            env!("CARGO_PKG_NAME"),
+            failed.len(),
+            details
+        ));
+    }
+
+    xml.push("  </testsuite>\n</testsuites>".to_string());
+    xml.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reports() -> Vec<FileReport> {
+        let diagnostic = |line, severity, code: &str, message: &str| {
+            let location = |linechar| Location {
+                line,
+                linechar,
+                index: 0,
+            };
+            Diagnostic::new(
+                severity,
+                code,
+                &location(4),
+                &location(7),
+                message.to_string(),
+            )
+        };
+
+        vec![
+            FileReport {
+                path: PathBuf::from("src/a.nut"),
+                diagnostics: vec![
+                    diagnostic(
+                        0,
+                        Severity::Error,
+                        "undeclared-variable",
+                        "Variable 'foo' is not declared",
+                    ),
+                    diagnostic(
+                        2,
+                        Severity::Warning,
+                        "type-mismatch",
+                        "Operator < on \"a\" & 1",
+                    ),
+                ],
+            },
+            FileReport {
+                path: PathBuf::from("src/b.nut"),
+                diagnostics: vec![diagnostic(
+                    1,
+                    Severity::Hint,
+                    "unused-parameter",
+                    "Unused parameter 'x'",
+                )],
+            },
+        ]
+    }
+
+    #[test]
+    fn test_exit_code_reflects_highest_severity() {
+        let mut reports = reports();
+        assert_eq!(exit_code(&reports), EXIT_LINT_ERRORS);
+
+        reports[0].diagnostics.remove(0);
+        assert_eq!(exit_code(&reports), EXIT_FAILURE);
+
+        reports[0].diagnostics.clear();
+        assert_eq!(exit_code(&reports), EXIT_SUCCESS);
+    }
+
+    #[test]
+    fn test_unreadable_files_are_reported() {
+        let mut reports = reports();
+        reports.push(lint(PathBuf::from("missing.nut"), &Config::default()));
+
+        assert_eq!(reports[2].diagnostics.len(), 1);
+        assert_eq!(reports[2].diagnostics[0].code, "read-error");
+        assert_eq!(exit_code(&reports), EXIT_LINT_ERRORS);
+        assert!(human(&reports).ends_with("3 files checked: 2 errors, 1 warnings"));
+    }
+
+    #[test]
+    fn test_human_and_checkstyle_output() {
+        assert_eq!(
+            human(&reports()),
+            "src/a.nut:1:5: error[undeclared-variable]: Variable 'foo' is not declared\n\
+             src/a.nut:3:5: warning[type-mismatch]: Operator < on \"a\" & 1\n\
+             src/b.nut:2:5: hint[unused-parameter]: Unused parameter 'x'\n\
+             2 files checked: 1 errors, 1 warnings"
+        );
+
+        assert_eq!(
+            checkstyle(&reports()),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <checkstyle version=\"4.3\">\n  \
+             <file name=\"src/a.nut\">\n    \
+             <error line=\"1\" column=\"5\" severity=\"error\" message=\"Variable &apos;foo&apos; is not declared\" source=\"squirrel.undeclared-variable\"/>\n    \
+             <error line=\"3\" column=\"5\" severity=\"warning\" message=\"Operator &lt; on &quot;a&quot; &amp; 1\" source=\"squirrel.type-mismatch\"/>\n  \
+             </file>\n  \
+             <file name=\"src/b.nut\">\n    \
+             <error line=\"2\" column=\"5\" severity=\"info\" message=\"Unused parameter &apos;x&apos;\" source=\"squirrel.unused-parameter\"/>\n  \
+             </file>\n\
+             </checkstyle>"
+        );
+    }
+
+    #[test]
+    fn test_sarif_and_junit_output() {
+        let sarif: serde_json::Value = serde_json::from_str(&sarif(&reports())).unwrap();
+        let run = &sarif["runs"][0];
+
+        assert_eq!(sarif["version"], "2.1.0");
+        assert_eq!(run["tool"]["driver"]["rules"].as_array().unwrap().len(), 3);
+        assert_eq!(run["results"][1]["ruleId"], "type-mismatch");
+        assert_eq!(run["results"][1]["level"], "warning");
+        assert_eq!(run["results"][2]["level"], "note");
+        assert_eq!(
+            run["results"][0]["locations"][0]["physicalLocation"]["region"]["startLine"],
+            1
+        );
+
+        let junit = junit(&reports());
+        assert!(junit.contains("tests=\"2\" failures=\"1\""));
+        assert!(junit.contains("<testcase name=\"src/b.nut\" classname=\"squirrel_lsp\"/>"));
+        assert!(junit.contains("<failure message=\"2 problems\">"));
+    }
+}
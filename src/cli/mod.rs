@@ -0,0 +1,163 @@
+use std::path::{Path, PathBuf};
+
+use glob::Pattern;
+
+use crate::squirrel_formatter::FormatterOptions;
+
+mod codemod;
+mod dump;
+mod fmt;
+mod lint;
+
+/// Exit code when everything went fine
+const EXIT_SUCCESS: i32 = 0;
+/// Exit code when a file needs attention, e.g. isn't formatted
+const EXIT_FAILURE: i32 = 1;
+/// Exit code for invalid arguments and files that can't be read or parsed
+const EXIT_ERROR: i32 = 2;
+
+const USAGE: &str = "Usage: squirrel_lsp [<command> [<args>...]]
+
+Starts the language server on stdio when no command is given.
+
+Commands:
+    codemod  Rewrite code matching a pattern
+    dump     Print how a file is lexed and parsed
+    fmt      Format files
+    lint     Report the diagnostics of files
+
+Run `squirrel_lsp <command> --help` for the options of a command.";
+
+/// Runs the command named by the first argument, e.g. `squirrel_lsp fmt --check src`,
+/// returning its exit code. Returns `None` when no command is given, in which
+/// case the language server is started.
+pub fn run(args: &[String]) -> Option<i32> {
+    let (command, args) = args.split_first()?;
+
+    match command.as_str() {
+        "codemod" => Some(codemod::run(args)),
+        "dump" => Some(dump::run(args)),
+        "fmt" => Some(fmt::run(args)),
+        "lint" => Some(lint::run(args)),
+        "help" | "-h" | "--help" => {
+            println!("{}", USAGE);
+            Some(EXIT_SUCCESS)
+        }
+        // Options editors start the language server with, e.g. `--stdio`
+        option if option.starts_with('-') => None,
+        command => {
+            eprintln!("Unknown command {}\n\n{}", command, USAGE);
+            Some(EXIT_ERROR)
+        }
+    }
+}
+
+/// Squirrel files at the paths, directories being searched recursively, leaving
+/// out the ones matching an exclude pattern.
+fn collect_files(paths: &[PathBuf], excludes: &[Pattern]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+
+    for path in paths {
+        collect_path(path, excludes, &mut files);
+    }
+
+    files
+}
+
+fn collect_path(path: &Path, excludes: &[Pattern], files: &mut Vec<PathBuf>) {
+    if excludes.iter().any(|pattern| pattern.matches_path(path)) {
+        return;
+    }
+
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return;
+    }
+
+    let Ok(entries) = std::fs::read_dir(path) else {
+        files.push(path.to_path_buf());
+        return;
+    };
+
+    let mut entries = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect::<Vec<_>>();
+    entries.sort();
+
+    for entry in entries {
+        if entry.is_dir()
+            || entry
+                .extension()
+                .is_some_and(|extension| extension == "nut")
+        {
+            collect_path(&entry, excludes, files);
+        }
+    }
+}
+
+/// Formatter options for a file, a broken `.squirrelfmt.json` being reported
+/// like an invalid argument.
+fn formatter_options(path: &Path) -> Result<FormatterOptions, i32> {
+    FormatterOptions::discover(path)
+        .map(Option::unwrap_or_default)
+        .map_err(|error| {
+            eprintln!("{}", error);
+            EXIT_ERROR
+        })
+}
+
+/// Splits `--name=value` and `--name value` options, advancing past the value.
+fn option_value<'a>(
+    arg: &'a str,
+    name: &str,
+    args: &mut impl Iterator<Item = &'a String>,
+) -> Option<Result<&'a str, String>> {
+    let rest = arg.strip_prefix(name)?;
+
+    if let Some(value) = rest.strip_prefix('=') {
+        return Some(Ok(value));
+    }
+    if !rest.is_empty() {
+        return None;
+    }
+
+    Some(
+        args.next()
+            .map(String::as_str)
+            .ok_or_else(|| format!("Missing value for {}", name)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_unknown_commands_are_usage_errors() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(run(&args(&["fmtx"])), Some(EXIT_ERROR));
+        assert_eq!(run(&args(&["--help"])), Some(EXIT_SUCCESS));
+        assert_eq!(run(&args(&["--stdio"])), None);
+        assert_eq!(run(&[]), None);
+    }
+
+    #[test]
+    fn test_collect_files_searches_directories_and_skips_excluded() {
+        let root = std::env::temp_dir().join("squirrel_lsp_collect_files");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src/vendor")).unwrap();
+        fs::write(root.join("src/a.nut"), "").unwrap();
+        fs::write(root.join("src/b.txt"), "").unwrap();
+        fs::write(root.join("src/vendor/c.nut"), "").unwrap();
+
+        let excludes = vec![Pattern::new("**/vendor").unwrap()];
+        let files = collect_files(&[root.join("src")], &excludes);
+
+        assert_eq!(files, vec![root.join("src/a.nut")]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,369 @@
+#[cfg(feature = "lsp")]
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "lsp")]
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, Command, Diagnostic, Position, Range,
+    TextEdit, Url, WorkspaceEdit,
+};
+
+#[cfg(feature = "lsp")]
+use crate::refactor::to_range;
+use crate::{
+    scope_table::{ScopeTable, VariableDeclaration, VariableUsage},
+    squirrel_lexer::Location,
+};
+
+/// Command adding a name to `apiDefinitions`, its only argument is the name.
+/// The name is only kept for the session: the server can't write the client
+/// settings, so the next configuration change or restart drops it again.
+pub const ADD_API_DEFINITION_COMMAND: &str = "squirrel.addApiDefinition";
+
+/// How many "did you mean" suggestions are offered for an undeclared variable.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Attached to diagnostics as `data` so that quick fixes can be computed from
+/// the diagnostic alone, without analyzing the document again.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum FixData {
+    /// A variable that isn't declared, along with similarly named visible declarations
+    Undeclared {
+        name: String,
+        suggestions: Vec<String>,
+    },
+    /// A declaration that is never used, `removal` is set when the whole
+    /// statement declaring it can be removed without losing side effects
+    Unused {
+        name: String,
+        removal: Option<(Location, Location)>,
+    },
+}
+
+impl FixData {
+    /// Fixes for a usage of an undeclared variable in `scope`, suggesting the
+    /// visible declarations and API definitions with a similar name.
+    pub fn undeclared(
+        scope_table: &ScopeTable,
+        scope: usize,
+        usage: &VariableUsage,
+        api_definitions: &[String],
+    ) -> Self {
+        let visible = scope_table.visible_declarations(scope);
+
+        FixData::Undeclared {
+            name: usage.name.clone(),
+            suggestions: suggestions(
+                &usage.name,
+                visible
+                    .iter()
+                    .map(|variable| variable.name.as_str())
+                    .chain(api_definitions.iter().map(String::as_str)),
+            ),
+        }
+    }
+
+    /// Fixes for an unused local or const, removing the statement declaring it
+    /// when that is safe.
+    pub fn unused(scope_table: &ScopeTable, declaration: &VariableDeclaration) -> Self {
+        FixData::Unused {
+            name: declaration.name.clone(),
+            removal: scope_table.removable_statement(declaration),
+        }
+    }
+
+    pub fn to_value(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self).ok()
+    }
+
+    #[cfg(feature = "lsp")]
+    pub fn from_diagnostic(diagnostic: &Diagnostic) -> Option<Self> {
+        serde_json::from_value(diagnostic.data.clone()?).ok()
+    }
+}
+
+/// Levenshtein distance between two names.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Candidates close enough to `name` to likely be a typo of it, closest first.
+pub fn suggestions<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let max_distance = (name.chars().count() / 3).max(1);
+
+    let mut suggestions = candidates
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect::<Vec<_>>();
+
+    suggestions.sort();
+    suggestions.dedup();
+
+    suggestions
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate.to_string())
+        .collect()
+}
+
+/// Quick fixes for the given diagnostics of a document.
+#[cfg(feature = "lsp")]
+pub fn code_actions(uri: &Url, text: &str, diagnostics: &[Diagnostic]) -> Vec<CodeActionOrCommand> {
+    let mut actions = Vec::new();
+
+    for diagnostic in diagnostics {
+        match FixData::from_diagnostic(diagnostic) {
+            Some(FixData::Undeclared { name, suggestions }) => {
+                for suggestion in suggestions {
+                    actions.push(quick_fix(
+                        format!("Did you mean '{}'?", suggestion),
+                        uri,
+                        diagnostic,
+                        TextEdit::new(diagnostic.range, suggestion),
+                        true,
+                    ));
+                }
+
+                let line = diagnostic.range.start.line;
+                actions.push(quick_fix(
+                    format!("Declare local '{}'", name),
+                    uri,
+                    diagnostic,
+                    TextEdit::new(
+                        Range::new(Position::new(line, 0), Position::new(line, 0)),
+                        format!("{}local {};\n", indentation(text, line), name),
+                    ),
+                    false,
+                ));
+
+                actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                    title: format!("Add '{}' to API definitions", name),
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    diagnostics: Some(vec![diagnostic.clone()]),
+                    command: Some(Command::new(
+                        format!("Add '{}' to API definitions", name),
+                        ADD_API_DEFINITION_COMMAND.to_string(),
+                        Some(vec![serde_json::Value::String(name)]),
+                    )),
+                    ..CodeAction::default()
+                }));
+            }
+            Some(FixData::Unused { name, removal }) => {
+                // The diagnostic starts at the name
+                let start = diagnostic.range.start;
+                actions.push(quick_fix(
+                    format!("Prefix '{}' with '_'", name),
+                    uri,
+                    diagnostic,
+                    TextEdit::new(Range::new(start, start), "_".to_string()),
+                    true,
+                ));
+
+                if let Some((from, to)) = removal {
+                    actions.push(quick_fix(
+                        format!("Remove declaration of '{}'", name),
+                        uri,
+                        diagnostic,
+                        TextEdit::new(whole_lines(text, to_range(&from, &to)), String::new()),
+                        false,
+                    ));
+                }
+            }
+            None => {}
+        }
+    }
+
+    actions
+}
+
+#[cfg(feature = "lsp")]
+fn quick_fix(
+    title: String,
+    uri: &Url,
+    diagnostic: &Diagnostic,
+    edit: TextEdit,
+    is_preferred: bool,
+) -> CodeActionOrCommand {
+    CodeActionOrCommand::CodeAction(CodeAction {
+        title,
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(vec![diagnostic.clone()]),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(uri.clone(), vec![edit])])),
+            ..WorkspaceEdit::default()
+        }),
+        is_preferred: Some(is_preferred),
+        ..CodeAction::default()
+    })
+}
+
+#[cfg(feature = "lsp")]
+pub(crate) fn indentation(text: &str, line: u32) -> String {
+    text.lines()
+        .nth(line as usize)
+        .map(|line| {
+            line.chars()
+                .take_while(|c| c.is_whitespace())
+                .collect::<String>()
+        })
+        .unwrap_or_default()
+}
+
+/// Extends the range to full lines when nothing else shares them, so removing
+/// a statement doesn't leave an empty line behind.
+#[cfg(feature = "lsp")]
+pub(crate) fn whole_lines(text: &str, range: Range) -> Range {
+    let lines = text.lines().collect::<Vec<_>>();
+
+    let (Some(first), Some(last)) = (
+        lines.get(range.start.line as usize),
+        lines.get(range.end.line as usize),
+    ) else {
+        return range;
+    };
+
+    let before = first.chars().take(range.start.character as usize);
+    let after = last.chars().skip(range.end.character as usize);
+
+    if before.chain(after).all(|c| c.is_whitespace() || c == ';') {
+        Range::new(
+            Position::new(range.start.line, 0),
+            Position::new(range.end.line + 1, 0),
+        )
+    } else {
+        range
+    }
+}
+
+#[cfg(all(test, feature = "lsp"))]
+mod tests {
+    use tower_lsp::lsp_types::{CodeActionOrCommand, Position, Range, Url};
+
+    use crate::{
+        diagnostics::validate_variables, scope_table::ScopeTable, squirrel_parser::Parser,
+    };
+
+    use super::{code_actions, edit_distance};
+
+    fn action_titles(input: &str) -> Vec<String> {
+        let mut parser = Parser::new(input);
+        let statements = parser.parse().unwrap();
+        let scope_table = ScopeTable::new(&statements);
+        let diagnostics = validate_variables(input, &scope_table, &[])
+            .into_iter()
+            .map(Into::into)
+            .collect::<Vec<_>>();
+        let uri = Url::parse("file:///test.nut").unwrap();
+
+        code_actions(&uri, input, &diagnostics)
+            .into_iter()
+            .map(|action| match action {
+                CodeActionOrCommand::CodeAction(action) => action.title,
+                CodeActionOrCommand::Command(command) => command.title,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("counter", "counter"), 0);
+        assert_eq!(edit_distance("countr", "counter"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_undeclared_variable_fixes() {
+        let input = "
+local counter = 0;
+::print(countr);
+";
+        assert_eq!(
+            action_titles(input),
+            vec![
+                "Prefix 'counter' with '_'",
+                "Remove declaration of 'counter'",
+                "Did you mean 'counter'?",
+                "Declare local 'countr'",
+                "Add 'countr' to API definitions",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unused_variable_with_side_effects() {
+        let input = "
+local a = ::foo();
+";
+        assert_eq!(action_titles(input), vec!["Prefix 'a' with '_'"]);
+    }
+
+    #[test]
+    fn test_prefix_unused_variable() {
+        let input = "
+local count = 1;
+";
+        let mut parser = Parser::new(input);
+        let statements = parser.parse().unwrap();
+        let scope_table = ScopeTable::new(&statements);
+        let diagnostics = validate_variables(input, &scope_table, &[])
+            .into_iter()
+            .map(Into::into)
+            .collect::<Vec<_>>();
+        let uri = Url::parse("file:///test.nut").unwrap();
+
+        let CodeActionOrCommand::CodeAction(action) = &code_actions(&uri, input, &diagnostics)[0]
+        else {
+            panic!("expected a code action");
+        };
+        let edit = &action.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri][0];
+
+        let mut lines = input.split('\n').map(String::from).collect::<Vec<_>>();
+        let start = edit.range.start;
+        lines[start.line as usize].insert_str(start.character as usize, &edit.new_text);
+
+        assert_eq!(lines.join("\n"), "\nlocal _count = 1;\n");
+    }
+
+    #[test]
+    fn test_remove_unused_declaration() {
+        let input = "
+local a = 1;
+";
+        let mut parser = Parser::new(input);
+        let statements = parser.parse().unwrap();
+        let scope_table = ScopeTable::new(&statements);
+        let diagnostics = validate_variables(input, &scope_table, &[])
+            .into_iter()
+            .map(Into::into)
+            .collect::<Vec<_>>();
+        let uri = Url::parse("file:///test.nut").unwrap();
+
+        let CodeActionOrCommand::CodeAction(action) = &code_actions(&uri, input, &diagnostics)[1]
+        else {
+            panic!("expected a code action");
+        };
+        let edits = &action.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri];
+
+        assert_eq!(
+            edits[0].range,
+            Range::new(Position::new(1, 0), Position::new(2, 0))
+        );
+    }
+}
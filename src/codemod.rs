@@ -0,0 +1,735 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use serde_json::{Map, Value};
+
+use crate::{
+    ast_visitor::{visit, AstVisitor, AstVisitorResult},
+    dialect::Dialect,
+    grammar::expressions::*,
+    grammar::statements::*,
+    precedence::Requirements,
+    squirrel_formatter::FormatterOptions,
+    squirrel_lexer::{skip_whitespace, Lexer, Location, Token},
+    squirrel_parser::{Parser, ParserErrorWithLocation},
+    squirrel_printer::Printer,
+};
+
+/// Command rewriting the open documents, with the search and replace snippets
+/// as arguments.
+pub const CODEMOD_COMMAND: &str = "squirrel.codemod";
+
+/// Identifiers that `$name` metavariables are turned into before a snippet is
+/// parsed.
+const METAVARIABLE_PREFIX: &str = "__codemod_";
+
+/// Operators and accesses whose operands get parentheses when a metavariable
+/// is replaced with a binary or ternary expression.
+const TIGHT_EXPRESSIONS: [&str; 6] = [
+    "UnaryOperator",
+    "PostfixUnaryOperator",
+    "BinaryOperator",
+    "MemberAccess",
+    "ArrayAccess",
+    "FunctionCall",
+];
+
+#[derive(Debug)]
+pub enum CodemodError {
+    /// The search or replace snippet isn't valid Squirrel
+    Syntax(Box<ParserErrorWithLocation>),
+    /// A snippet holds something else than a single expression or statement
+    NotSingleNode,
+    /// The search is an expression while the replacement is a statement
+    ExpectedExpression,
+    /// The replacement uses a metavariable the search doesn't bind
+    UnboundMetavariable(String),
+}
+
+impl Display for CodemodError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodemodError::Syntax(error) => write!(
+                f,
+                "{} at {}:{}",
+                error,
+                error.from.line + 1,
+                error.from.linechar + 1
+            ),
+            CodemodError::NotSingleNode => {
+                write!(f, "Expected a single expression or statement")
+            }
+            CodemodError::ExpectedExpression => {
+                write!(f, "Expected an expression to replace an expression")
+            }
+            CodemodError::UnboundMetavariable(name) => {
+                write!(f, "${} isn't bound by the search", name)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Snippet {
+    Expression(Expression),
+    Statement(Statement),
+}
+
+/// Structural search and replace rule, e.g. from `$obj.bindenv(this)` to
+/// `$obj.bindenv(this.env)`.
+///
+/// Both sides are Squirrel snippets holding a single expression or statement,
+/// where `$name` metavariables stand for any expression, statement or name.
+/// A metavariable used more than once only matches the same code each time.
+/// Matches are compared ignoring whitespace and comments, and replaced with
+/// the replacement as printed by the formatter, in parentheses when it binds
+/// looser than the code around the match. Matches nested in another
+/// match are left alone, as are matches holding comments, which the printed
+/// replacement would drop.
+#[derive(Debug)]
+pub struct Rule {
+    search: Snippet,
+    search_value: Value,
+    replace: Value,
+    dialect: Dialect,
+}
+
+/// Replacement of the text between two locations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edit {
+    pub from: Location,
+    pub to: Location,
+    pub text: String,
+}
+
+/// Replacements for the matches of a rule in a text.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Edits {
+    pub edits: Vec<Edit>,
+    /// Starts of the matches left alone because they hold comments
+    pub skipped: Vec<Location>,
+}
+
+impl Rule {
+    /// Rule for code written in `dialect`, which the snippets are parsed in too.
+    pub fn new(search: &str, replace: &str, dialect: Dialect) -> Result<Self, CodemodError> {
+        let search = parse_snippet(search, dialect)?;
+        let replace = match (&search, parse_snippet(replace, dialect)?) {
+            (Snippet::Expression(_), Snippet::Statement(_)) => {
+                return Err(CodemodError::ExpectedExpression)
+            }
+            (Snippet::Statement(_), Snippet::Expression(expression)) => {
+                let (from, to) = (expression.get_from(), expression.get_to());
+                Snippet::Statement(Statement::new_expression(expression, from, to))
+            }
+            (_, replace) => replace,
+        };
+
+        let search_value = snippet_value(&search);
+        let replace = snippet_value(&replace);
+
+        let mut bound = Vec::new();
+        collect_metavariables(&search_value, &mut bound);
+        let mut used = Vec::new();
+        collect_metavariables(&replace, &mut used);
+
+        if let Some(name) = used.into_iter().find(|name| !bound.contains(name)) {
+            return Err(CodemodError::UnboundMetavariable(name));
+        }
+
+        Ok(Self {
+            search,
+            search_value,
+            replace,
+            dialect,
+        })
+    }
+
+    /// Replacements for every match of the rule in the parsed text, in the
+    /// order they appear.
+    pub fn edits(&self, text: &str, statements: &Statements, options: &FormatterOptions) -> Edits {
+        let mut finder = MatchFinder {
+            rule: self,
+            matches: Vec::new(),
+        };
+        visit(statements, &mut finder);
+
+        let requirements = Requirements::new(statements);
+        let mut edits = Edits::default();
+        for (from, to, bindings) in finder.matches {
+            let span = (from.index, to.index);
+            let from = skip_whitespace(text, &from);
+            if has_comments(&text[from.index..to.index]) {
+                edits.skipped.push(from);
+                continue;
+            }
+
+            let replacement = substitute(&self.replace, &bindings, false);
+            let printed = match &self.search {
+                Snippet::Expression(_) => {
+                    let Ok(expression) = serde_json::from_value::<Expression>(replacement) else {
+                        continue;
+                    };
+                    let expression = requirements.parenthesize(expression, span);
+                    let (from, to) = (expression.get_from(), expression.get_to());
+                    print(Statement::new_expression(expression, from, to), options)
+                }
+                Snippet::Statement(_) => match serde_json::from_value(replacement) {
+                    Ok(statement) => print(statement, options),
+                    Err(_) => continue,
+                },
+            };
+
+            let text = indent(&printed, line_indentation(text, from.index));
+            edits.edits.push(Edit { from, to, text });
+        }
+
+        edits
+    }
+
+    /// Rewrites every match of the rule in the text, returning the rewritten
+    /// text and where the matches left alone start.
+    pub fn apply(
+        &self,
+        text: &str,
+        options: &FormatterOptions,
+    ) -> Result<(String, Vec<Location>), Box<ParserErrorWithLocation>> {
+        let statements = Parser::new(text)
+            .with_dialect(self.dialect)
+            .parse()
+            .map_err(Box::new)?;
+        let edits = self.edits(text, &statements, options);
+
+        Ok((apply_edits(text, &edits.edits), edits.skipped))
+    }
+}
+
+/// Applies edits sorted by position that don't overlap.
+pub fn apply_edits(text: &str, edits: &[Edit]) -> String {
+    let mut rewritten = String::with_capacity(text.len());
+    let mut position = 0;
+
+    for edit in edits {
+        rewritten.push_str(&text[position..edit.from.index]);
+        rewritten.push_str(&edit.text);
+        position = edit.to.index;
+    }
+
+    rewritten.push_str(&text[position..]);
+    rewritten
+}
+
+/// Finds the outermost nodes matching the search of a rule.
+struct MatchFinder<'r> {
+    rule: &'r Rule,
+    matches: Vec<(Location, Location, HashMap<String, Value>)>,
+}
+
+impl MatchFinder<'_> {
+    fn try_match(&mut self, value: Value, from: Location, to: Location) -> AstVisitorResult {
+        let mut bindings = HashMap::new();
+        if !matches(&self.rule.search_value, &value, &mut bindings) {
+            return AstVisitorResult::Continue;
+        }
+
+        self.matches.push((from, to, bindings));
+        AstVisitorResult::SkipChildren
+    }
+}
+
+impl AstVisitor for MatchFinder<'_> {
+    fn enter_statement(&mut self, statement: &Statement) -> AstVisitorResult {
+        let Snippet::Statement(search) = &self.rule.search else {
+            return AstVisitorResult::Continue;
+        };
+        if metavariable(&self.rule.search_value).is_none()
+            && std::mem::discriminant(search) != std::mem::discriminant(statement)
+        {
+            return AstVisitorResult::Continue;
+        }
+
+        let Ok(value) = serde_json::to_value(statement) else {
+            return AstVisitorResult::Continue;
+        };
+        self.try_match(value, statement.get_from(), statement.get_to())
+    }
+
+    fn enter_expression(&mut self, expression: &Expression) -> AstVisitorResult {
+        let Snippet::Expression(search) = &self.rule.search else {
+            return AstVisitorResult::Continue;
+        };
+        if metavariable(&self.rule.search_value).is_none()
+            && std::mem::discriminant(search) != std::mem::discriminant(expression)
+        {
+            return AstVisitorResult::Continue;
+        }
+
+        let Ok(value) = serde_json::to_value(expression) else {
+            return AstVisitorResult::Continue;
+        };
+        self.try_match(value, expression.get_from(), expression.get_to())
+    }
+}
+
+fn parse_snippet(snippet: &str, dialect: Dialect) -> Result<Snippet, CodemodError> {
+    let snippet = replace_metavariables(snippet);
+    let mut statements = Parser::new(&snippet)
+        .with_dialect(dialect)
+        .parse()
+        .map_err(|error| CodemodError::Syntax(Box::new(error)))?
+        .statements;
+
+    if statements.len() != 1 {
+        return Err(CodemodError::NotSingleNode);
+    }
+
+    match statements.remove(0) {
+        Statement::Expression(statement) => Ok(Snippet::Expression(statement.expression)),
+        statement => Ok(Snippet::Statement(statement)),
+    }
+}
+
+/// Turns the `$name` metavariables of a snippet into identifiers, leaving
+/// string and character literals and comments as they are.
+fn replace_metavariables(snippet: &str) -> String {
+    let mut replaced = String::with_capacity(snippet.len());
+    let mut chars = snippet.chars().peekable();
+
+    while let Some(char) = chars.next() {
+        replaced.push(char);
+
+        match char {
+            '"' | '\'' => {
+                // Verbatim strings escape quotes by doubling them instead
+                let verbatim = char == '"' && replaced.ends_with("@\"");
+                while let Some(next) = chars.next() {
+                    replaced.push(next);
+                    match next {
+                        '\\' if !verbatim => replaced.extend(chars.next()),
+                        '"' if verbatim && chars.peek() == Some(&'"') => {
+                            replaced.extend(chars.next())
+                        }
+                        next if next == char => break,
+                        _ => {}
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                while let Some(next) = chars.next_if(|next| *next != '\n') {
+                    replaced.push(next);
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                replaced.extend(chars.next());
+                while let Some(next) = chars.next() {
+                    replaced.push(next);
+                    if next == '*' && chars.peek() == Some(&'/') {
+                        replaced.extend(chars.next());
+                        break;
+                    }
+                }
+            }
+            '$' if chars
+                .peek()
+                .is_some_and(|next| next.is_ascii_alphabetic() || *next == '_') =>
+            {
+                replaced.pop();
+                replaced.push_str(METAVARIABLE_PREFIX);
+            }
+            _ => {}
+        }
+    }
+
+    replaced
+}
+
+fn snippet_value(snippet: &Snippet) -> Value {
+    match snippet {
+        Snippet::Expression(expression) => serde_json::to_value(expression),
+        Snippet::Statement(statement) => serde_json::to_value(statement),
+    }
+    .unwrap_or_default()
+}
+
+/// Whether the node is a string literal, whose value is never a metavariable.
+fn is_string_literal(value: &Value) -> bool {
+    value.get("StringLiteral").is_some() || value.get("MutliLineStringLiteral").is_some()
+}
+
+/// Name of the metavariable standing for a whole expression or statement, or
+/// for the name of a member or local.
+fn metavariable(value: &Value) -> Option<&str> {
+    let name = match value {
+        Value::String(name) => name,
+        Value::Object(object) => {
+            if let Some(statement) = object.get("Expression") {
+                return metavariable(statement.get("expression")?).filter(|_| object.len() == 1);
+            }
+            match object.get("Identifier")?.get("token")?.get("Identifier")? {
+                Value::String(name) => name,
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+
+    name.strip_prefix(METAVARIABLE_PREFIX)
+}
+
+fn collect_metavariables(value: &Value, names: &mut Vec<String>) {
+    if let Some(name) = metavariable(value) {
+        names.push(name.to_string());
+        return;
+    }
+
+    match value {
+        _ if is_string_literal(value) => {}
+        Value::Object(object) => object
+            .values()
+            .for_each(|value| collect_metavariables(value, names)),
+        Value::Array(values) => values
+            .iter()
+            .for_each(|value| collect_metavariables(value, names)),
+        _ => {}
+    }
+}
+
+fn is_location(key: &str) -> bool {
+    key == "from" || key == "to"
+}
+
+/// Whether the node has the shape of the pattern, binding the metavariables
+/// of the pattern to the code they stand for.
+fn matches(pattern: &Value, node: &Value, bindings: &mut HashMap<String, Value>) -> bool {
+    if let Some(name) = metavariable(pattern) {
+        if pattern.is_string() != node.is_string() {
+            return false;
+        }
+
+        return match bindings.get(name) {
+            Some(bound) => same(bound, node),
+            None => {
+                bindings.insert(name.to_string(), node.clone());
+                true
+            }
+        };
+    }
+
+    match (pattern, node) {
+        _ if is_string_literal(pattern) => same(pattern, node),
+        (Value::Object(pattern), Value::Object(node)) => {
+            pattern.len() == node.len()
+                && pattern.iter().all(|(key, pattern)| {
+                    is_location(key)
+                        || node
+                            .get(key)
+                            .is_some_and(|node| matches(pattern, node, bindings))
+                })
+        }
+        (Value::Array(pattern), Value::Array(node)) => {
+            pattern.len() == node.len()
+                && pattern
+                    .iter()
+                    .zip(node)
+                    .all(|(pattern, node)| matches(pattern, node, bindings))
+        }
+        (pattern, node) => pattern == node,
+    }
+}
+
+/// Whether two nodes only differ in their locations.
+fn same(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, a)| is_location(key) || b.get(key).is_some_and(|b| same(a, b)))
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same(a, b))
+        }
+        (a, b) => a == b,
+    }
+}
+
+/// The replacement with its metavariables replaced by the code they were bound
+/// to. `tight` tells whether the value is the operand of an operator or access.
+fn substitute(value: &Value, bindings: &HashMap<String, Value>, tight: bool) -> Value {
+    if let Some(name) = metavariable(value) {
+        let Some(bound) = bindings.get(name) else {
+            return value.clone();
+        };
+
+        return match (value, bound) {
+            // A name used where an expression is expected, or the other way around
+            (Value::Object(_), Value::String(name)) => {
+                let mut value = value.clone();
+                value["Identifier"]["token"]["Identifier"] = Value::String(name.clone());
+                value
+            }
+            (Value::String(_), Value::Object(_)) => match identifier_name(bound) {
+                Some(name) => Value::String(name),
+                None => value.clone(),
+            },
+            (_, bound) if tight && needs_grouping(bound) => grouping(bound),
+            (_, bound) => bound.clone(),
+        };
+    }
+
+    match value {
+        _ if is_string_literal(value) => value.clone(),
+        Value::Object(object) => {
+            let variant = object
+                .iter()
+                .next()
+                .filter(|_| object.len() == 1)
+                .map(|(variant, _)| variant.as_str());
+
+            let substituted = object
+                .iter()
+                .map(|(key, child)| {
+                    let child = match (variant, child) {
+                        (Some(variant), Value::Object(fields))
+                            if TIGHT_EXPRESSIONS.contains(&variant) =>
+                        {
+                            Value::Object(
+                                fields
+                                    .iter()
+                                    .map(|(field, value)| {
+                                        let tight = field != "arguments" && field != "index";
+                                        (field.clone(), substitute(value, bindings, tight))
+                                    })
+                                    .collect(),
+                            )
+                        }
+                        _ => substitute(child, bindings, false),
+                    };
+                    (key.clone(), child)
+                })
+                .collect::<Map<_, _>>();
+
+            Value::Object(substituted)
+        }
+        Value::Array(values) => Value::Array(
+            values
+                .iter()
+                .map(|value| substitute(value, bindings, false))
+                .collect(),
+        ),
+        value => value.clone(),
+    }
+}
+
+/// Name of an identifier expression.
+fn identifier_name(value: &Value) -> Option<String> {
+    value
+        .get("Identifier")?
+        .get("token")?
+        .get("Identifier")?
+        .as_str()
+        .map(String::from)
+}
+
+fn needs_grouping(value: &Value) -> bool {
+    value.get("BinaryOperator").is_some() || value.get("TernaryOperator").is_some()
+}
+
+fn grouping(value: &Value) -> Value {
+    let node = value.as_object().and_then(|object| object.values().next());
+    let location = |key: &str| node.and_then(|node| node.get(key)).cloned();
+
+    serde_json::json!({
+        "Grouping": {
+            "expression": value,
+            "from": location("from"),
+            "to": location("to"),
+        }
+    })
+}
+
+fn print(statement: Statement, options: &FormatterOptions) -> String {
+    let (from, to) = (statement.get_from(), statement.get_to());
+    let statements = Statements {
+        statements: vec![statement],
+        from,
+        to,
+    };
+
+    Printer::new(64, options)
+        .print(&statements)
+        .trim_end()
+        .to_string()
+}
+
+/// Whether the code holds comments.
+fn has_comments(code: &str) -> bool {
+    Lexer::new(code, false).tokens().is_ok_and(|tokens| {
+        tokens
+            .iter()
+            .any(|token| matches!(token.token, Token::Comment(..) | Token::MultiLineComment(_)))
+    })
+}
+
+/// Leading whitespace of the line holding the position.
+fn line_indentation(text: &str, index: usize) -> &str {
+    let start = text[..index].rfind('\n').map_or(0, |newline| newline + 1);
+    let line = &text[start..];
+
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// Indents every line but the first, which takes the place of the match.
+fn indent(text: &str, indentation: &str) -> String {
+    text.split('\n')
+        .enumerate()
+        .map(|(number, line)| match number {
+            0 => line.to_string(),
+            _ if line.is_empty() => String::new(),
+            _ => format!("{}{}", indentation, line),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewrite(search: &str, replace: &str, text: &str) -> String {
+        Rule::new(search, replace, Dialect::default())
+            .unwrap()
+            .apply(text, &FormatterOptions::default())
+            .unwrap()
+            .0
+    }
+
+    #[test]
+    fn test_rewrite_with_metavariables() {
+        assert_eq!(
+            rewrite(
+                "$obj.bindenv(this)",
+                "$obj.bindenv(this.env)",
+                "local f = callbacks[i].bindenv(this) // keep\nfoo.bindenv(other)\n"
+            ),
+            "local f = callbacks[i].bindenv(this.env) // keep\nfoo.bindenv(other)\n"
+        );
+
+        // Names of members are metavariables too, and operands get parentheses
+        assert_eq!(
+            rewrite("$a.$method($b)", "$method($b * 2, $a)", "x.call(y + z)"),
+            "call((y + z) * 2, x)"
+        );
+    }
+
+    #[test]
+    fn test_replacements_get_parentheses_where_they_land() {
+        assert_eq!(
+            rewrite(
+                "foo($x)",
+                "$x + 3",
+                "a = foo(q) * 2\nb = -foo(q)\nc = foo(q)\n"
+            ),
+            "a = (q + 3) * 2\nb = -(q + 3)\nc = q + 3\n"
+        );
+        assert_eq!(rewrite("foo($x)", "$x * 3", "foo(q) + 2"), "q * 3 + 2");
+    }
+
+    #[test]
+    fn test_repeated_metavariables_match_the_same_code() {
+        let rule = Rule::new("$a = $a + 1", "$a++", Dialect::default()).unwrap();
+        let options = FormatterOptions::default();
+
+        assert_eq!(
+            rule.apply("x.y = x.y + 1\nx = y + 1\n", &options)
+                .unwrap()
+                .0,
+            "x.y++\nx = y + 1\n"
+        );
+
+        let text = "if (a) {\n  a.b = a.b + 1\n}";
+        let statements = Parser::new(text).parse().unwrap();
+        let edits = rule.edits(text, &statements, &options).edits;
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].from.line, 1);
+        assert_eq!(edits[0].text, "a.b++");
+    }
+
+    #[test]
+    fn test_statement_rules_and_errors() {
+        assert_eq!(
+            rewrite(
+                "while ($condition) $body",
+                "for (; $condition;) $body",
+                "  while (running) {\n    step()\n  }"
+            ),
+            "  for (; running;) {\n    step()\n  }"
+        );
+
+        assert!(matches!(
+            Rule::new("local a = 1; local b = 2", "a", Dialect::default()),
+            Err(CodemodError::NotSingleNode)
+        ));
+        assert!(matches!(
+            Rule::new("$a", "local b = $a", Dialect::default()),
+            Err(CodemodError::ExpectedExpression)
+        ));
+        assert!(matches!(
+            Rule::new("foo($a)", "bar($b)", Dialect::default()),
+            Err(CodemodError::UnboundMetavariable(name)) if name == "b"
+        ));
+        assert!(matches!(
+            Rule::new("foo(", "bar()", Dialect::default()),
+            Err(CodemodError::Syntax(_))
+        ));
+    }
+
+    #[test]
+    fn test_string_literals_are_not_metavariables() {
+        assert_eq!(
+            rewrite(
+                "log(\"$x\", $b)",
+                "log($b)",
+                "log(\"hello\", 2)\nlog(\"$x\", 3)\n"
+            ),
+            "log(\"hello\", 2)\nlog(3)\n"
+        );
+        assert_eq!(
+            rewrite("log($a, $b)", "log(\"$price\", $b)", "log(1, 2)"),
+            "log(\"$price\", 2)"
+        );
+    }
+
+    #[test]
+    fn test_matches_holding_comments_are_skipped() {
+        let rule = Rule::new(
+            "$obj.bindenv(this)",
+            "$obj.bindenv(this.env)",
+            Dialect::default(),
+        )
+        .unwrap();
+        let text = "a.bindenv(/* keep */ this)\nb.bindenv(this) // kept\n";
+
+        let (rewritten, skipped) = rule.apply(text, &FormatterOptions::default()).unwrap();
+
+        assert_eq!(
+            rewritten,
+            "a.bindenv(/* keep */ this)\nb.bindenv(this.env) // kept\n"
+        );
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].line, 0);
+    }
+
+    #[test]
+    fn test_rules_parse_in_their_dialect() {
+        assert!(matches!(
+            Rule::new("$a ?? $b", "$a", Dialect::default()),
+            Err(CodemodError::Syntax(_))
+        ));
+
+        let rule = Rule::new("$a ?? null", "$a", Dialect::Quirrel).unwrap();
+        let (rewritten, _) = rule
+            .apply("let x = y ?? null", &FormatterOptions::default())
+            .unwrap();
+        assert_eq!(rewritten, "let x = y");
+    }
+}
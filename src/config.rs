@@ -0,0 +1,156 @@
+use crate::{diagnostics::Severity, dialect::Dialect, squirrel_formatter::FormatterOptions};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Config {
+    pub diagnostics: DiagnosticsConfig,
+    /// Globals provided by the host application, e.g. native functions, which
+    /// are never reported as undeclared
+    pub api_definitions: Vec<String>,
+    /// Formatter options used when the document has no `.squirrelfmt.json` above it
+    pub formatting: FormatterOptions,
+    /// Variant of Squirrel the scripts are written for, `squirrel3` unless set
+    pub dialect: Dialect,
+}
+
+impl Config {
+    /// Reads the configuration sent by the client either as `initializationOptions`
+    /// or as `workspace/didChangeConfiguration` settings. Settings may be nested
+    /// under a `squirrel` key, which is what most editors do for per-language settings.
+    pub fn from_json(value: serde_json::Value) -> Option<Self> {
+        let value = match value.get("squirrel") {
+            Some(nested) => nested.clone(),
+            None => value,
+        };
+
+        serde_json::from_value(value).ok()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DiagnosticsConfig {
+    /// Operations whose operand types are known not to support them, e.g. `"a" - 1`
+    pub type_mismatch: RuleSeverity,
+    /// Names declared twice in the same table, class, enum, parameter list, switch or scope
+    pub duplicate_declaration: RuleSeverity,
+    /// `const` initializers that can't be evaluated at compile time
+    pub non_constant_initializer: RuleSeverity,
+    /// Integer division or modulo by a constant zero
+    pub division_by_zero: RuleSeverity,
+    /// Shifts by a constant amount outside of 0..63
+    pub shift_out_of_range: RuleSeverity,
+    /// `if`/`while` conditions that always evaluate to the same value
+    pub constant_condition: RuleSeverity,
+    /// Function parameters that are never read, unless their name starts with `_`
+    pub unused_parameter: RuleSeverity,
+    /// Functions declared inside another function that are never called
+    pub unused_function: RuleSeverity,
+    /// Classes declared inside a function or bound to a `local` that are never used
+    pub unused_class: RuleSeverity,
+    /// Class members whose name starts with `_` that are never accessed within the class
+    pub unused_private_member: RuleSeverity,
+    /// `catch` variables that are never read
+    pub unused_catch_variable: RuleSeverity,
+    /// `yield` outside of functions, `resume` on non-generators and generator calls used as values
+    pub generator_misuse: RuleSeverity,
+}
+
+impl Default for DiagnosticsConfig {
+    fn default() -> Self {
+        Self {
+            type_mismatch: RuleSeverity::Warning,
+            duplicate_declaration: RuleSeverity::Warning,
+            non_constant_initializer: RuleSeverity::Error,
+            division_by_zero: RuleSeverity::Warning,
+            shift_out_of_range: RuleSeverity::Warning,
+            constant_condition: RuleSeverity::Warning,
+            unused_parameter: RuleSeverity::Hint,
+            unused_function: RuleSeverity::Warning,
+            unused_class: RuleSeverity::Warning,
+            unused_private_member: RuleSeverity::Warning,
+            unused_catch_variable: RuleSeverity::Hint,
+            generator_misuse: RuleSeverity::Warning,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleSeverity {
+    Off,
+    Hint,
+    Information,
+    Warning,
+    Error,
+}
+
+impl RuleSeverity {
+    pub fn to_severity(self) -> Option<Severity> {
+        match self {
+            RuleSeverity::Off => None,
+            RuleSeverity::Hint => Some(Severity::Hint),
+            RuleSeverity::Information => Some(Severity::Information),
+            RuleSeverity::Warning => Some(Severity::Warning),
+            RuleSeverity::Error => Some(Severity::Error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::squirrel_formatter::{BraceStyle, IndentStyle};
+
+    use super::*;
+
+    #[test]
+    fn test_config_from_nested_settings() {
+        let value = serde_json::json!({
+            "squirrel": {
+                "diagnostics": {
+                    "typeMismatch": "error"
+                }
+            }
+        });
+
+        let config = Config::from_json(value).unwrap();
+
+        assert_eq!(config.diagnostics.type_mismatch, RuleSeverity::Error);
+    }
+
+    #[test]
+    fn test_config_formatting() {
+        let value = serde_json::json!({
+            "formatting": {
+                "indentStyle": "tabs",
+                "braceStyle": "nextLine",
+                "trailingCommas": true
+            }
+        });
+
+        let config = Config::from_json(value).unwrap();
+
+        assert_eq!(config.formatting.indent_style, Some(IndentStyle::Tabs));
+        assert_eq!(config.formatting.indent_width, None);
+        assert_eq!(config.formatting.brace_style, BraceStyle::NextLine);
+        assert!(config.formatting.trailing_commas);
+    }
+
+    #[test]
+    fn test_config_dialect() {
+        let value = serde_json::json!({ "dialect": "quirrel" });
+
+        let config = Config::from_json(value).unwrap();
+
+        assert_eq!(config.dialect, Dialect::Quirrel);
+        assert!(Config::from_json(serde_json::json!({ "dialect": "lua" })).is_none());
+    }
+
+    #[test]
+    fn test_config_defaults() {
+        let config = Config::from_json(serde_json::json!({})).unwrap();
+
+        assert_eq!(config, Config::default());
+    }
+}
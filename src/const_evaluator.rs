@@ -0,0 +1,701 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{
+    ast_visitor::{visit, AstVisitor, AstVisitorResult},
+    config::DiagnosticsConfig,
+    diagnostics::{report, Diagnostic},
+    grammar::expressions::*,
+    grammar::statements::*,
+    scope_table::{DeclarationType, ScopeTable, VariableDeclarationKind, VariableUsage},
+    squirrel_lexer::Operator,
+};
+
+/// Const declarations may refer to other consts, this bounds how many are followed
+/// so cycles like `const A = B; const B = A;` terminate.
+const MAX_EVALUATION_DEPTH: usize = 32;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstValue {
+    Null,
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+}
+
+impl ConstValue {
+    /// Squirrel treats `null`, `false`, `0` and `0.0` as false, everything else as true.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            ConstValue::Null => false,
+            ConstValue::Integer(value) => *value != 0,
+            ConstValue::Float(value) => *value != 0.0,
+            ConstValue::Bool(value) => *value,
+            ConstValue::String(_) => true,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ConstValue::Null => "null",
+            ConstValue::Integer(_) => "integer",
+            ConstValue::Float(_) => "float",
+            ConstValue::Bool(_) => "bool",
+            ConstValue::String(_) => "string",
+        }
+    }
+
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            ConstValue::Integer(value) => Some(*value as f64),
+            ConstValue::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+/// Formats the value the way it would be written in source.
+impl Display for ConstValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConstValue::Null => write!(f, "null"),
+            ConstValue::Integer(value) => write!(f, "{}", value),
+            ConstValue::Float(value) if value.fract() == 0.0 => write!(f, "{:.1}", value),
+            ConstValue::Float(value) => write!(f, "{}", value),
+            ConstValue::Bool(value) => write!(f, "{}", value),
+            ConstValue::String(value) => write!(f, "\"{}\"", value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstError {
+    NotConstant,
+    DivisionByZero,
+    ShiftOutOfRange(i64),
+}
+
+pub struct ConstEvaluator<'a> {
+    scope_table: &'a ScopeTable,
+    usages: HashMap<usize, &'a VariableUsage>,
+    enums: HashMap<usize, &'a EnumStatement>,
+}
+
+/// Collects enum statements keyed by the position of their name. This walks the
+/// statements by hand since visitor callbacks don't hand out references that
+/// outlive the traversal. Enums are only allowed as statements, so walking
+/// statement bodies is enough.
+fn collect_enums<'a>(statements: &'a Statements, enums: &mut HashMap<usize, &'a EnumStatement>) {
+    for statement in &statements.statements {
+        collect_statement_enums(statement, enums);
+    }
+}
+
+fn collect_statement_enums<'a>(
+    statement: &'a Statement,
+    enums: &mut HashMap<usize, &'a EnumStatement>,
+) {
+    match statement {
+        Statement::Enum(enum_statement) => {
+            enums.insert(enum_statement.name.get_from().index, enum_statement);
+        }
+        Statement::Block(block) => collect_enums(&block.statements, enums),
+        Statement::If(if_statement) => {
+            collect_statement_enums(&if_statement.if_true, enums);
+            if let Some(if_false) = &if_statement.if_false {
+                collect_statement_enums(if_false, enums);
+            }
+        }
+        Statement::While(while_statement) => {
+            collect_statement_enums(&while_statement.statement, enums)
+        }
+        Statement::DoWhile(do_while_statement) => {
+            collect_statement_enums(&do_while_statement.statement, enums)
+        }
+        Statement::For(for_statement) => collect_statement_enums(&for_statement.statement, enums),
+        Statement::ForEach(for_each_statement) => {
+            collect_statement_enums(&for_each_statement.statement, enums)
+        }
+        Statement::Switch(switch_statement) => {
+            for case in &switch_statement.cases {
+                collect_enums(&case.statements, enums);
+            }
+            if let Some(default) = &switch_statement.default {
+                collect_enums(default, enums);
+            }
+        }
+        Statement::TryCatch(try_catch_statement) => {
+            collect_statement_enums(&try_catch_statement.try_statement, enums);
+            collect_statement_enums(&try_catch_statement.catch_statement, enums);
+        }
+        Statement::FunctionDefinition(function) => {
+            collect_statement_enums(&function.statement, enums)
+        }
+        _ => {}
+    }
+}
+
+impl<'a> ConstEvaluator<'a> {
+    pub fn new(statements: &'a Statements, scope_table: &'a ScopeTable) -> Self {
+        let mut enums = HashMap::new();
+        collect_enums(statements, &mut enums);
+
+        Self {
+            scope_table,
+            usages: scope_table.usages_by_index(),
+            enums,
+        }
+    }
+
+    pub fn evaluate(&self, expression: &Expression) -> Result<ConstValue, ConstError> {
+        self.evaluate_with_depth(expression, 0)
+    }
+
+    /// Values of all members of an enum. Members without an explicit value are
+    /// numbered by their own counter, so in `{ A, B = 10, C }` `C` is `1`.
+    pub fn enum_values(
+        &self,
+        statement: &EnumStatement,
+    ) -> Vec<(String, Result<ConstValue, ConstError>)> {
+        let mut counter = 0;
+
+        statement
+            .enumerations
+            .iter()
+            .map(|enumeration| {
+                let value = match &enumeration.value {
+                    Some(value) => self.evaluate(value),
+                    None => {
+                        counter += 1;
+                        Ok(ConstValue::Integer(counter - 1))
+                    }
+                };
+
+                (identifier_name(&enumeration.name), value)
+            })
+            .collect()
+    }
+
+    /// Finds the enum statement declared by the declaration at `index`.
+    pub fn enum_statement(&self, index: usize) -> Option<&'a EnumStatement> {
+        self.enums.get(&index).copied()
+    }
+
+    /// Finds the enum statement declaring the enumeration whose name is at `index`.
+    pub fn enum_containing(&self, index: usize) -> Option<&'a EnumStatement> {
+        self.enums.values().copied().find(|statement| {
+            statement
+                .enumerations
+                .iter()
+                .any(|enumeration| enumeration.name.get_from().index == index)
+        })
+    }
+
+    fn evaluate_with_depth(
+        &self,
+        expression: &Expression,
+        depth: usize,
+    ) -> Result<ConstValue, ConstError> {
+        if depth > MAX_EVALUATION_DEPTH {
+            return Err(ConstError::NotConstant);
+        }
+
+        match expression {
+            Expression::NullLiteral(_) => Ok(ConstValue::Null),
+            Expression::BooleanLiteral(boolean) => Ok(ConstValue::Bool(boolean.value)),
+            Expression::IntegerLiteral(integer) => Ok(ConstValue::Integer(integer.value)),
+            Expression::CharacterLiteral(character) => {
+                Ok(ConstValue::Integer(character.value as i64))
+            }
+            Expression::FloatLiteral(float) => Ok(ConstValue::Float(float.value)),
+            Expression::StringLiteral(string) => Ok(ConstValue::String(string.value.clone())),
+            Expression::MutliLineStringLiteral(string) => {
+                Ok(ConstValue::String(string.value.clone()))
+            }
+            Expression::Grouping(grouping) => match &grouping.expression {
+                Some(expression) => self.evaluate_with_depth(expression, depth + 1),
+                None => Err(ConstError::NotConstant),
+            },
+            Expression::Identifier(identifier) => {
+                let usage = self
+                    .usages
+                    .get(&identifier.from.index)
+                    .ok_or(ConstError::NotConstant)?;
+
+                let Some(DeclarationType::Variable(scope, sequence_number)) = usage.declaration
+                else {
+                    return Err(ConstError::NotConstant);
+                };
+
+                let declaration = &self.scope_table.scopes()[scope].variables[sequence_number];
+
+                match (&declaration.kind, &declaration.value) {
+                    (VariableDeclarationKind::Const, Some(value)) => {
+                        self.evaluate_with_depth(value, depth + 1)
+                    }
+                    _ => Err(ConstError::NotConstant),
+                }
+            }
+            Expression::MemberAccess(access) => {
+                let Expression::Identifier(identifier) = &access.expression else {
+                    return Err(ConstError::NotConstant);
+                };
+
+                let statement = self
+                    .usages
+                    .get(&identifier.from.index)
+                    .and_then(|usage| match usage.declaration {
+                        Some(DeclarationType::Variable(scope, sequence_number)) => {
+                            Some(&self.scope_table.scopes()[scope].variables[sequence_number])
+                        }
+                        _ => None,
+                    })
+                    .filter(|declaration| declaration.kind == VariableDeclarationKind::Enum)
+                    .and_then(|declaration| self.enum_statement(declaration.from.index))
+                    .ok_or(ConstError::NotConstant)?;
+
+                self.enum_values(statement)
+                    .into_iter()
+                    .find(|(name, _)| *name == access.member)
+                    .map(|(_, value)| value)
+                    .unwrap_or(Err(ConstError::NotConstant))
+            }
+            Expression::UnaryOperator(unary) => {
+                let value = self.evaluate_with_depth(&unary.expression, depth + 1)?;
+
+                match (&unary.operator, value) {
+                    (Operator::Minus, ConstValue::Integer(value)) => {
+                        Ok(ConstValue::Integer(value.wrapping_neg()))
+                    }
+                    (Operator::Minus, ConstValue::Float(value)) => Ok(ConstValue::Float(-value)),
+                    (Operator::Not, value) => Ok(ConstValue::Bool(!value.is_truthy())),
+                    (Operator::BitwiseNot, ConstValue::Integer(value)) => {
+                        Ok(ConstValue::Integer(!value))
+                    }
+                    (Operator::Typeof, value) => {
+                        Ok(ConstValue::String(value.type_name().to_string()))
+                    }
+                    _ => Err(ConstError::NotConstant),
+                }
+            }
+            Expression::BinaryOperator(binary) => self.evaluate_binary(binary, depth),
+            Expression::TernaryOperator(ternary) => {
+                if self
+                    .evaluate_with_depth(&ternary.condition, depth + 1)?
+                    .is_truthy()
+                {
+                    self.evaluate_with_depth(&ternary.if_true, depth + 1)
+                } else {
+                    self.evaluate_with_depth(&ternary.if_false, depth + 1)
+                }
+            }
+            _ => Err(ConstError::NotConstant),
+        }
+    }
+
+    fn evaluate_binary(
+        &self,
+        binary: &BinaryOperatorExpression,
+        depth: usize,
+    ) -> Result<ConstValue, ConstError> {
+        let left = self.evaluate_with_depth(&binary.left, depth + 1)?;
+
+        // Logical operators return one of their operands and short-circuit
+        match binary.operator {
+            Operator::And if !left.is_truthy() => return Ok(left),
+            Operator::Or if left.is_truthy() => return Ok(left),
+            Operator::NullCoalesce if left != ConstValue::Null => return Ok(left),
+            Operator::And | Operator::Or | Operator::NullCoalesce => {
+                return self.evaluate_with_depth(&binary.right, depth + 1)
+            }
+            _ => {}
+        }
+
+        let right = self.evaluate_with_depth(&binary.right, depth + 1)?;
+
+        use ConstValue::*;
+
+        match (&binary.operator, left, right) {
+            (Operator::Plus, String(l), r) => concatenate(l, &r, false),
+            (Operator::Plus, l, String(r)) => concatenate(r, &l, true),
+            (Operator::Plus, Integer(l), Integer(r)) => Ok(Integer(l.wrapping_add(r))),
+            (Operator::Minus, Integer(l), Integer(r)) => Ok(Integer(l.wrapping_sub(r))),
+            (Operator::Multiply, Integer(l), Integer(r)) => Ok(Integer(l.wrapping_mul(r))),
+            (Operator::Divide | Operator::Modulo, Integer(_), Integer(0)) => {
+                Err(ConstError::DivisionByZero)
+            }
+            (Operator::Divide, Integer(l), Integer(r)) => Ok(Integer(l.wrapping_div(r))),
+            (Operator::Modulo, Integer(l), Integer(r)) => Ok(Integer(l.wrapping_rem(r))),
+            (
+                operator @ (Operator::Plus
+                | Operator::Minus
+                | Operator::Multiply
+                | Operator::Divide
+                | Operator::Modulo),
+                l,
+                r,
+            ) => {
+                let (l, r) = (
+                    l.as_float().ok_or(ConstError::NotConstant)?,
+                    r.as_float().ok_or(ConstError::NotConstant)?,
+                );
+
+                Ok(Float(match operator {
+                    Operator::Plus => l + r,
+                    Operator::Minus => l - r,
+                    Operator::Multiply => l * r,
+                    Operator::Divide => l / r,
+                    _ => l % r,
+                }))
+            }
+            (
+                Operator::LeftShift | Operator::RightShift | Operator::UnsignedRightShift,
+                Integer(_),
+                Integer(r),
+            ) if !(0..64).contains(&r) => Err(ConstError::ShiftOutOfRange(r)),
+            (Operator::LeftShift, Integer(l), Integer(r)) => Ok(Integer(l << r)),
+            (Operator::RightShift, Integer(l), Integer(r)) => Ok(Integer(l >> r)),
+            (Operator::UnsignedRightShift, Integer(l), Integer(r)) => {
+                Ok(Integer(((l as u64) >> r) as i64))
+            }
+            (Operator::BitwiseAnd, Integer(l), Integer(r)) => Ok(Integer(l & r)),
+            (Operator::BitwiseOr, Integer(l), Integer(r)) => Ok(Integer(l | r)),
+            (Operator::BitwiseXor, Integer(l), Integer(r)) => Ok(Integer(l ^ r)),
+            (operator, l, r) => match compare(&l, &r) {
+                Some(ordering) => match operator {
+                    Operator::Equal => Ok(Bool(ordering.is_eq())),
+                    Operator::NotEqual => Ok(Bool(ordering.is_ne())),
+                    Operator::Less => Ok(Bool(ordering.is_lt())),
+                    Operator::LessEqual => Ok(Bool(ordering.is_le())),
+                    Operator::Greater => Ok(Bool(ordering.is_gt())),
+                    Operator::GreaterEqual => Ok(Bool(ordering.is_ge())),
+                    Operator::ThreeWayComparison => Ok(Integer(ordering as i64)),
+                    _ => Err(ConstError::NotConstant),
+                },
+                None => match operator {
+                    // Values of different types are never equal
+                    Operator::Equal => Ok(Bool(false)),
+                    Operator::NotEqual => Ok(Bool(true)),
+                    _ => Err(ConstError::NotConstant),
+                },
+            },
+        }
+    }
+}
+
+pub fn identifier_name(expression: &Expression) -> String {
+    match expression {
+        Expression::Identifier(identifier) => identifier.token.to_string(),
+        _ => String::new(),
+    }
+}
+
+fn concatenate(
+    string: String,
+    other: &ConstValue,
+    prepend: bool,
+) -> Result<ConstValue, ConstError> {
+    let other = match other {
+        ConstValue::String(value) => value.clone(),
+        // Float formatting depends on the host's printf, so those aren't folded
+        ConstValue::Float(_) => return Err(ConstError::NotConstant),
+        value => value.to_string(),
+    };
+
+    Ok(ConstValue::String(if prepend {
+        other + &string
+    } else {
+        string + &other
+    }))
+}
+
+fn compare(left: &ConstValue, right: &ConstValue) -> Option<std::cmp::Ordering> {
+    match (left, right) {
+        (ConstValue::String(l), ConstValue::String(r)) => Some(l.cmp(r)),
+        (ConstValue::Bool(l), ConstValue::Bool(r)) => Some(l.cmp(r)),
+        (ConstValue::Null, ConstValue::Null) => Some(std::cmp::Ordering::Equal),
+        (ConstValue::Integer(l), ConstValue::Integer(r)) => Some(l.cmp(r)),
+        (l, r) => l.as_float()?.partial_cmp(&r.as_float()?),
+    }
+}
+
+/// Reports const declarations that can't be evaluated at compile time, operations
+/// that would fail at runtime with constant operands and conditions that never change.
+pub struct ConstChecker<'a> {
+    evaluator: ConstEvaluator<'a>,
+    config: &'a DiagnosticsConfig,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> ConstChecker<'a> {
+    pub fn new(
+        statements: &'a Statements,
+        scope_table: &'a ScopeTable,
+        config: &'a DiagnosticsConfig,
+    ) -> Self {
+        Self {
+            evaluator: ConstEvaluator::new(statements, scope_table),
+            config,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    pub fn check(mut self, statements: &Statements) -> Vec<Diagnostic> {
+        visit(statements, &mut self);
+        self.diagnostics
+    }
+
+    fn check_condition(&mut self, condition: &Expression) {
+        if let Ok(value) = self.evaluator.evaluate(condition) {
+            report(
+                &mut self.diagnostics,
+                self.config.constant_condition.to_severity(),
+                "constant-condition",
+                &condition.get_from(),
+                &condition.get_to(),
+                format!("Condition is always {}", value.is_truthy()),
+            );
+        }
+    }
+}
+
+impl AstVisitor for ConstChecker<'_> {
+    fn enter_const_statement(&mut self, statement: &ConstStatement) -> AstVisitorResult {
+        if self.evaluator.evaluate(&statement.expression) == Err(ConstError::NotConstant) {
+            report(
+                &mut self.diagnostics,
+                self.config.non_constant_initializer.to_severity(),
+                "non-constant-initializer",
+                &statement.expression.get_from(),
+                &statement.expression.get_to(),
+                format!(
+                    "Initializer of const '{}' is not a constant expression",
+                    identifier_name(&statement.name)
+                ),
+            );
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_binary_operator_expression(
+        &mut self,
+        expression: &BinaryOperatorExpression,
+    ) -> AstVisitorResult {
+        let right = self.evaluator.evaluate(&expression.right);
+
+        match (&expression.operator, right) {
+            (
+                Operator::Divide | Operator::DivideEqual | Operator::Modulo | Operator::ModuloEqual,
+                Ok(ConstValue::Integer(0)),
+            ) => {
+                report(
+                    &mut self.diagnostics,
+                    self.config.division_by_zero.to_severity(),
+                    "division-by-zero",
+                    &expression.from,
+                    &expression.to,
+                    "Division by zero".to_string(),
+                );
+            }
+            (
+                Operator::LeftShift | Operator::RightShift | Operator::UnsignedRightShift,
+                Ok(ConstValue::Integer(shift)),
+            ) if !(0..64).contains(&shift) => {
+                report(
+                    &mut self.diagnostics,
+                    self.config.shift_out_of_range.to_severity(),
+                    "shift-out-of-range",
+                    &expression.from,
+                    &expression.to,
+                    format!("Shift amount {} is out of range 0..63", shift),
+                );
+            }
+            _ => {}
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_if_statement(&mut self, statement: &IfStatement) -> AstVisitorResult {
+        self.check_condition(&statement.condition);
+        AstVisitorResult::Continue
+    }
+
+    fn enter_while_statement(&mut self, statement: &WhileStatement) -> AstVisitorResult {
+        // `while (true)` and `while (1)` are the idiomatic infinite loops
+        let is_infinite_loop_idiom = match &statement.condition {
+            Expression::BooleanLiteral(boolean) => boolean.value,
+            Expression::IntegerLiteral(integer) => integer.value != 0,
+            _ => false,
+        };
+
+        if !is_infinite_loop_idiom {
+            self.check_condition(&statement.condition);
+        }
+
+        AstVisitorResult::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        config::DiagnosticsConfig, diagnostics::Diagnostic, grammar::statements::Statement,
+        scope_table::ScopeTable, squirrel_parser::Parser,
+    };
+
+    use super::{ConstChecker, ConstError, ConstEvaluator, ConstValue};
+
+    fn check(input: &str) -> Vec<Diagnostic> {
+        let mut parser = Parser::new(input);
+        let statements = parser.parse().unwrap();
+        let scope_table = ScopeTable::new(&statements);
+        let config = DiagnosticsConfig::default();
+
+        ConstChecker::new(&statements, &scope_table, &config).check(&statements)
+    }
+
+    #[test]
+    fn test_evaluate_const_values() {
+        let input = "
+const A = 2 + 3 * 4;
+const B = A / 4.0;
+const C = \"a\" + A;
+const D = (A > 10) && !false;
+const E = -(1 << 4) | 1;
+local f = B;
+local g = C;
+local h = D;
+local i = E;
+";
+        let mut parser = Parser::new(input);
+        let statements = parser.parse().unwrap();
+        let scope_table = ScopeTable::new(&statements);
+        let evaluator = ConstEvaluator::new(&statements, &scope_table);
+
+        let values = statements.statements[5..]
+            .iter()
+            .map(|statement| match statement {
+                Statement::Local(local) => evaluator
+                    .evaluate(local.initializations[0].expression.as_ref().unwrap())
+                    .unwrap(),
+                _ => panic!("Expected local statement"),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            values,
+            vec![
+                ConstValue::Float(3.5),
+                ConstValue::String("a14".to_string()),
+                ConstValue::Bool(true),
+                ConstValue::Integer(-15),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_evaluate_enum_values() {
+        let input = "
+enum Color {
+    Red,
+    Green = 10,
+    Blue
+}
+local a = Color.Blue;
+";
+        let mut parser = Parser::new(input);
+        let statements = parser.parse().unwrap();
+        let scope_table = ScopeTable::new(&statements);
+        let evaluator = ConstEvaluator::new(&statements, &scope_table);
+
+        let Statement::Enum(color) = &statements.statements[0] else {
+            panic!("Expected enum statement");
+        };
+
+        assert_eq!(
+            evaluator.enum_values(color),
+            vec![
+                ("Red".to_string(), Ok(ConstValue::Integer(0))),
+                ("Green".to_string(), Ok(ConstValue::Integer(10))),
+                ("Blue".to_string(), Ok(ConstValue::Integer(1))),
+            ]
+        );
+
+        let Statement::Local(local) = &statements.statements[1] else {
+            panic!("Expected local statement");
+        };
+
+        assert_eq!(
+            evaluator.evaluate(local.initializations[0].expression.as_ref().unwrap()),
+            Ok(ConstValue::Integer(1))
+        );
+    }
+
+    #[test]
+    fn test_evaluate_errors() {
+        let input = "
+local a = 1 / (2 - 2);
+local b = 1 << 64;
+";
+        let mut parser = Parser::new(input);
+        let statements = parser.parse().unwrap();
+        let scope_table = ScopeTable::new(&statements);
+        let evaluator = ConstEvaluator::new(&statements, &scope_table);
+
+        let errors = statements
+            .statements
+            .iter()
+            .map(|statement| match statement {
+                Statement::Local(local) => evaluator
+                    .evaluate(local.initializations[0].expression.as_ref().unwrap())
+                    .unwrap_err(),
+                _ => panic!("Expected local statement"),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            errors,
+            vec![ConstError::DivisionByZero, ConstError::ShiftOutOfRange(64)]
+        );
+    }
+
+    #[test]
+    fn test_const_diagnostics() {
+        let input = "
+const DEBUG = false;
+const SIZE = foo();
+local a = 10;
+local b = a / 0;
+local c = a >> -1;
+
+if (DEBUG) {
+    ::print(a);
+}
+
+while (true) {
+    break;
+}
+
+while (1 > 2) {
+    break;
+}
+";
+        let messages = check(input)
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            messages,
+            vec![
+                "Initializer of const 'SIZE' is not a constant expression",
+                "Division by zero",
+                "Shift amount -1 is out of range 0..63",
+                "Condition is always false",
+                "Condition is always false",
+            ]
+        );
+    }
+}
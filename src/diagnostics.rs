@@ -0,0 +1,262 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    code_actions::FixData,
+    config::Config,
+    const_evaluator, duplicate_checker, generator_checker,
+    scope_table::{self, ScopeTable},
+    squirrel_lexer::{skip_whitespace, Location},
+    squirrel_parser::{self, ParserError},
+    type_checker, unused_checker,
+};
+
+/// How serious a [`Diagnostic`] is, in the order editors rank them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+/// A problem found in a script, spanning the code between `from` and `to`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub from: Location,
+    pub to: Location,
+    pub severity: Severity,
+    /// Name of the check that found the problem, e.g. `unused-variable`
+    pub code: String,
+    pub message: String,
+    /// The code has no effect, editors show it faded out
+    pub unnecessary: bool,
+    /// What the quick fixes for the problem need to know about it
+    pub fix: Option<FixData>,
+}
+
+impl Diagnostic {
+    pub fn new(
+        severity: Severity,
+        code: &str,
+        from: &Location,
+        to: &Location,
+        message: String,
+    ) -> Self {
+        Self {
+            from: from.clone(),
+            to: to.clone(),
+            severity,
+            code: code.to_string(),
+            message,
+            unnecessary: false,
+            fix: None,
+        }
+    }
+}
+
+/// Adds a diagnostic unless its check is turned off, which is when `severity`
+/// is `None`. Returns the diagnostic so that callers can fill in the rest.
+pub(crate) fn report<'a>(
+    diagnostics: &'a mut Vec<Diagnostic>,
+    severity: Option<Severity>,
+    code: &str,
+    from: &Location,
+    to: &Location,
+    message: String,
+) -> Option<&'a mut Diagnostic> {
+    diagnostics.push(Diagnostic::new(severity?, code, from, to, message));
+    diagnostics.last_mut()
+}
+
+#[cfg(feature = "lsp")]
+impl From<Diagnostic> for tower_lsp::lsp_types::Diagnostic {
+    fn from(diagnostic: Diagnostic) -> Self {
+        use tower_lsp::lsp_types::{DiagnosticSeverity, DiagnosticTag, NumberOrString};
+
+        let severity = match diagnostic.severity {
+            Severity::Error => DiagnosticSeverity::ERROR,
+            Severity::Warning => DiagnosticSeverity::WARNING,
+            Severity::Information => DiagnosticSeverity::INFORMATION,
+            Severity::Hint => DiagnosticSeverity::HINT,
+        };
+
+        let mut converted = Self::new(
+            crate::refactor::to_range(&diagnostic.from, &diagnostic.to),
+            Some(severity),
+            Some(NumberOrString::String(diagnostic.code)),
+            None,
+            diagnostic.message,
+            None,
+            diagnostic
+                .unnecessary
+                .then(|| vec![DiagnosticTag::UNNECESSARY]),
+        );
+        converted.data = diagnostic.fix.and_then(|fix| fix.to_value());
+
+        converted
+    }
+}
+
+/// Parses the document and runs every checker over it, returning the syntax
+/// error if it doesn't parse.
+pub fn check(text: &str, config: &Config) -> Vec<Diagnostic> {
+    let mut parser = squirrel_parser::Parser::new(text).with_dialect(config.dialect);
+
+    let result = match parser.parse() {
+        Ok(result) => result,
+        Err(error) => {
+            // Code of another dialect is told apart so clients can offer to switch
+            let code = match error.error {
+                ParserError::UnavailableInDialect(_) => "dialect-unsupported",
+                _ => "syntax-error",
+            };
+
+            return vec![Diagnostic::new(
+                Severity::Error,
+                code,
+                &error.from,
+                &error.to,
+                error.details,
+            )];
+        }
+    };
+
+    let mut diagnostics = Vec::new();
+    let scope_table = scope_table::ScopeTable::new(&result);
+
+    let mut errors = validate_variables(text, &scope_table, &config.api_definitions);
+
+    diagnostics.append(&mut errors);
+
+    let mut type_errors = type_checker::TypeChecker::new(
+        text,
+        &scope_table,
+        config.diagnostics.type_mismatch.to_severity(),
+    )
+    .check(&result);
+
+    diagnostics.append(&mut type_errors);
+
+    let mut duplicate_errors = duplicate_checker::DuplicateChecker::new(
+        &scope_table,
+        config.diagnostics.duplicate_declaration.to_severity(),
+    )
+    .check(&result);
+
+    diagnostics.append(&mut duplicate_errors);
+
+    let mut const_errors =
+        const_evaluator::ConstChecker::new(&result, &scope_table, &config.diagnostics)
+            .check(&result);
+
+    diagnostics.append(&mut const_errors);
+
+    let mut unused_errors =
+        unused_checker::UnusedChecker::new(&scope_table, &config.diagnostics).check(&result);
+
+    diagnostics.append(&mut unused_errors);
+
+    let mut generator_errors = generator_checker::GeneratorChecker::new(
+        &result,
+        &scope_table,
+        config.diagnostics.generator_misuse.to_severity(),
+    )
+    .check(&result);
+
+    diagnostics.append(&mut generator_errors);
+
+    diagnostics
+}
+
+/// Reports unused locals and consts, and usages of variables that are
+/// neither declared nor listed in `api_definitions`. The diagnostics start at
+/// the name, leaving out the whitespace the declarations and usages of `text`
+/// start with.
+pub fn validate_variables(
+    text: &str,
+    scope_table: &ScopeTable,
+    api_definitions: &[String],
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    for variable in scope_table.unused_variables() {
+        let mut diagnostic = Diagnostic::new(
+            Severity::Warning,
+            "unused-variable",
+            &skip_whitespace(text, &variable.from),
+            &variable.to,
+            format!("Unused variable '{}'", variable.name),
+        );
+        diagnostic.unnecessary = true;
+        diagnostic.fix = Some(FixData::unused(scope_table, variable));
+
+        diagnostics.push(diagnostic);
+    }
+
+    for (scope, usage) in scope_table.undeclared_usages(api_definitions) {
+        let mut diagnostic = Diagnostic::new(
+            Severity::Error,
+            "undeclared-variable",
+            &skip_whitespace(text, &usage.from),
+            &usage.to,
+            format!("Variable '{}' is not declared", usage.name),
+        );
+        diagnostic.fix = Some(FixData::undeclared(
+            scope_table,
+            scope,
+            usage,
+            api_definitions,
+        ));
+
+        diagnostics.push(diagnostic);
+    }
+
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dialect::Dialect;
+
+    use super::*;
+
+    fn codes(text: &str) -> Vec<String> {
+        check(text, &Config::default())
+            .into_iter()
+            .map(|diagnostic| diagnostic.code)
+            .collect()
+    }
+
+    #[test]
+    fn test_check_reports_syntax_errors() {
+        assert_eq!(codes("local a = "), vec!["syntax-error"]);
+    }
+
+    #[test]
+    fn test_check_reports_constructs_of_other_dialects() {
+        assert_eq!(codes("let a = b ?? 1"), vec!["dialect-unsupported"]);
+
+        let config = Config {
+            dialect: Dialect::Quirrel,
+            ..Config::default()
+        };
+
+        assert!(check(
+            "let a = 1
+return a ?? 2",
+            &config
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_check_runs_every_checker() {
+        let codes = codes("local a = 1\nfoo(b)\nlocal t = {x = 1, x = 2}\n\"a\" - 1");
+
+        assert!(codes.contains(&"unused-variable".to_string()));
+        assert!(codes.contains(&"undeclared-variable".to_string()));
+        assert!(codes.contains(&"duplicate-declaration".to_string()));
+        assert!(codes.contains(&"type-mismatch".to_string()));
+    }
+}
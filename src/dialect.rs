@@ -0,0 +1,140 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::squirrel_lexer::Keyword;
+
+/// Variant of Squirrel a script is written for. It decides which keywords and
+/// operators the lexer recognizes and which constructs the parser accepts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Dialect {
+    /// Squirrel 2.x, without static members, `rawcall`, `__LINE__`, `__FILE__` and `<=>`
+    Squirrel2,
+    /// Squirrel 3.x
+    #[default]
+    Squirrel3,
+    /// Quirrel, adding null propagation, `??`, `let`, destructuring and imports to Squirrel 3
+    Quirrel,
+}
+
+/// Constructs that only some of the dialects have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    /// `static` class members
+    StaticMembers,
+    /// `a <=> b`
+    ThreeWayComparison,
+    /// `a?.b`, `a?[b]` and `a?(b)`
+    NullPropagation,
+    /// `a ?? b`
+    NullCoalescing,
+    /// `let a = 1`
+    Let,
+    /// `local {a, b} = table` and `local [a, b] = array`
+    Destructuring,
+    /// `import "module" as name` and `from "module" import name`
+    Import,
+}
+
+impl Dialect {
+    pub fn supports(self, feature: Feature) -> bool {
+        match feature {
+            Feature::StaticMembers | Feature::ThreeWayComparison => self != Dialect::Squirrel2,
+            Feature::NullPropagation
+            | Feature::NullCoalescing
+            | Feature::Let
+            | Feature::Destructuring
+            | Feature::Import => self == Dialect::Quirrel,
+        }
+    }
+
+    /// Whether the word is reserved in this dialect, others being identifiers.
+    pub fn has_keyword(self, keyword: &Keyword) -> bool {
+        match keyword {
+            Keyword::Static | Keyword::Line | Keyword::File | Keyword::Rawcall => {
+                self != Dialect::Squirrel2
+            }
+            Keyword::Let | Keyword::Import => self == Dialect::Quirrel,
+            _ => true,
+        }
+    }
+
+    /// Dialects having the feature, for suggesting one to switch to.
+    pub fn supporting(feature: Feature) -> Vec<Dialect> {
+        [Dialect::Squirrel2, Dialect::Squirrel3, Dialect::Quirrel]
+            .into_iter()
+            .filter(|dialect| dialect.supports(feature))
+            .collect()
+    }
+}
+
+impl Display for Dialect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Dialect::Squirrel2 => write!(f, "Squirrel 2"),
+            Dialect::Squirrel3 => write!(f, "Squirrel 3"),
+            Dialect::Quirrel => write!(f, "Quirrel"),
+        }
+    }
+}
+
+/// Parses the names used in the configuration, e.g. `quirrel`.
+impl FromStr for Dialect {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "squirrel2" => Ok(Dialect::Squirrel2),
+            "squirrel3" => Ok(Dialect::Squirrel3),
+            "quirrel" => Ok(Dialect::Quirrel),
+            _ => Err(format!(
+                "Unknown dialect {}, expected squirrel2, squirrel3 or quirrel",
+                value
+            )),
+        }
+    }
+}
+
+impl Display for Feature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Feature::StaticMembers => write!(f, "static class members"),
+            Feature::ThreeWayComparison => write!(f, "the '<=>' operator"),
+            Feature::NullPropagation => write!(f, "null propagation"),
+            Feature::NullCoalescing => write!(f, "the '??' operator"),
+            Feature::Let => write!(f, "'let' declarations"),
+            Feature::Destructuring => write!(f, "destructuring declarations"),
+            Feature::Import => write!(f, "imports"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dialect_features() {
+        assert!(!Dialect::Squirrel2.supports(Feature::StaticMembers));
+        assert!(Dialect::Squirrel3.supports(Feature::ThreeWayComparison));
+        assert!(!Dialect::Squirrel3.supports(Feature::NullCoalescing));
+        assert!(Dialect::Quirrel.supports(Feature::StaticMembers));
+        assert!(Dialect::Quirrel.supports(Feature::Destructuring));
+
+        assert_eq!(
+            Dialect::supporting(Feature::StaticMembers),
+            vec![Dialect::Squirrel3, Dialect::Quirrel]
+        );
+    }
+
+    #[test]
+    fn test_dialect_names() {
+        assert_eq!("quirrel".parse::<Dialect>(), Ok(Dialect::Quirrel));
+        assert!("squirrel4".parse::<Dialect>().is_err());
+        assert_eq!(
+            serde_json::to_value(Dialect::Squirrel2).unwrap(),
+            serde_json::json!("squirrel2")
+        );
+    }
+}
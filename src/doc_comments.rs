@@ -0,0 +1,142 @@
+use crate::squirrel_lexer::{Lexer, Token};
+
+#[derive(Debug)]
+struct Comment {
+    from_line: usize,
+    to_line: usize,
+    text: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParamAnnotation {
+    pub name: String,
+    pub types: Vec<String>,
+}
+
+/// Comments of a document, used to read documentation attached to declarations.
+/// A declaration's documentation is the run of comments ending on the line
+/// directly above it.
+#[derive(Debug)]
+pub struct DocComments {
+    comments: Vec<Comment>,
+}
+
+impl DocComments {
+    pub fn new(input: &str) -> Self {
+        let mut lexer = Lexer::new(input, false);
+        let mut comments = Vec::new();
+
+        while let Ok(token) = lexer.next() {
+            match token.token {
+                Token::Comment(text, _) | Token::MultiLineComment(text) => comments.push(Comment {
+                    from_line: token.from.line,
+                    to_line: token.to.line,
+                    text,
+                }),
+                Token::EOF => break,
+                _ => {}
+            }
+        }
+
+        Self { comments }
+    }
+
+    pub fn above(&self, line: usize) -> Vec<&str> {
+        let mut lines = Vec::new();
+        let mut line = line;
+
+        while line > 0 {
+            let comment = self.comments.iter().rev().find(|c| c.to_line == line - 1);
+
+            match comment {
+                Some(comment) => {
+                    lines.push(comment.text.as_str());
+                    line = comment.from_line;
+                }
+                None => break,
+            }
+        }
+
+        lines.reverse();
+        lines
+    }
+
+    pub fn param_annotations(&self, line: usize) -> Vec<ParamAnnotation> {
+        self.above(line)
+            .iter()
+            .flat_map(|comment| comment.lines())
+            .filter_map(parse_param_annotation)
+            .collect()
+    }
+}
+
+/// Parses `@param {type} name` and `@param name {type}`, where the type may be
+/// a union such as `{string|null}`.
+fn parse_param_annotation(line: &str) -> Option<ParamAnnotation> {
+    let rest = line[line.find("@param")? + "@param".len()..].trim_start();
+
+    let (types, name) = if let Some(rest) = rest.strip_prefix('{') {
+        let end = rest.find('}')?;
+        (&rest[..end], rest[end + 1..].split_whitespace().next()?)
+    } else {
+        let name = rest.split_whitespace().next()?;
+        let rest = rest[name.len()..].trim_start().strip_prefix('{')?;
+        let end = rest.find('}')?;
+        (&rest[..end], name)
+    };
+
+    Some(ParamAnnotation {
+        name: name.to_string(),
+        types: types.split('|').map(|t| t.trim().to_string()).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DocComments, ParamAnnotation};
+
+    #[test]
+    fn test_param_annotations() {
+        let input = "
+local a = 1;
+/**
+ * Does things
+ * @param {string|null} name
+ * @param count {integer}
+ */
+// @param {float} ratio
+function foo(name, count, ratio) {}
+";
+        let doc_comments = DocComments::new(input);
+
+        assert_eq!(
+            doc_comments.param_annotations(8),
+            vec![
+                ParamAnnotation {
+                    name: "name".to_string(),
+                    types: vec!["string".to_string(), "null".to_string()],
+                },
+                ParamAnnotation {
+                    name: "count".to_string(),
+                    types: vec!["integer".to_string()],
+                },
+                ParamAnnotation {
+                    name: "ratio".to_string(),
+                    types: vec!["float".to_string()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_detached_comments_are_ignored() {
+        let input = "
+// @param {string} name
+
+function foo(name) {}
+";
+        let doc_comments = DocComments::new(input);
+
+        assert_eq!(doc_comments.param_annotations(3), vec![]);
+    }
+}
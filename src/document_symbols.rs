@@ -0,0 +1,421 @@
+use tower_lsp::lsp_types::{DocumentSymbol, Position, Range, SymbolKind};
+
+use crate::{
+    ast_visitor::{visit, AstVisitor, AstVisitorResult},
+    const_evaluator::identifier_name,
+    generator_checker::Generators,
+    grammar::{expressions::TableExpression, statements::*},
+    squirrel_formatter::FormatterOptions,
+    squirrel_lexer::Location,
+    squirrel_printer::Printer,
+};
+
+/// Symbols declared within one function or class, the root script being the
+/// outermost frame. Anonymous functions and classes don't get a symbol, their
+/// children are added to the enclosing frame instead.
+struct Frame {
+    symbol: Option<DocumentSymbol>,
+    children: Vec<DocumentSymbol>,
+    is_class: bool,
+}
+
+struct SymbolCollector {
+    generators: Generators,
+    frames: Vec<Frame>,
+}
+
+impl SymbolCollector {
+    fn push_frame(&mut self, symbol: Option<DocumentSymbol>, is_class: bool) {
+        self.frames.push(Frame {
+            symbol,
+            children: Vec::new(),
+            is_class,
+        });
+    }
+
+    fn pop_frame(&mut self) {
+        let Some(mut frame) = self.frames.pop() else {
+            return;
+        };
+
+        frame
+            .children
+            .sort_by_key(|child| (child.range.start.line, child.range.start.character));
+
+        let Some(parent) = self.frames.last_mut() else {
+            return;
+        };
+
+        match frame.symbol {
+            Some(mut symbol) => {
+                if !frame.children.is_empty() {
+                    symbol.children = Some(frame.children);
+                }
+                parent.children.push(symbol);
+            }
+            None => parent.children.append(&mut frame.children),
+        }
+    }
+
+    fn add(&mut self, symbol: DocumentSymbol) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.children.push(symbol);
+        }
+    }
+
+    fn in_class(&self) -> bool {
+        self.frames.last().is_some_and(|frame| frame.is_class)
+    }
+}
+
+impl AstVisitor for SymbolCollector {
+    fn enter_function_declaration(&mut self, function: &FunctionDefinition) -> AstVisitorResult {
+        let (name, kind) = match &function.name {
+            Some(name) if self.in_class() => (identifier_name(name), SymbolKind::METHOD),
+            Some(name) => (identifier_name(name), SymbolKind::FUNCTION),
+            None if self.in_class() => ("constructor".to_string(), SymbolKind::CONSTRUCTOR),
+            None => {
+                self.push_frame(None, false);
+                return AstVisitorResult::Continue;
+            }
+        };
+
+        let detail = join_detail(
+            self.generators
+                .is_generator(function)
+                .then(|| "generator".to_string()),
+            &function.attributes,
+        );
+
+        let selection = match &function.name {
+            Some(name) => (name.get_from(), name.get_to()),
+            None => (function.from.clone(), function.from.clone()),
+        };
+
+        let function_symbol = symbol(
+            name,
+            detail,
+            kind,
+            (&function.from, &function.to),
+            (&selection.0, &selection.1),
+        );
+
+        self.push_frame(Some(function_symbol), false);
+        AstVisitorResult::Continue
+    }
+
+    fn leave_function_declaration(&mut self, _function: &FunctionDefinition) -> AstVisitorResult {
+        self.pop_frame();
+        AstVisitorResult::Continue
+    }
+
+    fn enter_class_definition(&mut self, class: &ClassDefinition) -> AstVisitorResult {
+        let class_symbol = class.name.as_ref().map(|name| {
+            symbol(
+                identifier_name(name),
+                join_detail(None, &class.attributes),
+                SymbolKind::CLASS,
+                (&class.from, &class.to),
+                (&name.get_from(), &name.get_to()),
+            )
+        });
+
+        self.push_frame(class_symbol, true);
+
+        for member in &class.members {
+            if let ClassMemberDefinition::Field(field) = member {
+                let field_symbol = symbol(
+                    identifier_name(&field.name),
+                    join_detail(
+                        field.is_static.then(|| "static".to_string()),
+                        &field.attributes,
+                    ),
+                    SymbolKind::FIELD,
+                    (&field.from, &field.to),
+                    (&field.name.get_from(), &field.name.get_to()),
+                );
+
+                self.add(field_symbol);
+            }
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn leave_class_definition(&mut self, _class: &ClassDefinition) -> AstVisitorResult {
+        self.pop_frame();
+        AstVisitorResult::Continue
+    }
+
+    fn enter_enum_statement(&mut self, statement: &EnumStatement) -> AstVisitorResult {
+        let members = statement
+            .enumerations
+            .iter()
+            .map(|enumeration| {
+                symbol(
+                    identifier_name(&enumeration.name),
+                    None,
+                    SymbolKind::ENUM_MEMBER,
+                    (&enumeration.from, &enumeration.to),
+                    (&enumeration.name.get_from(), &enumeration.name.get_to()),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut enum_symbol = symbol(
+            identifier_name(&statement.name),
+            None,
+            SymbolKind::ENUM,
+            (&statement.name.get_from(), &statement.to),
+            (&statement.name.get_from(), &statement.name.get_to()),
+        );
+
+        if !members.is_empty() {
+            enum_symbol.children = Some(members);
+        }
+
+        self.add(enum_symbol);
+        AstVisitorResult::Continue
+    }
+
+    fn enter_const_statement(&mut self, statement: &ConstStatement) -> AstVisitorResult {
+        let const_symbol = symbol(
+            identifier_name(&statement.name),
+            None,
+            SymbolKind::CONSTANT,
+            (&statement.from, &statement.to),
+            (&statement.name.get_from(), &statement.name.get_to()),
+        );
+
+        self.add(const_symbol);
+        AstVisitorResult::Continue
+    }
+
+    fn enter_local_statement(&mut self, statement: &LocalStatement) -> AstVisitorResult {
+        // Locals within functions are implementation details
+        if self.frames.len() > 1 {
+            return AstVisitorResult::Continue;
+        }
+
+        for initialization in &statement.initializations {
+            let local_symbol = symbol(
+                initialization.name.clone(),
+                None,
+                SymbolKind::VARIABLE,
+                (&initialization.from, &initialization.to),
+                (&initialization.from, &initialization.to),
+            );
+
+            self.add(local_symbol);
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_destructuring_statement(
+        &mut self,
+        statement: &DestructuringStatement,
+    ) -> AstVisitorResult {
+        if self.frames.len() > 1 {
+            return AstVisitorResult::Continue;
+        }
+
+        for binding in &statement.bindings {
+            let local_symbol = symbol(
+                binding.name.clone(),
+                None,
+                SymbolKind::VARIABLE,
+                (&binding.from, &binding.to),
+                (&binding.from, &binding.to),
+            );
+
+            self.add(local_symbol);
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_import_statement(&mut self, statement: &ImportStatement) -> AstVisitorResult {
+        if let Some(alias) = &statement.alias {
+            let module_symbol = symbol(
+                identifier_name(alias),
+                Some(statement.module.clone()),
+                SymbolKind::MODULE,
+                (&statement.from, &statement.to),
+                (&alias.get_from(), &alias.get_to()),
+            );
+
+            self.add(module_symbol);
+        }
+
+        for name in &statement.names {
+            let import_symbol = symbol(
+                name.binding().to_string(),
+                Some(statement.module.clone()),
+                SymbolKind::VARIABLE,
+                (&name.from, &name.to),
+                (&name.from, &name.to),
+            );
+
+            self.add(import_symbol);
+        }
+
+        AstVisitorResult::Continue
+    }
+}
+
+/// Appends the printed attributes of a class or class member to its detail.
+fn join_detail(detail: Option<String>, attributes: &Option<TableExpression>) -> Option<String> {
+    let attributes = attributes.as_ref().map(|attributes| {
+        Printer::new(16, &FormatterOptions::default()).print_attribute_list(attributes)
+    });
+
+    match (detail, attributes) {
+        (Some(detail), Some(attributes)) => Some(format!("{} {}", detail, attributes)),
+        (detail, attributes) => detail.or(attributes),
+    }
+}
+
+#[allow(deprecated)]
+fn symbol(
+    name: String,
+    detail: Option<String>,
+    kind: SymbolKind,
+    range: (&Location, &Location),
+    selection_range: (&Location, &Location),
+) -> DocumentSymbol {
+    DocumentSymbol {
+        name,
+        detail,
+        kind,
+        tags: None,
+        deprecated: None,
+        range: to_range(range.0, range.1),
+        selection_range: to_range(selection_range.0, selection_range.1),
+        children: None,
+    }
+}
+
+fn to_range(from: &Location, to: &Location) -> Range {
+    Range::new(
+        Position::new(from.line as u32, from.linechar as u32),
+        Position::new(to.line as u32, to.linechar as u32),
+    )
+}
+
+/// Builds the document outline: functions, classes with their members, enums,
+/// consts and top level locals. Generator functions and attributes of classes
+/// and their members are shown in the detail.
+pub fn document_symbols(statements: &Statements) -> Vec<DocumentSymbol> {
+    let mut collector = SymbolCollector {
+        generators: Generators::new(statements),
+        frames: Vec::new(),
+    };
+
+    collector.push_frame(None, false);
+    visit(statements, &mut collector);
+
+    collector
+        .frames
+        .pop()
+        .map(|frame| frame.children)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::SymbolKind;
+
+    use crate::squirrel_parser::Parser;
+
+    use super::document_symbols;
+
+    #[test]
+    fn test_document_symbols() {
+        let input = "
+const MAX = 10;
+local count = 0;
+
+class Foo {
+    bar = 1;
+    constructor() {}
+    function gen() {
+        yield bar;
+    }
+}
+
+function main() {
+    local ignored = 1;
+    function helper() {}
+}
+";
+        let mut parser = Parser::new(input);
+        let statements = parser.parse().unwrap();
+        let symbols = document_symbols(&statements);
+
+        let names = symbols
+            .iter()
+            .map(|symbol| (symbol.name.as_str(), symbol.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                ("MAX", SymbolKind::CONSTANT),
+                ("count", SymbolKind::VARIABLE),
+                ("Foo", SymbolKind::CLASS),
+                ("main", SymbolKind::FUNCTION),
+            ]
+        );
+
+        let members = symbols[2]
+            .children
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|symbol| (symbol.name.as_str(), symbol.kind, symbol.detail.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            members,
+            vec![
+                ("bar", SymbolKind::FIELD, None),
+                ("constructor", SymbolKind::CONSTRUCTOR, None),
+                ("gen", SymbolKind::METHOD, Some("generator")),
+            ]
+        );
+
+        assert_eq!(symbols[3].children.as_ref().unwrap()[0].name, "helper");
+    }
+
+    #[test]
+    fn test_document_symbols_attributes() {
+        let input = "
+class Foo </ version = 2 /> {
+    </ editable = true />
+    static bar = 1;
+    </ deprecated = true /> function gen() { yield bar; }
+    </ hidden = true /> constructor() {}
+}
+";
+        let mut parser = Parser::new(input);
+        let statements = parser.parse().unwrap();
+        let symbols = document_symbols(&statements);
+
+        assert_eq!(symbols[0].detail.as_deref(), Some("</ version = 2 />"));
+
+        let details = symbols[0]
+            .children
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|symbol| (symbol.name.as_str(), symbol.detail.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            details,
+            vec![
+                ("bar", Some("static </ editable = true />")),
+                ("gen", Some("generator </ deprecated = true />")),
+                ("constructor", Some("</ hidden = true />")),
+            ]
+        );
+    }
+}
@@ -0,0 +1,524 @@
+use std::{collections::HashMap, fmt::Display, hash::Hash};
+
+use crate::{
+    ast_visitor::{visit, AstVisitor, AstVisitorResult},
+    diagnostics::{report, Diagnostic, Severity},
+    grammar::expressions::*,
+    grammar::statements::*,
+    scope_table::{ScopeTable, VariableDeclarationKind},
+    squirrel_lexer::{Location, Operator, Token},
+};
+
+/// Reports names declared more than once where only the last one would take
+/// effect: table keys, class members, enumerations, parameters, case labels and
+/// locals within one scope.
+pub struct DuplicateChecker<'a> {
+    scope_table: &'a ScopeTable,
+    severity: Option<Severity>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Value of a key or label known before running the script, tagged with its
+/// type so that `1` and `"1"` or `FOO` and `"FOO"` are told apart.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Integer(i64),
+    /// Bits of the value, floats being neither `Eq` nor `Hash`
+    Float(u64),
+    String(String),
+    /// Name of a variable, member or constant holding the value
+    Identifier(String),
+    Boolean(bool),
+    Null,
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Key::Integer(value) => write!(f, "{}", value),
+            Key::Float(bits) => write!(f, "{}", f64::from_bits(*bits)),
+            Key::String(value) | Key::Identifier(value) => write!(f, "{}", value),
+            Key::Boolean(value) => write!(f, "{}", value),
+            Key::Null => write!(f, "null"),
+        }
+    }
+}
+
+/// Tracks the first occurrence of every name within one declaration list.
+struct Declarations<K = String> {
+    first: HashMap<K, Location>,
+}
+
+impl<K: Eq + Hash> Declarations<K> {
+    fn new() -> Self {
+        Self {
+            first: HashMap::new(),
+        }
+    }
+
+    /// Returns the location of the earlier declaration when `name` was seen before.
+    fn insert(&mut self, name: K, from: &Location) -> Option<Location> {
+        match self.first.get(&name) {
+            Some(first) => Some(first.clone()),
+            None => {
+                self.first.insert(name, from.clone());
+                None
+            }
+        }
+    }
+}
+
+impl<'a> DuplicateChecker<'a> {
+    pub fn new(scope_table: &'a ScopeTable, severity: Option<Severity>) -> Self {
+        Self {
+            scope_table,
+            severity,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    pub fn check(mut self, statements: &Statements) -> Vec<Diagnostic> {
+        if self.severity.is_none() {
+            return Vec::new();
+        }
+
+        self.check_locals();
+        visit(statements, &mut self);
+
+        self.diagnostics
+    }
+
+    fn check_locals(&mut self) {
+        for scope in self.scope_table.scopes() {
+            let mut declarations = Declarations::new();
+
+            for variable in &scope.variables {
+                let is_local = match variable.kind {
+                    VariableDeclarationKind::Const => true,
+                    // Named classes are registered as locals but redefine a slot
+                    VariableDeclarationKind::Local => {
+                        !matches!(variable.value, Some(Expression::Class(_)))
+                    }
+                    _ => false,
+                };
+
+                if !is_local {
+                    continue;
+                }
+
+                if let Some(first) = declarations.insert(variable.name.clone(), &variable.from) {
+                    self.report(
+                        &variable.from,
+                        &variable.to,
+                        format!(
+                            "Variable '{}' is already declared in this scope on line {}",
+                            variable.name,
+                            first.line + 1
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_parameters(&mut self, function: &FunctionDefinition) {
+        let mut declarations = Declarations::new();
+
+        for parameter in &function.parameters {
+            let name = match parameter {
+                Expression::Identifier(identifier) => identifier,
+                Expression::BinaryOperator(op) if op.operator == Operator::Assign => {
+                    match &op.left {
+                        Expression::Identifier(identifier) => identifier,
+                        _ => continue,
+                    }
+                }
+                _ => continue,
+            };
+
+            if let Some(first) = declarations.insert(name.token.to_string(), &name.from) {
+                self.report(
+                    &name.from,
+                    &name.to,
+                    format!(
+                        "Duplicate parameter '{}', first declared on line {}",
+                        name.token,
+                        first.line + 1
+                    ),
+                );
+            }
+        }
+    }
+
+    fn report(&mut self, from: &Location, to: &Location, message: String) {
+        report(
+            &mut self.diagnostics,
+            self.severity,
+            "duplicate-declaration",
+            from,
+            to,
+            message,
+        );
+    }
+}
+
+impl AstVisitor for DuplicateChecker<'_> {
+    fn enter_table_expression(&mut self, table: &TableExpression) -> AstVisitorResult {
+        let mut declarations = Declarations::new();
+
+        for entry in &table.entries {
+            let (key, name) = match entry {
+                TableEntry::Field(field) => (&field.name, slot_key(&field.name)),
+                TableEntry::FieldWithExpressionKey(field) => (&field.key, constant_key(&field.key)),
+                TableEntry::FieldWithStringKey(field) => (&field.key, constant_key(&field.key)),
+                TableEntry::Function(function) => match &function.function.name {
+                    Some(name) => (name, slot_key(name)),
+                    None => continue,
+                },
+            };
+
+            let Some(name) = name else {
+                continue;
+            };
+
+            if let Some(first) = declarations.insert(name.clone(), &entry_from(entry)) {
+                let message = format!(
+                    "Duplicate table key '{}', first declared on line {}",
+                    name,
+                    first.line + 1
+                );
+                self.report(&key.get_from(), &key.get_to(), message);
+            }
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_class_definition(&mut self, class: &ClassDefinition) -> AstVisitorResult {
+        let mut declarations = Declarations::new();
+        let mut constructor: Option<&Location> = None;
+
+        for member in &class.members {
+            let name = match member {
+                ClassMemberDefinition::Field(field) => &field.name,
+                ClassMemberDefinition::Method(method) => match &method.name {
+                    Some(name) => name,
+                    None => continue,
+                },
+                ClassMemberDefinition::Constructor(definition) => {
+                    match constructor {
+                        Some(first) => {
+                            let message = format!(
+                                "Duplicate constructor, first declared on line {}",
+                                first.line + 1
+                            );
+                            self.report(&definition.from, &definition.to, message);
+                        }
+                        None => constructor = Some(&definition.from),
+                    }
+                    continue;
+                }
+            };
+
+            let Some(key) = slot_key(name) else {
+                continue;
+            };
+
+            if let Some(first) = declarations.insert(key.clone(), member_from(member)) {
+                let message = format!(
+                    "Duplicate class member '{}', first declared on line {}",
+                    key,
+                    first.line + 1
+                );
+                self.report(&name.get_from(), &name.get_to(), message);
+            }
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_enum_statement(&mut self, statement: &EnumStatement) -> AstVisitorResult {
+        let mut declarations = Declarations::new();
+
+        for enumeration in &statement.enumerations {
+            let Some(name) = slot_key(&enumeration.name) else {
+                continue;
+            };
+
+            if let Some(first) = declarations.insert(name.clone(), &enumeration.from) {
+                self.report(
+                    &enumeration.name.get_from(),
+                    &enumeration.name.get_to(),
+                    format!(
+                        "Duplicate enumeration '{}', first declared on line {}",
+                        name,
+                        first.line + 1
+                    ),
+                );
+            }
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_function_declaration(&mut self, function: &FunctionDefinition) -> AstVisitorResult {
+        self.check_parameters(function);
+        AstVisitorResult::Continue
+    }
+
+    fn enter_switch_statement(&mut self, statement: &SwitchStatement) -> AstVisitorResult {
+        let mut declarations = Declarations::new();
+
+        for case in &statement.cases {
+            let Some(label) = constant_key(&case.expression) else {
+                continue;
+            };
+
+            if let Some(first) = declarations.insert(label.clone(), &case.from) {
+                self.report(
+                    &case.expression.get_from(),
+                    &case.expression.get_to(),
+                    format!(
+                        "Duplicate case label '{}', first used on line {}",
+                        label,
+                        first.line + 1
+                    ),
+                );
+            }
+        }
+
+        AstVisitorResult::Continue
+    }
+}
+
+/// Key of a slot declared by name, e.g. `a = 1` in a table, which is the same
+/// slot as `["a"] = 1`.
+fn slot_key(name: &Expression) -> Option<Key> {
+    match name {
+        Expression::Identifier(identifier) => match &identifier.token {
+            Token::Identifier(name) => Some(Key::String(name.clone())),
+            _ => None,
+        },
+        name => constant_key(name),
+    }
+}
+
+/// Value of expressions that always evaluate to the same key or label.
+fn constant_key(expression: &Expression) -> Option<Key> {
+    match expression {
+        Expression::StringLiteral(string) => Some(Key::String(string.value.clone())),
+        Expression::IntegerLiteral(integer) => Some(Key::Integer(integer.value)),
+        Expression::CharacterLiteral(character) => Some(Key::Integer(character.value as i64)),
+        Expression::FloatLiteral(float) => Some(Key::Float(float.value.to_bits())),
+        Expression::BooleanLiteral(boolean) => Some(Key::Boolean(boolean.value)),
+        Expression::NullLiteral(_) => Some(Key::Null),
+        Expression::UnaryOperator(unary) if unary.operator == Operator::Minus => {
+            match constant_key(&unary.expression)? {
+                Key::Integer(value) => Some(Key::Integer(value.wrapping_neg())),
+                Key::Float(bits) => Some(Key::Float((-f64::from_bits(bits)).to_bits())),
+                _ => None,
+            }
+        }
+        expression => name_path(expression).map(Key::Identifier),
+    }
+}
+
+/// Source form of a name or a chain of accesses to one, e.g. `Color.Red`.
+fn name_path(expression: &Expression) -> Option<String> {
+    match expression {
+        Expression::Identifier(identifier) => match &identifier.token {
+            Token::Identifier(name) => Some(name.clone()),
+            _ => None,
+        },
+        Expression::MemberAccess(access) => Some(format!(
+            "{}.{}",
+            name_path(&access.expression)?,
+            access.member
+        )),
+        Expression::ScopeResolution(resolution) => Some(format!(
+            "{}::{}",
+            resolution.scope.join("::"),
+            resolution.accessor
+        )),
+        _ => None,
+    }
+}
+
+fn entry_from(entry: &TableEntry) -> Location {
+    match entry {
+        TableEntry::Field(field) => field.from.clone(),
+        TableEntry::Function(function) => function.from.clone(),
+        TableEntry::FieldWithExpressionKey(field) => field.from.clone(),
+        TableEntry::FieldWithStringKey(field) => field.from.clone(),
+    }
+}
+
+fn member_from(member: &ClassMemberDefinition) -> &Location {
+    match member {
+        ClassMemberDefinition::Field(field) => &field.from,
+        ClassMemberDefinition::Method(method) => &method.from,
+        ClassMemberDefinition::Constructor(constructor) => &constructor.from,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::{Diagnostic, Severity};
+
+    use crate::{scope_table::ScopeTable, squirrel_parser::Parser};
+
+    use super::DuplicateChecker;
+
+    fn check(input: &str) -> Vec<Diagnostic> {
+        let mut parser = Parser::new(input);
+        let statements = parser.parse().unwrap();
+        let scope_table = ScopeTable::new(&statements);
+
+        DuplicateChecker::new(&scope_table, Some(Severity::Warning)).check(&statements)
+    }
+
+    #[test]
+    fn test_duplicate_table_keys() {
+        let input = "
+local a = {
+    foo = 1,
+    [\"foo\"] = 2,
+    function bar() {}
+    bar = 3
+};
+";
+        let diagnostics = check(input);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            diagnostics[0].message,
+            "Duplicate table key 'foo', first declared on line 3"
+        );
+        assert_eq!(
+            diagnostics[1].message,
+            "Duplicate table key 'bar', first declared on line 5"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_class_members() {
+        let input = "
+class Foo {
+    bar = 1;
+    constructor() {}
+    function bar() {}
+    constructor(a) {}
+}
+";
+        let diagnostics = check(input);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            diagnostics[0].message,
+            "Duplicate class member 'bar', first declared on line 3"
+        );
+        assert_eq!(
+            diagnostics[1].message,
+            "Duplicate constructor, first declared on line 4"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_enumerations_parameters_and_cases() {
+        let input = "
+enum Color {
+    Red,
+    Red
+}
+
+function foo(a, b, a = 1) {
+    switch (b) {
+        case 1:
+            break;
+        case \"a\":
+            break;
+        case 1:
+            break;
+    }
+}
+";
+        let diagnostics = check(input);
+
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(
+            diagnostics[0].message,
+            "Duplicate enumeration 'Red', first declared on line 3"
+        );
+        assert_eq!(
+            diagnostics[1].message,
+            "Duplicate parameter 'a', first declared on line 7"
+        );
+        assert_eq!(
+            diagnostics[2].message,
+            "Duplicate case label '1', first used on line 9"
+        );
+    }
+
+    #[test]
+    fn test_redeclared_locals() {
+        let input = "
+local a = 1;
+local a = 2;
+{
+    local a = 3;
+}
+";
+        let diagnostics = check(input);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "Variable 'a' is already declared in this scope on line 2"
+        );
+    }
+
+    #[test]
+    fn test_keys_of_different_types_are_distinct() {
+        let input = "
+const FOO = 1;
+local a = {
+    [1] = 1,
+    [\"1\"] = 2,
+    [FOO] = 3,
+    FOO = 4,
+    [1.0] = 5
+};
+switch (a) {
+    case FOO:
+        break;
+    case \"FOO\":
+        break;
+    case 1:
+        break;
+    case \"1\":
+        break;
+}
+";
+        assert_eq!(check(input), Vec::new());
+
+        let input = "
+local a = {
+    [FOO] = 1,
+    [FOO] = 2,
+    [-1] = 3,
+    [-1] = 4
+};
+";
+        let diagnostics = check(input);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            diagnostics[0].message,
+            "Duplicate table key 'FOO', first declared on line 3"
+        );
+        assert_eq!(
+            diagnostics[1].message,
+            "Duplicate table key '-1', first declared on line 5"
+        );
+    }
+}
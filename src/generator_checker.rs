@@ -0,0 +1,399 @@
+use std::collections::HashSet;
+
+use crate::{
+    ast_visitor::{visit, AstVisitor, AstVisitorResult},
+    diagnostics::{report, Diagnostic, Severity},
+    grammar::expressions::*,
+    grammar::statements::*,
+    scope_table::{ScopeTable, VariableDeclarationKind},
+    squirrel_lexer::{Location, Operator},
+    type_checker::DeclarationFacts,
+};
+
+/// Following locals to their values stops after this many steps.
+const MAX_RESOLVE_DEPTH: usize = 16;
+
+/// Finds functions containing a `yield` of their own, keyed by the position of
+/// the function definition. Calling such a function creates a generator instead
+/// of running its body.
+#[derive(Default)]
+pub struct Generators {
+    functions: HashSet<usize>,
+    stack: Vec<usize>,
+    root_yields: Vec<(Location, Location)>,
+}
+
+impl Generators {
+    pub fn new(statements: &Statements) -> Self {
+        let mut generators = Self::default();
+        visit(statements, &mut generators);
+        generators
+    }
+
+    pub fn is_generator(&self, function: &FunctionDefinition) -> bool {
+        self.functions.contains(&function.from.index)
+    }
+}
+
+impl AstVisitor for Generators {
+    fn enter_function_declaration(&mut self, function: &FunctionDefinition) -> AstVisitorResult {
+        self.stack.push(function.from.index);
+        AstVisitorResult::Continue
+    }
+
+    fn leave_function_declaration(&mut self, _function: &FunctionDefinition) -> AstVisitorResult {
+        self.stack.pop();
+        AstVisitorResult::Continue
+    }
+
+    fn enter_yield_statement(&mut self, statement: &YieldStatement) -> AstVisitorResult {
+        match self.stack.last() {
+            Some(function) => {
+                self.functions.insert(*function);
+            }
+            None => self
+                .root_yields
+                .push((statement.from.clone(), statement.to.clone())),
+        }
+
+        AstVisitorResult::Continue
+    }
+}
+
+/// What a `resume` operand is known to evaluate to.
+enum ResumeTarget<'a> {
+    Function(&'a FunctionDefinition, String),
+    Value(&'static str),
+    Unknown,
+}
+
+/// Reports `yield` outside of functions, `resume` on values that can't be
+/// generators and calls of generator functions whose result is used as if the
+/// function body ran.
+pub struct GeneratorChecker<'a> {
+    scope_table: &'a ScopeTable,
+    severity: Option<Severity>,
+    generators: Generators,
+    facts: DeclarationFacts<'a>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> GeneratorChecker<'a> {
+    pub fn new(
+        statements: &Statements,
+        scope_table: &'a ScopeTable,
+        severity: Option<Severity>,
+    ) -> Self {
+        Self {
+            scope_table,
+            severity,
+            generators: Generators::new(statements),
+            facts: DeclarationFacts::new(scope_table),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    pub fn check(mut self, statements: &Statements) -> Vec<Diagnostic> {
+        if self.severity.is_none() {
+            return Vec::new();
+        }
+
+        self.facts.collect(statements);
+
+        for (from, to) in self.generators.root_yields.clone() {
+            self.report(
+                &from,
+                &to,
+                "'yield' can only be used inside a function".to_string(),
+            );
+        }
+
+        visit(statements, &mut self);
+
+        self.diagnostics
+    }
+
+    fn resume_target<'e>(&self, expression: &'e Expression, depth: usize) -> ResumeTarget<'e>
+    where
+        'a: 'e,
+    {
+        if depth > MAX_RESOLVE_DEPTH {
+            return ResumeTarget::Unknown;
+        }
+
+        match expression {
+            Expression::NullLiteral(_) => ResumeTarget::Value("null"),
+            Expression::BooleanLiteral(_) => ResumeTarget::Value("bool"),
+            Expression::IntegerLiteral(_) | Expression::CharacterLiteral(_) => {
+                ResumeTarget::Value("integer")
+            }
+            Expression::FloatLiteral(_) => ResumeTarget::Value("float"),
+            Expression::StringLiteral(_) | Expression::MutliLineStringLiteral(_) => {
+                ResumeTarget::Value("string")
+            }
+            Expression::Table(_) => ResumeTarget::Value("table"),
+            Expression::Array(_) => ResumeTarget::Value("array"),
+            Expression::Class(_) => ResumeTarget::Value("class"),
+            Expression::Function(function) => {
+                ResumeTarget::Function(function.as_ref(), "function".to_string())
+            }
+            Expression::Grouping(grouping) => match &grouping.expression {
+                Some(expression) => self.resume_target(expression, depth + 1),
+                None => ResumeTarget::Unknown,
+            },
+            Expression::Identifier(identifier) => {
+                let Some(declaration) = self.facts.declaration_of(identifier) else {
+                    return ResumeTarget::Unknown;
+                };
+
+                if self.facts.is_reassigned(declaration) {
+                    return ResumeTarget::Unknown;
+                }
+
+                let scope_table: &'a ScopeTable = self.scope_table;
+                let variable = &scope_table.scopes()[declaration.0].variables[declaration.1];
+
+                match (&variable.kind, &variable.value) {
+                    (
+                        VariableDeclarationKind::Function
+                        | VariableDeclarationKind::Local
+                        | VariableDeclarationKind::Const,
+                        Some(Expression::Function(function)),
+                    ) => ResumeTarget::Function(function.as_ref(), variable.name.clone()),
+                    (
+                        VariableDeclarationKind::Local | VariableDeclarationKind::Const,
+                        Some(value),
+                    ) => self.resume_target(value, depth + 1),
+                    _ => ResumeTarget::Unknown,
+                }
+            }
+            _ => ResumeTarget::Unknown,
+        }
+    }
+
+    /// Name of the called function when `expression` is a call of a generator function.
+    fn generator_call(&self, expression: &Expression) -> Option<String> {
+        let Expression::FunctionCall(call) = expression else {
+            return None;
+        };
+
+        match self.resume_target(&call.function, 0) {
+            ResumeTarget::Function(function, name) if self.generators.is_generator(function) => {
+                Some(name)
+            }
+            _ => None,
+        }
+    }
+
+    /// Reports a generator call whose result is used as the function's return value.
+    fn check_value(&mut self, expression: &Expression) {
+        if let Some(name) = self.generator_call(expression) {
+            self.report(
+                &expression.get_from(),
+                &expression.get_to(),
+                format!(
+                    "Calling generator function '{}' returns a generator, not its result",
+                    name
+                ),
+            );
+        }
+    }
+
+    fn report(&mut self, from: &Location, to: &Location, message: String) {
+        report(
+            &mut self.diagnostics,
+            self.severity,
+            "generator-misuse",
+            from,
+            to,
+            message,
+        );
+    }
+}
+
+impl AstVisitor for GeneratorChecker<'_> {
+    fn enter_resume_expression(&mut self, expression: &ResumeExpression) -> AstVisitorResult {
+        let message = match self.resume_target(&expression.expression, 0) {
+            ResumeTarget::Function(function, name) if self.generators.is_generator(function) => {
+                format!(
+                    "'{}' is a generator function, call it to create a generator before resuming",
+                    name
+                )
+            }
+            ResumeTarget::Function(_, name) => {
+                format!("'resume' expects a generator, found function '{}'", name)
+            }
+            ResumeTarget::Value(type_name) => {
+                format!("'resume' expects a generator, found {}", type_name)
+            }
+            ResumeTarget::Unknown => return AstVisitorResult::Continue,
+        };
+
+        self.report(&expression.from, &expression.to, message);
+        AstVisitorResult::Continue
+    }
+
+    fn enter_expression_statement(&mut self, statement: &ExpressionStatement) -> AstVisitorResult {
+        if let Some(name) = self.generator_call(&statement.expression) {
+            self.report(
+                &statement.from,
+                &statement.to,
+                format!(
+                    "Calling generator function '{}' only creates a generator, use 'resume' or 'foreach' to run it",
+                    name
+                ),
+            );
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_binary_operator_expression(
+        &mut self,
+        expression: &BinaryOperatorExpression,
+    ) -> AstVisitorResult {
+        if !expression.operator.is_assignment() {
+            self.check_value(&expression.left);
+            self.check_value(&expression.right);
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_unary_operator_expression(
+        &mut self,
+        expression: &UnaryOperatorExpression,
+    ) -> AstVisitorResult {
+        if expression.operator != Operator::Typeof {
+            self.check_value(&expression.expression);
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_member_access_expression(
+        &mut self,
+        expression: &MemberAccessExpression,
+    ) -> AstVisitorResult {
+        self.check_value(&expression.expression);
+        AstVisitorResult::Continue
+    }
+
+    fn enter_array_access_expression(
+        &mut self,
+        expression: &ArrayAccessExpression,
+    ) -> AstVisitorResult {
+        self.check_value(&expression.array);
+        AstVisitorResult::Continue
+    }
+
+    fn enter_ternary_operator_expression(
+        &mut self,
+        expression: &TernaryOperatorExpression,
+    ) -> AstVisitorResult {
+        self.check_value(&expression.condition);
+        AstVisitorResult::Continue
+    }
+
+    fn enter_if_statement(&mut self, statement: &IfStatement) -> AstVisitorResult {
+        self.check_value(&statement.condition);
+        AstVisitorResult::Continue
+    }
+
+    fn enter_while_statement(&mut self, statement: &WhileStatement) -> AstVisitorResult {
+        self.check_value(&statement.condition);
+        AstVisitorResult::Continue
+    }
+
+    fn enter_do_while_statement(&mut self, statement: &DoWhileStatement) -> AstVisitorResult {
+        self.check_value(&statement.condition);
+        AstVisitorResult::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::{Diagnostic, Severity};
+
+    use crate::{scope_table::ScopeTable, squirrel_parser::Parser};
+
+    use super::GeneratorChecker;
+
+    fn check(input: &str) -> Vec<Diagnostic> {
+        let mut parser = Parser::new(input);
+        let statements = parser.parse().unwrap();
+        let scope_table = ScopeTable::new(&statements);
+
+        GeneratorChecker::new(&statements, &scope_table, Some(Severity::Warning)).check(&statements)
+    }
+
+    #[test]
+    fn test_resume_non_generators() {
+        let input = "
+function gen() {
+    yield 1;
+}
+
+function plain() {
+    return 1;
+}
+
+local g = gen();
+local n = 10;
+resume g;
+resume gen;
+resume plain;
+resume n;
+";
+        let diagnostics = check(input);
+
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(
+            diagnostics[0].message,
+            "'gen' is a generator function, call it to create a generator before resuming"
+        );
+        assert_eq!(
+            diagnostics[1].message,
+            "'resume' expects a generator, found function 'plain'"
+        );
+        assert_eq!(
+            diagnostics[2].message,
+            "'resume' expects a generator, found integer"
+        );
+    }
+
+    #[test]
+    fn test_generator_call_results() {
+        let input = "
+function gen() {
+    yield 1;
+}
+
+gen();
+local a = gen() + 1;
+foreach (v in gen()) {}
+";
+        let diagnostics = check(input);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            diagnostics[0].message,
+            "Calling generator function 'gen' only creates a generator, use 'resume' or 'foreach' to run it"
+        );
+        assert_eq!(
+            diagnostics[1].message,
+            "Calling generator function 'gen' returns a generator, not its result"
+        );
+    }
+
+    #[test]
+    fn test_yield_at_root() {
+        let diagnostics = check("yield 1;");
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "'yield' can only be used inside a function"
+        );
+    }
+}
@@ -3,7 +3,9 @@ use serde::{Deserialize, Serialize};
 use crate::squirrel_lexer::Location;
 
 use super::{
-    expression::Expression, function_definition::FunctionDefinition, statements::Statement,
+    expression::{Expression, TableExpression},
+    function_definition::FunctionDefinition,
+    statements::Statement,
 };
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -11,6 +13,8 @@ pub struct ClassDefinition {
     pub name: Option<Expression>,
     pub extends: Option<Expression>,
     pub members: Vec<ClassMemberDefinition>,
+    /// `</ key = value />` between the class head and its body.
+    pub attributes: Option<TableExpression>,
     pub from: Location,
     pub to: Location,
 }
@@ -27,6 +31,8 @@ pub struct ClassFieldDefinition {
     pub name: Expression,
     pub expression: Expression,
     pub is_static: bool,
+    /// `</ key = value />` before the field.
+    pub attributes: Option<TableExpression>,
     pub from: Location,
     pub to: Location,
 }
@@ -36,6 +42,7 @@ impl ClassMemberDefinition {
         name: Expression,
         expression: Expression,
         is_static: bool,
+        attributes: Option<TableExpression>,
         from: Location,
         to: Location,
     ) -> ClassMemberDefinition {
@@ -43,6 +50,7 @@ impl ClassMemberDefinition {
             name,
             expression,
             is_static,
+            attributes,
             from,
             to,
         })
@@ -53,6 +61,7 @@ impl ClassMemberDefinition {
         parameters: Vec<Expression>,
         statement: Statement,
         is_static: bool,
+        attributes: Option<TableExpression>,
         from: Location,
         to: Location,
     ) -> ClassMemberDefinition {
@@ -61,6 +70,9 @@ impl ClassMemberDefinition {
             parameters,
             statement,
             is_static,
+            is_local: false,
+            is_lambda: false,
+            attributes,
             from,
             to,
         })
@@ -70,6 +82,7 @@ impl ClassMemberDefinition {
         parameters: Vec<Expression>,
         statement: Statement,
         is_static: bool,
+        attributes: Option<TableExpression>,
         from: Location,
         to: Location,
     ) -> ClassMemberDefinition {
@@ -78,6 +91,9 @@ impl ClassMemberDefinition {
             parameters,
             statement,
             is_static,
+            is_local: false,
+            is_lambda: false,
+            attributes,
             from,
             to,
         })
@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+
+use crate::squirrel_lexer::{Location, Token};
+
+/// Where a comment sits relative to the code around it, which decides where
+/// the formatter puts it back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CommentPlacement {
+    /// Alone on its lines, e.g. above a statement
+    OwnLine,
+    /// Following code on its line, e.g. `local a = 1 // comment`
+    EndOfLine,
+    /// A block comment followed by code on its line, e.g. `foo(/* comment */ a)`
+    Inline,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Comment {
+    /// Either a [`Token::Comment`] or a [`Token::MultiLineComment`]
+    pub token: Token,
+    pub placement: CommentPlacement,
+    pub from: Location,
+    pub to: Location,
+}
+
+impl Comment {
+    /// Creates the comment found between `from` and `to` in `source`, placed
+    /// according to the code around it on its lines.
+    pub fn new(token: Token, from: Location, to: Location, source: &str) -> Self {
+        // An unterminated block comment ends past the end of the source
+        let start = from.index.min(source.len());
+        let end = to.index.min(source.len());
+
+        let line_start = source[..start].rfind('\n').map_or(0, |newline| newline + 1);
+        let line_end = source[end..]
+            .find('\n')
+            .map_or(source.len(), |newline| end + newline);
+
+        let code_before = !source[line_start..start].trim().is_empty();
+        let code_after = !source[end..line_end].trim().is_empty();
+
+        let placement = match (code_before, code_after) {
+            (_, true) => CommentPlacement::Inline,
+            (true, false) => CommentPlacement::EndOfLine,
+            (false, false) => CommentPlacement::OwnLine,
+        };
+
+        Self {
+            token,
+            placement,
+            from,
+            to,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        match &self.token {
+            Token::Comment(text, _) | Token::MultiLineComment(text) => text.trim(),
+            _ => "",
+        }
+    }
+
+    /// Whether the comment runs to the end of its line, so no code can follow it there
+    pub fn is_line_comment(&self) -> bool {
+        matches!(self.token, Token::Comment(..))
+    }
+}
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+use crate::squirrel_lexer::Location;
+
+use super::{expression::Expression, local_statement::Initialization};
+
+/// `local {a, b = 1} = table` or `let [a, b] = array`, declaring a local for
+/// each slot or element, with an optional default for when it's missing.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DestructuringStatement {
+    pub kind: DestructuringKind,
+    pub bindings: Vec<Initialization>,
+    pub expression: Expression,
+    /// Declared with `let` rather than `local`.
+    pub is_let: bool,
+    pub from: Location,
+    pub to: Location,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DestructuringKind {
+    /// `{a, b}`, binding slots by their name
+    Table,
+    /// `[a, b]`, binding elements by their position
+    Array,
+}
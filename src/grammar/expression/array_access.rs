@@ -8,6 +8,8 @@ use super::Expression;
 pub struct ArrayAccessExpression {
     pub array: Expression,
     pub index: Expression,
+    /// Written as `a?[b]`, evaluating to null when the array is null.
+    pub null_safe: bool,
     pub from: Location,
     pub to: Location,
 }
@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+use crate::squirrel_lexer::Location;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CharacterLiteralExpression {
+    pub value: char,
+    /// Source between the quotes, escape sequences included
+    pub source: String,
+    pub from: Location,
+    pub to: Location,
+}
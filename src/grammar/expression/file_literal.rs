@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+
+use crate::squirrel_lexer::Location;
+
+/// `__FILE__`, the name the script was compiled with.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FileLiteralExpression {
+    pub from: Location,
+    pub to: Location,
+}
@@ -5,6 +5,8 @@ use crate::squirrel_lexer::Location;
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct FloatLiteralExpression {
     pub value: f64,
+    /// Source of the literal, e.g. `1e3`
+    pub source: String,
     pub from: Location,
     pub to: Location,
 }
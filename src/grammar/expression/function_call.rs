@@ -8,6 +8,8 @@ use super::Expression;
 pub struct FunctionCallExpression {
     pub function: Expression,
     pub arguments: Vec<Expression>,
+    /// Written as `f?(a)`, evaluating to null when the function is null.
+    pub null_safe: bool,
     pub from: Location,
     pub to: Location,
 }
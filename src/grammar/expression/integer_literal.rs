@@ -5,6 +5,8 @@ use crate::squirrel_lexer::Location;
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct IntegerLiteralExpression {
     pub value: i64,
+    /// Source of the literal, e.g. `0x1F`
+    pub source: String,
     pub from: Location,
     pub to: Location,
 }
@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+
+use crate::squirrel_lexer::Location;
+
+/// `__LINE__`, the line the expression is on.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LineLiteralExpression {
+    pub from: Location,
+    pub to: Location,
+}
@@ -8,6 +8,8 @@ use super::Expression;
 pub struct MemberAccessExpression {
     pub expression: Expression,
     pub member: String,
+    /// Written as `a?.b`, evaluating to null when the expression is null.
+    pub null_safe: bool,
     pub from: Location,
     pub to: Location,
 }
@@ -6,17 +6,21 @@ mod array;
 mod array_access;
 mod binary_operator;
 mod boolean_literal;
+mod character_literal;
 mod clone;
 mod delete;
+mod file_literal;
 mod float_literal;
 mod function_call;
 mod grouping;
 mod identifier;
 mod integer_literal;
+mod line_literal;
 mod member_access;
 mod multiline_string_literal;
 mod null_literal;
 mod postfix_unary_operator;
+mod raw_call;
 mod resume;
 mod scope_resolution;
 mod spread;
@@ -29,17 +33,21 @@ pub use array::*;
 pub use array_access::*;
 pub use binary_operator::*;
 pub use boolean_literal::*;
+pub use character_literal::*;
 pub use clone::*;
 pub use delete::*;
+pub use file_literal::*;
 pub use float_literal::*;
 pub use function_call::*;
 pub use grouping::*;
 pub use identifier::*;
 pub use integer_literal::*;
+pub use line_literal::*;
 pub use member_access::*;
 pub use multiline_string_literal::*;
 pub use null_literal::*;
 pub use postfix_unary_operator::*;
+pub use raw_call::*;
 pub use resume::*;
 pub use scope_resolution::*;
 pub use spread::*;
@@ -60,6 +68,7 @@ pub enum Expression {
     MutliLineStringLiteral(Box<MutliLineStringLiteralExpression>),
     FloatLiteral(Box<FloatLiteralExpression>),
     IntegerLiteral(Box<IntegerLiteralExpression>),
+    CharacterLiteral(Box<CharacterLiteralExpression>),
     Table(Box<TableExpression>),
     Clone(Box<CloneExpression>),
     Resume(Box<ResumeExpression>),
@@ -70,7 +79,10 @@ pub enum Expression {
     Identifier(Box<IdentifierExpression>),
     NullLiteral(Box<NullLiteralExpression>),
     BooleanLiteral(Box<BooleanLiteralExpression>),
+    LineLiteral(Box<LineLiteralExpression>),
+    FileLiteral(Box<FileLiteralExpression>),
     FunctionCall(Box<FunctionCallExpression>),
+    RawCall(Box<RawCallExpression>),
     ScopeResolution(Box<ScopeResolutionExpression>),
     MemberAccess(Box<MemberAccessExpression>),
     Class(Box<ClassDefinition>),
@@ -84,7 +96,7 @@ impl Expression {
     }
 
     pub fn scope_resolution(
-        scope: Option<String>,
+        scope: Vec<String>,
         accessor: String,
         from: Location,
         to: Location,
@@ -188,12 +200,14 @@ impl Expression {
     pub fn member_access(
         expression: Expression,
         member: String,
+        null_safe: bool,
         from: Location,
         to: Location,
     ) -> Expression {
         Expression::MemberAccess(Box::new(MemberAccessExpression {
             expression,
             member,
+            null_safe,
             from,
             to,
         }))
@@ -202,11 +216,21 @@ impl Expression {
     pub fn function_call(
         function: Expression,
         arguments: Vec<Expression>,
+        null_safe: bool,
         from: Location,
         to: Location,
     ) -> Expression {
         Expression::FunctionCall(Box::new(FunctionCallExpression {
             function,
+            arguments,
+            null_safe,
+            from,
+            to,
+        }))
+    }
+
+    pub fn raw_call(arguments: Vec<Expression>, from: Location, to: Location) -> Expression {
+        Expression::RawCall(Box::new(RawCallExpression {
             arguments,
             from,
             to,
@@ -216,12 +240,14 @@ impl Expression {
     pub fn array_access(
         array: Expression,
         index: Expression,
+        null_safe: bool,
         from: Location,
         to: Location,
     ) -> Expression {
         Expression::ArrayAccess(Box::new(ArrayAccessExpression {
             array,
             index,
+            null_safe,
             from,
             to,
         }))
@@ -239,18 +265,50 @@ impl Expression {
         }))
     }
 
-    pub fn integer_literal(value: i64, from: Location, to: Location) -> Expression {
-        Expression::IntegerLiteral(Box::new(IntegerLiteralExpression { value, from, to }))
+    pub fn integer_literal(value: i64, source: String, from: Location, to: Location) -> Expression {
+        Expression::IntegerLiteral(Box::new(IntegerLiteralExpression {
+            value,
+            source,
+            from,
+            to,
+        }))
     }
 
-    pub fn float_literal(value: f64, from: Location, to: Location) -> Expression {
-        Expression::FloatLiteral(Box::new(FloatLiteralExpression { value, from, to }))
+    pub fn character_literal(
+        value: char,
+        source: String,
+        from: Location,
+        to: Location,
+    ) -> Expression {
+        Expression::CharacterLiteral(Box::new(CharacterLiteralExpression {
+            value,
+            source,
+            from,
+            to,
+        }))
+    }
+
+    pub fn float_literal(value: f64, source: String, from: Location, to: Location) -> Expression {
+        Expression::FloatLiteral(Box::new(FloatLiteralExpression {
+            value,
+            source,
+            from,
+            to,
+        }))
     }
 
     pub fn null_literal(from: Location, to: Location) -> Expression {
         Expression::NullLiteral(Box::new(NullLiteralExpression { from, to }))
     }
 
+    pub fn line_literal(from: Location, to: Location) -> Expression {
+        Expression::LineLiteral(Box::new(LineLiteralExpression { from, to }))
+    }
+
+    pub fn file_literal(from: Location, to: Location) -> Expression {
+        Expression::FileLiteral(Box::new(FileLiteralExpression { from, to }))
+    }
+
     pub fn boolean_literal(value: bool, from: Location, to: Location) -> Expression {
         Expression::BooleanLiteral(Box::new(BooleanLiteralExpression { value, from, to }))
     }
@@ -270,4 +328,70 @@ impl Expression {
     pub fn table(entries: Vec<TableEntry>, from: Location, to: Location) -> Expression {
         Expression::Table(Box::new(TableExpression { entries, from, to }))
     }
+
+    pub fn get_from(&self) -> Location {
+        match self {
+            Expression::UnaryOperator(expression) => expression.from.clone(),
+            Expression::PostfixUnaryOperator(expression) => expression.from.clone(),
+            Expression::BinaryOperator(expression) => expression.from.clone(),
+            Expression::Spread(expression) => expression.from.clone(),
+            Expression::StringLiteral(expression) => expression.from.clone(),
+            Expression::MutliLineStringLiteral(expression) => expression.from.clone(),
+            Expression::FloatLiteral(expression) => expression.from.clone(),
+            Expression::IntegerLiteral(expression) => expression.from.clone(),
+            Expression::CharacterLiteral(expression) => expression.from.clone(),
+            Expression::Table(expression) => expression.from.clone(),
+            Expression::Clone(expression) => expression.from.clone(),
+            Expression::Resume(expression) => expression.from.clone(),
+            Expression::Delete(expression) => expression.from.clone(),
+            Expression::Array(expression) => expression.from.clone(),
+            Expression::ArrayAccess(expression) => expression.from.clone(),
+            Expression::Grouping(expression) => expression.from.clone(),
+            Expression::Identifier(expression) => expression.from.clone(),
+            Expression::NullLiteral(expression) => expression.from.clone(),
+            Expression::BooleanLiteral(expression) => expression.from.clone(),
+            Expression::LineLiteral(expression) => expression.from.clone(),
+            Expression::FileLiteral(expression) => expression.from.clone(),
+            Expression::FunctionCall(expression) => expression.from.clone(),
+            Expression::RawCall(expression) => expression.from.clone(),
+            Expression::ScopeResolution(expression) => expression.from.clone(),
+            Expression::MemberAccess(expression) => expression.from.clone(),
+            Expression::Class(expression) => expression.from.clone(),
+            Expression::TernaryOperator(expression) => expression.from.clone(),
+            Expression::Function(expression) => expression.from.clone(),
+        }
+    }
+
+    pub fn get_to(&self) -> Location {
+        match self {
+            Expression::UnaryOperator(expression) => expression.to.clone(),
+            Expression::PostfixUnaryOperator(expression) => expression.to.clone(),
+            Expression::BinaryOperator(expression) => expression.to.clone(),
+            Expression::Spread(expression) => expression.to.clone(),
+            Expression::StringLiteral(expression) => expression.to.clone(),
+            Expression::MutliLineStringLiteral(expression) => expression.to.clone(),
+            Expression::FloatLiteral(expression) => expression.to.clone(),
+            Expression::IntegerLiteral(expression) => expression.to.clone(),
+            Expression::CharacterLiteral(expression) => expression.to.clone(),
+            Expression::Table(expression) => expression.to.clone(),
+            Expression::Clone(expression) => expression.to.clone(),
+            Expression::Resume(expression) => expression.to.clone(),
+            Expression::Delete(expression) => expression.to.clone(),
+            Expression::Array(expression) => expression.to.clone(),
+            Expression::ArrayAccess(expression) => expression.to.clone(),
+            Expression::Grouping(expression) => expression.to.clone(),
+            Expression::Identifier(expression) => expression.to.clone(),
+            Expression::NullLiteral(expression) => expression.to.clone(),
+            Expression::BooleanLiteral(expression) => expression.to.clone(),
+            Expression::LineLiteral(expression) => expression.to.clone(),
+            Expression::FileLiteral(expression) => expression.to.clone(),
+            Expression::FunctionCall(expression) => expression.to.clone(),
+            Expression::RawCall(expression) => expression.to.clone(),
+            Expression::ScopeResolution(expression) => expression.to.clone(),
+            Expression::MemberAccess(expression) => expression.to.clone(),
+            Expression::Class(expression) => expression.to.clone(),
+            Expression::TernaryOperator(expression) => expression.to.clone(),
+            Expression::Function(expression) => expression.to.clone(),
+        }
+    }
 }
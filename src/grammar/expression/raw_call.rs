@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+use crate::squirrel_lexer::Location;
+
+use super::Expression;
+
+/// `rawcall(function, environment, arguments...)`, calling a function without
+/// going through its `_call` metamethod.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RawCallExpression {
+    pub arguments: Vec<Expression>,
+    pub from: Location,
+    pub to: Location,
+}
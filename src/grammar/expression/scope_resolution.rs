@@ -2,9 +2,11 @@ use serde::{Deserialize, Serialize};
 
 use crate::squirrel_lexer::Location;
 
+/// `scope::accessor`, `scope` holding every namespace before the accessor, e.g.
+/// `["a", "b"]` for `a::b::c`. It is empty for the root table access `::c`.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ScopeResolutionExpression {
-    pub scope: Option<String>,
+    pub scope: Vec<String>,
     pub accessor: String,
     pub from: Location,
     pub to: Location,
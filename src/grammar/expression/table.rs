@@ -16,6 +16,7 @@ pub enum TableEntry {
     Field(TableEntryField),
     Function(TableEntryFunction),
     FieldWithExpressionKey(TableEntryFieldWithExpressionKey),
+    FieldWithStringKey(TableEntryFieldWithStringKey),
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -40,3 +41,12 @@ pub struct TableEntryFieldWithExpressionKey {
     pub from: Location,
     pub to: Location,
 }
+
+/// JSON style `"key": value` slot.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TableEntryFieldWithStringKey {
+    pub key: Expression,
+    pub expression: Expression,
+    pub from: Location,
+    pub to: Location,
+}
@@ -2,7 +2,10 @@ use serde::{Deserialize, Serialize};
 
 use crate::squirrel_lexer::Location;
 
-use super::{expression::Expression, statements::Statement};
+use super::{
+    expression::{Expression, TableExpression},
+    statements::Statement,
+};
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct FunctionDefinition {
@@ -10,6 +13,13 @@ pub struct FunctionDefinition {
     pub parameters: Vec<Expression>,
     pub statement: Statement,
     pub is_static: bool,
+    /// Declared as `local function name()`.
+    pub is_local: bool,
+    /// Written as a lambda, `@(parameters) expression`, with a return statement
+    /// of the expression as its body.
+    pub is_lambda: bool,
+    /// `</ key = value />` before a class method or constructor.
+    pub attributes: Option<TableExpression>,
     pub from: Location,
     pub to: Location,
 }
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+
+use crate::squirrel_lexer::Location;
+
+use super::expression::Expression;
+
+/// `import "module" as name` or `from "module" import a, b as c`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ImportStatement {
+    /// Path of the module, as written between the quotes.
+    pub module: String,
+    /// Identifier the whole module is bound to.
+    pub alias: Option<Expression>,
+    /// Slots of the module bound with `from`.
+    pub names: Vec<ImportedName>,
+    pub from: Location,
+    pub to: Location,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ImportedName {
+    pub name: String,
+    pub alias: Option<String>,
+    pub from: Location,
+    pub to: Location,
+}
+
+impl ImportedName {
+    /// Name the slot is declared as in the script.
+    pub fn binding(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+}
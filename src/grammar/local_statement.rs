@@ -7,6 +7,8 @@ use super::expression::Expression;
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct LocalStatement {
     pub initializations: Vec<Initialization>,
+    /// Declared with `let`, which Quirrel doesn't allow to be assigned again.
+    pub is_let: bool,
     pub from: Location,
     pub to: Location,
 }
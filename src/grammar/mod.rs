@@ -1,8 +1,10 @@
 mod block_statement;
 mod break_statement;
 mod class_definition;
+mod comment;
 mod const_statement;
 mod continue_statement;
+mod destructuring_statement;
 mod do_while_statement;
 mod enum_statement;
 mod expression;
@@ -11,6 +13,7 @@ mod for_statement;
 mod foreach_statement;
 mod function_definition;
 mod if_statement;
+mod import_statement;
 mod local_statement;
 mod return_statement;
 mod switch_statement;
@@ -23,12 +26,17 @@ pub mod expressions {
     pub use super::expression::*;
 }
 
+pub mod trivia {
+    pub use super::comment::*;
+}
+
 pub mod statements {
     pub use super::block_statement::*;
     pub use super::break_statement::*;
     pub use super::class_definition::*;
     pub use super::const_statement::*;
     pub use super::continue_statement::*;
+    pub use super::destructuring_statement::*;
     pub use super::do_while_statement::*;
     pub use super::enum_statement::*;
     use super::expression::Expression;
@@ -38,6 +46,7 @@ pub mod statements {
     pub use super::foreach_statement::*;
     pub use super::function_definition::*;
     pub use super::if_statement::*;
+    pub use super::import_statement::*;
     pub use super::local_statement::*;
     pub use super::return_statement::*;
     pub use super::switch_statement::*;
@@ -67,6 +76,8 @@ pub mod statements {
         Expression(Box<ExpressionStatement>),
         Const(Box<ConstStatement>),
         Local(Box<LocalStatement>),
+        Destructuring(Box<DestructuringStatement>),
+        Import(Box<ImportStatement>),
         FunctionDefinition(Box<FunctionDefinition>),
         Class(Box<ClassDefinition>),
         Enum(Box<EnumStatement>),
@@ -227,11 +238,47 @@ pub mod statements {
 
         pub fn new_local(
             initializations: Vec<Initialization>,
+            is_let: bool,
             from: Location,
             to: Location,
         ) -> Statement {
             Statement::Local(Box::new(LocalStatement {
                 initializations,
+                is_let,
+                from,
+                to,
+            }))
+        }
+
+        pub fn new_destructuring(
+            kind: DestructuringKind,
+            bindings: Vec<Initialization>,
+            expression: Expression,
+            is_let: bool,
+            from: Location,
+            to: Location,
+        ) -> Statement {
+            Statement::Destructuring(Box::new(DestructuringStatement {
+                kind,
+                bindings,
+                expression,
+                is_let,
+                from,
+                to,
+            }))
+        }
+
+        pub fn new_import(
+            module: String,
+            alias: Option<Expression>,
+            names: Vec<ImportedName>,
+            from: Location,
+            to: Location,
+        ) -> Statement {
+            Statement::Import(Box::new(ImportStatement {
+                module,
+                alias,
+                names,
                 from,
                 to,
             }))
@@ -307,6 +354,10 @@ pub mod statements {
                 Statement::Expression(expression_statement) => expression_statement.from.clone(),
                 Statement::Const(const_statement) => const_statement.from.clone(),
                 Statement::Local(local_statement) => local_statement.from.clone(),
+                Statement::Destructuring(destructuring_statement) => {
+                    destructuring_statement.from.clone()
+                }
+                Statement::Import(import_statement) => import_statement.from.clone(),
                 Statement::FunctionDefinition(function_definition) => {
                     function_definition.from.clone()
                 }
@@ -315,7 +366,7 @@ pub mod statements {
             }
         }
 
-        fn get_to(&self) -> Location {
+        pub fn get_to(&self) -> Location {
             match self {
                 Statement::Block(block) => block.to.clone(),
                 Statement::If(if_statement) => if_statement.to.clone(),
@@ -333,6 +384,10 @@ pub mod statements {
                 Statement::Expression(expression_statement) => expression_statement.to.clone(),
                 Statement::Const(const_statement) => const_statement.to.clone(),
                 Statement::Local(local_statement) => local_statement.to.clone(),
+                Statement::Destructuring(destructuring_statement) => {
+                    destructuring_statement.to.clone()
+                }
+                Statement::Import(import_statement) => import_statement.to.clone(),
                 Statement::FunctionDefinition(function_definition) => {
                     function_definition.to.clone()
                 }
@@ -0,0 +1,296 @@
+use crate::{
+    ast_visitor::{visit, AstVisitor, AstVisitorResult},
+    const_evaluator::{identifier_name, ConstEvaluator},
+    generator_checker::Generators,
+    grammar::expressions::*,
+    grammar::statements::*,
+    scope_table::{ScopeTable, VariableDeclaration, VariableDeclarationKind},
+    squirrel_formatter::FormatterOptions,
+    squirrel_printer::Printer,
+};
+
+/// Finds a member access whose member name is under the cursor, e.g. `Red` in `Color.Red`.
+struct MemberAccessFinder {
+    line: usize,
+    character: usize,
+    found: Option<Expression>,
+}
+
+impl AstVisitor for MemberAccessFinder {
+    fn enter_member_access_expression(
+        &mut self,
+        expression: &MemberAccessExpression,
+    ) -> AstVisitorResult {
+        let member_from = expression
+            .to
+            .linechar
+            .saturating_sub(expression.member.len());
+
+        if expression.to.line == self.line
+            && member_from <= self.character
+            && self.character <= expression.to.linechar
+        {
+            self.found = Some(Expression::MemberAccess(Box::new(expression.clone())));
+            return AstVisitorResult::Break;
+        }
+
+        AstVisitorResult::Continue
+    }
+}
+
+/// Finds the attributes of the class field whose name starts at the given index.
+struct FieldAttributesFinder {
+    index: usize,
+    found: Option<TableExpression>,
+}
+
+impl AstVisitor for FieldAttributesFinder {
+    fn enter_class_definition(&mut self, class: &ClassDefinition) -> AstVisitorResult {
+        for member in &class.members {
+            if let ClassMemberDefinition::Field(field) = member {
+                if field.name.get_from().index == self.index {
+                    self.found = field.attributes.clone();
+                    return AstVisitorResult::Break;
+                }
+            }
+        }
+
+        AstVisitorResult::Continue
+    }
+}
+
+/// Builds the hover contents for the symbol at the given position as a Squirrel
+/// code snippet, including values of constants and enumerations.
+pub fn hover(
+    statements: &Statements,
+    scope_table: &ScopeTable,
+    line: usize,
+    character: usize,
+) -> Option<String> {
+    let evaluator = ConstEvaluator::new(statements, scope_table);
+
+    let mut finder = MemberAccessFinder {
+        line,
+        character,
+        found: None,
+    };
+    visit(statements, &mut finder);
+
+    if let Some(Expression::MemberAccess(access)) = &finder.found {
+        if let Ok(value) = evaluator.evaluate(finder.found.as_ref().unwrap()) {
+            let enum_name = identifier_name(&access.expression);
+            return Some(format!("{}.{} = {}", enum_name, access.member, value));
+        }
+    }
+
+    let declaration = match scope_table.find_variable_usage_by_location(line, character) {
+        Some(usage) => scope_table.get_declaration(usage.declaration.as_ref()?)?,
+        None => scope_table.find_variable_declaration_by_location(line, character)?,
+    };
+
+    let description = describe_declaration(declaration, &evaluator, &Generators::new(statements));
+
+    match attributes(statements, declaration) {
+        Some(attributes) => Some(format!(
+            "{}\n{}",
+            Printer::new(16, &FormatterOptions::default()).print_attribute_list(&attributes),
+            description
+        )),
+        None => Some(description),
+    }
+}
+
+/// Attributes of a class, class field or class method declaration.
+fn attributes(
+    statements: &Statements,
+    declaration: &VariableDeclaration,
+) -> Option<TableExpression> {
+    match (&declaration.kind, &declaration.value) {
+        (VariableDeclarationKind::Local, Some(Expression::Class(class))) => {
+            class.attributes.clone()
+        }
+        (VariableDeclarationKind::ClassMember, Some(Expression::Function(function))) => {
+            function.attributes.clone()
+        }
+        (VariableDeclarationKind::ClassMember, _) => {
+            let mut finder = FieldAttributesFinder {
+                index: declaration.from.index,
+                found: None,
+            };
+            visit(statements, &mut finder);
+            finder.found
+        }
+        _ => None,
+    }
+}
+
+fn describe_declaration(
+    declaration: &VariableDeclaration,
+    evaluator: &ConstEvaluator,
+    generators: &Generators,
+) -> String {
+    let value = declaration
+        .value
+        .as_ref()
+        .and_then(|value| evaluator.evaluate(value).ok());
+
+    match declaration.kind {
+        VariableDeclarationKind::Const => match value {
+            Some(value) => format!("const {} = {}", declaration.name, value),
+            None => format!("const {}", declaration.name),
+        },
+        VariableDeclarationKind::Local => match (&declaration.value, value) {
+            (Some(Expression::Class(_)), _) => format!("class {}", declaration.name),
+            (Some(Expression::Function(function)), _) if function.is_local => format!(
+                "local function {}({})",
+                declaration.name,
+                parameters(function)
+            ),
+            (_, Some(value)) => format!("local {} = {}", declaration.name, value),
+            _ => format!("local {}", declaration.name),
+        },
+        VariableDeclarationKind::Function => match &declaration.value {
+            Some(Expression::Function(function)) if generators.is_generator(function) => format!(
+                "(generator) function {}({})",
+                declaration.name,
+                parameters(function)
+            ),
+            Some(Expression::Function(function)) => {
+                format!("function {}({})", declaration.name, parameters(function))
+            }
+            _ => format!("function {}()", declaration.name),
+        },
+        VariableDeclarationKind::Parameter => format!("(parameter) {}", declaration.name),
+        VariableDeclarationKind::CatchVariable => format!("(catch variable) {}", declaration.name),
+        VariableDeclarationKind::Import => format!("(import) {}", declaration.name),
+        VariableDeclarationKind::ClassMember => format!("(member) {}", declaration.name),
+        VariableDeclarationKind::Enum => {
+            let Some(statement) = evaluator.enum_statement(declaration.from.index) else {
+                return format!("enum {}", declaration.name);
+            };
+
+            let members = evaluator
+                .enum_values(statement)
+                .into_iter()
+                .map(|(name, value)| match value {
+                    Ok(value) => format!("    {} = {}", name, value),
+                    Err(_) => format!("    {}", name),
+                })
+                .collect::<Vec<_>>();
+
+            format!("enum {} {{\n{}\n}}", declaration.name, members.join(",\n"))
+        }
+        VariableDeclarationKind::EnumMember => {
+            let Some(statement) = evaluator.enum_containing(declaration.from.index) else {
+                return format!("(enum member) {}", declaration.name);
+            };
+
+            let value = evaluator
+                .enum_values(statement)
+                .into_iter()
+                .find(|(name, _)| *name == declaration.name)
+                .and_then(|(_, value)| value.ok());
+
+            match value {
+                Some(value) => format!(
+                    "{}.{} = {}",
+                    identifier_name(&statement.name),
+                    declaration.name,
+                    value
+                ),
+                None => format!("{}.{}", identifier_name(&statement.name), declaration.name),
+            }
+        }
+    }
+}
+
+fn parameters(function: &FunctionDefinition) -> String {
+    function
+        .parameters
+        .iter()
+        .map(|parameter| match parameter {
+            Expression::Identifier(identifier) => identifier.token.to_string(),
+            Expression::BinaryOperator(op) => identifier_name(&op.left),
+            Expression::Spread(_) => "...".to_string(),
+            _ => String::new(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{scope_table::ScopeTable, squirrel_parser::Parser};
+
+    use super::hover;
+
+    #[test]
+    fn test_hover_constant_values() {
+        let input = "
+const A = 2 * 21;
+enum Color {
+    Red,
+    Green = 10,
+    Blue
+}
+local a = A + Color.Blue;
+function gen(n) { yield n; }
+";
+        let mut parser = Parser::new(input);
+        let statements = parser.parse().unwrap();
+        let scope_table = ScopeTable::new(&statements);
+
+        assert_eq!(
+            hover(&statements, &scope_table, 7, 10),
+            Some("const A = 42".to_string())
+        );
+        assert_eq!(
+            hover(&statements, &scope_table, 7, 22),
+            Some("Color.Blue = 1".to_string())
+        );
+        assert_eq!(
+            hover(&statements, &scope_table, 7, 6),
+            Some("local a = 43".to_string())
+        );
+        assert_eq!(
+            hover(&statements, &scope_table, 4, 5),
+            Some("Color.Green = 10".to_string())
+        );
+        assert_eq!(
+            hover(&statements, &scope_table, 8, 10),
+            Some("(generator) function gen(n)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_hover_attributes() {
+        let input = "
+class Foo </ version = 2 /> {
+    </ editable = true, label = \"Name\" />
+    name = null;
+    </ deprecated = true /> function bar() {}
+    baz = 1;
+}
+";
+        let mut parser = Parser::new(input);
+        let statements = parser.parse().unwrap();
+        let scope_table = ScopeTable::new(&statements);
+
+        assert_eq!(
+            hover(&statements, &scope_table, 1, 7),
+            Some("</ version = 2 />\nclass Foo".to_string())
+        );
+        assert_eq!(
+            hover(&statements, &scope_table, 3, 5),
+            Some("</ editable = true, label = \"Name\" />\n(member) name".to_string())
+        );
+        assert_eq!(
+            hover(&statements, &scope_table, 4, 38),
+            Some("</ deprecated = true />\n(member) bar".to_string())
+        );
+        assert_eq!(
+            hover(&statements, &scope_table, 5, 5),
+            Some("(member) baz".to_string())
+        );
+    }
+}
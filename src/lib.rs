@@ -0,0 +1,89 @@
+//! Lexer, parser, scope analysis and formatter for the Squirrel language, the
+//! building blocks of the `squirrel_lsp` language server.
+//!
+//! The stable API consists of the modules below and the types re-exported at
+//! the crate root:
+//!
+//! - [`squirrel_lexer`]: [`Lexer`] turning source text into [`Token`]s
+//! - [`dialect`]: the [`Dialect`] of Squirrel the lexer and parser follow
+//! - [`squirrel_parser`]: [`Parser`] building the [`grammar`] AST
+//! - [`ast_visitor`]: [`AstVisitor`] walking the AST with [`visit`]
+//! - [`ast_visitor_mut`]: [`AstVisitorMut`] rewriting the AST with [`visit_mut`]
+//! - [`ast_index`]: [`AstIndex`] giving nodes a [`NodeId`] and finding them by position
+//! - [`codemod`]: structural search and replace [`Rule`]s written as Squirrel snippets
+//! - [`scope_table`]: [`ScopeTable`] resolving declarations and their usages
+//! - [`diagnostics`]: every check the language server reports as a [`Diagnostic`], with their [`Config`]
+//! - [`squirrel_formatter`]: [`Formatter`] and its [`FormatterOptions`]
+//! - [`syntax`]: lossless [`SyntaxTree`] keeping whitespace and comments
+//!
+//! The language server dependencies are behind the default `lsp` feature, tools
+//! only analyzing or formatting scripts can leave them out with
+//! `default-features = false`.
+//!
+//! ```
+//! use squirrel_analysis::{Formatter, FormatterOptions, Parser, ScopeTable};
+//!
+//! let source = "local a   =  1\nprint(a)";
+//!
+//! let statements = Parser::new(source).parse().unwrap();
+//! let scope_table = ScopeTable::new(&statements);
+//! assert_eq!(scope_table.scopes()[0].variables[0].name, "a");
+//!
+//! let formatted = Formatter::new(source).format(&FormatterOptions::default()).unwrap();
+//! assert_eq!(formatted, "local a = 1\nprint(a)");
+//! ```
+
+pub mod ast_index;
+pub mod ast_visitor;
+pub mod ast_visitor_mut;
+pub mod codemod;
+pub mod config;
+pub mod diagnostics;
+pub mod dialect;
+pub mod grammar;
+pub mod scope_table;
+pub mod squirrel_formatter;
+pub mod squirrel_lexer;
+pub mod squirrel_parser;
+pub mod syntax;
+
+// Editor features the language server binary is built from. They follow the
+// needs of the server and aren't part of the stable API.
+#[doc(hidden)]
+pub mod cli;
+#[doc(hidden)]
+pub mod code_actions;
+#[cfg(feature = "lsp")]
+#[doc(hidden)]
+pub mod document_symbols;
+#[doc(hidden)]
+pub mod hover;
+#[cfg(feature = "lsp")]
+#[doc(hidden)]
+pub mod range_formatting;
+#[cfg(feature = "lsp")]
+#[doc(hidden)]
+pub mod refactor;
+
+mod const_evaluator;
+mod doc_comments;
+mod duplicate_checker;
+mod generator_checker;
+mod precedence;
+mod pretty;
+mod squirrel_printer;
+mod type_checker;
+mod unused_checker;
+
+pub use ast_index::{AstIndex, NodeId};
+pub use ast_visitor::{visit, AstVisitor, AstVisitorResult};
+pub use ast_visitor_mut::{visit_mut, AstVisitorMut};
+pub use codemod::Rule;
+pub use config::Config;
+pub use diagnostics::{Diagnostic, Severity};
+pub use dialect::Dialect;
+pub use scope_table::ScopeTable;
+pub use squirrel_formatter::{Formatter, FormatterOptions};
+pub use squirrel_lexer::{Lexer, Location, Token};
+pub use squirrel_parser::{Parser, ParserErrorWithLocation};
+pub use syntax::SyntaxTree;
@@ -1,7 +1,10 @@
 use std::collections::HashMap;
 
 use difference::{Changeset, Difference};
-use squirrel_formatter::Formatter;
+use squirrel_analysis::{
+    cli, code_actions, codemod, diagnostics, document_symbols, hover, range_formatting, refactor,
+    scope_table, squirrel_parser, Config, Formatter, FormatterOptions, Rule,
+};
 use tokio::fs;
 use tokio::sync::Mutex;
 use tower_lsp::jsonrpc::Result;
@@ -9,17 +12,10 @@ use tower_lsp::lsp_types::request::{GotoDeclarationParams, GotoDeclarationRespon
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer, LspService, Server};
 
-mod ast_visitor;
-mod grammar;
-mod scope_table;
-mod squirrel_formatter;
-mod squirrel_lexer;
-mod squirrel_parser;
-mod squirrel_printer;
-
 #[derive(Debug)]
 struct Backend {
     documents: Mutex<HashMap<Url, String>>,
+    config: Mutex<Config>,
     client: Client,
 }
 
@@ -30,32 +26,115 @@ impl Backend {
             documents.insert(params.uri.clone(), params.text.clone());
         }
 
-        let mut parser = squirrel_parser::Parser::new(&params.text);
+        self.validate(params.uri, &params.text, Some(params.version))
+            .await;
+    }
 
-        let result = parser.parse();
+    async fn validate(&self, uri: Url, text: &str, version: Option<i32>) {
+        let config = self.config.lock().await.clone();
+        let diagnostics = diagnostics::check(text, &config)
+            .into_iter()
+            .map(Into::into)
+            .collect();
 
-        let mut diagnostics = Vec::new();
-        if result.is_err() {
-            let error = result.err().unwrap();
+        self.client
+            .publish_diagnostics(uri, diagnostics, version)
+            .await;
+    }
 
-            diagnostics.push(Diagnostic::new_simple(
-                Range::new(
-                    Position::new(error.from.line as u32, error.from.linechar as u32),
-                    Position::new(error.to.line as u32, error.to.linechar as u32),
-                ),
-                error.details,
+    /// Options from the closest `.squirrelfmt.json`, or the client configuration if
+    /// there is none, with the editor's indentation where neither sets one.
+    async fn formatter_options(&self, uri: &Url, options: &FormattingOptions) -> FormatterOptions {
+        self.project_formatter_options(uri)
+            .await
+            .with_editor_indentation(options.tab_size, options.insert_spaces)
+    }
+
+    /// Options from the closest `.squirrelfmt.json`, or the client configuration if
+    /// there is none. A broken file is shown to the user and left out.
+    async fn project_formatter_options(&self, uri: &Url) -> FormatterOptions {
+        let project_options = match uri.to_file_path() {
+            Ok(path) => FormatterOptions::discover(&path),
+            Err(()) => Ok(None),
+        };
+
+        match project_options {
+            Ok(Some(options)) => options,
+            Ok(None) => self.config.lock().await.formatting.clone(),
+            Err(error) => {
+                self.client
+                    .show_message(MessageType::ERROR, error.to_string())
+                    .await;
+                self.config.lock().await.formatting.clone()
+            }
+        }
+    }
+
+    /// Rewrites the open documents with a structural search and replace rule,
+    /// returning the number of replacements.
+    async fn codemod(&self, arguments: &[serde_json::Value]) -> Result<Option<serde_json::Value>> {
+        let [serde_json::Value::String(search), serde_json::Value::String(replace)] = arguments
+        else {
+            return Err(tower_lsp::jsonrpc::Error::invalid_params(
+                "expected the search and replace snippets",
             ));
-        } else {
-            let result = result.unwrap();
-            let scope_table = scope_table::ScopeTable::new(&result);
+        };
 
-            let mut errors = scope_table.validate_variables();
+        let dialect = self.config.lock().await.dialect;
+        let rule = Rule::new(search, replace, dialect)
+            .map_err(|error| tower_lsp::jsonrpc::Error::invalid_params(error.to_string()))?;
+
+        let documents = self.documents.lock().await.clone();
+        let mut changes = HashMap::new();
+        let mut count = 0;
+        let mut skipped = 0;
+
+        for (uri, text) in documents {
+            let Ok(statements) = squirrel_parser::Parser::new(&text)
+                .with_dialect(dialect)
+                .parse()
+            else {
+                continue;
+            };
+
+            let options = self.project_formatter_options(&uri).await;
+            let edits = rule.edits(&text, &statements, &options);
+            skipped += edits.skipped.len();
+            let edits = edits
+                .edits
+                .into_iter()
+                .map(|edit| TextEdit {
+                    range: Range::new(
+                        Position::new(edit.from.line as u32, edit.from.linechar as u32),
+                        Position::new(edit.to.line as u32, edit.to.linechar as u32),
+                    ),
+                    new_text: edit.text,
+                })
+                .collect::<Vec<_>>();
+
+            if !edits.is_empty() {
+                count += edits.len();
+                changes.insert(uri, edits);
+            }
+        }
 
-            diagnostics.append(&mut errors);
+        if !changes.is_empty() {
+            self.client.apply_edit(WorkspaceEdit::new(changes)).await?;
         }
-        self.client
-            .publish_diagnostics(params.uri.clone(), diagnostics, Some(params.version))
-            .await;
+
+        if skipped > 0 {
+            self.client
+                .show_message(
+                    MessageType::WARNING,
+                    format!(
+                        "{} matches holding comments were left alone, replacing them would drop the comments",
+                        skipped
+                    ),
+                )
+                .await;
+        }
+
+        Ok(Some(serde_json::Value::from(count)))
     }
 }
 
@@ -79,7 +158,8 @@ impl LanguageServer for Backend {
             tower_lsp::jsonrpc::Error::invalid_params("failed to read file contents")
         })?;
 
-        let mut parser = squirrel_parser::Parser::new(&contents);
+        let dialect = self.config.lock().await.dialect;
+        let mut parser = squirrel_parser::Parser::new(&contents).with_dialect(dialect);
 
         let result = parser.parse();
 
@@ -147,7 +227,8 @@ impl LanguageServer for Backend {
             tower_lsp::jsonrpc::Error::invalid_params("failed to read file contents")
         })?;
 
-        let mut parser = squirrel_parser::Parser::new(&contents);
+        let dialect = self.config.lock().await.dialect;
+        let mut parser = squirrel_parser::Parser::new(&contents).with_dialect(dialect);
 
         let result = parser.parse();
 
@@ -197,7 +278,11 @@ impl LanguageServer for Backend {
         ))))
     }
 
-    async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        if let Some(config) = params.initialization_options.and_then(Config::from_json) {
+            *self.config.lock().await = config;
+        }
+
         Ok(InitializeResult {
             capabilities: ServerCapabilities {
                 inlay_hint_provider: Some(OneOf::Left(true)),
@@ -214,7 +299,21 @@ impl LanguageServer for Backend {
                 definition_provider: Some(OneOf::Left(true)),
                 declaration_provider: Some(DeclarationCapability::Simple(true)),
                 document_formatting_provider: Some(OneOf::Left(true)),
-                //hover_provider: Some(HoverProviderCapability::Simple(true)),
+                document_range_formatting_provider: Some(OneOf::Left(true)),
+                document_on_type_formatting_provider: Some(DocumentOnTypeFormattingOptions {
+                    first_trigger_character: "}".to_string(),
+                    more_trigger_character: Some(vec!["\n".to_string()]),
+                }),
+                hover_provider: Some(HoverProviderCapability::Simple(true)),
+                document_symbol_provider: Some(OneOf::Left(true)),
+                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: vec![
+                        code_actions::ADD_API_DEFINITION_COMMAND.to_string(),
+                        codemod::CODEMOD_COMMAND.to_string(),
+                    ],
+                    ..ExecuteCommandOptions::default()
+                }),
                 ..ServerCapabilities::default()
             },
             ..InitializeResult::default()
@@ -256,6 +355,17 @@ impl LanguageServer for Backend {
         })
         .await
     }
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        if let Some(config) = Config::from_json(params.settings) {
+            *self.config.lock().await = config;
+        }
+
+        let documents = self.documents.lock().await.clone();
+        for (uri, text) in documents {
+            self.validate(uri, &text, None).await;
+        }
+    }
+
     async fn initialized(&self, _: InitializedParams) {
         self.client
             .log_message(MessageType::INFO, "server initialized!")
@@ -274,9 +384,14 @@ impl LanguageServer for Backend {
                 .clone()
         };
 
-        let mut formatter = Formatter::new(&contents);
+        let options = self
+            .formatter_options(&params.text_document.uri, &params.options)
+            .await;
+
+        let dialect = self.config.lock().await.dialect;
+        let mut formatter = Formatter::new(&contents).with_dialect(dialect);
         let mut formatted = formatter
-            .format()
+            .format(&options)
             .map_err(|_| tower_lsp::jsonrpc::Error::invalid_params("failed to format document"))?;
 
         if let Some(insert_final_newline) = params.options.insert_final_newline {
@@ -355,14 +470,196 @@ impl LanguageServer for Backend {
         Ok(Some(edits))
     }
 
+    async fn range_formatting(
+        &self,
+        params: DocumentRangeFormattingParams,
+    ) -> Result<Option<Vec<TextEdit>>> {
+        let contents = {
+            self.documents
+                .lock()
+                .await
+                .get(&params.text_document.uri)
+                .ok_or(tower_lsp::jsonrpc::Error::invalid_params(
+                    "failed to get document",
+                ))?
+                .clone()
+        };
+
+        let options = self
+            .formatter_options(&params.text_document.uri, &params.options)
+            .await;
+
+        let dialect = self.config.lock().await.dialect;
+
+        Ok(range_formatting::format_range(
+            &contents,
+            params.range,
+            &options,
+            dialect,
+        ))
+    }
+
+    async fn on_type_formatting(
+        &self,
+        params: DocumentOnTypeFormattingParams,
+    ) -> Result<Option<Vec<TextEdit>>> {
+        let uri = params.text_document_position.text_document.uri;
+        let contents = {
+            self.documents
+                .lock()
+                .await
+                .get(&uri)
+                .ok_or(tower_lsp::jsonrpc::Error::invalid_params(
+                    "failed to get document",
+                ))?
+                .clone()
+        };
+
+        let options = self.formatter_options(&uri, &params.options).await;
+        let dialect = self.config.lock().await.dialect;
+
+        Ok(range_formatting::format_on_type(
+            &contents,
+            params.text_document_position.position,
+            &params.ch,
+            &options,
+            dialect,
+        ))
+    }
+
     async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
-        let _ = params;
-        Ok(Some(Hover {
-            contents: HoverContents::Scalar(MarkedString::String("Hello world".to_string())),
+        let contents = {
+            self.documents
+                .lock()
+                .await
+                .get(&params.text_document_position_params.text_document.uri)
+                .ok_or(tower_lsp::jsonrpc::Error::invalid_params(
+                    "failed to get document",
+                ))?
+                .clone()
+        };
+
+        let dialect = self.config.lock().await.dialect;
+        let mut parser = squirrel_parser::Parser::new(&contents).with_dialect(dialect);
+
+        let result = parser.parse();
+
+        if result.is_err() {
+            return Ok(None);
+        }
+
+        let result = result.unwrap();
+        let scope_table = scope_table::ScopeTable::new(&result);
+
+        let position = params.text_document_position_params.position;
+        let text = hover::hover(
+            &result,
+            &scope_table,
+            position.line as usize,
+            position.character as usize,
+        );
+
+        Ok(text.map(|text| Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```squirrel\n{}\n```", text),
+            }),
             range: None,
         }))
     }
 
+    async fn document_symbol(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> Result<Option<DocumentSymbolResponse>> {
+        let contents = {
+            self.documents
+                .lock()
+                .await
+                .get(&params.text_document.uri)
+                .ok_or(tower_lsp::jsonrpc::Error::invalid_params(
+                    "failed to get document",
+                ))?
+                .clone()
+        };
+
+        let dialect = self.config.lock().await.dialect;
+        let mut parser = squirrel_parser::Parser::new(&contents).with_dialect(dialect);
+
+        let Ok(result) = parser.parse() else {
+            return Ok(None);
+        };
+
+        Ok(Some(DocumentSymbolResponse::Nested(
+            document_symbols::document_symbols(&result),
+        )))
+    }
+
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let contents = {
+            self.documents
+                .lock()
+                .await
+                .get(&params.text_document.uri)
+                .ok_or(tower_lsp::jsonrpc::Error::invalid_params(
+                    "failed to get document",
+                ))?
+                .clone()
+        };
+
+        let mut actions = code_actions::code_actions(
+            &params.text_document.uri,
+            &contents,
+            &params.context.diagnostics,
+        );
+        let dialect = self.config.lock().await.dialect;
+        actions.extend(refactor::refactorings(
+            &params.text_document.uri,
+            &contents,
+            params.range,
+            dialect,
+        ));
+
+        Ok(Some(actions))
+    }
+
+    async fn execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> Result<Option<serde_json::Value>> {
+        if params.command == codemod::CODEMOD_COMMAND {
+            return self.codemod(&params.arguments).await;
+        }
+
+        if params.command != code_actions::ADD_API_DEFINITION_COMMAND {
+            return Err(tower_lsp::jsonrpc::Error::invalid_params(format!(
+                "unknown command {}",
+                params.command
+            )));
+        }
+
+        let Some(serde_json::Value::String(name)) = params.arguments.first() else {
+            return Err(tower_lsp::jsonrpc::Error::invalid_params(
+                "expected the name of the definition",
+            ));
+        };
+
+        // Only in memory, it has to be added to the client settings to be kept
+        {
+            let mut config = self.config.lock().await;
+            if !config.api_definitions.contains(name) {
+                config.api_definitions.push(name.clone());
+            }
+        }
+
+        let documents = self.documents.lock().await.clone();
+        for (uri, text) in documents {
+            self.validate(uri, &text, None).await;
+        }
+
+        Ok(None)
+    }
+
     async fn shutdown(&self) -> Result<()> {
         Ok(())
     }
@@ -370,12 +667,18 @@ impl LanguageServer for Backend {
 
 #[tokio::main]
 async fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if let Some(code) = cli::run(&args) {
+        std::process::exit(code);
+    }
+
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
 
     let (service, socket) = LspService::new(|client| Backend {
         client,
         documents: Mutex::new(HashMap::new()),
+        config: Mutex::new(Config::default()),
     });
     Server::new(stdin, stdout, socket).serve(service).await;
 }
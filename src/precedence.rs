@@ -0,0 +1,97 @@
+//! Operator precedence of expressions, for putting an expression in place of
+//! another without changing how the code around it is read.
+
+use std::collections::HashMap;
+
+use crate::{
+    ast_visitor::{visit, AstVisitor, AstVisitorResult},
+    grammar::expressions::*,
+    grammar::statements::*,
+    squirrel_parser::Parser,
+};
+
+/// Precedence of expressions binding tighter than any operator.
+const PRIMARY: usize = 12;
+
+/// Precedence of an expression, using the levels the parser reads operators in.
+pub(crate) fn precedence(expression: &Expression) -> usize {
+    match expression {
+        Expression::BinaryOperator(binary) => (0..=10)
+            .find(|level| {
+                Parser::operator_precedence(*level)
+                    .is_some_and(|operators| operators.contains(&binary.operator))
+            })
+            .unwrap_or(0),
+        Expression::UnaryOperator(_) => 11,
+        Expression::TernaryOperator(_) | Expression::Function(_) | Expression::Class(_) => 1,
+        _ => PRIMARY,
+    }
+}
+
+pub(crate) fn span(expression: &Expression) -> (usize, usize) {
+    (expression.get_from().index, expression.get_to().index)
+}
+
+/// The precedence an expression needs in its position to be used without
+/// parentheses, keyed by its span. Operands of the same precedence are
+/// parenthesized as well since the parser doesn't keep operators left-associative.
+#[derive(Default)]
+pub(crate) struct Requirements {
+    required: HashMap<(usize, usize), usize>,
+}
+
+impl Requirements {
+    pub(crate) fn new(statements: &Statements) -> Self {
+        let mut requirements = Self::default();
+        visit(statements, &mut requirements);
+        requirements
+    }
+
+    fn add(&mut self, expression: &Expression, precedence: usize) {
+        self.required.insert(span(expression), precedence);
+    }
+
+    /// Expressions outside of operators only need parentheses when they contain a comma.
+    fn get(&self, span: (usize, usize)) -> usize {
+        self.required.get(&span).copied().unwrap_or(1)
+    }
+
+    /// Wraps `replacement` in parentheses when it can't stand in place of the
+    /// expression at `span` as is.
+    pub(crate) fn parenthesize(&self, replacement: Expression, span: (usize, usize)) -> Expression {
+        if precedence(&replacement) >= self.get(span) {
+            return replacement;
+        }
+
+        let (from, to) = (replacement.get_from(), replacement.get_to());
+        Expression::grouping(Some(replacement), from, to)
+    }
+}
+
+impl AstVisitor for Requirements {
+    fn enter_expression(&mut self, expression: &Expression) -> AstVisitorResult {
+        match expression {
+            Expression::BinaryOperator(binary) => {
+                let required = precedence(expression) + 1;
+                self.add(&binary.left, required);
+                self.add(&binary.right, required);
+            }
+            Expression::TernaryOperator(ternary) => {
+                self.add(&ternary.condition, 2);
+                self.add(&ternary.if_true, 2);
+                self.add(&ternary.if_false, 2);
+            }
+            Expression::UnaryOperator(unary) => self.add(&unary.expression, PRIMARY),
+            Expression::PostfixUnaryOperator(postfix) => self.add(&postfix.expression, PRIMARY),
+            Expression::FunctionCall(call) => self.add(&call.function, PRIMARY),
+            Expression::MemberAccess(access) => self.add(&access.expression, PRIMARY),
+            Expression::ArrayAccess(access) => self.add(&access.array, PRIMARY),
+            Expression::Clone(clone) => self.add(&clone.expression, PRIMARY),
+            Expression::Resume(resume) => self.add(&resume.expression, PRIMARY),
+            Expression::Delete(delete) => self.add(&delete.expression, PRIMARY),
+            _ => {}
+        }
+
+        AstVisitorResult::Continue
+    }
+}
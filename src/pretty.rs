@@ -0,0 +1,461 @@
+//! Wadler style document algebra used by the [`Printer`](crate::squirrel_printer::Printer).
+//!
+//! The printer describes the code as a [`Doc`] made of groups, lines and indents,
+//! and [`layout`] decides which groups fit within the maximum line width and can be
+//! printed on a single line, rendering the formatted source.
+
+use crate::{squirrel_formatter::FormatterOptions, squirrel_lexer::Token};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Doc {
+    Token(Token),
+    /// Source printed verbatim, e.g. a region excluded from formatting
+    Text(String),
+    Concat(Vec<Doc>),
+    /// Printed on a single line when it fits, otherwise all of its lines break.
+    /// `force_break` makes the group break regardless of its width.
+    Group {
+        doc: Box<Doc>,
+        force_break: bool,
+    },
+    /// Contents are indented by one level after each line break within them
+    Indent(Box<Doc>),
+    /// A space when the enclosing group is flat, a line break otherwise
+    Line,
+    /// Nothing when the enclosing group is flat, a line break otherwise
+    SoftLine,
+    /// Always a line break
+    HardLine,
+    /// Alternating contents and separators, where each separator only breaks
+    /// when the contents following it wouldn't fit on the current line
+    Fill(Vec<Doc>),
+    IfBreak {
+        broken: Box<Doc>,
+        flat: Box<Doc>,
+    },
+    /// Deferred to the end of the line, e.g. a line comment after a list
+    /// element, which goes after the comma following the element
+    LineSuffix(Box<Doc>),
+    /// Breaks the enclosing groups, e.g. after a line comment
+    BreakParent,
+}
+
+impl Doc {
+    pub fn indent(doc: Doc) -> Self {
+        Doc::Indent(Box::new(doc))
+    }
+
+    pub fn if_break(broken: Doc, flat: Doc) -> Self {
+        Doc::IfBreak {
+            broken: Box::new(broken),
+            flat: Box::new(flat),
+        }
+    }
+
+    pub fn line_suffix(doc: Doc) -> Self {
+        Doc::LineSuffix(Box::new(doc))
+    }
+
+    pub fn nil() -> Self {
+        Doc::Concat(Vec::new())
+    }
+
+    /// Whether the document contains a group that always breaks, which makes
+    /// every group around it break as well
+    pub fn has_forced_break(&self) -> bool {
+        match self {
+            Doc::Group {
+                force_break: true, ..
+            } => true,
+            Doc::BreakParent => true,
+            Doc::Text(text) => text.contains('\n'),
+            Doc::Group { doc, .. } | Doc::Indent(doc) | Doc::LineSuffix(doc) => {
+                doc.has_forced_break()
+            }
+            Doc::Concat(docs) | Doc::Fill(docs) => docs.iter().any(Doc::has_forced_break),
+            Doc::IfBreak { broken, .. } => broken.has_forced_break(),
+            Doc::Token(_) | Doc::Line | Doc::SoftLine | Doc::HardLine => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+enum Command<'a> {
+    Print(Mode, &'a Doc),
+    Fill(Mode, &'a [Doc]),
+    LeaveIndent(usize),
+}
+
+/// Lays out the document within `options.max_width` columns.
+pub fn layout(doc: &Doc, options: &FormatterOptions) -> String {
+    let mut layout = Layout {
+        options,
+        output: String::new(),
+        column: 0,
+        line_start: false,
+        line_suffix: Vec::new(),
+        pending_indentation: Vec::new(),
+        applied_indentation: Vec::new(),
+        next_id: 0,
+    };
+
+    let mut commands = vec![Command::Print(Mode::Break, doc)];
+
+    loop {
+        let Some(command) = commands.pop() else {
+            if layout.line_suffix.is_empty() {
+                break;
+            }
+
+            commands.extend(layout.line_suffix.drain(..).rev());
+            continue;
+        };
+
+        match command {
+            Command::Print(mode, doc) => layout.print(mode, doc, &mut commands),
+            Command::Fill(mode, parts) => layout.fill(mode, parts, &mut commands),
+            Command::LeaveIndent(id) => layout.leave_indent(id),
+        }
+    }
+
+    layout.output
+}
+
+struct Layout<'a> {
+    options: &'a FormatterOptions,
+    output: String,
+    column: usize,
+    /// Nothing but the indentation, which is only written once the line
+    /// turns out not to be blank, was printed on the current line
+    line_start: bool,
+    line_suffix: Vec<Command<'a>>,
+    // Of several indents entered on the same line only the innermost one takes
+    // effect after a line break, so that e.g. a function passed as the argument
+    // of a call is only indented once.
+    pending_indentation: Vec<usize>,
+    applied_indentation: Vec<usize>,
+    next_id: usize,
+}
+
+impl<'a> Layout<'a> {
+    fn print(&mut self, mode: Mode, doc: &'a Doc, commands: &mut Vec<Command<'a>>) {
+        match doc {
+            Doc::Token(Token::Space) => {
+                // Spaces never start a line or follow each other
+                if !self.line_start && !self.output.is_empty() && !self.output.ends_with(' ') {
+                    self.write(" ");
+                }
+            }
+            Doc::Token(token) => self.write(&token.to_source_string()),
+            Doc::Text(text) => self.write(text),
+            Doc::Concat(docs) => {
+                commands.extend(docs.iter().rev().map(|doc| Command::Print(mode, doc)));
+            }
+            Doc::Group { doc, force_break } => {
+                let flat = mode == Mode::Flat
+                    || (!force_break
+                        && fits(
+                            Command::Print(Mode::Flat, doc),
+                            commands,
+                            self.remaining_width(),
+                        ));
+
+                match flat {
+                    true => commands.push(Command::Print(Mode::Flat, doc)),
+                    false => commands.push(Command::Print(Mode::Break, doc)),
+                }
+            }
+            Doc::Indent(doc) => {
+                let id = self.next_id;
+                self.next_id += 1;
+
+                self.pending_indentation
+                    .clone_from(&self.applied_indentation);
+                self.pending_indentation.push(id);
+
+                commands.push(Command::LeaveIndent(id));
+                commands.push(Command::Print(mode, doc));
+            }
+            Doc::Line => match mode {
+                Mode::Flat => self.write(" "),
+                Mode::Break => self.newline(mode, doc, commands),
+            },
+            Doc::SoftLine => {
+                if mode == Mode::Break {
+                    self.newline(mode, doc, commands);
+                }
+            }
+            Doc::HardLine => self.newline(mode, doc, commands),
+            Doc::Fill(parts) => commands.push(Command::Fill(mode, parts)),
+            Doc::IfBreak { broken, flat } => match mode {
+                Mode::Flat => commands.push(Command::Print(mode, flat)),
+                Mode::Break => commands.push(Command::Print(mode, broken)),
+            },
+            Doc::LineSuffix(doc) => self.line_suffix.push(Command::Print(mode, doc)),
+            Doc::BreakParent => {}
+        }
+    }
+
+    fn fill(&mut self, mode: Mode, parts: &'a [Doc], commands: &mut Vec<Command<'a>>) {
+        let Some((contents, rest)) = parts.split_first() else {
+            return;
+        };
+
+        let contents_mode = match fits(
+            Command::Print(Mode::Flat, contents),
+            &[],
+            self.remaining_width(),
+        ) {
+            true => Mode::Flat,
+            false => mode,
+        };
+
+        let Some((separator, rest)) = rest.split_first() else {
+            commands.push(Command::Print(contents_mode, contents));
+            return;
+        };
+
+        // The separator only stays flat when the contents after it still fit
+        let next_fits = rest.first().is_none_or(|next| {
+            let pair = Doc::Concat(vec![contents.clone(), separator.clone(), next.clone()]);
+            fits(
+                Command::Print(Mode::Flat, &pair),
+                &[],
+                self.remaining_width(),
+            )
+        });
+
+        let separator_mode = match next_fits {
+            true => Mode::Flat,
+            false => mode,
+        };
+
+        commands.push(Command::Fill(mode, rest));
+        commands.push(Command::Print(separator_mode, separator));
+        commands.push(Command::Print(contents_mode, contents));
+    }
+
+    fn leave_indent(&mut self, id: usize) {
+        if self.pending_indentation.last() == Some(&id) {
+            self.pending_indentation.pop();
+        }
+
+        if self.applied_indentation.last() == Some(&id) {
+            self.applied_indentation.pop();
+        }
+    }
+
+    /// Breaks the line, after flushing the line suffixes in front of the break
+    fn newline(&mut self, mode: Mode, line: &'a Doc, commands: &mut Vec<Command<'a>>) {
+        if !self.line_suffix.is_empty() {
+            commands.push(Command::Print(mode, line));
+            commands.extend(self.line_suffix.drain(..).rev());
+            return;
+        }
+
+        if let Some(id) = self.pending_indentation.last().cloned() {
+            if self.applied_indentation.last() != Some(&id) {
+                self.applied_indentation.push(id);
+            }
+        }
+
+        let trimmed = self.output.trim_end_matches([' ', '\t']).len();
+        self.output.truncate(trimmed);
+        self.output.push('\n');
+
+        self.line_start = true;
+        self.column = self.applied_indentation.len() * self.options.indentation_width();
+    }
+
+    fn write(&mut self, text: &str) {
+        if self.line_start {
+            self.line_start = false;
+            self.output
+                .push_str(&self.options.indentation(self.applied_indentation.len()));
+        }
+
+        match text.rfind('\n') {
+            Some(newline) => self.column = text[newline + 1..].chars().count(),
+            None => self.column += text.chars().count(),
+        }
+
+        self.output.push_str(text);
+    }
+
+    fn remaining_width(&self) -> isize {
+        self.options.max_width as isize - self.column as isize
+    }
+}
+
+/// Checks whether `next` followed by the `rest` of the commands fits on the
+/// current line, up to the first line break.
+fn fits(next: Command, rest: &[Command], mut width: isize) -> bool {
+    let mut rest = rest.iter().rev();
+    let mut stack = vec![match next {
+        Command::Print(mode, doc) => (mode, doc),
+        _ => return true,
+    }];
+
+    while width >= 0 {
+        let (mode, doc) = match stack.pop() {
+            Some(command) => command,
+            None => match rest.next() {
+                Some(Command::Print(mode, doc)) => (*mode, *doc),
+                Some(Command::Fill(mode, parts)) => {
+                    stack.extend(parts.iter().rev().map(|doc| (*mode, doc)));
+                    continue;
+                }
+                Some(_) => continue,
+                None => return true,
+            },
+        };
+
+        match doc {
+            Doc::Token(token) => width -= width_of(token),
+            Doc::Text(text) => match text.split_once('\n') {
+                Some((first_line, _)) => return first_line.chars().count() as isize <= width,
+                None => width -= text.chars().count() as isize,
+            },
+            Doc::Concat(docs) | Doc::Fill(docs) => {
+                stack.extend(docs.iter().rev().map(|doc| (mode, doc)));
+            }
+            Doc::Group { doc, force_break } => {
+                let mode = match force_break {
+                    true => Mode::Break,
+                    false => mode,
+                };
+                stack.push((mode, doc));
+            }
+            Doc::Indent(doc) => stack.push((mode, doc)),
+            Doc::Line => match mode {
+                Mode::Flat => width -= 1,
+                Mode::Break => return true,
+            },
+            Doc::SoftLine => {
+                if mode == Mode::Break {
+                    return true;
+                }
+            }
+            Doc::HardLine => return true,
+            Doc::IfBreak { broken, flat } => match mode {
+                Mode::Flat => stack.push((mode, flat)),
+                Mode::Break => stack.push((mode, broken)),
+            },
+            // Line suffixes go past the end of the line, so they never overflow it
+            Doc::LineSuffix(_) | Doc::BreakParent => {}
+        }
+    }
+
+    false
+}
+
+fn width_of(token: &Token) -> isize {
+    token.to_source_string().chars().count() as isize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::squirrel_lexer::{CommentMarker, Operator};
+
+    fn group(doc: Doc) -> Doc {
+        Doc::Group {
+            force_break: doc.has_forced_break(),
+            doc: Box::new(doc),
+        }
+    }
+
+    fn identifier(name: &str) -> Doc {
+        Doc::Token(Token::Identifier(name.to_string()))
+    }
+
+    fn list(items: &[&str]) -> Doc {
+        let mut docs = vec![Doc::Token(Token::LeftBracket)];
+        let mut elements = Vec::new();
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                elements.push(Doc::Token(Token::Operator(Operator::Comma)));
+                elements.push(Doc::Line);
+            }
+            elements.push(identifier(item));
+        }
+        docs.push(Doc::indent(Doc::Concat(
+            [vec![Doc::SoftLine], elements].concat(),
+        )));
+        docs.push(Doc::SoftLine);
+        docs.push(Doc::Token(Token::RightBracket));
+        group(Doc::Concat(docs))
+    }
+
+    fn render(doc: &Doc, max_width: usize) -> String {
+        let options = FormatterOptions {
+            max_width,
+            ..FormatterOptions::default()
+        };
+
+        layout(doc, &options)
+    }
+
+    #[test]
+    fn test_group_stays_flat_when_it_fits() {
+        let doc = list(&["a", "b", "c"]);
+
+        assert_eq!(render(&doc, 80), "[a, b, c]");
+        assert_eq!(render(&doc, 8), "[\n  a,\n  b,\n  c\n]");
+    }
+
+    #[test]
+    fn test_nested_groups_break_from_the_outside() {
+        let doc = group(Doc::Concat(vec![
+            identifier("foo"),
+            Doc::Token(Token::LeftParenthesis),
+            Doc::indent(Doc::Concat(vec![Doc::SoftLine, list(&["aaaa", "bbbb"])])),
+            Doc::SoftLine,
+            Doc::Token(Token::RightParenthesis),
+        ]));
+
+        assert_eq!(render(&doc, 80), "foo([aaaa, bbbb])");
+        assert_eq!(render(&doc, 15), "foo(\n  [aaaa, bbbb]\n)");
+    }
+
+    #[test]
+    fn test_fill_breaks_only_separators_that_overflow() {
+        let items = ["aa", "bb", "cc", "dd", "ee"];
+        let mut parts = Vec::new();
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                parts.push(Doc::Line);
+            }
+            let mut contents = vec![identifier(item)];
+            if i < items.len() - 1 {
+                contents.push(Doc::Token(Token::Operator(Operator::Comma)));
+            }
+            parts.push(Doc::Concat(contents));
+        }
+
+        assert_eq!(render(&Doc::Fill(parts), 11), "aa, bb, cc,\ndd, ee");
+    }
+
+    #[test]
+    fn test_line_suffix_is_deferred_to_the_line_break() {
+        let comment = Doc::line_suffix(Doc::Concat(vec![
+            Doc::Token(Token::Space),
+            Doc::Token(Token::Comment(" first".to_string(), CommentMarker::Slashes)),
+        ]));
+        let doc = group(Doc::Concat(vec![
+            identifier("a"),
+            comment,
+            Doc::BreakParent,
+            Doc::Token(Token::Operator(Operator::Comma)),
+            Doc::Line,
+            identifier("b"),
+        ]));
+
+        assert_eq!(render(&doc, 80), "a, // first\nb");
+    }
+}
@@ -0,0 +1,229 @@
+use tower_lsp::lsp_types::{Position, Range, TextEdit};
+
+use crate::{
+    dialect::Dialect,
+    refactor::{edit, offset},
+    squirrel_formatter::FormatterOptions,
+    squirrel_printer::Printer,
+    syntax::{SyntaxKind, SyntaxTree},
+};
+
+/// Byte span of the smallest statement, or run of sibling statements, covering
+/// the offsets `start..=end`.
+fn enclosing_statements(tree: &SyntaxTree, start: usize, end: usize) -> Option<(usize, usize)> {
+    let token = tree.root().token_at_offset(start)?;
+
+    token
+        .parent()
+        .ancestors()
+        .filter(|node| node.kind().is_statement_list())
+        .find_map(|list| {
+            let statements = list
+                .children()
+                .into_iter()
+                .filter(|child| child.kind().is_statement())
+                .map(|child| child.text_range())
+                .collect::<Vec<_>>();
+
+            // The range must lie within whatever holds the list, otherwise a
+            // deeper list wouldn't cover all of it
+            let range = list.text_range();
+            let within = match list.kind() {
+                SyntaxKind::BlockStatement => range.start < start && end < range.end,
+                SyntaxKind::SwitchCase => match (statements.first(), statements.last()) {
+                    (Some(first), Some(last)) => first.start <= start && end <= last.end,
+                    _ => false,
+                },
+                _ => true,
+            };
+
+            let touched = statements
+                .iter()
+                .filter(|statement| statement.start <= end && start <= statement.end)
+                .collect::<Vec<_>>();
+
+            match (within, touched.first(), touched.last()) {
+                (true, Some(first), Some(last)) => Some((first.start, last.end)),
+                _ => None,
+            }
+        })
+}
+
+/// Formats the statements between two offsets on their own, indenting them
+/// like the line they start on.
+fn format_span(
+    tree: &SyntaxTree,
+    from: usize,
+    to: usize,
+    options: &FormatterOptions,
+) -> Option<Vec<TextEdit>> {
+    let text = tree.text();
+    let statements = tree.statements(from, to)?;
+
+    let formatted = Printer::new(to - from, options)
+        .with_comments(text, tree.comments(from, to))
+        .print(&statements);
+
+    let line_start = text[..from].rfind('\n').map_or(0, |newline| newline + 1);
+    let indentation = text[line_start..from]
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .collect::<String>();
+
+    let formatted = formatted
+        .trim()
+        .lines()
+        .enumerate()
+        .map(|(i, line)| match line.trim().is_empty() || i == 0 {
+            true => line.trim_end().to_string(),
+            false => format!("{}{}", indentation, line),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    if formatted == text[from..to] {
+        return Some(Vec::new());
+    }
+
+    Some(vec![edit(text, from, to, formatted)])
+}
+
+/// Formats the statements covering the range, leaving the rest of the document as is.
+pub fn format_range(
+    text: &str,
+    range: Range,
+    options: &FormatterOptions,
+    dialect: Dialect,
+) -> Option<Vec<TextEdit>> {
+    let start = offset(text, range.start);
+    let end = offset(text, range.end).max(start);
+
+    let tree = SyntaxTree::parse(text, dialect).ok()?;
+    let (from, to) = enclosing_statements(&tree, start, end)?;
+
+    format_span(&tree, from, to, options)
+}
+
+/// Formats the statement closed by a `}` that was just typed, or the statement
+/// ending on the line a newline was just typed at the end of.
+pub fn format_on_type(
+    text: &str,
+    position: Position,
+    typed: &str,
+    options: &FormatterOptions,
+    dialect: Dialect,
+) -> Option<Vec<TextEdit>> {
+    let cursor = offset(text, position);
+    let tree = SyntaxTree::parse(text, dialect).ok()?;
+
+    let (from, to) = match typed {
+        "}" => {
+            let brace = cursor.checked_sub(1)?;
+            enclosing_statements(&tree, brace, brace)?
+        }
+        "\n" => {
+            let line_start = text[..cursor].rfind('\n')?;
+            let previous = text[..line_start].trim_end();
+            let last = previous.len().checked_sub(1)?;
+            let (from, to) = enclosing_statements(&tree, last, last)?;
+
+            // Typing a newline within a statement, e.g. after `{`, would otherwise
+            // reformat the line being edited
+            if to > line_start {
+                return Some(Vec::new());
+            }
+
+            (from, to)
+        }
+        _ => return Some(Vec::new()),
+    };
+
+    format_span(&tree, from, to, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(text: &str, edits: Vec<TextEdit>) -> String {
+        let mut result = text.to_string();
+
+        for edit in edits.iter().rev() {
+            let from = offset(&result, edit.range.start);
+            let to = offset(&result, edit.range.end);
+            result.replace_range(from..to, &edit.new_text);
+        }
+
+        result
+    }
+
+    #[test]
+    fn test_format_range_only_touches_enclosing_statements() {
+        let text = "local a   =  1\nfunction foo() {\n    local b=[1,2]\n    local c   = 3\n}\nlocal d   =  4\n";
+
+        let range = Range::new(Position::new(2, 6), Position::new(2, 8));
+        let edits = format_range(
+            text,
+            range,
+            &FormatterOptions::default(),
+            Dialect::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            apply(text, edits),
+            "local a   =  1\nfunction foo() {\n    local b = [1, 2]\n    local c   = 3\n}\nlocal d   =  4\n"
+        );
+    }
+
+    #[test]
+    fn test_format_range_spanning_statements() {
+        let text = "local a   =  1\nif (a) {\n  foo( a )\n}\nlocal d   =  4\n";
+
+        let range = Range::new(Position::new(0, 0), Position::new(2, 4));
+        let edits = format_range(
+            text,
+            range,
+            &FormatterOptions::default(),
+            Dialect::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            apply(text, edits),
+            "local a = 1\nif (a) {\n  foo(a)\n}\nlocal d   =  4\n"
+        );
+    }
+
+    #[test]
+    fn test_format_on_type() {
+        let options = FormatterOptions::default();
+
+        let text = "local a   =  1\nif (a) {\nfoo( a )\n}";
+        let edits =
+            format_on_type(text, Position::new(3, 1), "}", &options, Dialect::default()).unwrap();
+        assert_eq!(apply(text, edits), "local a   =  1\nif (a) {\n  foo(a)\n}");
+
+        let text = "local a   =  1\nlocal b   =  2\n";
+        let edits = format_on_type(
+            text,
+            Position::new(2, 0),
+            "\n",
+            &options,
+            Dialect::default(),
+        )
+        .unwrap();
+        assert_eq!(apply(text, edits), "local a   =  1\nlocal b = 2\n");
+
+        let text = "if (a) {\n\n}";
+        let edits = format_on_type(
+            text,
+            Position::new(1, 0),
+            "\n",
+            &options,
+            Dialect::default(),
+        )
+        .unwrap();
+        assert!(edits.is_empty());
+    }
+}
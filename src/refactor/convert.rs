@@ -0,0 +1,650 @@
+use std::collections::HashMap;
+
+use tower_lsp::lsp_types::{CodeActionKind, CodeActionOrCommand, TextEdit};
+
+use crate::{
+    ast_visitor::{visit, AstVisitor, AstVisitorResult},
+    code_actions::{indentation, whole_lines},
+    grammar::expressions::*,
+    grammar::statements::*,
+    scope_table::{DeclarationType, VariableDeclarationKind},
+    squirrel_lexer::{Keyword, Location, Operator, Token},
+};
+
+use super::{edit, location_at, skip_whitespace, to_range, tokens_between, RefactorContext};
+
+/// The innermost table or class around the cursor.
+struct Container<'c> {
+    text: &'c str,
+    cursor: usize,
+    size: usize,
+    table: Option<TableExpression>,
+    class: Option<ClassDefinition>,
+}
+
+impl Container<'_> {
+    fn find<'a>(context: &'a RefactorContext) -> Container<'a> {
+        let mut container = Container {
+            text: context.text,
+            cursor: context.selection.start,
+            size: usize::MAX,
+            table: None,
+            class: None,
+        };
+
+        visit(context.statements, &mut container);
+        container
+    }
+
+    fn contains(&mut self, from: &Location, to: &Location) -> bool {
+        let from = skip_whitespace(self.text, from).index;
+        let is_innermost =
+            from <= self.cursor && self.cursor < to.index && to.index - from < self.size;
+
+        if is_innermost {
+            self.size = to.index - from;
+        }
+
+        is_innermost
+    }
+}
+
+impl AstVisitor for Container<'_> {
+    fn enter_table_expression(&mut self, table: &TableExpression) -> AstVisitorResult {
+        if self.contains(&table.from, &table.to) {
+            self.table = Some(table.clone());
+            self.class = None;
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_class_definition(&mut self, class: &ClassDefinition) -> AstVisitorResult {
+        if self.contains(&class.from, &class.to) {
+            self.class = Some(class.clone());
+            self.table = None;
+        }
+
+        AstVisitorResult::Continue
+    }
+}
+
+/// Indentation of the members of a table or class, and the indentation one
+/// level deeper, derived from how the document is indented.
+fn member_indentation(text: &str, first_member: &Location, close: usize) -> (String, String) {
+    let outer = indentation(text, location_at(text, close).line as u32);
+    let member = indentation(text, first_member.line as u32);
+
+    let unit = match member.strip_prefix(&outer) {
+        Some(unit) if !unit.is_empty() => unit.to_string(),
+        _ => "    ".to_string(),
+    };
+
+    (member, unit)
+}
+
+/// The name a table is bound to, either by a `local` or by an assignment to a
+/// global, along with the local's declaration.
+struct TableName<'t> {
+    table: &'t TableExpression,
+    name: Option<(String, Option<Location>)>,
+}
+
+impl TableName<'_> {
+    fn is_table(&self, expression: &Expression) -> bool {
+        matches!(expression, Expression::Table(table) if table.from.index == self.table.from.index)
+    }
+}
+
+impl AstVisitor for TableName<'_> {
+    fn enter_local_statement(&mut self, statement: &LocalStatement) -> AstVisitorResult {
+        for initialization in &statement.initializations {
+            if initialization
+                .expression
+                .as_ref()
+                .is_some_and(|expression| self.is_table(expression))
+            {
+                self.name = Some((
+                    initialization.name.clone(),
+                    Some(initialization.from.clone()),
+                ));
+            }
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_binary_operator_expression(
+        &mut self,
+        expression: &BinaryOperatorExpression,
+    ) -> AstVisitorResult {
+        if matches!(expression.operator, Operator::Assign | Operator::LeftArrow)
+            && self.is_table(&expression.right)
+        {
+            if let Expression::Identifier(identifier) = &expression.left {
+                self.name = Some((identifier.token.to_string(), None));
+            }
+        }
+
+        AstVisitorResult::Continue
+    }
+}
+
+/// Accesses of a table's members through the name it's bound to, made from
+/// within the table's own functions.
+struct SelfReferences<'r> {
+    name: &'r str,
+    members: &'r [String],
+    functions: Vec<(usize, usize)>,
+    identifiers: Vec<(usize, usize)>,
+}
+
+impl AstVisitor for SelfReferences<'_> {
+    fn enter_member_access_expression(
+        &mut self,
+        expression: &MemberAccessExpression,
+    ) -> AstVisitorResult {
+        let Expression::Identifier(identifier) = &expression.expression else {
+            return AstVisitorResult::Continue;
+        };
+
+        let is_within_function = self
+            .functions
+            .iter()
+            .any(|(from, to)| *from <= identifier.from.index && identifier.from.index < *to);
+
+        if is_within_function
+            && identifier.token == Token::Identifier(self.name.to_string())
+            && self.members.contains(&expression.member)
+        {
+            self.identifiers
+                .push((identifier.from.index, identifier.to.index));
+        }
+
+        AstVisitorResult::Continue
+    }
+}
+
+/// Converts a table into a class with the same members. Functions of the table
+/// referring to its members through the name the table is bound to use `this`
+/// instead, and arrays and tables held by fields are created in a constructor
+/// since class fields share their initial value between instances.
+pub(crate) fn table_to_class(context: &RefactorContext) -> Option<CodeActionOrCommand> {
+    let table = Container::find(context).table?;
+    let text = context.text;
+
+    let mut members = Vec::new();
+    let mut mutable = Vec::new();
+    let mut functions = Vec::new();
+    let mut spans = Vec::new();
+
+    for entry in &table.entries {
+        match entry {
+            TableEntry::Field(field) => {
+                let Expression::Identifier(name) = &field.name else {
+                    return None;
+                };
+
+                members.push(name.token.to_string());
+                spans.push((field.from.clone(), field.to.index));
+
+                if matches!(
+                    field.expression,
+                    Expression::Array(_) | Expression::Table(_)
+                ) {
+                    mutable.push((name.token.to_string(), &field.expression));
+                }
+            }
+            TableEntry::Function(entry) => {
+                let Some(Expression::Identifier(name)) = &entry.function.name else {
+                    return None;
+                };
+
+                members.push(name.token.to_string());
+                functions.push((entry.function.from.index, entry.function.to.index));
+                spans.push((entry.from.clone(), entry.to.index));
+            }
+            TableEntry::FieldWithExpressionKey(_) | TableEntry::FieldWithStringKey(_) => {
+                return None
+            }
+        }
+    }
+
+    let open = skip_whitespace(text, &table.from).index;
+    let close = table.to.index - 1;
+    let mut edits = vec![edit(text, open, open, "class ")];
+
+    // Class members are separated by new lines or semicolons
+    let mut trailing_gap = None;
+    for (index, (_, end)) in spans.iter().enumerate() {
+        let next = spans
+            .get(index + 1)
+            .map_or(close, |(from, _)| skip_whitespace(text, from).index);
+
+        if index + 1 == spans.len() {
+            trailing_gap = Some((*end, next));
+            if !mutable.is_empty() {
+                continue;
+            }
+        }
+
+        for (token, from, to) in tokens_between(text, *end, next, true) {
+            if token != Token::Operator(Operator::Comma) {
+                continue;
+            }
+
+            let is_same_line =
+                next != close && location_at(text, next).line == location_at(text, from).line;
+
+            edits.push(edit(text, from, to, if is_same_line { ";" } else { "" }));
+        }
+    }
+
+    if !mutable.is_empty() {
+        let (first, _) = spans.first()?;
+        let (member, unit) = member_indentation(text, first, close);
+
+        for (_, value) in &mutable {
+            let (from, to) = (skip_whitespace(text, &value.get_from()), value.get_to());
+            edits.push(edit(text, from.index, to.index, "null"));
+        }
+
+        let is_multi_line = location_at(text, close).line > location_at(text, open).line
+            && text[..close].trim_end_matches([' ', '\t']).ends_with('\n');
+
+        if is_multi_line {
+            let assignments = mutable
+                .iter()
+                .map(|(name, value)| {
+                    let value =
+                        &text[skip_whitespace(text, &value.get_from()).index..value.get_to().index];
+                    format!(
+                        "{}{}this.{} = {}\n",
+                        member,
+                        unit,
+                        name,
+                        value.replace('\n', &format!("\n{}", unit))
+                    )
+                })
+                .collect::<String>();
+
+            let (end, next) = trailing_gap?;
+            for (token, from, to) in tokens_between(text, end, next, true) {
+                if token == Token::Operator(Operator::Comma) {
+                    edits.push(edit(text, from, to, ""));
+                }
+            }
+
+            let line_start = text[..close].rfind('\n').map_or(0, |newline| newline + 1);
+            edits.push(edit(
+                text,
+                line_start,
+                line_start,
+                format!(
+                    "\n{}constructor() {{\n{}{}}}\n",
+                    member, assignments, member
+                ),
+            ));
+        } else {
+            let assignments = mutable
+                .iter()
+                .map(|(name, value)| {
+                    let value =
+                        &text[skip_whitespace(text, &value.get_from()).index..value.get_to().index];
+                    format!("this.{} = {}", name, value)
+                })
+                .collect::<Vec<_>>()
+                .join("; ");
+
+            let (end, next) = trailing_gap?;
+            edits.push(edit(
+                text,
+                end,
+                next,
+                format!("; constructor() {{ {} }} ", assignments),
+            ));
+        }
+    }
+
+    // Once it's a class, the name refers to the class rather than the instance
+    let mut table_name = TableName {
+        table: &table,
+        name: None,
+    };
+    visit(context.statements, &mut table_name);
+
+    if let Some((name, declaration)) = table_name.name {
+        let mut references = SelfReferences {
+            name: &name,
+            members: &members,
+            functions,
+            identifiers: Vec::new(),
+        };
+        visit(context.statements, &mut references);
+
+        let usages = context.scope_table.usages_by_index();
+        for (from, to) in references.identifiers {
+            let resolved = usages
+                .get(&from)
+                .and_then(|usage| usage.declaration.as_ref())
+                .and_then(|declaration| match declaration {
+                    DeclarationType::Variable(..) => {
+                        context.scope_table.get_declaration(declaration)
+                    }
+                    _ => None,
+                });
+
+            let refers_to_table = match (&declaration, resolved) {
+                (Some(location), Some(variable)) => {
+                    variable.kind == VariableDeclarationKind::Local
+                        && variable.from.index == location.index
+                }
+                (None, None) => true,
+                _ => false,
+            };
+
+            if refers_to_table {
+                edits.push(edit(text, from, to, "this"));
+            }
+        }
+    }
+
+    Some(context.action(
+        "Convert table to class",
+        CodeActionKind::REFACTOR_REWRITE,
+        edits,
+    ))
+}
+
+/// Assignments of a constructor without parameters that only initializes
+/// fields of its class, by field name.
+fn field_initializers<'c>(
+    constructor: &'c FunctionDefinition,
+    fields: &[String],
+) -> Option<HashMap<String, &'c Expression>> {
+    if !constructor.parameters.is_empty() {
+        return None;
+    }
+
+    let Statement::Block(block) = &constructor.statement else {
+        return None;
+    };
+
+    let mut initializers = HashMap::new();
+
+    for statement in &block.statements.statements {
+        let Statement::Expression(statement) = statement else {
+            return None;
+        };
+
+        let Expression::BinaryOperator(assignment) = &statement.expression else {
+            return None;
+        };
+
+        let Expression::MemberAccess(access) = &assignment.left else {
+            return None;
+        };
+
+        let is_this = matches!(
+            &access.expression,
+            Expression::Identifier(identifier) if identifier.token == Token::Keyword(Keyword::This)
+        );
+
+        if assignment.operator != Operator::Assign
+            || !is_this
+            || !fields.contains(&access.member)
+            || initializers
+                .insert(access.member.clone(), &assignment.right)
+                .is_some()
+        {
+            return None;
+        }
+    }
+
+    Some(initializers)
+}
+
+/// Converts a class without a base class into a table. A constructor is only
+/// supported when all it does is initialize fields, the values are moved to
+/// the fields then.
+pub(crate) fn class_to_table(context: &RefactorContext) -> Option<CodeActionOrCommand> {
+    let class = Container::find(context).class?;
+    let text = context.text;
+
+    if class.extends.is_some() {
+        return None;
+    }
+
+    let fields = class
+        .members
+        .iter()
+        .filter_map(|member| match member {
+            ClassMemberDefinition::Field(field) => Some(identifier_text(&field.name)?),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let start = skip_whitespace(text, &class.from).index;
+    let open = start + text[start..].find('{')?;
+    let close = class.to.index - 1;
+
+    let header = match &class.name {
+        Some(name) => {
+            let name = &text[skip_whitespace(text, &name.get_from()).index..name.get_to().index];
+            format!("{} <- ", name)
+        }
+        None => String::new(),
+    };
+
+    let mut edits = vec![edit(text, start, open, header)];
+
+    let mut initializers = HashMap::new();
+    let mut spans = Vec::new();
+
+    for member in &class.members {
+        let (from, to, name, is_static) = match member {
+            ClassMemberDefinition::Field(field) => {
+                (&field.from, &field.to, &field.name, field.is_static)
+            }
+            ClassMemberDefinition::Method(method) => (
+                &method.from,
+                &method.to,
+                method.name.as_ref()?,
+                method.is_static,
+            ),
+            ClassMemberDefinition::Constructor(constructor) => {
+                initializers = field_initializers(constructor, &fields)?;
+
+                let from = skip_whitespace(text, &constructor.from);
+                edits.push(TextEdit::new(
+                    whole_lines(text, to_range(&from, &constructor.to)),
+                    String::new(),
+                ));
+                spans.push((from.index, constructor.to.index, true));
+                continue;
+            }
+        };
+
+        if is_static {
+            let previous = spans.last().map_or(open + 1, |(_, to, _)| *to);
+            let name_from = skip_whitespace(text, &name.get_from()).index;
+
+            for (token, token_from, _) in tokens_between(text, previous, name_from, true) {
+                if token == Token::Keyword(Keyword::Static) {
+                    let next = skip_whitespace(text, &location_at(text, token_from + 6)).index;
+                    edits.push(edit(text, token_from, next, ""));
+                }
+            }
+        }
+
+        spans.push((skip_whitespace(text, from).index, to.index, false));
+    }
+
+    // Fields are moved in the same order as the constructor initialized them
+    for member in &class.members {
+        let ClassMemberDefinition::Field(field) = member else {
+            continue;
+        };
+
+        let Some(value) = identifier_text(&field.name).and_then(|name| initializers.get(&name))
+        else {
+            continue;
+        };
+
+        let value_from = skip_whitespace(text, &value.get_from());
+        let statement_indentation = indentation(text, value_from.line as u32);
+        let field_indentation = indentation(text, field.from.line as u32);
+        let value = text[value_from.index..value.get_to().index].replace(
+            &format!("\n{}", statement_indentation),
+            &format!("\n{}", field_indentation),
+        );
+
+        let current = skip_whitespace(text, &field.expression.get_from());
+        edits.push(edit(
+            text,
+            current.index,
+            field.expression.get_to().index,
+            value,
+        ));
+    }
+
+    // Table entries are separated by commas
+    for (index, (_, end, is_constructor)) in spans.iter().enumerate() {
+        if *is_constructor {
+            continue;
+        }
+
+        let has_next = spans[index + 1..]
+            .iter()
+            .any(|(_, _, is_constructor)| !is_constructor);
+        let next = spans.get(index + 1).map_or(close, |(from, _, _)| *from);
+        let separator = tokens_between(text, *end, next, true)
+            .into_iter()
+            .find(|(token, _, _)| *token == Token::Semicolon);
+
+        match (separator, has_next) {
+            (Some((_, from, to)), true) => edits.push(edit(text, from, to, ",")),
+            (Some((_, from, to)), false) => edits.push(edit(text, from, to, "")),
+            (None, true) => edits.push(edit(text, *end, *end, ",")),
+            (None, false) => {}
+        }
+    }
+
+    Some(context.action(
+        "Convert class to table",
+        CodeActionKind::REFACTOR_REWRITE,
+        edits,
+    ))
+}
+
+fn identifier_text(expression: &Expression) -> Option<String> {
+    match expression {
+        Expression::Identifier(identifier) => Some(identifier.token.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{CodeActionOrCommand, Position, Range, Url};
+
+    use crate::{
+        dialect::Dialect,
+        refactor::{offset, refactorings},
+    };
+
+    fn apply(input: &str, position: Position, title: &str) -> Option<String> {
+        let uri = Url::parse("file:///test.nut").unwrap();
+
+        let mut edits = refactorings(
+            &uri,
+            input,
+            Range::new(position, position),
+            Dialect::default(),
+        )
+        .into_iter()
+        .find_map(|action| match action {
+            CodeActionOrCommand::CodeAction(action) if action.title == title => {
+                Some(action.edit.unwrap().changes.unwrap()[&uri].clone())
+            }
+            _ => None,
+        })?;
+
+        edits.sort_by_key(|edit| (edit.range.start.line, edit.range.start.character));
+
+        let mut output = input.to_string();
+        for edit in edits.iter().rev() {
+            let from = offset(input, edit.range.start);
+            let to = offset(input, edit.range.end);
+            output.replace_range(from..to, &edit.new_text);
+        }
+
+        Some(output)
+    }
+
+    #[test]
+    fn test_table_to_class() {
+        let input = "
+local t = {
+    a = 1,
+    items = [],
+    function get() {
+        return t.a;
+    }
+};
+";
+        assert_eq!(
+            apply(input, Position::new(2, 4), "Convert table to class").unwrap(),
+            "
+local t = class {
+    a = 1
+    items = null
+    function get() {
+        return this.a;
+    }
+
+    constructor() {
+        this.items = []
+    }
+};
+"
+        );
+    }
+
+    #[test]
+    fn test_class_to_table() {
+        let input = "
+class Foo {
+    static b = 2;
+    c = null;
+    constructor() {
+        this.c = [];
+    }
+    function get() { return c; }
+}
+";
+        assert_eq!(
+            apply(input, Position::new(3, 4), "Convert class to table").unwrap(),
+            "
+Foo <- {
+    b = 2,
+    c = [],
+    function get() { return c; }
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_class_with_base_is_not_converted() {
+        let input = "
+class Foo extends Bar {
+    a = 1;
+}
+";
+        assert_eq!(
+            apply(input, Position::new(2, 4), "Convert class to table"),
+            None
+        );
+    }
+}
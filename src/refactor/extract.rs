@@ -0,0 +1,576 @@
+use tower_lsp::lsp_types::{CodeActionKind, CodeActionOrCommand, TextEdit};
+
+use crate::{
+    ast_visitor::{visit, AstVisitor, AstVisitorResult},
+    code_actions::indentation,
+    grammar::expressions::*,
+    grammar::statements::*,
+    scope_table::{DeclarationType, VariableDeclaration, VariableDeclarationKind},
+    squirrel_lexer::{Location, Operator},
+};
+
+use super::{
+    skip_whitespace, to_position, to_range, AssignedIdentifiers, RefactorContext, Selection,
+};
+
+/// Finds the statements of the innermost statement list that are entirely
+/// covered by the selection, as long as no statement is only partially selected.
+struct StatementSelection<'s> {
+    text: &'s str,
+    selection: &'s Selection,
+    statements: Option<Vec<Statement>>,
+}
+
+impl StatementSelection<'_> {
+    fn check(&mut self, list: &Statements) {
+        let (Some(first), Some(last)) = (list.statements.first(), list.statements.last()) else {
+            return;
+        };
+
+        if skip_whitespace(self.text, &first.get_from()).index > self.selection.start
+            || last.get_to().index < self.selection.end
+        {
+            return;
+        }
+
+        let mut selected = Vec::new();
+
+        for statement in &list.statements {
+            let from = skip_whitespace(self.text, &statement.get_from());
+            let to = statement.get_to();
+
+            if self.selection.contains(&from, &to) {
+                selected.push(statement.clone());
+            } else if from.index < self.selection.end && self.selection.start < to.index {
+                return;
+            }
+        }
+
+        if !selected.is_empty() {
+            self.statements = Some(selected);
+        }
+    }
+}
+
+impl AstVisitor for StatementSelection<'_> {
+    fn enter_block_statement(&mut self, statement: &BlockStatement) -> AstVisitorResult {
+        self.check(&statement.statements);
+        AstVisitorResult::Continue
+    }
+
+    fn enter_switch_statement(&mut self, statement: &SwitchStatement) -> AstVisitorResult {
+        for case in &statement.cases {
+            self.check(&case.statements);
+        }
+
+        if let Some(default) = &statement.default {
+            self.check(default);
+        }
+
+        AstVisitorResult::Continue
+    }
+}
+
+/// Detects `return`, `yield`, `break` and `continue` that would leave the
+/// selected statements, which can't be moved into a function of their own.
+#[derive(Default)]
+struct EscapingControlFlow {
+    functions: usize,
+    loops: usize,
+    switches: usize,
+    escapes: bool,
+}
+
+impl AstVisitor for EscapingControlFlow {
+    fn enter_function_declaration(&mut self, _function: &FunctionDefinition) -> AstVisitorResult {
+        self.functions += 1;
+        AstVisitorResult::Continue
+    }
+
+    fn leave_function_declaration(&mut self, _function: &FunctionDefinition) -> AstVisitorResult {
+        self.functions -= 1;
+        AstVisitorResult::Continue
+    }
+
+    fn enter_while_statement(&mut self, _statement: &WhileStatement) -> AstVisitorResult {
+        self.loops += 1;
+        AstVisitorResult::Continue
+    }
+
+    fn leave_while_statement(&mut self, _statement: &WhileStatement) -> AstVisitorResult {
+        self.loops -= 1;
+        AstVisitorResult::Continue
+    }
+
+    fn enter_do_while_statement(&mut self, _statement: &DoWhileStatement) -> AstVisitorResult {
+        self.loops += 1;
+        AstVisitorResult::Continue
+    }
+
+    fn leave_do_while_statement(&mut self, _statement: &DoWhileStatement) -> AstVisitorResult {
+        self.loops -= 1;
+        AstVisitorResult::Continue
+    }
+
+    fn enter_for_statement(&mut self, _statement: &ForStatement) -> AstVisitorResult {
+        self.loops += 1;
+        AstVisitorResult::Continue
+    }
+
+    fn leave_for_statement(&mut self, _statement: &ForStatement) -> AstVisitorResult {
+        self.loops -= 1;
+        AstVisitorResult::Continue
+    }
+
+    fn enter_for_each_statement(&mut self, _statement: &ForEachStatement) -> AstVisitorResult {
+        self.loops += 1;
+        AstVisitorResult::Continue
+    }
+
+    fn leave_for_each_statement(&mut self, _statement: &ForEachStatement) -> AstVisitorResult {
+        self.loops -= 1;
+        AstVisitorResult::Continue
+    }
+
+    fn enter_switch_statement(&mut self, _statement: &SwitchStatement) -> AstVisitorResult {
+        self.switches += 1;
+        AstVisitorResult::Continue
+    }
+
+    fn leave_switch_statement(&mut self, _statement: &SwitchStatement) -> AstVisitorResult {
+        self.switches -= 1;
+        AstVisitorResult::Continue
+    }
+
+    fn enter_return_statement(&mut self, _statement: &ReturnStatement) -> AstVisitorResult {
+        self.escapes |= self.functions == 0;
+        AstVisitorResult::Continue
+    }
+
+    fn enter_yield_statement(&mut self, _statement: &YieldStatement) -> AstVisitorResult {
+        self.escapes |= self.functions == 0;
+        AstVisitorResult::Continue
+    }
+
+    fn enter_break_statement(&mut self, _statement: &BreakStatement) -> AstVisitorResult {
+        self.escapes |= self.functions == 0 && self.loops == 0 && self.switches == 0;
+        AstVisitorResult::Continue
+    }
+
+    fn enter_continue_statement(&mut self, _statement: &ContinueStatement) -> AstVisitorResult {
+        self.escapes |= self.functions == 0 && self.loops == 0;
+        AstVisitorResult::Continue
+    }
+}
+
+/// Variables that live on the stack of the enclosing function and so have to
+/// be passed to an extracted function explicitly.
+fn is_local(declaration: &VariableDeclaration) -> bool {
+    matches!(
+        declaration.kind,
+        VariableDeclarationKind::Local
+            | VariableDeclarationKind::Parameter
+            | VariableDeclarationKind::CatchVariable
+    )
+}
+
+/// Extracts the selected statements into a `local` function. Variables declared
+/// outside of the selection become parameters, a variable assigned within the
+/// selection and needed afterwards is returned.
+pub(crate) fn extract_function(context: &RefactorContext) -> Option<CodeActionOrCommand> {
+    let selection = &context.selection;
+    if selection.is_empty() {
+        return None;
+    }
+
+    let mut finder = StatementSelection {
+        text: context.text,
+        selection,
+        statements: None,
+    };
+    finder.check(context.statements);
+    visit(context.statements, &mut finder);
+
+    let statements = finder.statements?;
+    let from = skip_whitespace(context.text, &statements.first()?.get_from());
+    let to = statements.last()?.get_to();
+    let selected = Statements {
+        statements,
+        from: from.clone(),
+        to: to.clone(),
+    };
+
+    let mut control_flow = EscapingControlFlow::default();
+    visit(&selected, &mut control_flow);
+    if control_flow.escapes {
+        return None;
+    }
+
+    let scope_table = context.scope_table;
+    let within = |location: &Location| from.index <= location.index && location.index < to.index;
+
+    let mut parameters: Vec<(usize, &VariableDeclaration)> = Vec::new();
+    let mut outputs: Vec<(&VariableDeclaration, bool)> = Vec::new();
+
+    let mut assigned = AssignedIdentifiers::default();
+    visit(&selected, &mut assigned);
+
+    for scope in scope_table.scopes() {
+        for usage in &scope.variable_usage {
+            let Some(declaration) = &usage.declaration else {
+                continue;
+            };
+
+            let Some(variable) = scope_table.get_declaration(declaration) else {
+                // `this` and `base` would refer to something else within a function
+                if within(&usage.from) {
+                    return None;
+                }
+                continue;
+            };
+
+            if within(&usage.from) && !within(&variable.from) && is_local(variable) {
+                if !parameters
+                    .iter()
+                    .any(|(_, parameter)| std::ptr::eq(*parameter, variable))
+                {
+                    parameters.push((usage.from.index, variable));
+                }
+
+                let is_output = outputs
+                    .iter()
+                    .any(|(output, _)| std::ptr::eq(*output, variable));
+                if assigned.positions.contains(&usage.from.index) && !is_output {
+                    outputs.push((variable, false));
+                }
+            }
+
+            // Declared within the selection but still needed after it
+            if usage.from.index >= to.index
+                && within(&variable.from)
+                && !outputs
+                    .iter()
+                    .any(|(output, _)| std::ptr::eq(*output, variable))
+            {
+                outputs.push((variable, true));
+            }
+        }
+    }
+
+    if outputs.len() > 1 {
+        return None;
+    }
+
+    // Only variables declared before the selection become parameters
+    parameters.sort_by_key(|(index, _)| *index);
+    let parameters = parameters
+        .iter()
+        .map(|(_, variable)| variable.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let name = context.unique_name("extracted");
+    let body = &context.text[from.index..to.index];
+
+    let (returned, call) = match outputs.first() {
+        Some((output, true)) => (
+            format!("return {}\n", output.name),
+            format!("local {} = {}({})", output.name, name, parameters),
+        ),
+        Some((output, false)) => (
+            format!("return {}\n", output.name),
+            format!("{} = {}({})", output.name, name, parameters),
+        ),
+        None => (String::new(), format!("{}({})", name, parameters)),
+    };
+
+    let code = format!(
+        "local {} = function({}) {{\n{}\n{}}}\n\n{}\n",
+        name, parameters, body, returned, call
+    );
+
+    let indentation = indentation(context.text, from.line as u32);
+    let new_text = context.format(&code, &indentation)?;
+
+    Some(context.action(
+        "Extract to local function",
+        CodeActionKind::REFACTOR_EXTRACT,
+        vec![TextEdit::new(to_range(&from, &to), new_text)],
+    ))
+}
+
+/// Finds the expression matching the selection along with the statement of the
+/// innermost statement list containing it.
+struct ExpressionSelection<'s> {
+    text: &'s str,
+    selection: &'s Selection,
+    expression: Option<Expression>,
+    statement: Option<Statement>,
+    is_assigned: bool,
+}
+
+impl ExpressionSelection<'_> {
+    fn is_selected(&self, expression: &Expression) -> bool {
+        skip_whitespace(self.text, &expression.get_from()).index == self.selection.start
+            && expression.get_to().index == self.selection.end
+    }
+
+    fn check(&mut self, list: &Statements) {
+        for statement in &list.statements {
+            if statement.get_from().index <= self.selection.start
+                && self.selection.end <= statement.get_to().index
+            {
+                self.statement = Some(statement.clone());
+            }
+        }
+    }
+}
+
+impl AstVisitor for ExpressionSelection<'_> {
+    fn enter_expression(&mut self, expression: &Expression) -> AstVisitorResult {
+        if self.expression.is_none() && self.is_selected(expression) {
+            self.expression = Some(expression.clone());
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_binary_operator_expression(
+        &mut self,
+        expression: &BinaryOperatorExpression,
+    ) -> AstVisitorResult {
+        if expression.operator.is_assignment() && self.is_selected(&expression.left) {
+            self.is_assigned = true;
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_unary_operator_expression(
+        &mut self,
+        expression: &UnaryOperatorExpression,
+    ) -> AstVisitorResult {
+        if matches!(
+            expression.operator,
+            Operator::Increment | Operator::Decrement
+        ) && self.is_selected(&expression.expression)
+        {
+            self.is_assigned = true;
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_postfix_unary_operator_expression(
+        &mut self,
+        expression: &PostfixUnaryOperatorExpression,
+    ) -> AstVisitorResult {
+        if self.is_selected(&expression.expression) {
+            self.is_assigned = true;
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_block_statement(&mut self, statement: &BlockStatement) -> AstVisitorResult {
+        self.check(&statement.statements);
+        AstVisitorResult::Continue
+    }
+
+    fn enter_switch_statement(&mut self, statement: &SwitchStatement) -> AstVisitorResult {
+        for case in &statement.cases {
+            self.check(&case.statements);
+        }
+
+        if let Some(default) = &statement.default {
+            self.check(default);
+        }
+
+        AstVisitorResult::Continue
+    }
+}
+
+/// Extracts the selected expression into a `local` declared right before the
+/// statement containing it.
+pub(crate) fn extract_local(context: &RefactorContext) -> Option<CodeActionOrCommand> {
+    let selection = &context.selection;
+    if selection.is_empty() {
+        return None;
+    }
+
+    let mut finder = ExpressionSelection {
+        text: context.text,
+        selection,
+        expression: None,
+        statement: None,
+        is_assigned: false,
+    };
+    finder.check(context.statements);
+    visit(context.statements, &mut finder);
+
+    let (Some(expression), Some(statement)) = (finder.expression, finder.statement) else {
+        return None;
+    };
+
+    if finder.is_assigned {
+        return None;
+    }
+
+    if let Expression::BinaryOperator(binary) = &expression {
+        if binary.operator.is_assignment() {
+            return None;
+        }
+    }
+
+    // Moving the expression in front of the statement must not change how
+    // often or whether it's evaluated
+    match &statement {
+        Statement::If(if_statement) => {
+            let condition = &if_statement.condition;
+            if selection.start < condition.get_from().index
+                || condition.get_to().index < selection.end
+            {
+                return None;
+            }
+        }
+        Statement::While(_)
+        | Statement::DoWhile(_)
+        | Statement::For(_)
+        | Statement::ForEach(_)
+        | Statement::TryCatch(_)
+        | Statement::FunctionDefinition(_)
+        | Statement::Class(_)
+        | Statement::Enum(_)
+        | Statement::Const(_) => return None,
+        _ => {}
+    }
+
+    // Variables declared by the statement itself aren't available before it
+    let statement_from = skip_whitespace(context.text, &statement.get_from());
+    for scope in context.scope_table.scopes() {
+        for usage in &scope.variable_usage {
+            if !selection.contains_index(usage.from.index) {
+                continue;
+            }
+
+            let Some(DeclarationType::Variable(..)) = &usage.declaration else {
+                continue;
+            };
+
+            let variable = context
+                .scope_table
+                .get_declaration(usage.declaration.as_ref()?)?;
+
+            if statement_from.index <= variable.from.index && variable.from.index < selection.start
+            {
+                return None;
+            }
+        }
+    }
+
+    let name = context.unique_name("value");
+    let indentation = indentation(context.text, statement_from.line as u32);
+    let declaration = context.format(
+        &format!(
+            "local {} = {}\n",
+            name,
+            &context.text[selection.start..selection.end]
+        ),
+        &indentation,
+    )?;
+
+    let insert_at = to_position(&statement_from);
+
+    Some(context.action(
+        "Extract to local variable",
+        CodeActionKind::REFACTOR_EXTRACT,
+        vec![
+            TextEdit::new(
+                tower_lsp::lsp_types::Range::new(insert_at, insert_at),
+                format!("{}\n{}", declaration, indentation),
+            ),
+            TextEdit::new(
+                to_range(
+                    &skip_whitespace(context.text, &expression.get_from()),
+                    &expression.get_to(),
+                ),
+                name,
+            ),
+        ],
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{CodeActionOrCommand, Position, Range, TextEdit, Url};
+
+    use crate::{dialect::Dialect, refactor::refactorings};
+
+    fn edits(input: &str, range: Range, title: &str) -> Option<Vec<TextEdit>> {
+        let uri = Url::parse("file:///test.nut").unwrap();
+
+        refactorings(&uri, input, range, Dialect::default())
+            .into_iter()
+            .find_map(|action| match action {
+                CodeActionOrCommand::CodeAction(action) if action.title == title => {
+                    Some(action.edit.unwrap().changes.unwrap()[&uri].clone())
+                }
+                _ => None,
+            })
+    }
+
+    #[test]
+    fn test_extract_function() {
+        let input = "
+function foo(a) {
+    local b = a * 2;
+    local c = b + 1;
+    ::print(c);
+    return c;
+}
+";
+        let range = Range::new(Position::new(2, 4), Position::new(3, 20));
+        let edits = edits(input, range, "Extract to local function").unwrap();
+
+        assert_eq!(edits.len(), 1);
+        assert_eq!(
+            edits[0].new_text,
+            "local extracted = function (a) {
+      local b = a * 2
+      local c = b + 1
+      return c
+    }
+
+    local c = extracted(a)"
+        );
+    }
+
+    #[test]
+    fn test_extract_function_rejects_escaping_return() {
+        let input = "
+function foo(a) {
+    if (a) {
+        return 1;
+    }
+    return 2;
+}
+";
+        let range = Range::new(Position::new(2, 4), Position::new(4, 5));
+
+        assert_eq!(edits(input, range, "Extract to local function"), None);
+    }
+
+    #[test]
+    fn test_extract_local() {
+        let input = "
+function foo(a) {
+    return a * 2 + 1;
+}
+";
+        let range = Range::new(Position::new(2, 11), Position::new(2, 16));
+        let edits = edits(input, range, "Extract to local variable").unwrap();
+
+        assert_eq!(edits[0].new_text, "local value = a * 2\n    ");
+        assert_eq!(edits[1].new_text, "value");
+    }
+}
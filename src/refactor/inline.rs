@@ -0,0 +1,733 @@
+use std::collections::HashMap;
+
+use tower_lsp::lsp_types::{CodeActionKind, CodeActionOrCommand, TextEdit};
+
+use crate::{
+    ast_visitor::{visit, AstVisitor, AstVisitorResult},
+    code_actions::{indentation, whole_lines},
+    grammar::expressions::*,
+    grammar::statements::*,
+    precedence::{span, Requirements},
+    scope_table::{
+        has_side_effects, DeclarationType, VariableDeclaration, VariableDeclarationKind,
+        VariableUsage,
+    },
+    squirrel_lexer::{Location, Operator},
+    unused_checker::LocalDefinitions,
+};
+
+use super::{skip_whitespace, to_range, AssignedIdentifiers, RefactorContext};
+
+/// Statements that declare nothing but a single variable or function, keyed
+/// by where the declaration starts.
+#[derive(Default)]
+struct DeclaringStatements {
+    statements: HashMap<usize, (Location, Location)>,
+}
+
+impl AstVisitor for DeclaringStatements {
+    fn enter_local_statement(&mut self, statement: &LocalStatement) -> AstVisitorResult {
+        if let [initialization] = statement.initializations.as_slice() {
+            self.statements.insert(
+                initialization.from.index,
+                (statement.from.clone(), statement.to.clone()),
+            );
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_function_declaration(&mut self, function: &FunctionDefinition) -> AstVisitorResult {
+        self.statements.insert(
+            function.from.index,
+            (function.from.clone(), function.to.clone()),
+        );
+
+        AstVisitorResult::Continue
+    }
+}
+
+/// Spans of functions and loops, code within them may run later or several
+/// times compared to the code surrounding them.
+#[derive(Default)]
+struct DeferredRanges {
+    functions: Vec<(usize, usize)>,
+    loops: Vec<(usize, usize)>,
+}
+
+impl DeferredRanges {
+    fn is_deferred(ranges: &[(usize, usize)], declaration: usize, usage: usize) -> bool {
+        ranges
+            .iter()
+            .any(|(from, to)| (*from..*to).contains(&usage) && !(*from..*to).contains(&declaration))
+    }
+}
+
+impl AstVisitor for DeferredRanges {
+    fn enter_function_declaration(&mut self, function: &FunctionDefinition) -> AstVisitorResult {
+        self.functions
+            .push((function.from.index, function.to.index));
+        AstVisitorResult::Continue
+    }
+
+    fn enter_while_statement(&mut self, statement: &WhileStatement) -> AstVisitorResult {
+        self.loops.push((statement.from.index, statement.to.index));
+        AstVisitorResult::Continue
+    }
+
+    fn enter_do_while_statement(&mut self, statement: &DoWhileStatement) -> AstVisitorResult {
+        self.loops.push((statement.from.index, statement.to.index));
+        AstVisitorResult::Continue
+    }
+
+    fn enter_for_statement(&mut self, statement: &ForStatement) -> AstVisitorResult {
+        self.loops.push((statement.from.index, statement.to.index));
+        AstVisitorResult::Continue
+    }
+
+    fn enter_for_each_statement(&mut self, statement: &ForEachStatement) -> AstVisitorResult {
+        self.loops.push((statement.from.index, statement.to.index));
+        AstVisitorResult::Continue
+    }
+}
+
+/// Spans of calls, assignments and other expressions changing state when
+/// they're evaluated, not counting the effects of their operands.
+#[derive(Default)]
+struct Effects {
+    spans: Vec<(usize, usize)>,
+}
+
+impl Effects {
+    /// Whether any effect is evaluated after `from` and before the usage,
+    /// leaving out the expressions holding the usage since they're evaluated after it.
+    fn before_usage(&self, from: usize, usage: &VariableUsage) -> bool {
+        self.spans.iter().any(|(start, end)| {
+            let holds_usage = *start <= usage.from.index && usage.to.index <= *end;
+            from <= *start && *start < usage.from.index && !holds_usage
+        })
+    }
+}
+
+impl AstVisitor for Effects {
+    fn enter_expression(&mut self, expression: &Expression) -> AstVisitorResult {
+        let changes_state = match expression {
+            Expression::FunctionCall(_)
+            | Expression::RawCall(_)
+            | Expression::Resume(_)
+            | Expression::Delete(_)
+            | Expression::PostfixUnaryOperator(_) => true,
+            Expression::UnaryOperator(unary) => {
+                matches!(unary.operator, Operator::Increment | Operator::Decrement)
+            }
+            Expression::BinaryOperator(binary) => binary.operator.is_assignment(),
+            _ => false,
+        };
+
+        if changes_state {
+            self.spans.push(span(expression));
+        }
+
+        AstVisitorResult::Continue
+    }
+}
+
+/// Calls whose callee is a plain identifier, keyed by the position of the identifier.
+#[derive(Default)]
+struct Calls {
+    calls: HashMap<usize, FunctionCallExpression>,
+}
+
+impl AstVisitor for Calls {
+    fn enter_function_call_expression(
+        &mut self,
+        expression: &FunctionCallExpression,
+    ) -> AstVisitorResult {
+        if let Expression::Identifier(identifier) = &expression.function {
+            self.calls.insert(identifier.from.index, expression.clone());
+        }
+
+        AstVisitorResult::Continue
+    }
+}
+
+fn same_declaration(a: &Option<DeclarationType>, b: &Option<DeclarationType>) -> bool {
+    match (a, b) {
+        (
+            Some(DeclarationType::Variable(a_scope, a_sequence)),
+            Some(DeclarationType::Variable(b_scope, b_sequence)),
+        ) => a_scope == b_scope && a_sequence == b_sequence,
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+fn is_declaration(usage: &VariableUsage, declaration: &VariableDeclaration) -> bool {
+    matches!(
+        usage.declaration,
+        Some(DeclarationType::Variable(scope, sequence_number))
+            if scope == declaration.scope && sequence_number == declaration.sequence_number
+    )
+}
+
+/// Usages along with the scope they're in.
+fn all_usages<'a>(
+    context: &'a RefactorContext,
+) -> impl Iterator<Item = (usize, &'a VariableUsage)> {
+    context
+        .scope_table
+        .scopes()
+        .iter()
+        .enumerate()
+        .flat_map(|(index, scope)| scope.variable_usage.iter().map(move |usage| (index, usage)))
+}
+
+/// The local or function whose name or usage is under the cursor.
+fn target<'a>(context: &'a RefactorContext) -> Option<&'a VariableDeclaration> {
+    let cursor = context.selection.start;
+
+    for (_, usage) in all_usages(context) {
+        if usage.from.index <= cursor && cursor <= usage.to.index {
+            return context
+                .scope_table
+                .get_declaration(usage.declaration.as_ref()?);
+        }
+    }
+
+    context
+        .scope_table
+        .scopes()
+        .iter()
+        .flat_map(|scope| scope.variables.iter())
+        .find(|variable| {
+            let start = skip_whitespace(context.text, &variable.from).index;
+            variable.kind == VariableDeclarationKind::Local
+                && start <= cursor
+                && cursor <= start + variable.name.len()
+        })
+}
+
+/// Usages of the declaration outside of its own statement, `None` when it refers
+/// to itself. The name of a function declaration, `local function` included,
+/// counts as a usage within the function and is left out.
+fn usages<'a>(
+    context: &'a RefactorContext,
+    declaration: &VariableDeclaration,
+) -> Option<Vec<(usize, &'a VariableUsage)>> {
+    let name = match &declaration.value {
+        Some(Expression::Function(function))
+            if declaration.kind == VariableDeclarationKind::Function || function.is_local =>
+        {
+            function.name.as_ref().map(|name| name.get_from().index)
+        }
+        _ => None,
+    };
+
+    let mut usages = Vec::new();
+
+    for (scope, usage) in all_usages(context) {
+        if !is_declaration(usage, declaration) || Some(usage.from.index) == name {
+            continue;
+        }
+
+        if declaration.from.index <= usage.from.index && usage.from.index < declaration.to.index {
+            return None;
+        }
+
+        usages.push((scope, usage));
+    }
+
+    Some(usages)
+}
+
+/// Replaces the usages of a local with its initializer and removes the declaration.
+/// The initializer has to be free of side effects unless it's used only once,
+/// with no calls or assignments evaluated in between.
+pub(crate) fn inline_local(context: &RefactorContext) -> Option<CodeActionOrCommand> {
+    let declaration = target(context)?;
+    if declaration.kind != VariableDeclarationKind::Local {
+        return None;
+    }
+
+    // A `local function` is no initializer, it's inlined as a function instead
+    let value = match declaration.value.as_ref()? {
+        Expression::Function(function) if function.is_local => return None,
+        value => value,
+    };
+
+    let mut declaring = DeclaringStatements::default();
+    visit(context.statements, &mut declaring);
+    let (statement_from, statement_to) = declaring.statements.get(&declaration.from.index)?;
+
+    let usages = usages(context, declaration)?;
+    if usages.is_empty() {
+        return None;
+    }
+
+    let mut assigned = AssignedIdentifiers::default();
+    visit(context.statements, &mut assigned);
+
+    if usages
+        .iter()
+        .any(|(_, usage)| assigned.positions.contains(&usage.from.index))
+    {
+        return None;
+    }
+
+    let mut deferred = DeferredRanges::default();
+    visit(context.statements, &mut deferred);
+
+    let is_deferred = |ranges: &[(usize, usize)]| {
+        usages.iter().any(|(_, usage)| {
+            DeferredRanges::is_deferred(ranges, declaration.from.index, usage.from.index)
+        })
+    };
+
+    if has_side_effects(value) {
+        let mut effects = Effects::default();
+        visit(context.statements, &mut effects);
+
+        // The initializer would run after whatever is evaluated before its usage
+        let moves_past_effects = usages
+            .iter()
+            .any(|(_, usage)| effects.before_usage(statement_to.index, usage));
+
+        if usages.len() > 1
+            || is_deferred(&deferred.functions)
+            || is_deferred(&deferred.loops)
+            || moves_past_effects
+        {
+            return None;
+        }
+    }
+
+    // Variables read by the initializer have to hold the same value wherever it's inlined
+    let usages_by_index = context.scope_table.usages_by_index();
+    let (value_from, value_to) = span(value);
+
+    for (_, read) in all_usages(context) {
+        if read.from.index < value_from || value_to <= read.from.index {
+            continue;
+        }
+
+        match &read.declaration {
+            Some(DeclarationType::Variable(..)) => {
+                let is_assigned = assigned.positions.iter().any(|position| {
+                    usages_by_index.get(position).is_some_and(|usage| {
+                        same_declaration(&usage.declaration, &read.declaration)
+                    })
+                });
+
+                if is_assigned {
+                    return None;
+                }
+            }
+            Some(DeclarationType::This | DeclarationType::Base) => {
+                if is_deferred(&deferred.functions) {
+                    return None;
+                }
+                continue;
+            }
+            None => {}
+        }
+
+        for (scope, _) in &usages {
+            let visible = context
+                .scope_table
+                .find_variable_declaration(&read.name, *scope);
+
+            if !same_declaration(&visible, &read.declaration) {
+                return None;
+            }
+        }
+    }
+
+    let requirements = Requirements::new(context.statements);
+    let mut edits = vec![TextEdit::new(
+        whole_lines(
+            context.text,
+            to_range(&skip_whitespace(context.text, statement_from), statement_to),
+        ),
+        String::new(),
+    )];
+
+    for (_, usage) in &usages {
+        let replacement =
+            requirements.parenthesize(value.clone(), (usage.from.index, usage.to.index));
+        let indentation = indentation(context.text, usage.from.line as u32);
+
+        edits.push(TextEdit::new(
+            to_range(&usage.from, &usage.to),
+            context.print(&replacement, &indentation)?,
+        ));
+    }
+
+    Some(context.action(
+        &format!("Inline local '{}'", declaration.name),
+        CodeActionKind::REFACTOR_INLINE,
+        edits,
+    ))
+}
+
+/// Copies an expression, replacing the nodes `replace` returns a replacement for.
+/// Functions, classes and tables aren't supported since their statements would
+/// have to be copied too.
+fn substitute(
+    expression: &Expression,
+    replace: &mut impl FnMut(&Expression) -> Option<Expression>,
+) -> Option<Expression> {
+    if let Some(replacement) = replace(expression) {
+        return Some(replacement);
+    }
+
+    let mut copy = expression.clone();
+
+    match &mut copy {
+        Expression::UnaryOperator(unary) => {
+            unary.expression = substitute(&unary.expression, replace)?;
+        }
+        Expression::PostfixUnaryOperator(postfix) => {
+            postfix.expression = substitute(&postfix.expression, replace)?;
+        }
+        Expression::BinaryOperator(binary) => {
+            binary.left = substitute(&binary.left, replace)?;
+            binary.right = substitute(&binary.right, replace)?;
+        }
+        Expression::TernaryOperator(ternary) => {
+            ternary.condition = substitute(&ternary.condition, replace)?;
+            ternary.if_true = substitute(&ternary.if_true, replace)?;
+            ternary.if_false = substitute(&ternary.if_false, replace)?;
+        }
+        Expression::Clone(clone) => {
+            clone.expression = substitute(&clone.expression, replace)?;
+        }
+        Expression::Resume(resume) => {
+            resume.expression = substitute(&resume.expression, replace)?;
+        }
+        Expression::Delete(delete) => {
+            delete.expression = substitute(&delete.expression, replace)?;
+        }
+        Expression::Array(array) => {
+            for element in &mut array.elements {
+                *element = substitute(element, replace)?;
+            }
+        }
+        Expression::ArrayAccess(access) => {
+            access.array = substitute(&access.array, replace)?;
+            access.index = substitute(&access.index, replace)?;
+        }
+        Expression::Grouping(grouping) => {
+            if let Some(expression) = &mut grouping.expression {
+                *expression = substitute(expression, replace)?;
+            }
+        }
+        Expression::FunctionCall(call) => {
+            call.function = substitute(&call.function, replace)?;
+            for argument in &mut call.arguments {
+                *argument = substitute(argument, replace)?;
+            }
+        }
+        Expression::RawCall(call) => {
+            for argument in &mut call.arguments {
+                *argument = substitute(argument, replace)?;
+            }
+        }
+        Expression::MemberAccess(access) => {
+            access.expression = substitute(&access.expression, replace)?;
+        }
+        Expression::Table(_) | Expression::Class(_) | Expression::Function(_) => return None,
+        Expression::Spread(_)
+        | Expression::StringLiteral(_)
+        | Expression::MutliLineStringLiteral(_)
+        | Expression::FloatLiteral(_)
+        | Expression::IntegerLiteral(_)
+        | Expression::CharacterLiteral(_)
+        | Expression::Identifier(_)
+        | Expression::NullLiteral(_)
+        | Expression::BooleanLiteral(_)
+        | Expression::LineLiteral(_)
+        | Expression::FileLiteral(_)
+        | Expression::ScopeResolution(_) => {}
+    }
+
+    Some(copy)
+}
+
+/// The expression a function consisting of a single `return` statement returns.
+fn returned_expression(function: &FunctionDefinition) -> Option<&Expression> {
+    let Statement::Block(block) = &function.statement else {
+        return None;
+    };
+
+    match block.statements.statements.as_slice() {
+        [Statement::Return(statement)] => statement.expression.as_ref(),
+        _ => None,
+    }
+}
+
+/// Replaces calls of a local function that only returns an expression with
+/// that expression, substituting the arguments for the parameters.
+pub(crate) fn inline_function(context: &RefactorContext) -> Option<CodeActionOrCommand> {
+    let declaration = target(context)?;
+    let Some(Expression::Function(function)) = &declaration.value else {
+        return None;
+    };
+
+    // Both `local f = function` and `local function f` declare a local
+    match declaration.kind {
+        VariableDeclarationKind::Local => {}
+        VariableDeclarationKind::Function
+            if LocalDefinitions::new(context.statements).is_local_function(function) => {}
+        _ => return None,
+    }
+
+    let mut declaring = DeclaringStatements::default();
+    visit(context.statements, &mut declaring);
+    let (statement_from, statement_to) = declaring.statements.get(&declaration.from.index)?;
+
+    let body = returned_expression(function)?;
+    let parameters = function
+        .parameters
+        .iter()
+        .map(|parameter| match parameter {
+            Expression::Identifier(identifier) => Some(identifier.token.to_string()),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let usages = usages(context, declaration)?;
+    if usages.is_empty() {
+        return None;
+    }
+
+    let mut calls = Calls::default();
+    visit(context.statements, &mut calls);
+
+    let calls = usages
+        .iter()
+        .map(|(scope, usage)| {
+            calls
+                .calls
+                .get(&usage.from.index)
+                .filter(|call| call.arguments.len() == parameters.len())
+                .map(|call| (*scope, call))
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    // Parameters are replaced by the arguments, everything else the function
+    // reads has to mean the same at every call
+    let (body_from, body_to) = span(body);
+    let mut parameter_usages = HashMap::new();
+
+    for (_, read) in all_usages(context) {
+        if read.from.index < body_from || body_to <= read.from.index {
+            continue;
+        }
+
+        match &read.declaration {
+            Some(DeclarationType::This | DeclarationType::Base) => return None,
+            Some(declared) => {
+                let variable = context.scope_table.get_declaration(declared)?;
+                if variable.kind == VariableDeclarationKind::Parameter
+                    && function.from.index <= variable.from.index
+                    && variable.from.index < function.to.index
+                {
+                    let index = parameters.iter().position(|name| *name == variable.name)?;
+                    parameter_usages.insert(read.from.index, index);
+                    continue;
+                }
+            }
+            None => {}
+        }
+
+        for (scope, _) in &calls {
+            let visible = context
+                .scope_table
+                .find_variable_declaration(&read.name, *scope);
+
+            if !same_declaration(&visible, &read.declaration) {
+                return None;
+            }
+        }
+    }
+
+    // Arguments with side effects have to be evaluated exactly once
+    for (_, call) in &calls {
+        for (index, argument) in call.arguments.iter().enumerate() {
+            let count = parameter_usages
+                .values()
+                .filter(|used| **used == index)
+                .count();
+            if has_side_effects(argument) && count != 1 {
+                return None;
+            }
+        }
+    }
+
+    let requirements = Requirements::new(context.statements);
+    let mut edits = vec![TextEdit::new(
+        whole_lines(
+            context.text,
+            to_range(&skip_whitespace(context.text, statement_from), statement_to),
+        ),
+        String::new(),
+    )];
+
+    for (_, call) in &calls {
+        let inlined = substitute(body, &mut |expression| {
+            let Expression::Identifier(identifier) = expression else {
+                return None;
+            };
+
+            let index = parameter_usages.get(&identifier.from.index)?;
+            Some(requirements.parenthesize(call.arguments[*index].clone(), span(expression)))
+        })?;
+
+        let inlined = requirements.parenthesize(inlined, (call.from.index, call.to.index));
+        let call_from = skip_whitespace(context.text, &call.from);
+        let indentation = indentation(context.text, call_from.line as u32);
+
+        edits.push(TextEdit::new(
+            to_range(&call_from, &call.to),
+            context.print(&inlined, &indentation)?,
+        ));
+    }
+
+    Some(context.action(
+        &format!("Inline function '{}'", declaration.name),
+        CodeActionKind::REFACTOR_INLINE,
+        edits,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{CodeActionOrCommand, Position, Range, TextEdit, Url};
+
+    use crate::{dialect::Dialect, refactor::refactorings};
+
+    fn edits(input: &str, position: Position, title: &str) -> Option<Vec<TextEdit>> {
+        let uri = Url::parse("file:///test.nut").unwrap();
+
+        refactorings(
+            &uri,
+            input,
+            Range::new(position, position),
+            Dialect::default(),
+        )
+        .into_iter()
+        .find_map(|action| match action {
+            CodeActionOrCommand::CodeAction(action) if action.title == title => {
+                Some(action.edit.unwrap().changes.unwrap()[&uri].clone())
+            }
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn test_inline_local() {
+        let input = "
+function foo(a) {
+    local b = a + 1;
+    return b * 2;
+}
+";
+        let edits = edits(input, Position::new(2, 10), "Inline local 'b'").unwrap();
+
+        assert_eq!(
+            edits[0].range,
+            Range::new(Position::new(2, 0), Position::new(3, 0))
+        );
+        assert_eq!(edits[1].new_text, "(a + 1)");
+    }
+
+    #[test]
+    fn test_inline_local_with_side_effects() {
+        let input = "
+local b = ::bar();
+::print(b);
+::print(b);
+";
+        assert_eq!(edits(input, Position::new(1, 6), "Inline local 'b'"), None);
+    }
+
+    #[test]
+    fn test_inline_local_past_other_effects() {
+        let input = "
+local x = ::get();
+::set(5);
+::print(x);
+";
+        assert_eq!(edits(input, Position::new(1, 6), "Inline local 'x'"), None);
+
+        let input = "
+local x = ::get();
+::print(::other(), x);
+";
+        assert_eq!(edits(input, Position::new(1, 6), "Inline local 'x'"), None);
+
+        let input = "
+local x = ::get();
+::print(x);
+";
+        let edits = edits(input, Position::new(1, 6), "Inline local 'x'").unwrap();
+        assert_eq!(edits[1].new_text, "::get()");
+    }
+
+    #[test]
+    fn test_inline_function() {
+        let input = "
+function foo(a) {
+    local twice = function(x) {
+        return x * 2;
+    };
+    return twice(a + 1) + 1;
+}
+";
+        let edits = edits(input, Position::new(5, 12), "Inline function 'twice'").unwrap();
+
+        assert_eq!(
+            edits[0].range,
+            Range::new(Position::new(2, 0), Position::new(5, 0))
+        );
+        assert_eq!(edits[1].new_text, "(a + 1) * 2");
+    }
+
+    #[test]
+    fn test_inline_local_function() {
+        let input = "
+function foo(a) {
+    local function twice(x) {
+        return x * 2;
+    }
+    return twice(a) + 1;
+}
+";
+        assert_eq!(
+            edits(input, Position::new(5, 12), "Inline local 'twice'"),
+            None
+        );
+
+        let edits = edits(input, Position::new(5, 12), "Inline function 'twice'").unwrap();
+
+        assert_eq!(
+            edits[0].range,
+            Range::new(Position::new(2, 0), Position::new(5, 0))
+        );
+        assert_eq!(edits[1].new_text, "a * 2");
+    }
+
+    #[test]
+    fn test_inline_recursive_local_function() {
+        let input = "
+local function count(n) {
+    return n > 0 ? count(n - 1) + 1 : 0;
+}
+::print(count(3));
+";
+        assert_eq!(
+            edits(input, Position::new(4, 9), "Inline function 'count'"),
+            None
+        );
+    }
+}
@@ -0,0 +1,292 @@
+mod convert;
+mod extract;
+mod inline;
+mod organize;
+
+use std::collections::{HashMap, HashSet};
+
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, Position, Range, TextEdit, Url, WorkspaceEdit,
+};
+
+pub(crate) use crate::squirrel_lexer::{location_at, skip_whitespace};
+use crate::{
+    ast_visitor::{AstVisitor, AstVisitorResult},
+    dialect::Dialect,
+    grammar::expressions::*,
+    grammar::statements::*,
+    scope_table::ScopeTable,
+    squirrel_formatter::{Formatter, FormatterOptions},
+    squirrel_lexer::{Lexer, Location, Operator, Token},
+    squirrel_parser::Parser,
+    squirrel_printer::Printer,
+};
+
+/// Refactorings applicable to the selected range of a document written in `dialect`.
+pub fn refactorings(
+    uri: &Url,
+    text: &str,
+    range: Range,
+    dialect: Dialect,
+) -> Vec<CodeActionOrCommand> {
+    let mut parser = Parser::new(text).with_dialect(dialect);
+    let Ok(statements) = parser.parse() else {
+        return Vec::new();
+    };
+
+    let scope_table = ScopeTable::new(&statements);
+    let context = RefactorContext {
+        uri,
+        text,
+        statements: &statements,
+        scope_table: &scope_table,
+        selection: Selection::new(text, range),
+        dialect,
+    };
+
+    let mut actions = Vec::new();
+    actions.extend(extract::extract_function(&context));
+    actions.extend(extract::extract_local(&context));
+    actions.extend(inline::inline_local(&context));
+    actions.extend(inline::inline_function(&context));
+    actions.extend(convert::table_to_class(&context));
+    actions.extend(convert::class_to_table(&context));
+    actions.extend(organize::organize_enum(&context));
+    actions.extend(organize::organize_consts(&context));
+
+    actions
+}
+
+/// Byte offsets of the selected text with surrounding whitespace trimmed, which
+/// is what positions in the AST are based on.
+pub(crate) struct Selection {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Selection {
+    fn new(text: &str, range: Range) -> Self {
+        let bytes = text.as_bytes();
+        let mut start = offset(text, range.start);
+        let mut end = offset(text, range.end).max(start);
+
+        while start < end && bytes[start].is_ascii_whitespace() {
+            start += 1;
+        }
+
+        while end > start && bytes[end - 1].is_ascii_whitespace() {
+            end -= 1;
+        }
+
+        Self { start, end }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn contains(&self, from: &Location, to: &Location) -> bool {
+        self.start <= from.index && to.index <= self.end
+    }
+
+    pub fn contains_index(&self, index: usize) -> bool {
+        self.start <= index && index < self.end
+    }
+}
+
+pub(crate) struct RefactorContext<'a> {
+    pub uri: &'a Url,
+    pub text: &'a str,
+    pub statements: &'a Statements,
+    pub scope_table: &'a ScopeTable,
+    pub selection: Selection,
+    pub dialect: Dialect,
+}
+
+impl RefactorContext<'_> {
+    /// `base`, or `base` followed by a number when the name is already used anywhere
+    /// in the document.
+    pub fn unique_name(&self, base: &str) -> String {
+        let names = self
+            .scope_table
+            .scopes()
+            .iter()
+            .flat_map(|scope| {
+                scope
+                    .variables
+                    .iter()
+                    .map(|variable| variable.name.as_str())
+                    .chain(scope.variable_usage.iter().map(|usage| usage.name.as_str()))
+            })
+            .collect::<HashSet<_>>();
+
+        let mut name = base.to_string();
+        let mut counter = 1;
+
+        while names.contains(name.as_str()) {
+            name = format!("{}{}", base, counter);
+            counter += 1;
+        }
+
+        name
+    }
+
+    /// Formats code with the document formatter, indenting all but the first
+    /// line by `indentation` so that the result can be placed within the document.
+    pub fn format(&self, code: &str, indentation: &str) -> Option<String> {
+        let formatted = Formatter::new(code)
+            .with_dialect(self.dialect)
+            .format(&FormatterOptions::default())
+            .ok()?;
+
+        let lines = formatted
+            .trim()
+            .lines()
+            .enumerate()
+            .map(|(i, line)| match line.is_empty() || i == 0 {
+                true => line.to_string(),
+                false => format!("{}{}", indentation, line),
+            })
+            .collect::<Vec<_>>();
+
+        Some(lines.join("\n"))
+    }
+
+    /// Prints an expression built from the AST, formatted the same way as
+    /// [`RefactorContext::format`].
+    pub fn print(&self, expression: &Expression, indentation: &str) -> Option<String> {
+        let from = expression.get_from();
+        let to = expression.get_to();
+        let statements = Statements {
+            statements: vec![Statement::Expression(Box::new(ExpressionStatement {
+                expression: expression.clone(),
+                from: from.clone(),
+                to: to.clone(),
+            }))],
+            from,
+            to,
+        };
+
+        let code = Printer::new(64, &FormatterOptions::default()).print(&statements);
+
+        self.format(&code, indentation)
+    }
+
+    pub fn action(
+        &self,
+        title: &str,
+        kind: CodeActionKind,
+        edits: Vec<TextEdit>,
+    ) -> CodeActionOrCommand {
+        CodeActionOrCommand::CodeAction(CodeAction {
+            title: title.to_string(),
+            kind: Some(kind),
+            edit: Some(WorkspaceEdit {
+                changes: Some(HashMap::from([(self.uri.clone(), edits)])),
+                ..WorkspaceEdit::default()
+            }),
+            ..CodeAction::default()
+        })
+    }
+}
+
+/// Positions of identifiers that get written to, either by an assignment or
+/// by an increment or decrement.
+#[derive(Default)]
+pub(crate) struct AssignedIdentifiers {
+    pub positions: Vec<usize>,
+}
+
+impl AssignedIdentifiers {
+    fn add(&mut self, expression: &Expression) {
+        if let Expression::Identifier(identifier) = expression {
+            self.positions.push(identifier.from.index);
+        }
+    }
+}
+
+impl AstVisitor for AssignedIdentifiers {
+    fn enter_binary_operator_expression(
+        &mut self,
+        expression: &BinaryOperatorExpression,
+    ) -> AstVisitorResult {
+        if expression.operator.is_assignment() {
+            self.add(&expression.left);
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_unary_operator_expression(
+        &mut self,
+        expression: &UnaryOperatorExpression,
+    ) -> AstVisitorResult {
+        if matches!(
+            expression.operator,
+            Operator::Increment | Operator::Decrement
+        ) {
+            self.add(&expression.expression);
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_postfix_unary_operator_expression(
+        &mut self,
+        expression: &PostfixUnaryOperatorExpression,
+    ) -> AstVisitorResult {
+        self.add(&expression.expression);
+        AstVisitorResult::Continue
+    }
+}
+
+/// Tokens between two offsets of the source, along with their offsets.
+pub(crate) fn tokens_between(
+    text: &str,
+    from: usize,
+    to: usize,
+    skip_comments: bool,
+) -> Vec<(Token, usize, usize)> {
+    let mut lexer = Lexer::new(&text[from..to], skip_comments);
+    let mut tokens = Vec::new();
+
+    while let Ok(token) = lexer.next() {
+        if token.token == Token::EOF {
+            break;
+        }
+
+        tokens.push((token.token, from + token.from.index, from + token.to.index));
+    }
+
+    tokens
+}
+
+pub(crate) fn edit(text: &str, from: usize, to: usize, new_text: impl Into<String>) -> TextEdit {
+    TextEdit::new(
+        to_range(&location_at(text, from), &location_at(text, to)),
+        new_text.into(),
+    )
+}
+
+/// Byte offset of an LSP position, counting characters the same way the lexer does.
+pub(crate) fn offset(text: &str, position: Position) -> usize {
+    let mut offset = 0;
+
+    for (line, content) in text.split_inclusive('\n').enumerate() {
+        if line == position.line as usize {
+            return offset + (position.character as usize).min(content.len());
+        }
+
+        offset += content.len();
+    }
+
+    text.len()
+}
+
+pub(crate) fn to_position(location: &Location) -> Position {
+    Position::new(location.line as u32, location.linechar as u32)
+}
+
+pub(crate) fn to_range(from: &Location, to: &Location) -> Range {
+    Range::new(to_position(from), to_position(to))
+}
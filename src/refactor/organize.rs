@@ -0,0 +1,679 @@
+use std::cmp::Ordering;
+
+use tower_lsp::lsp_types::{CodeActionKind, CodeActionOrCommand};
+
+use crate::{
+    ast_visitor::{visit, AstVisitor, AstVisitorResult},
+    code_actions::indentation,
+    const_evaluator::{identifier_name, ConstEvaluator, ConstValue},
+    grammar::statements::*,
+    scope_table::VariableDeclarationKind,
+    squirrel_lexer::Token,
+};
+
+use super::{edit, location_at, skip_whitespace, tokens_between, RefactorContext};
+
+/// An enum member or a const of a block of consts, with the comments around it.
+#[derive(Clone)]
+struct Member {
+    name: String,
+    value: Option<String>,
+    evaluated: Option<ConstValue>,
+    leading: Vec<String>,
+    trailing: Option<String>,
+}
+
+/// How members are written out.
+struct Layout<'l> {
+    indentation: &'l str,
+    prefix: &'l str,
+    separator: &'l str,
+    last_separator: &'l str,
+    align: bool,
+}
+
+impl Layout<'_> {
+    fn render(&self, members: &[Member], footer: &[String]) -> String {
+        let width = members
+            .iter()
+            .filter(|member| member.value.is_some())
+            .map(|member| member.name.len())
+            .max()
+            .filter(|_| self.align)
+            .unwrap_or(0);
+
+        let mut lines = Vec::new();
+
+        for (index, member) in members.iter().enumerate() {
+            for comment in &member.leading {
+                lines.push(format!("{}{}", self.indentation, comment));
+            }
+
+            let mut line = format!("{}{}{}", self.indentation, self.prefix, member.name);
+
+            if let Some(value) = &member.value {
+                line.push_str(&format!(
+                    "{:width$} = {}",
+                    "",
+                    value,
+                    width = width.saturating_sub(member.name.len())
+                ));
+            }
+
+            line.push_str(match index + 1 == members.len() {
+                true => self.last_separator,
+                false => self.separator,
+            });
+
+            if let Some(comment) = &member.trailing {
+                line.push_str(&format!(" {}", comment));
+            }
+
+            lines.push(line);
+        }
+
+        for comment in footer {
+            lines.push(format!("{}{}", self.indentation, comment));
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// Assigns the comments between members to the member ending on the same line,
+/// or otherwise to the member following them. Comments after the last member
+/// on lines of their own are returned.
+fn attach_comments(
+    text: &str,
+    members: &mut [Member],
+    spans: &[(usize, usize)],
+    open: Option<usize>,
+    close: Option<usize>,
+) -> Vec<String> {
+    let mut footer = Vec::new();
+
+    for index in 0..=members.len() {
+        let previous = match index {
+            0 => open,
+            _ => Some(spans[index - 1].1),
+        };
+
+        let next = match spans.get(index) {
+            Some((from, _)) => Some(*from),
+            None => close,
+        };
+
+        let (Some(previous), Some(next)) = (previous, next) else {
+            continue;
+        };
+
+        let previous_line = location_at(text, previous).line;
+
+        for (token, from, to) in tokens_between(text, previous, next, false) {
+            if !matches!(token, Token::Comment(..) | Token::MultiLineComment(_)) {
+                continue;
+            }
+
+            let comment = text[from..to].trim().to_string();
+
+            if index > 0 && location_at(text, from).line == previous_line {
+                members[index - 1].trailing = Some(comment);
+            } else if let Some(member) = members.get_mut(index) {
+                member.leading.push(comment);
+            } else {
+                footer.push(comment);
+            }
+        }
+    }
+
+    footer
+}
+
+/// Whether the `=` of all members with a value are in the same column.
+fn is_aligned(text: &str, assignments: &[(usize, usize)]) -> bool {
+    let columns = assignments
+        .iter()
+        .filter_map(|(name_end, value_start)| {
+            let position = text[*name_end..*value_start].find('=')?;
+            Some(location_at(text, name_end + position).linechar)
+        })
+        .collect::<Vec<_>>();
+
+    columns.len() > 1 && columns.iter().all(|column| *column == columns[0])
+}
+
+fn compare(a: &ConstValue, b: &ConstValue) -> Option<Ordering> {
+    match (a, b) {
+        (ConstValue::String(a), ConstValue::String(b)) => Some(a.cmp(b)),
+        (ConstValue::Bool(a), ConstValue::Bool(b)) => Some(a.cmp(b)),
+        _ => a.as_float()?.partial_cmp(&b.as_float()?),
+    }
+}
+
+fn sorted_by_name(members: &[Member]) -> Vec<Member> {
+    let mut sorted = members.to_vec();
+    sorted.sort_by_key(|member| (member.name.to_lowercase(), member.name.clone()));
+    sorted
+}
+
+/// Members ordered by their value, `None` when some value isn't known or can't
+/// be compared with the others.
+fn sorted_by_value(members: &[Member]) -> Option<Vec<Member>> {
+    let first = members.first()?.evaluated.as_ref()?;
+
+    for member in members {
+        compare(first, member.evaluated.as_ref()?)?;
+    }
+
+    let mut sorted = members.to_vec();
+    sorted.sort_by(|a, b| {
+        compare(a.evaluated.as_ref().unwrap(), b.evaluated.as_ref().unwrap())
+            .unwrap_or(Ordering::Equal)
+    });
+
+    Some(sorted)
+}
+
+fn is_same_order(a: &[Member], b: &[Member]) -> bool {
+    a.iter()
+        .map(|member| &member.name)
+        .eq(b.iter().map(|member| &member.name))
+}
+
+struct EnumFinder {
+    cursor: usize,
+    statement: Option<EnumStatement>,
+}
+
+impl AstVisitor for EnumFinder {
+    fn enter_enum_statement(&mut self, statement: &EnumStatement) -> AstVisitorResult {
+        if statement.name.get_from().index <= self.cursor && self.cursor < statement.to.index {
+            self.statement = Some(statement.clone());
+        }
+
+        AstVisitorResult::Continue
+    }
+}
+
+/// Source actions sorting the members of the enum around the cursor, aligning
+/// their values and switching between implicit and explicit values. Members
+/// with an implicit value are numbered explicitly when sorting would change
+/// their value.
+pub(crate) fn organize_enum(context: &RefactorContext) -> Vec<CodeActionOrCommand> {
+    let cursor = context.selection.start;
+    let text = context.text;
+
+    let mut finder = EnumFinder {
+        cursor,
+        statement: None,
+    };
+    visit(context.statements, &mut finder);
+
+    let Some(statement) = finder.statement else {
+        return Vec::new();
+    };
+
+    let name_from = skip_whitespace(text, &statement.name.get_from());
+    let (Some(start), Some(open)) = (
+        text[..name_from.index].rfind("enum"),
+        text[name_from.index..].find('{'),
+    ) else {
+        return Vec::new();
+    };
+
+    let open = name_from.index + open;
+    let close = statement.to.index - 1;
+    let outer = indentation(text, location_at(text, start).line as u32);
+    let member_indentation = match statement.enumerations.first() {
+        Some(first) if skip_whitespace(text, &first.name.get_from()).line > name_from.line => {
+            indentation(
+                text,
+                skip_whitespace(text, &first.name.get_from()).line as u32,
+            )
+        }
+        _ => format!("{}    ", outer),
+    };
+
+    let evaluator = ConstEvaluator::new(context.statements, context.scope_table);
+    let values = evaluator.enum_values(&statement);
+
+    let mut members = Vec::new();
+    let mut spans = Vec::new();
+    let mut assignments = Vec::new();
+
+    for (enumeration, (_, evaluated)) in statement.enumerations.iter().zip(values) {
+        let from = skip_whitespace(text, &enumeration.name.get_from()).index;
+        let end = match &enumeration.value {
+            Some(value) => {
+                let value_from = skip_whitespace(text, &value.get_from()).index;
+                assignments.push((enumeration.name.get_to().index, value_from));
+                value.get_to().index
+            }
+            None => enumeration.name.get_to().index,
+        };
+
+        let value = match &enumeration.value {
+            Some(value) => match context.print(value, &member_indentation) {
+                Some(value) => Some(value),
+                None => return Vec::new(),
+            },
+            None => None,
+        };
+
+        members.push(Member {
+            name: identifier_name(&enumeration.name),
+            value,
+            evaluated: evaluated.ok(),
+            leading: Vec::new(),
+            trailing: None,
+        });
+        spans.push((from, end));
+    }
+
+    if members.is_empty() {
+        return Vec::new();
+    }
+
+    let footer = attach_comments(text, &mut members, &spans, Some(open + 1), Some(close));
+    let name = &text[name_from.index..statement.name.get_to().index];
+    let aligned = is_aligned(text, &assignments);
+
+    let render = |members: &[Member], align: bool| {
+        let layout = Layout {
+            indentation: &member_indentation,
+            prefix: "",
+            separator: ",",
+            last_separator: "",
+            align,
+        };
+
+        format!(
+            "enum {} {{\n{}\n{}}}",
+            name,
+            layout.render(members, &footer),
+            outer
+        )
+    };
+
+    let explicit = |members: &[Member]| {
+        members
+            .iter()
+            .map(|member| Member {
+                value: member
+                    .value
+                    .clone()
+                    .or_else(|| member.evaluated.as_ref().map(ToString::to_string)),
+                ..member.clone()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // Implicit values are numbered in the order the members are declared in
+    let keep_values = |sorted: Vec<Member>| {
+        let implicit = |members: &[Member]| {
+            members
+                .iter()
+                .filter(|member| member.value.is_none())
+                .map(|member| member.name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        match implicit(&sorted) == implicit(&members) {
+            true => sorted,
+            false => explicit(&sorted),
+        }
+    };
+
+    let mut actions = Vec::new();
+    let mut action = |title: &str, new_text: String| {
+        actions.push(context.action(
+            title,
+            CodeActionKind::SOURCE,
+            vec![edit(text, start, statement.to.index, new_text)],
+        ));
+    };
+
+    let by_name = sorted_by_name(&members);
+    if !is_same_order(&by_name, &members) {
+        action(
+            "Sort enum members by name",
+            render(&keep_values(by_name), aligned),
+        );
+    }
+
+    if let Some(by_value) = sorted_by_value(&members) {
+        if !is_same_order(&by_value, &members) {
+            action(
+                "Sort enum members by value",
+                render(&keep_values(by_value), aligned),
+            );
+        }
+    }
+
+    if !aligned && assignments.len() > 1 {
+        action("Align enum values", render(&members, true));
+    }
+
+    if members.iter().any(|member| member.value.is_none()) {
+        action(
+            "Number enum members explicitly",
+            render(&explicit(&members), aligned),
+        );
+    }
+
+    // An explicit value can be left out when it's what the counter of implicit values is at
+    let mut counter = 0;
+    let mut stripped = members.clone();
+    for member in &mut stripped {
+        match (&member.value, &member.evaluated) {
+            (None, _) => counter += 1,
+            (Some(_), Some(ConstValue::Integer(value))) if *value == counter => {
+                member.value = None;
+                counter += 1;
+            }
+            _ => {}
+        }
+    }
+
+    if stripped
+        .iter()
+        .filter(|member| member.value.is_none())
+        .count()
+        > members
+            .iter()
+            .filter(|member| member.value.is_none())
+            .count()
+    {
+        action("Remove sequential enum values", render(&stripped, aligned));
+    }
+
+    actions
+}
+
+/// Runs of consts declared one after another without empty lines between them.
+struct ConstBlocks<'t> {
+    text: &'t str,
+    cursor: usize,
+    block: Option<Vec<ConstStatement>>,
+}
+
+impl ConstBlocks<'_> {
+    fn check(&mut self, list: &Statements) {
+        let mut block: Vec<ConstStatement> = Vec::new();
+        let mut contains_cursor = false;
+
+        for statement in &list.statements {
+            let Statement::Const(constant) = statement else {
+                self.finish(&mut block, &mut contains_cursor);
+                continue;
+            };
+
+            let from = skip_whitespace(self.text, &constant.from);
+            let is_adjacent = block
+                .last()
+                .is_none_or(|previous| from.line <= previous.to.line + 1);
+
+            if !is_adjacent {
+                self.finish(&mut block, &mut contains_cursor);
+            }
+
+            contains_cursor |= from.index <= self.cursor && self.cursor <= constant.to.index;
+            block.push(constant.as_ref().clone());
+        }
+
+        self.finish(&mut block, &mut contains_cursor);
+    }
+
+    fn finish(&mut self, block: &mut Vec<ConstStatement>, contains_cursor: &mut bool) {
+        if *contains_cursor {
+            self.block = Some(std::mem::take(block));
+        }
+
+        block.clear();
+        *contains_cursor = false;
+    }
+}
+
+impl AstVisitor for ConstBlocks<'_> {
+    fn enter_block_statement(&mut self, statement: &BlockStatement) -> AstVisitorResult {
+        self.check(&statement.statements);
+        AstVisitorResult::Continue
+    }
+
+    fn enter_switch_statement(&mut self, statement: &SwitchStatement) -> AstVisitorResult {
+        for case in &statement.cases {
+            self.check(&case.statements);
+        }
+
+        if let Some(default) = &statement.default {
+            self.check(default);
+        }
+
+        AstVisitorResult::Continue
+    }
+}
+
+/// Source actions sorting the block of consts around the cursor and aligning
+/// their values. Blocks where a const refers to another one of the same block
+/// aren't sorted, since consts have to be declared before they're used.
+pub(crate) fn organize_consts(context: &RefactorContext) -> Vec<CodeActionOrCommand> {
+    let text = context.text;
+
+    let mut blocks = ConstBlocks {
+        text,
+        cursor: context.selection.start,
+        block: None,
+    };
+    blocks.check(context.statements);
+    visit(context.statements, &mut blocks);
+
+    let Some(block) = blocks.block.filter(|block| block.len() > 1) else {
+        return Vec::new();
+    };
+
+    let first = skip_whitespace(text, &block[0].from);
+    let last = &block[block.len() - 1];
+    let member_indentation = indentation(text, first.line as u32);
+
+    let terminator = match text[last.to.index..]
+        .trim_start_matches([' ', '\t'])
+        .starts_with(';')
+    {
+        true => ";",
+        false => "",
+    };
+    let end = match terminator {
+        ";" => last.to.index + text[last.to.index..].find(';').unwrap_or(0) + 1,
+        _ => last.to.index,
+    };
+
+    let evaluator = ConstEvaluator::new(context.statements, context.scope_table);
+
+    let mut members = Vec::new();
+    let mut spans = Vec::new();
+    let mut assignments = Vec::new();
+
+    for constant in &block {
+        let value_from = skip_whitespace(text, &constant.expression.get_from()).index;
+        assignments.push((constant.name.get_to().index, value_from));
+        spans.push((
+            skip_whitespace(text, &constant.from).index,
+            constant.expression.get_to().index,
+        ));
+
+        let Some(value) = context.print(&constant.expression, &member_indentation) else {
+            return Vec::new();
+        };
+
+        members.push(Member {
+            name: identifier_name(&constant.name),
+            value: Some(value),
+            evaluated: evaluator.evaluate(&constant.expression).ok(),
+            leading: Vec::new(),
+            trailing: None,
+        });
+    }
+
+    let footer = attach_comments(text, &mut members, &spans, None, None);
+    let aligned = is_aligned(text, &assignments);
+
+    let render = |members: &[Member], align: bool| {
+        let layout = Layout {
+            indentation: &member_indentation,
+            prefix: "const ",
+            separator: terminator,
+            last_separator: terminator,
+            align,
+        };
+
+        layout.render(members, &footer)[member_indentation.len()..].to_string()
+    };
+
+    let refers_to_block = context.scope_table.scopes().iter().any(|scope| {
+        scope.variable_usage.iter().any(|usage| {
+            let is_within = assignments
+                .iter()
+                .zip(&spans)
+                .any(|((_, from), (_, to))| *from <= usage.from.index && usage.from.index < *to);
+
+            is_within
+                && usage
+                    .declaration
+                    .as_ref()
+                    .and_then(|declaration| context.scope_table.get_declaration(declaration))
+                    .is_some_and(|declaration| {
+                        declaration.kind == VariableDeclarationKind::Const
+                            && first.index <= declaration.from.index
+                            && declaration.from.index < end
+                    })
+        })
+    });
+
+    let mut actions = Vec::new();
+    let mut action = |title: &str, new_text: String| {
+        actions.push(context.action(
+            title,
+            CodeActionKind::SOURCE,
+            vec![edit(text, first.index, end, new_text)],
+        ));
+    };
+
+    if !refers_to_block {
+        let by_name = sorted_by_name(&members);
+        if !is_same_order(&by_name, &members) {
+            action("Sort consts by name", render(&by_name, aligned));
+        }
+
+        if let Some(by_value) = sorted_by_value(&members) {
+            if !is_same_order(&by_value, &members) {
+                action("Sort consts by value", render(&by_value, aligned));
+            }
+        }
+    }
+
+    if !aligned {
+        action("Align const values", render(&members, true));
+    }
+
+    actions
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{CodeActionOrCommand, Position, Range, Url};
+
+    use crate::{
+        dialect::Dialect,
+        refactor::{offset, refactorings},
+    };
+
+    fn apply(input: &str, position: Position, title: &str) -> Option<String> {
+        let uri = Url::parse("file:///test.nut").unwrap();
+
+        let edits = refactorings(
+            &uri,
+            input,
+            Range::new(position, position),
+            Dialect::default(),
+        )
+        .into_iter()
+        .find_map(|action| match action {
+            CodeActionOrCommand::CodeAction(action) if action.title == title => {
+                Some(action.edit.unwrap().changes.unwrap()[&uri].clone())
+            }
+            _ => None,
+        })?;
+
+        let mut output = input.to_string();
+        for edit in edits.iter().rev() {
+            let from = offset(input, edit.range.start);
+            let to = offset(input, edit.range.end);
+            output.replace_range(from..to, &edit.new_text);
+        }
+
+        Some(output)
+    }
+
+    #[test]
+    fn test_sort_enum_by_name() {
+        let input = "
+enum Color {
+    Red, // first
+    Green = 5,
+    Blue
+}
+";
+        assert_eq!(
+            apply(input, Position::new(2, 4), "Sort enum members by name").unwrap(),
+            "
+enum Color {
+    Blue = 1,
+    Green = 5,
+    Red = 0 // first
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_remove_sequential_enum_values() {
+        let input = "
+enum E { A = 0, B = 1, C = 5 }
+";
+        assert_eq!(
+            apply(input, Position::new(1, 9), "Remove sequential enum values").unwrap(),
+            "
+enum E {
+    A,
+    B,
+    C = 5
+}
+"
+        );
+        assert_eq!(
+            apply(input, Position::new(1, 9), "Number enum members explicitly"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_sort_consts_by_value() {
+        let input = "
+const LONG_NAME = 3;
+const B         = 1;
+const C         = 2;
+
+const D = 0;
+";
+        assert_eq!(
+            apply(input, Position::new(2, 6), "Sort consts by value").unwrap(),
+            "
+const B         = 1;
+const C         = 2;
+const LONG_NAME = 3;
+
+const D = 0;
+"
+        );
+    }
+}
@@ -1,4 +1,6 @@
-use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
+use std::collections::HashMap;
+
+use serde::Serialize;
 
 use crate::{
     ast_visitor::{visit, AstVisitor, AstVisitorResult},
@@ -7,12 +9,21 @@ use crate::{
     squirrel_lexer::{Location, Operator},
 };
 
+/// Scopes of a script with the variables declared in them and every usage of a
+/// name, resolved to its declaration where one is visible.
 #[derive(Debug)]
 pub struct ScopeTable {
     scopes: Vec<Scope>,
+    /// Statements that only declare one variable and can be removed along with
+    /// it, keyed by the position of the declaration
+    removable_statements: HashMap<usize, (Location, Location)>,
+    /// Usages as `(scope, position)`, ordered by where they start
+    usages_by_location: Vec<(usize, usize)>,
+    /// Declarations as `(scope, sequence_number)`, ordered by where they start
+    declarations_by_location: Vec<(usize, usize)>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct Scope {
     pub parent: Option<usize>,
     pub children: Vec<usize>,
@@ -31,33 +42,40 @@ impl Scope {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize)]
 pub enum VariableDeclarationKind {
     Local,
     Const,
     Function,
+    Parameter,
+    CatchVariable,
     ClassMember,
+    Enum,
+    EnumMember,
+    /// A module or one of its slots bound by an `import`
+    Import,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct VariableDeclaration {
     pub sequence_number: usize,
     pub kind: VariableDeclarationKind,
     pub name: String,
+    #[serde(skip)]
     pub value: Option<Expression>,
     pub scope: usize,
     pub from: Location,
     pub to: Location,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub enum DeclarationType {
     Variable(usize, usize),
     This,
     Base,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct VariableUsage {
     pub name: String,
     pub declaration: Option<DeclarationType>,
@@ -123,7 +141,7 @@ impl AstVisitor for ScopeTableVisitor<'_> {
                             });
                     }
                 }
-                TableEntry::FieldWithExpressionKey(_) => {}
+                TableEntry::FieldWithExpressionKey(_) | TableEntry::FieldWithStringKey(_) => {}
             }
         }
 
@@ -148,7 +166,7 @@ impl AstVisitor for ScopeTableVisitor<'_> {
             self.scope_table.scopes[*scope]
                 .variables
                 .push(VariableDeclaration {
-                    kind: VariableDeclarationKind::Local,
+                    kind: VariableDeclarationKind::CatchVariable,
                     sequence_number,
                     name: ident.token.to_string(),
                     value: None,
@@ -181,7 +199,7 @@ impl AstVisitor for ScopeTableVisitor<'_> {
                 kind: VariableDeclarationKind::Local,
                 sequence_number: self.scope_table.scopes[*scope].variables.len(),
                 name: ident.token.to_string(),
-                value: None,
+                value: Some(Expression::Class(Box::new(class.clone()))),
                 scope: *scope,
                 from: class.from.clone(),
                 to: class.to.clone(),
@@ -234,11 +252,16 @@ impl AstVisitor for ScopeTableVisitor<'_> {
         let scope = self.scope_stack.last().unwrap();
 
         if let Some(Expression::Identifier(ident)) = &function.name {
+            // `local function` binds a local rather than a slot of the environment
+            let kind = match function.is_local {
+                true => VariableDeclarationKind::Local,
+                false => VariableDeclarationKind::Function,
+            };
             let variable = VariableDeclaration {
-                kind: VariableDeclarationKind::Function,
+                kind,
                 sequence_number: self.scope_table.scopes[*scope].variables.len(),
                 name: ident.token.to_string(),
-                value: None,
+                value: Some(Expression::Function(Box::new(function.clone()))),
                 scope: *scope,
                 from: function.from.clone(),
                 to: function.to.clone(),
@@ -253,7 +276,7 @@ impl AstVisitor for ScopeTableVisitor<'_> {
         for parameter in &function.parameters {
             if let Expression::Identifier(ident) = parameter {
                 let variable = VariableDeclaration {
-                    kind: VariableDeclarationKind::Local,
+                    kind: VariableDeclarationKind::Parameter,
                     sequence_number: self.scope_table.scopes[*scope].variables.len(),
                     name: ident.token.to_string(),
                     value: None,
@@ -267,7 +290,7 @@ impl AstVisitor for ScopeTableVisitor<'_> {
                 if op.operator == Operator::Assign {
                     if let Expression::Identifier(ident) = &op.left {
                         let variable = VariableDeclaration {
-                            kind: VariableDeclarationKind::Local,
+                            kind: VariableDeclarationKind::Parameter,
                             sequence_number: self.scope_table.scopes[*scope].variables.len(),
                             name: ident.token.to_string(),
                             value: Some(op.right.clone()),
@@ -297,6 +320,19 @@ impl AstVisitor for ScopeTableVisitor<'_> {
         let scope = self.scope_stack.last().unwrap();
         let mut variables = Vec::new();
 
+        if let [initialization] = statement.initializations.as_slice() {
+            if !initialization
+                .expression
+                .as_ref()
+                .is_some_and(has_side_effects)
+            {
+                self.scope_table.removable_statements.insert(
+                    initialization.from.index,
+                    (statement.from.clone(), statement.to.clone()),
+                );
+            }
+        }
+
         for initialization in &statement.initializations {
             let variable = VariableDeclaration {
                 kind: VariableDeclarationKind::Local,
@@ -318,6 +354,58 @@ impl AstVisitor for ScopeTableVisitor<'_> {
         AstVisitorResult::Continue
     }
 
+    fn enter_destructuring_statement(
+        &mut self,
+        statement: &DestructuringStatement,
+    ) -> AstVisitorResult {
+        let scope = self.scope_stack.last().unwrap();
+
+        for binding in &statement.bindings {
+            let variable = VariableDeclaration {
+                kind: VariableDeclarationKind::Local,
+                sequence_number: self.scope_table.scopes[*scope].variables.len(),
+                name: binding.name.clone(),
+                value: None,
+                scope: *scope,
+                from: binding.from.clone(),
+                to: binding.to.clone(),
+            };
+
+            self.scope_table.scopes[*scope].variables.push(variable);
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_import_statement(&mut self, statement: &ImportStatement) -> AstVisitorResult {
+        let scope = self.scope_stack.last().unwrap();
+        let mut variables = Vec::new();
+
+        if let Some(Expression::Identifier(alias)) = &statement.alias {
+            variables.push((alias.token.to_string(), &alias.from, &alias.to));
+        }
+
+        for name in &statement.names {
+            variables.push((name.binding().to_string(), &name.from, &name.to));
+        }
+
+        for (name, from, to) in variables {
+            let variable = VariableDeclaration {
+                kind: VariableDeclarationKind::Import,
+                sequence_number: self.scope_table.scopes[*scope].variables.len(),
+                name,
+                value: None,
+                scope: *scope,
+                from: from.clone(),
+                to: to.clone(),
+            };
+
+            self.scope_table.scopes[*scope].variables.push(variable);
+        }
+
+        AstVisitorResult::Continue
+    }
+
     fn enter_block_statement(&mut self, _statement: &BlockStatement) -> AstVisitorResult {
         self.spawn_child_scope();
         AstVisitorResult::Continue
@@ -390,6 +478,11 @@ impl AstVisitor for ScopeTableVisitor<'_> {
         let scope = self.scope_stack.last().unwrap();
 
         if let Expression::Identifier(name) = &statement.name {
+            self.scope_table.removable_statements.insert(
+                name.from.index,
+                (statement.from.clone(), statement.to.clone()),
+            );
+
             let variable = VariableDeclaration {
                 kind: VariableDeclarationKind::Const,
                 sequence_number: self.scope_table.scopes[*scope].variables.len(),
@@ -405,8 +498,42 @@ impl AstVisitor for ScopeTableVisitor<'_> {
         AstVisitorResult::Continue
     }
 
-    fn enter_enum_statement(&mut self, _statement: &EnumStatement) -> AstVisitorResult {
+    fn enter_enum_statement(&mut self, statement: &EnumStatement) -> AstVisitorResult {
+        let scope = self.scope_stack.last().unwrap();
+
+        if let Expression::Identifier(name) = &statement.name {
+            let variable = VariableDeclaration {
+                kind: VariableDeclarationKind::Enum,
+                sequence_number: self.scope_table.scopes[*scope].variables.len(),
+                name: name.token.to_string(),
+                value: None,
+                scope: *scope,
+                from: name.from.clone(),
+                to: name.to.clone(),
+            };
+
+            self.scope_table.scopes[*scope].variables.push(variable);
+        }
+
         self.spawn_child_scope();
+
+        let scope = self.scope_stack.last().unwrap();
+        for enumeration in &statement.enumerations {
+            if let Expression::Identifier(name) = &enumeration.name {
+                let variable = VariableDeclaration {
+                    kind: VariableDeclarationKind::EnumMember,
+                    sequence_number: self.scope_table.scopes[*scope].variables.len(),
+                    name: name.token.to_string(),
+                    value: enumeration.value.clone(),
+                    scope: *scope,
+                    from: name.from.clone(),
+                    to: name.to.clone(),
+                };
+
+                self.scope_table.scopes[*scope].variables.push(variable);
+            }
+        }
+
         AstVisitorResult::Continue
     }
 
@@ -482,10 +609,81 @@ impl AstVisitor for ScopeTableVisitor<'_> {
     }
 }
 
+/// Whether evaluating the expression may do more than produce a value, in which
+/// case a statement containing it can't be removed.
+pub(crate) fn has_side_effects(expression: &Expression) -> bool {
+    match expression {
+        Expression::FunctionCall(_)
+        | Expression::RawCall(_)
+        | Expression::Resume(_)
+        | Expression::Delete(_)
+        | Expression::PostfixUnaryOperator(_) => true,
+        Expression::UnaryOperator(unary) => {
+            matches!(unary.operator, Operator::Increment | Operator::Decrement)
+                || has_side_effects(&unary.expression)
+        }
+        Expression::BinaryOperator(binary) => {
+            binary.operator.is_assignment()
+                || has_side_effects(&binary.left)
+                || has_side_effects(&binary.right)
+        }
+        Expression::TernaryOperator(ternary) => {
+            has_side_effects(&ternary.condition)
+                || has_side_effects(&ternary.if_true)
+                || has_side_effects(&ternary.if_false)
+        }
+        Expression::Grouping(grouping) => {
+            grouping.expression.as_ref().is_some_and(has_side_effects)
+        }
+        Expression::Array(array) => array.elements.iter().any(has_side_effects),
+        Expression::Table(table) => table.entries.iter().any(|entry| match entry {
+            TableEntry::Field(field) => has_side_effects(&field.expression),
+            TableEntry::FieldWithExpressionKey(field) => {
+                has_side_effects(&field.key) || has_side_effects(&field.expression)
+            }
+            TableEntry::FieldWithStringKey(field) => has_side_effects(&field.expression),
+            TableEntry::Function(_) => false,
+        }),
+        Expression::Clone(clone) => has_side_effects(&clone.expression),
+        Expression::MemberAccess(access) => has_side_effects(&access.expression),
+        Expression::ArrayAccess(access) => {
+            has_side_effects(&access.array) || has_side_effects(&access.index)
+        }
+        Expression::Spread(_) => true,
+        _ => false,
+    }
+}
+
+/// Binary searches entries ordered by where they start for one spanning the
+/// position on a single line, checking the entries starting earlier on the
+/// same line in case they overlap.
+fn find_by_location<'t, T>(
+    sorted: &[(usize, usize)],
+    line: usize,
+    character: usize,
+    get: impl Fn((usize, usize)) -> (&'t T, &'t Location, &'t Location),
+) -> Option<&'t T> {
+    let started = sorted.partition_point(|entry| {
+        let (_, from, _) = get(*entry);
+        (from.line, from.linechar) <= (line, character)
+    });
+
+    sorted[..started]
+        .iter()
+        .rev()
+        .map(|entry| get(*entry))
+        .take_while(|(_, from, _)| from.line == line)
+        .find(|(_, _, to)| to.line == line && to.linechar >= character)
+        .map(|(item, _, _)| item)
+}
+
 impl ScopeTable {
     pub fn new(statements: &Statements) -> Self {
         let mut table = Self {
             scopes: vec![Scope::new(None)],
+            removable_statements: HashMap::new(),
+            usages_by_location: Vec::new(),
+            declarations_by_location: Vec::new(),
         };
 
         {
@@ -497,9 +695,47 @@ impl ScopeTable {
             visit(statements, &mut visitor);
         }
 
+        for (scope_index, scope) in table.scopes.iter().enumerate() {
+            table
+                .usages_by_location
+                .extend((0..scope.variable_usage.len()).map(|position| (scope_index, position)));
+            table
+                .declarations_by_location
+                .extend((0..scope.variables.len()).map(|position| (scope_index, position)));
+        }
+
+        let scopes = &table.scopes;
+        table.usages_by_location.sort_by_key(|(scope, position)| {
+            let from = &scopes[*scope].variable_usage[*position].from;
+            (from.line, from.linechar)
+        });
+        table
+            .declarations_by_location
+            .sort_by_key(|(scope, position)| {
+                let from = &scopes[*scope].variables[*position].from;
+                (from.line, from.linechar)
+            });
+
         table
     }
 
+    pub fn scopes(&self) -> &[Scope] {
+        &self.scopes
+    }
+
+    /// Indexes every usage by the position of its first character in the source.
+    pub fn usages_by_index(&self) -> HashMap<usize, &VariableUsage> {
+        let mut usages = HashMap::new();
+
+        for scope in &self.scopes {
+            for usage in &scope.variable_usage {
+                usages.insert(usage.from.index, usage);
+            }
+        }
+
+        usages
+    }
+
     pub fn get_declaration(&self, decl: &DeclarationType) -> Option<&VariableDeclaration> {
         match decl {
             DeclarationType::Base => None,
@@ -515,19 +751,15 @@ impl ScopeTable {
         line: usize,
         character: usize,
     ) -> Option<&VariableUsage> {
-        for scope in &self.scopes {
-            for variable_usage in &scope.variable_usage {
-                if variable_usage.from.line == line
-                    && variable_usage.from.linechar <= character
-                    && variable_usage.to.line == line
-                    && variable_usage.to.linechar >= character
-                {
-                    return Some(variable_usage);
-                }
-            }
-        }
-
-        None
+        find_by_location(
+            &self.usages_by_location,
+            line,
+            character,
+            |(scope, position)| {
+                let usage = &self.scopes[scope].variable_usage[position];
+                (usage, &usage.from, &usage.to)
+            },
+        )
     }
 
     pub fn find_variable_declaration_by_location(
@@ -535,68 +767,80 @@ impl ScopeTable {
         line: usize,
         character: usize,
     ) -> Option<&VariableDeclaration> {
-        for scope in &self.scopes {
-            for variable_declaration in &scope.variables {
-                if variable_declaration.from.line == line
-                    && variable_declaration.from.linechar <= character
-                    && variable_declaration.to.line == line
-                    && variable_declaration.to.linechar >= character
-                {
-                    return Some(variable_declaration);
-                }
-            }
-        }
+        find_by_location(
+            &self.declarations_by_location,
+            line,
+            character,
+            |(scope, sequence_number)| {
+                let declaration = &self.scopes[scope].variables[sequence_number];
+                (declaration, &declaration.from, &declaration.to)
+            },
+        )
+    }
 
-        None
+    /// Locals and consts that are never used. Names starting with `_` are
+    /// exempt, and classes and local functions are left to the unused checker.
+    pub fn unused_variables(&self) -> Vec<&VariableDeclaration> {
+        self.scopes
+            .iter()
+            .flat_map(|scope| scope.variables.iter())
+            .filter(|variable| {
+                matches!(
+                    variable.kind,
+                    VariableDeclarationKind::Const | VariableDeclarationKind::Local
+                )
+            })
+            .filter(|variable| match &variable.value {
+                Some(Expression::Class(_)) => false,
+                Some(Expression::Function(function)) => !function.is_local,
+                _ => true,
+            })
+            .filter(|variable| !variable.name.starts_with('_'))
+            .filter(|variable| {
+                self.find_variable_usages(&variable.name, variable.scope)
+                    .is_empty()
+            })
+            .collect()
     }
 
-    pub fn validate_variables(&self) -> Vec<Diagnostic> {
-        let mut diagnostics = Vec::new();
+    /// Usages of variables that are neither declared nor listed in
+    /// `api_definitions`, along with the scope they are in.
+    pub fn undeclared_usages(&self, api_definitions: &[String]) -> Vec<(usize, &VariableUsage)> {
+        self.scopes
+            .iter()
+            .enumerate()
+            .flat_map(|(index, scope)| scope.variable_usage.iter().map(move |usage| (index, usage)))
+            .filter(|(_, usage)| {
+                usage.declaration.is_none() && !api_definitions.contains(&usage.name)
+            })
+            .collect()
+    }
 
-        for scope in &self.scopes {
-            for variable in &scope.variables {
-                if variable.kind != VariableDeclarationKind::Const
-                    && variable.kind != VariableDeclarationKind::Local
-                {
-                    continue;
-                }
+    /// The statement declaring only `declaration`, if it can be removed along with it.
+    pub fn removable_statement(
+        &self,
+        declaration: &VariableDeclaration,
+    ) -> Option<(Location, Location)> {
+        self.removable_statements
+            .get(&declaration.from.index)
+            .cloned()
+    }
 
-                if variable.name.starts_with("_") {
-                    continue;
-                }
+    /// Declarations of the scope and all of its parents.
+    pub fn visible_declarations(&self, mut current_scope: usize) -> Vec<&VariableDeclaration> {
+        let mut declarations = Vec::new();
 
-                let usages = self.find_variable_usages(&variable.name, variable.scope);
-
-                if usages.is_empty() {
-                    diagnostics.push(Diagnostic::new(
-                        Range::new(
-                            Position::new(variable.from.line as u32, variable.from.linechar as u32),
-                            Position::new(variable.to.line as u32, variable.to.linechar as u32),
-                        ),
-                        Some(DiagnosticSeverity::WARNING),
-                        None,
-                        None,
-                        format!("Unused variable '{}'", variable.name),
-                        None,
-                        None,
-                    ));
-                }
-            }
+        loop {
+            let scope = &self.scopes[current_scope];
+            declarations.extend(scope.variables.iter());
 
-            for usage in &scope.variable_usage {
-                if usage.declaration.is_none() {
-                    diagnostics.push(Diagnostic::new_simple(
-                        Range::new(
-                            Position::new(usage.from.line as u32, usage.from.linechar as u32),
-                            Position::new(usage.to.line as u32, usage.to.linechar as u32),
-                        ),
-                        format!("Variable '{}' is not declared", usage.name),
-                    ));
-                }
+            match scope.parent {
+                Some(parent) => current_scope = parent,
+                None => break,
             }
         }
 
-        diagnostics
+        declarations
     }
 
     pub fn find_variable_usages(&self, name: &str, scope: usize) -> Vec<&VariableUsage> {
@@ -645,7 +889,9 @@ impl ScopeTable {
 
 #[cfg(test)]
 mod tests {
-    use crate::{scope_table::ScopeTable, squirrel_parser::Parser};
+    use crate::{
+        diagnostics::validate_variables, scope_table::ScopeTable, squirrel_parser::Parser,
+    };
 
     #[test]
     fn test_single_scope_declaration_and_usage() {
@@ -752,7 +998,7 @@ local a = 1;
 
         let scope_table = ScopeTable::new(&statements);
 
-        assert_eq!(scope_table.validate_variables().len(), 0);
+        assert_eq!(validate_variables(input, &scope_table, &[]).len(), 0);
     }
 
     #[test]
@@ -767,7 +1013,7 @@ function foo(a, b, c) {
 
         let scope_table = ScopeTable::new(&statements);
 
-        assert_eq!(scope_table.validate_variables().len(), 0);
+        assert_eq!(validate_variables(input, &scope_table, &[]).len(), 0);
     }
 
     #[test]
@@ -787,7 +1033,7 @@ foo.stuff(1, 2);
 
         let scope_table = ScopeTable::new(&statements);
 
-        assert_eq!(scope_table.validate_variables().len(), 0);
+        assert_eq!(validate_variables(input, &scope_table, &[]).len(), 0);
     }
 
     #[test]
@@ -802,7 +1048,7 @@ foreach (i, v in [1, 2, 3]) {
 
         let scope_table = ScopeTable::new(&statements);
 
-        assert_eq!(scope_table.validate_variables().len(), 0);
+        assert_eq!(validate_variables(input, &scope_table, &[]).len(), 0);
     }
 
     #[test]
@@ -817,8 +1063,8 @@ for (local i = 0; i < 10; i += 1) {
 
         let scope_table = ScopeTable::new(&statements);
 
-        println!("{:#?}", scope_table.validate_variables());
-        assert_eq!(scope_table.validate_variables().len(), 0);
+        println!("{:#?}", validate_variables(input, &scope_table, &[]));
+        assert_eq!(validate_variables(input, &scope_table, &[]).len(), 0);
     }
 
     #[test]
@@ -835,7 +1081,7 @@ try {
 
         let scope_table = ScopeTable::new(&statements);
 
-        assert_eq!(scope_table.validate_variables().len(), 0);
+        assert_eq!(validate_variables(input, &scope_table, &[]).len(), 0);
     }
 
     #[test]
@@ -856,7 +1102,7 @@ foo.stuff();
 
         let scope_table = ScopeTable::new(&statements);
 
-        assert_eq!(scope_table.validate_variables().len(), 0);
+        assert_eq!(validate_variables(input, &scope_table, &[]).len(), 0);
     }
 
     #[test]
@@ -879,7 +1125,7 @@ foo.stuff();
 
         let scope_table = ScopeTable::new(&statements);
 
-        assert_eq!(scope_table.validate_variables().len(), 0);
+        assert_eq!(validate_variables(input, &scope_table, &[]).len(), 0);
     }
 
     #[test]
@@ -898,7 +1144,7 @@ tbl.foo();
 
         let scope_table = ScopeTable::new(&statements);
 
-        assert_eq!(scope_table.validate_variables().len(), 0);
+        assert_eq!(validate_variables(input, &scope_table, &[]).len(), 0);
     }
 
     #[test]
@@ -917,6 +1163,37 @@ tbl.foo();
 
         let scope_table = ScopeTable::new(&statements);
 
-        assert_eq!(scope_table.validate_variables().len(), 0);
+        assert_eq!(validate_variables(input, &scope_table, &[]).len(), 0);
+    }
+
+    #[test]
+    fn test_find_by_location() {
+        let input = "
+local a = 1;
+function foo(b) {
+    return a + b;
+}
+";
+        let mut parser = Parser::new(input);
+        let statements = parser.parse().unwrap();
+
+        let scope_table = ScopeTable::new(&statements);
+
+        let usage = scope_table.find_variable_usage_by_location(3, 15).unwrap();
+        assert_eq!(usage.name, "b");
+        assert_eq!(
+            scope_table
+                .find_variable_usage_by_location(3, 11)
+                .unwrap()
+                .name,
+            "a"
+        );
+        assert!(scope_table.find_variable_usage_by_location(3, 4).is_none());
+
+        let declaration = scope_table.find_variable_declaration_by_location(2, 13);
+        assert_eq!(declaration.unwrap().name, "b");
+        assert!(scope_table
+            .find_variable_declaration_by_location(4, 0)
+            .is_none());
     }
 }
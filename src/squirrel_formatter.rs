@@ -1,366 +1,175 @@
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
 use crate::{
-    squirrel_lexer::{Lexer, Operator, Token, TokenWithLocation},
+    dialect::Dialect,
     squirrel_parser::{Parser, ParserErrorWithLocation},
-    squirrel_printer::{PrintInstruction, Printer},
+    squirrel_printer::Printer,
 };
 
-#[derive(Debug, Clone)]
-pub struct Formatter {
-    input: String,
+/// Name of the project file formatter options are read from. It is looked up in
+/// the directory of the formatted document and all of its parents.
+pub const FORMATTER_CONFIG_FILE: &str = ".squirrelfmt.json";
+
+const DEFAULT_INDENT_WIDTH: usize = 2;
+const DEFAULT_MAX_WIDTH: usize = 100;
+const DEFAULT_MAX_BLANK_LINES: usize = 1;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IndentStyle {
+    #[default]
+    Spaces,
+    Tabs,
 }
 
-impl Formatter {
-    pub fn new(input: &str) -> Self {
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BraceStyle {
+    /// Keeps `{` on the line it was written on
+    #[default]
+    Preserve,
+    /// `if (a) {`
+    SameLine,
+    /// `if (a)` followed by `{` on its own line
+    NextLine,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct FormatterOptions {
+    /// Indent with tabs or spaces. Follows the editor settings when not set.
+    pub indent_style: Option<IndentStyle>,
+    /// Number of spaces per indentation level. Follows the editor settings when not set.
+    pub indent_width: Option<usize>,
+    /// Placement of the opening brace of blocks, functions, classes, enums and switches
+    pub brace_style: BraceStyle,
+    /// Pad the contents of non-empty parentheses with spaces, e.g. `foo( a, b )`
+    pub space_inside_parentheses: bool,
+    /// Put a comma after the last entry of multi-line tables and arrays
+    pub trailing_commas: bool,
+    /// Line width arrays, tables, argument lists and call chains are kept within
+    /// when deciding whether they are printed on a single line
+    pub max_width: usize,
+    /// Number of consecutive blank lines kept between statements, any more are removed
+    pub max_blank_lines: usize,
+}
+
+impl Default for FormatterOptions {
+    fn default() -> Self {
         Self {
-            input: input.to_string(),
+            indent_style: None,
+            indent_width: None,
+            brace_style: BraceStyle::default(),
+            space_inside_parentheses: false,
+            trailing_commas: false,
+            max_width: DEFAULT_MAX_WIDTH,
+            max_blank_lines: DEFAULT_MAX_BLANK_LINES,
         }
     }
+}
 
-    pub fn format(&mut self) -> Result<String, ParserErrorWithLocation> {
-        let mut original_non_formatted_sections = {
-            let mut lexer_with_comments = Lexer::new(&self.input, false);
-            let mut sections = Vec::new();
-
-            let mut index_start = None;
-
-            loop {
-                let token = lexer_with_comments.next();
-
-                if let Ok(TokenWithLocation {
-                    token: Token::EOF, ..
-                }) = token
-                {
-                    break;
-                }
-
-                if let Ok(token) = token {
-                    if let Token::Comment(c) | Token::MultiLineComment(c) = token.token {
-                        if c.trim() == "<sqlsp:ignore_formatting>" {
-                            if index_start.is_none() {
-                                index_start = Some(token.from.index);
-                            }
-                        } else if c.trim() == "</sqlsp:ignore_formatting>" {
-                            if let Some(start) = index_start {
-                                sections.push(self.input[start..token.to.index].to_string());
-                                index_start = None;
-                            }
-                        }
-                    }
-                } else {
-                    break;
-                }
-            }
+/// A [`FORMATTER_CONFIG_FILE`] that can't be read or doesn't hold valid options.
+#[derive(Debug)]
+pub struct FormatterConfigError {
+    pub path: PathBuf,
+    pub message: String,
+}
 
-            sections
-        };
+impl Display for FormatterConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
 
-        let original_comments = {
-            let mut lexer_with_comments = Lexer::new(&self.input, false);
-            let mut tokens = Vec::new();
-
-            loop {
-                let token = lexer_with_comments.next();
-                match token {
-                    Ok(TokenWithLocation {
-                        token: Token::EOF, ..
-                    }) => break,
-                    Ok(token) => tokens.push(token.token),
-                    Err(_) => break,
-                }
-            }
+impl FormatterOptions {
+    /// Reads the options from the closest [`FORMATTER_CONFIG_FILE`] above `path`,
+    /// `None` when there is none.
+    pub fn discover(path: &Path) -> Result<Option<Self>, FormatterConfigError> {
+        let Some(file) = path
+            .ancestors()
+            .map(|directory| directory.join(FORMATTER_CONFIG_FILE))
+            .find(|file| file.is_file())
+        else {
+            return Ok(None);
+        };
 
-            tokens
+        let config_error = |message: String| FormatterConfigError {
+            path: file.clone(),
+            message,
         };
 
-        let formatted_tokens = {
-            let mut parser = Parser::new(&self.input);
+        let contents =
+            std::fs::read_to_string(&file).map_err(|error| config_error(error.to_string()))?;
+        serde_json::from_str(&contents)
+            .map(Some)
+            .map_err(|error| config_error(error.to_string()))
+    }
 
-            let ast = parser.parse()?;
-            let mut printer = Printer::new(1000);
+    /// Fills in the indentation settings that were not configured explicitly
+    /// with the ones the editor sent along with the formatting request.
+    pub fn with_editor_indentation(mut self, tab_size: u32, insert_spaces: bool) -> Self {
+        self.indent_style.get_or_insert(match insert_spaces {
+            true => IndentStyle::Spaces,
+            false => IndentStyle::Tabs,
+        });
+        self.indent_width.get_or_insert(tab_size as usize);
+        self
+    }
 
-            printer.print(&ast)
-        };
+    /// Width of one indentation level in columns, counting tabs as `indent_width` columns
+    pub fn indentation_width(&self) -> usize {
+        self.indent_width.unwrap_or(DEFAULT_INDENT_WIDTH)
+    }
 
-        let mut result_tokens = Vec::new();
-        let mut formatted_token_iterator = formatted_tokens.into_iter().peekable();
-        let mut original_token_iterator = original_comments.into_iter().peekable();
-
-        let mut newline_counter = 0;
-        let mut indentation_stack = Vec::new();
-        let mut applied_indentation_stack = Vec::new();
-
-        loop {
-            let formatted = formatted_token_iterator.peek();
-            let original = original_token_iterator.peek();
-
-            let formatted = {
-                if let Some(PrintInstruction::IncrementIndentation { id }) = formatted {
-                    indentation_stack.clone_from(&applied_indentation_stack);
-                    indentation_stack.push(*id);
-                    formatted_token_iterator.next();
-                    continue;
-                }
-
-                if let Some(PrintInstruction::DecrementIndentation { id }) = formatted {
-                    if indentation_stack.last() == Some(id) {
-                        indentation_stack.pop();
-                    }
-
-                    if applied_indentation_stack.last() == Some(id) {
-                        applied_indentation_stack.pop();
-                    }
-
-                    formatted_token_iterator.next();
-                    continue;
-                }
-
-                formatted.map(|token| match token {
-                    PrintInstruction::EmitToken(token) => token,
-                    PrintInstruction::DecrementIndentation { .. } => {
-                        unreachable!("SetIndentation should be handled above")
-                    }
-                    PrintInstruction::IncrementIndentation { .. } => {
-                        unreachable!("IncrementIndentation should be handled above")
-                    }
-                })
-            };
-
-            let mut newline_indent = || {
-                if let Some(id) = indentation_stack.last().cloned() {
-                    if applied_indentation_stack.last() != Some(&id) {
-                        applied_indentation_stack.push(id);
-                    }
-                }
-                result_tokens.push(Token::Newline);
-                result_tokens.push(Token::Indent(applied_indentation_stack.len()));
-            };
-
-            match (formatted, original) {
-                (Some(formatted), Some(original)) => {
-                    if *formatted == *original {
-                        if *formatted == Token::Newline {
-                            if newline_counter < 2 {
-                                newline_indent();
-                            }
-                            newline_counter += 1;
-                        } else {
-                            result_tokens.push(formatted.clone());
-                            newline_counter = 0;
-                        }
-
-                        println!("Formatted next");
-                        formatted_token_iterator.next();
-
-                        println!("Original next");
-                        original_token_iterator.next();
-                    } else {
-                        match (formatted, original) {
-                            (Token::Newline, Token::Comment(comment)) => {
-                                result_tokens.push(Token::Space);
-                                result_tokens.push(Token::Comment(comment.clone()));
-
-                                newline_counter = 0;
-
-                                println!("Original next");
-                                original_token_iterator.next();
-                            }
-                            (Token::Newline, Token::MultiLineComment(comment)) => {
-                                result_tokens.push(Token::Space);
-                                result_tokens.push(Token::MultiLineComment(comment.clone()));
-                                result_tokens.push(Token::Space);
-                                newline_counter = 0;
-
-                                println!("Original next");
-                                original_token_iterator.next();
-                            }
-                            (_, Token::Comment(comment)) => {
-                                result_tokens.push(Token::Space);
-                                result_tokens.push(Token::Comment(comment.clone()));
-                                newline_counter = 0;
-
-                                println!("Original next");
-                                original_token_iterator.next();
-                            }
-                            (_, Token::MultiLineComment(comment)) => {
-                                result_tokens.push(Token::Space);
-                                result_tokens.push(Token::MultiLineComment(comment.clone()));
-                                result_tokens.push(Token::Space);
-                                newline_counter = 0;
-
-                                println!("Original next");
-                                original_token_iterator.next();
-                            }
-                            (Token::Space, _) => {
-                                result_tokens.push(Token::Space);
-                                newline_counter = 0;
-
-                                println!("Formatted next");
-                                formatted_token_iterator.next();
-                            }
-                            // Formatter may decide to remove commas in favour of newlines
-                            (Token::Newline, Token::Operator(Operator::Comma)) => {
-                                if newline_counter < 2 {
-                                    newline_indent();
-                                }
-                                newline_counter += 1;
-
-                                println!("Formatted next");
-                                formatted_token_iterator.next();
-
-                                println!("Original next");
-                                original_token_iterator.next();
-                            }
-                            // Formatter may decide to remove newlines in favour of commas
-                            (Token::Operator(Operator::Comma), Token::Newline) => {
-                                result_tokens.push(Token::Operator(Operator::Comma));
-                                newline_counter = 0;
-
-                                println!("Formatted next");
-                                formatted_token_iterator.next();
-
-                                println!("Original next");
-                                original_token_iterator.next();
-                            }
-                            // Formatter may decide to remove semicolons
-                            (_, Token::Semicolon) => {
-                                println!("Original next");
-                                original_token_iterator.next();
-                            }
-                            (Token::Newline, Token::Newline) => {
-                                if newline_counter < 2 {
-                                    newline_indent();
-                                }
-                                newline_counter += 1;
-
-                                println!("Formatted next");
-                                formatted_token_iterator.next();
-
-                                println!("Original next");
-                                original_token_iterator.next();
-                            }
-                            (Token::Newline, _) => {
-                                if newline_counter < 2 {
-                                    newline_indent();
-                                }
-                                newline_counter += 1;
-
-                                println!("Formatted next");
-                                formatted_token_iterator.next();
-                            }
-                            (Token::Dummy, Token::Newline) => {
-                                if newline_counter < 2 {
-                                    newline_indent();
-                                }
-                                newline_counter += 1;
-
-                                println!("Formatted next");
-                                formatted_token_iterator.next();
-
-                                println!("Original next");
-                                original_token_iterator.next();
-                            }
-                            (Token::Dummy, _) => {
-                                println!("Formatted next");
-                                formatted_token_iterator.next();
-                            }
-                            (_, Token::Newline) => {
-                                if newline_counter < 2 {
-                                    newline_indent();
-                                }
-                                newline_counter += 1;
-
-                                println!("Original next");
-                                original_token_iterator.next();
-                            }
-                            // Commas, might be removed from the formatted output
-                            (_, Token::Operator(Operator::Comma)) => {
-                                original_token_iterator.next();
-                            }
-                            _ => panic!(
-                                "Formatted token {:?} does not match original token {:?}",
-                                formatted, original
-                            ),
-                        }
-                    }
-                }
-                (None, Some(token)) => {
-                    if *token == Token::EOF {
-                        break;
-                    }
-                    if token == &Token::Newline {
-                        if newline_counter < 2 {
-                            newline_indent();
-                        }
-                        newline_counter += 1;
-                    } else {
-                        result_tokens.push(token.clone());
-                        newline_counter = 0;
-                    }
-                    original_token_iterator.next();
-                }
-                (Some(token), None) => {
-                    if *token == Token::EOF {
-                        break;
-                    }
-                    if token == &Token::Newline {
-                        if newline_counter < 2 {
-                            newline_indent();
-                        }
-                        newline_counter += 1;
-                    } else {
-                        result_tokens.push(token.clone());
-                        newline_counter = 0;
-                    }
-                    formatted_token_iterator.next();
-                }
-                (None, None) => break,
-            }
+    pub fn indentation(&self, level: usize) -> String {
+        match self.indent_style.unwrap_or_default() {
+            IndentStyle::Tabs => "\t".repeat(level),
+            IndentStyle::Spaces => " ".repeat(level * self.indentation_width()),
         }
+    }
+}
 
-        // Remove double spaces
-
-        let mut i = 0;
-        while i < result_tokens.len() {
-            if i == 0 && result_tokens[i] == Token::Space {
-                result_tokens.remove(i);
-            } else if let Token::Space = result_tokens[i] {
-                if i + 1 < result_tokens.len() && matches!(result_tokens[i + 1], Token::Space) {
-                    result_tokens.remove(i + 1);
-                } else {
-                    i += 1;
-                }
-            } else if let Token::Indent(_) = result_tokens[i] {
-                if i + 1 < result_tokens.len() && matches!(result_tokens[i + 1], Token::Space) {
-                    result_tokens.remove(i + 1);
-                } else {
-                    i += 1;
-                }
-            } else {
-                i += 1;
-            }
+/// Formats a whole script, keeping its comments and blank lines between statements.
+#[derive(Debug, Clone)]
+pub struct Formatter {
+    input: String,
+    dialect: Dialect,
+}
+
+impl Formatter {
+    pub fn new(input: &str) -> Self {
+        Self {
+            input: input.to_string(),
+            dialect: Dialect::default(),
         }
+    }
 
-        let mut result = String::with_capacity(self.input.len());
-
-        let mut ignore_formatting = false;
-        for token in result_tokens {
-            if let Token::Comment(c) | Token::MultiLineComment(c) = &token {
-                if c.trim() == "<sqlsp:ignore_formatting>" {
-                    ignore_formatting = true;
-                    continue;
-                } else if c.trim() == "</sqlsp:ignore_formatting>" {
-                    result.push_str(original_non_formatted_sections.remove(0).as_str());
-                    ignore_formatting = false;
-                    continue;
-                }
-            }
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
 
-            if !ignore_formatting {
-                result.push_str(&token.to_source_string());
-            }
+    pub fn format(
+        &mut self,
+        options: &FormatterOptions,
+    ) -> Result<String, ParserErrorWithLocation> {
+        let mut parser = Parser::new(&self.input).with_dialect(self.dialect);
+        let ast = parser.parse()?;
+
+        let mut printer = Printer::new(self.input.len(), options)
+            .with_comments(&self.input, std::mem::take(&mut parser.comments));
+
+        let mut formatted = printer.print(&ast);
+        if self.input.ends_with('\n') && !formatted.ends_with('\n') {
+            formatted.push('\n');
         }
 
-        Ok(result)
+        Ok(formatted)
     }
 }
 
@@ -370,6 +179,178 @@ mod tests {
 
     use super::*;
 
+    fn format(code: &str, options: FormatterOptions) -> String {
+        Formatter::new(code).format(&options).unwrap()
+    }
+
+    #[test]
+    fn test_format_with_editor_indentation() {
+        let code = "function foo() {\nif (a) {\nbar()\n}\n}";
+
+        let tabs = FormatterOptions::default().with_editor_indentation(4, false);
+        let spaces = FormatterOptions::default().with_editor_indentation(4, true);
+        let configured = FormatterOptions {
+            indent_width: Some(3),
+            ..FormatterOptions::default()
+        }
+        .with_editor_indentation(4, true);
+
+        assert_eq!(
+            format(code, tabs),
+            "function foo() {\n\tif (a) {\n\t\tbar()\n\t}\n}"
+        );
+        assert_eq!(
+            format(code, spaces),
+            "function foo() {\n    if (a) {\n        bar()\n    }\n}"
+        );
+        assert_eq!(
+            format(code, configured),
+            "function foo() {\n   if (a) {\n      bar()\n   }\n}"
+        );
+    }
+
+    #[test]
+    fn test_format_brace_style() {
+        let same_line = "if (a) {\n  b()\n} else {\n  c()\n}";
+        let next_line = "if (a)\n{\n  b()\n}\nelse\n{\n  c()\n}";
+
+        let options = |brace_style| FormatterOptions {
+            brace_style,
+            ..FormatterOptions::default()
+        };
+
+        assert_eq!(format(same_line, options(BraceStyle::NextLine)), next_line);
+        assert_eq!(format(next_line, options(BraceStyle::NextLine)), next_line);
+        assert_eq!(format(next_line, options(BraceStyle::SameLine)), same_line);
+        assert_eq!(format(same_line, FormatterOptions::default()), same_line);
+        assert_eq!(format(next_line, FormatterOptions::default()), next_line);
+    }
+
+    #[test]
+    fn test_format_quirrel() {
+        let code = "let {a,b=1}=foo?.bar\nlocal [c]=baz?[0]?(a)??[]\nimport \"m\" as m\nfrom \"s\" import f,g as h\nx?.y(1)?.z(2)";
+        let formatted = "let {a, b = 1} = foo?.bar\nlocal [c] = baz?[0]?(a) ?? []\nimport \"m\" as m\nfrom \"s\" import f, g as h\nx?.y(1)?.z(2)";
+
+        let format = |code: &str| {
+            Formatter::new(code)
+                .with_dialect(Dialect::Quirrel)
+                .format(&FormatterOptions::default())
+                .unwrap()
+        };
+
+        assert_eq!(format(code), formatted);
+        assert_eq!(format(formatted), formatted);
+        assert!(Formatter::new(code)
+            .format(&FormatterOptions::default())
+            .is_err());
+    }
+
+    #[test]
+    fn test_format_parentheses_and_trailing_commas() {
+        let code = "foo(a, [1, 2], {x = 1}, bar())";
+
+        let options = FormatterOptions {
+            space_inside_parentheses: true,
+            trailing_commas: true,
+            ..FormatterOptions::default()
+        };
+        let narrow = FormatterOptions {
+            max_width: 12,
+            ..options.clone()
+        };
+
+        assert_eq!(format(code, options), "foo( a, [1, 2], { x = 1 }, bar() )");
+        assert_eq!(
+            format(code, narrow),
+            "foo(\n  a,\n  [1, 2],\n  { x = 1 },\n  bar()\n)"
+        );
+    }
+
+    #[test]
+    fn test_format_breaks_lists_exceeding_max_width() {
+        let code = "local t = {a = 1, b = [1, 2, 3]}\nitems.filter(isValid).map(toString)";
+
+        let options = FormatterOptions {
+            max_width: 30,
+            ..FormatterOptions::default()
+        };
+
+        assert_eq!(
+            format(code, FormatterOptions::default()),
+            "local t = { a = 1, b = [1, 2, 3] }\nitems.filter(isValid).map(toString)"
+        );
+        assert_eq!(
+            format(code, options),
+            "local t = {\n  a = 1,\n  b = [1, 2, 3]\n}\nitems\n  .filter(isValid)\n  .map(toString)"
+        );
+    }
+
+    #[test]
+    fn test_format_keeps_end_of_line_comments_on_their_line() {
+        let code = "local a = 1 // one\nif (a) { // open\n  foo(a) /* call */\n} // close\n";
+
+        assert_eq!(
+            format(code, FormatterOptions::default()),
+            "local a = 1 // one\nif (a) { // open\n  foo(a) /* call */\n} // close\n"
+        );
+    }
+
+    #[test]
+    fn test_format_keeps_comments_in_arguments_and_tables() {
+        let code = "foo(a, // first\n  b)\nlocal t = {\n  // leading\n  x = 1, // trailing\n  y = /* value */ 2\n  // last\n}";
+
+        assert_eq!(
+            format(code, FormatterOptions::default()),
+            "foo(\n  a, // first\n  b\n)\nlocal t = {\n  // leading\n  x = 1, // trailing\n  y = /* value */ 2\n  // last\n}"
+        );
+    }
+
+    #[test]
+    fn test_format_attaches_comments_to_the_token_they_follow() {
+        let code = "if (a) // c\n  foo()\nlocal /* b */ x /* c */ = 1\nif /* b */ (true) {\n}";
+
+        assert_eq!(format(code, FormatterOptions::default()), code);
+    }
+
+    #[test]
+    fn test_format_keeps_comments_in_empty_lists_and_bodies() {
+        let code = "foo(/* none */)\nclass C { /* only */ }\nlocal t = { /* empty */ }\nlocal a = [ /* empty */ ]";
+
+        assert_eq!(
+            format(code, FormatterOptions::default()),
+            "foo(/* none */)\nclass C { /* only */\n}\nlocal t = { /* empty */ }\nlocal a = [/* empty */]"
+        );
+    }
+
+    #[test]
+    fn test_format_class_attributes() {
+        let code = "class Foo extends Bar </ version=2 /> {\n</ editable=true, label=\"x\" />\nname = null\n</ deprecated = true/> function baz() { return 1 }\n}";
+
+        assert_eq!(
+            format(code, FormatterOptions::default()),
+            "class Foo extends Bar </ version = 2 /> {\n  </ editable = true, label = \"x\" />\n  name = null\n\n  </ deprecated = true /> function baz() {\n    return 1\n  }\n}"
+        );
+    }
+
+    #[test]
+    fn test_format_limits_blank_lines_between_statements() {
+        let code = "local a = {\n  x = 1\n}\nlocal b = 2\n\n\n\n// comment\n\n\nlocal c = 3";
+
+        let options = FormatterOptions {
+            max_blank_lines: 2,
+            ..FormatterOptions::default()
+        };
+
+        assert_eq!(
+            format(code, FormatterOptions::default()),
+            "local a = {\n  x = 1\n}\nlocal b = 2\n\n// comment\n\nlocal c = 3"
+        );
+        assert_eq!(
+            format(code, options),
+            "local a = {\n  x = 1\n}\nlocal b = 2\n\n\n// comment\n\n\nlocal c = 3"
+        );
+    }
+
     #[test]
     fn print_test_cases() {
         let paths = fs::read_dir("./test_cases").unwrap();
@@ -384,7 +365,7 @@ mod tests {
 
                 let contents = fs::read_to_string(path.unwrap().path()).unwrap();
                 let mut parser = Formatter::new(&contents);
-                let formatted = parser.format();
+                let formatted = parser.format(&FormatterOptions::default());
                 let printed_path = "./test_cases/".to_string() + file_name + ".nut.formatted";
 
                 fs::write(Path::new(&printed_path), formatted.unwrap()).unwrap();
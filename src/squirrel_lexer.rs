@@ -2,6 +2,10 @@ use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
 
+use crate::dialect::{Dialect, Feature};
+
+/// Splits source text into tokens, skipping spaces and, unless asked to keep
+/// them, comments.
 #[derive(Debug, Clone)]
 pub struct Lexer<'a> {
     pub input: &'a [u8],
@@ -9,8 +13,11 @@ pub struct Lexer<'a> {
     pub location: Location,
     pub token_counter: usize,
     pub skip_comments: bool,
+    /// Decides which words are keywords and which operators are recognized
+    pub dialect: Dialect,
 }
 
+/// Position in the source, with 0-based line and byte offsets.
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Location {
     pub line: usize,
@@ -18,6 +25,39 @@ pub struct Location {
     pub index: usize,
 }
 
+/// Statements start at the indentation preceding them, this moves `location`
+/// past any whitespace to where the statement's first token is.
+pub(crate) fn skip_whitespace(text: &str, location: &Location) -> Location {
+    let mut location = location.clone();
+
+    for c in text[location.index.min(text.len())..].chars() {
+        match c {
+            '\n' => {
+                location.line += 1;
+                location.linechar = 0;
+            }
+            c if c.is_whitespace() => location.linechar += c.len_utf8(),
+            _ => break,
+        }
+
+        location.index += c.len_utf8();
+    }
+
+    location
+}
+
+/// Location of a byte offset, the inverse of `refactor::offset`.
+pub(crate) fn location_at(text: &str, index: usize) -> Location {
+    let before = &text[..index.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+
+    Location {
+        line: before.matches('\n').count(),
+        linechar: before.len() - line_start,
+        index: before.len(),
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum LexerError {
     InvalidToken(Option<char>),
@@ -63,13 +103,24 @@ pub enum Keyword {
     Line,
     File,
     Rawcall,
+    Let,
+    Import,
 }
 
 impl TryFrom<&str> for Keyword {
     type Error = LexerError;
 
+    /// Keywords of the default dialect.
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        match value {
+        Keyword::try_from((value, Dialect::default()))
+    }
+}
+
+impl TryFrom<(&str, Dialect)> for Keyword {
+    type Error = LexerError;
+
+    fn try_from((value, dialect): (&str, Dialect)) -> Result<Self, Self::Error> {
+        let keyword = match value {
             "base" => Ok(Keyword::Base),
             "break" => Ok(Keyword::Break),
             "case" => Ok(Keyword::Case),
@@ -105,8 +156,16 @@ impl TryFrom<&str> for Keyword {
             "__FILE__" => Ok(Keyword::File),
             "rawcall" => Ok(Keyword::Rawcall),
             "do" => Ok(Keyword::Do),
+            "let" => Ok(Keyword::Let),
+            "import" => Ok(Keyword::Import),
             _ => Err(LexerError::InvalidKeyword(value.to_string())),
+        }?;
+
+        if !dialect.has_keyword(&keyword) {
+            return Err(LexerError::InvalidKeyword(value.to_string()));
         }
+
+        Ok(keyword)
     }
 }
 
@@ -148,6 +207,8 @@ impl Into<&str> for &Keyword {
             Keyword::File => "__FILE__",
             Keyword::Rawcall => "rawcall",
             Keyword::Do => "do",
+            Keyword::Let => "let",
+            Keyword::Import => "import",
         }
     }
 }
@@ -155,7 +216,7 @@ impl Into<&str> for &Keyword {
 // !	!=	||	==	&&	>=	<=	>
 // <=>	+	+=	-	-=	/	/=	*
 // *=	%	%=	++	--	<-	=	&
-// ^	|	~	>>	<<	>>>
+// ^	|	~	>>	<<	>>>	??
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Operator {
     Not,
@@ -193,6 +254,24 @@ pub enum Operator {
     Typeof,
     InstanceOf,
     In,
+    /// `??`, only in Quirrel
+    NullCoalesce,
+}
+
+impl Operator {
+    /// `=`, `<-` and the compound assignments like `+=`
+    pub fn is_assignment(&self) -> bool {
+        matches!(
+            self,
+            Operator::Assign
+                | Operator::LeftArrow
+                | Operator::PlusEqual
+                | Operator::MinusEqual
+                | Operator::MultiplyEqual
+                | Operator::DivideEqual
+                | Operator::ModuloEqual
+        )
+    }
 }
 
 impl From<&Operator> for &str {
@@ -233,6 +312,25 @@ impl From<&Operator> for &str {
             Operator::InstanceOf => "instanceof",
             Operator::In => "in",
             Operator::Comma => ",",
+            Operator::NullCoalesce => "??",
+        }
+    }
+}
+
+/// What a single line comment starts with, kept so it's printed back the same way.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum CommentMarker {
+    /// `//`
+    Slashes,
+    /// `#`
+    Hash,
+}
+
+impl CommentMarker {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommentMarker::Slashes => "//",
+            CommentMarker::Hash => "#",
         }
     }
 }
@@ -241,12 +339,18 @@ impl From<&Operator> for &str {
 pub enum Token {
     Identifier(String),
     Newline,
-    Comment(String),
+    /// Text of a single line comment after its marker
+    Comment(String, CommentMarker),
     MultiLineComment(String),
     String(String),
     MultiLineString(String),
-    Integer(i64),
-    Float(f64),
+    /// Value of an integer literal along with its source, e.g. `0x1F` for 31
+    Integer(i64, String),
+    /// Value of a character literal along with its source between the quotes,
+    /// e.g. `\x41` for `'A'`
+    Character(char, String),
+    /// Value of a float literal along with its source, e.g. `1e3` for 1000
+    Float(f64, String),
     Operator(Operator),
     Keyword(Keyword),
     LeftBrace,
@@ -260,6 +364,17 @@ pub enum Token {
     QuestionMark,
     Semicolon,
     DoubleColon,
+    At,
+    /// `</`, opening the attributes of a class or class member
+    AttributesOpen,
+    /// `/>`
+    AttributesClose,
+    /// `?.`, accessing a member unless the object is null, only in Quirrel
+    QuestionDot,
+    /// `?[`
+    QuestionLeftBracket,
+    /// `?(`
+    QuestionLeftParenthesis,
     Space,
     Tab,
     Indent(usize),
@@ -272,21 +387,13 @@ impl Token {
         match self {
             Token::Identifier(value) => value.clone(),
             Token::Newline => "\n".to_string(),
-            Token::Comment(value) => format!("// {}", value.trim()),
+            Token::Comment(value, marker) => format!("{} {}", marker.as_str(), value.trim()),
             Token::MultiLineComment(value) => format!("/* {} */", value.trim()),
             Token::String(value) => format!("\"{}\"", value),
             Token::MultiLineString(value) => format!("@\"{}\"", value),
-            Token::Integer(value) => value.to_string(),
-            Token::Float(value) => {
-                let whole = value.trunc();
-                let fractional = value.fract();
-
-                if fractional == 0.0 {
-                    whole.to_string() + ".0"
-                } else {
-                    value.to_string()
-                }
-            }
+            Token::Integer(_, source) => source.clone(),
+            Token::Character(_, source) => format!("'{}'", source),
+            Token::Float(_, source) => source.clone(),
             Token::Operator(value) => Into::<&str>::into(value).to_string(),
             Token::Keyword(value) => Into::<&str>::into(value).to_string(),
             Token::LeftBrace => "{".to_string(),
@@ -300,6 +407,12 @@ impl Token {
             Token::QuestionMark => "?".to_string(),
             Token::Semicolon => ";".to_string(),
             Token::DoubleColon => "::".to_string(),
+            Token::At => "@".to_string(),
+            Token::AttributesOpen => "</".to_string(),
+            Token::AttributesClose => "/>".to_string(),
+            Token::QuestionDot => "?.".to_string(),
+            Token::QuestionLeftBracket => "?[".to_string(),
+            Token::QuestionLeftParenthesis => "?(".to_string(),
             Token::EOF => "".to_string(),
             Token::Space => " ".to_string(),
             Token::Tab => "\t".to_string(),
@@ -315,12 +428,13 @@ impl Display for Token {
         match self {
             Token::Identifier(value) => write!(f, "{}", value),
             Token::Newline => write!(f, "newline"),
-            Token::Comment(value) => write!(f, "{}", value),
+            Token::Comment(value, _) => write!(f, "{}", value),
             Token::MultiLineComment(value) => write!(f, "{}", value),
             Token::String(value) => write!(f, "{}", value),
             Token::MultiLineString(value) => write!(f, "{}", value),
-            Token::Integer(value) => write!(f, "{}", value),
-            Token::Float(value) => write!(f, "{}", value),
+            Token::Integer(_, source) => write!(f, "{}", source),
+            Token::Character(_, source) => write!(f, "'{}'", source),
+            Token::Float(_, source) => write!(f, "{}", source),
             Token::Operator(value) => write!(f, "{}", Into::<&str>::into(value)),
             Token::Keyword(value) => write!(f, "{}", Into::<&str>::into(value)),
             Token::LeftBrace => write!(f, "{{"),
@@ -334,6 +448,12 @@ impl Display for Token {
             Token::QuestionMark => write!(f, "?"),
             Token::Semicolon => write!(f, ";"),
             Token::DoubleColon => write!(f, "::"),
+            Token::At => write!(f, "@"),
+            Token::AttributesOpen => write!(f, "</"),
+            Token::AttributesClose => write!(f, "/>"),
+            Token::QuestionDot => write!(f, "?."),
+            Token::QuestionLeftBracket => write!(f, "?["),
+            Token::QuestionLeftParenthesis => write!(f, "?("),
             Token::EOF => write!(f, "EOF"),
             Token::Space => write!(f, " "),
             Token::Tab => write!(f, "<TAB>"),
@@ -343,6 +463,7 @@ impl Display for Token {
     }
 }
 
+/// Token along with the source range it was read from, `to` being exclusive.
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct TokenWithLocation {
     pub token: Token,
@@ -381,9 +502,15 @@ impl<'a> Lexer<'a> {
             },
             token_counter: 0,
             skip_comments,
+            dialect: Dialect::default(),
         }
     }
 
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
     pub fn peek(&self) -> Result<TokenWithLocation, LexerErrorWithLocation> {
         let mut lexer = self.clone();
 
@@ -422,6 +549,20 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    /// Lexes the rest of the input, leaving out the end of file token.
+    pub fn tokens(&mut self) -> Result<Vec<TokenWithLocation>, LexerErrorWithLocation> {
+        let mut tokens = Vec::new();
+
+        loop {
+            let token = self.next()?;
+            if token.token == Token::EOF {
+                return Ok(tokens);
+            }
+
+            tokens.push(token);
+        }
+    }
+
     pub fn next(&mut self) -> Result<TokenWithLocation, LexerErrorWithLocation> {
         if self.position >= self.input.len() {
             let index = self.token_counter;
@@ -575,6 +716,7 @@ impl<'a> Lexer<'a> {
             }
             '?' => Token::QuestionMark,
             ';' => Token::Semicolon,
+            '@' => Token::At,
             _ => return None,
         };
 
@@ -583,95 +725,159 @@ impl<'a> Lexer<'a> {
     }
 
     fn number(&mut self) -> Result<Option<Token>, LexerError> {
-        if self.matches("0x") {
+        let start = self.position;
+
+        if self.matches("0x") || self.matches("0X") {
             self.increment_position(false);
             self.increment_position(false);
             let digits = self.take_all_hexadecimal_digits();
-            return Ok(Some(Token::Integer(
-                i64::from_str_radix(&digits, 16).unwrap(),
-            )));
+
+            // Hexadecimal literals wrap around, 0xFFFFFFFFFFFFFFFF being -1
+            return u64::from_str_radix(&digits, 16)
+                .map(|value| Some(Token::Integer(value as i64, self.source_from(start))))
+                .map_err(|_| LexerError::InvalidToken(self.current_char()));
+        }
+
+        if self.is_current_char('\'') {
+            return self.character().map(Some);
         }
 
         let current_char = self.current_char().ok_or(LexerError::UnexpectedEOF)?;
 
-        if current_char.is_ascii_digit() {
-            if self.matches("0.") {
-                self.increment_position(false);
-                self.increment_position(false);
-                let digits = self.take_all_digits();
+        if !current_char.is_ascii_digit() {
+            return Ok(None);
+        }
 
-                if digits.is_empty() {
-                    return Err(LexerError::InvalidToken(self.current_char()));
-                }
+        let is_octal = current_char == '0'
+            && self
+                .input
+                .get(self.position + 1)
+                .is_some_and(|next| next.is_ascii_digit());
 
-                return Ok(Some(Token::Float(format!("0.{}", digits).parse().unwrap())));
+        if is_octal {
+            self.increment_position(false);
+            let digits = self.take_all_base8_digits();
+
+            if digits.is_empty() {
+                return Ok(Some(Token::Integer(0, self.source_from(start))));
             }
 
-            if self.is_current_char('0') {
-                self.increment_position(false);
-                let digits = self.take_all_base8_digits();
+            return i64::from_str_radix(&digits, 8)
+                .map(|value| Some(Token::Integer(value, self.source_from(start))))
+                .map_err(|_| LexerError::InvalidToken(self.current_char()));
+        }
 
-                if digits.is_empty() {
-                    return Ok(Some(Token::Integer(0)));
-                }
+        let mut digits = self.take_all_digits();
+        let mut is_float = false;
 
-                return Ok(Some(Token::Integer(
-                    i64::from_str_radix(&digits, 8).unwrap(),
-                )));
-            }
+        if self.is_current_char('.') {
+            self.increment_position(false);
+            digits.push('.');
+            digits.push_str(&self.take_all_digits());
+            is_float = true;
+        }
+
+        if self.is_current_char('e') || self.is_current_char('E') {
+            self.increment_position(false);
+            digits.push('e');
 
-            let digits = self.take_all_digits();
-            if self.is_current_char('.') {
+            if self.is_current_char('-') || self.is_current_char('+') {
+                digits.push(self.current_char().unwrap());
                 self.increment_position(false);
+            }
 
-                if self.is_current_char('e') || self.is_current_char('E') {
-                    self.increment_position(false);
-                    let mut sign = 1;
-                    if self.is_current_char('-') {
-                        sign = -1;
-                        self.increment_position(false);
-                    }
+            let exponent = self.take_all_digits();
 
-                    if self.is_current_char('+') {
-                        self.increment_position(false);
-                    }
+            if exponent.is_empty() {
+                return Err(LexerError::InvalidToken(self.current_char()));
+            }
 
-                    let exponent = self.take_all_digits();
+            digits.push_str(&exponent);
+            is_float = true;
+        }
 
-                    if exponent.is_empty() {
-                        return Err(LexerError::InvalidToken(self.current_char()));
-                    }
+        if is_float {
+            return digits
+                .parse::<f64>()
+                .map(|value| Some(Token::Float(value, self.source_from(start))))
+                .map_err(|_| LexerError::InvalidToken(self.current_char()));
+        }
 
-                    let float = digits.parse::<f64>().unwrap();
-                    let exponent = exponent.parse::<i32>().unwrap();
-                    let float = float * 10.0f64.powi(exponent * sign);
+        digits
+            .parse::<i64>()
+            .map(|value| Some(Token::Integer(value, self.source_from(start))))
+            .map_err(|_| LexerError::InvalidToken(self.current_char()))
+    }
 
-                    return Ok(Some(Token::Float(float)));
-                }
-                let digits = format!("{}.{}", digits, self.take_all_digits());
-                if digits.is_empty() {
-                    return Err(LexerError::InvalidToken(self.current_char()));
-                }
-                return Ok(Some(Token::Float(digits.parse::<f64>().unwrap())));
-            } else {
-                return Ok(Some(Token::Integer(digits.parse::<i64>().unwrap())));
+    /// Character literal such as `'a'` or `'\\n'`, which Squirrel treats as
+    /// an integer.
+    fn character(&mut self) -> Result<Token, LexerError> {
+        self.increment_position(false);
+        let start = self.position;
+
+        let value = match self.current_char() {
+            None => return Err(LexerError::UnexpectedEOF),
+            Some('\'') | Some('\n') => return Err(LexerError::InvalidToken(self.current_char())),
+            Some('\\') => {
+                self.increment_position(false);
+                self.escape_sequence()?
+            }
+            Some(value) => {
+                self.increment_position(false);
+                value
             }
+        };
+
+        if !self.is_current_char('\'') {
+            return Err(LexerError::InvalidToken(self.current_char()));
         }
 
-        if self.is_current_char('\'') {
-            self.increment_position(false);
-            let next_char = self.current_char().ok_or(LexerError::UnexpectedEOF)?;
-            self.increment_position(false);
+        let source = self.source_from(start);
+        self.increment_position(false);
+        Ok(Token::Character(value, source))
+    }
 
-            if self.is_current_char('\'') {
-                self.increment_position(false);
-                return Ok(Some(Token::Integer(next_char as i64)));
-            } else {
-                return Err(LexerError::InvalidToken(self.current_char()));
+    /// Source read since `start`.
+    fn source_from(&self, start: usize) -> String {
+        String::from_utf8_lossy(&self.input[start..self.position]).to_string()
+    }
+
+    /// Value of the escape sequence following a backslash.
+    fn escape_sequence(&mut self) -> Result<char, LexerError> {
+        let escaped = self.current_char().ok_or(LexerError::UnexpectedEOF)?;
+        self.increment_position(false);
+
+        let max_digits = match escaped {
+            't' => return Ok('\t'),
+            'a' => return Ok('\x07'),
+            'b' => return Ok('\x08'),
+            'n' => return Ok('\n'),
+            'r' => return Ok('\r'),
+            'v' => return Ok('\x0b'),
+            'f' => return Ok('\x0c'),
+            '0' => return Ok('\0'),
+            '\\' | '"' | '\'' => return Ok(escaped),
+            'x' => 2,
+            'u' => 4,
+            'U' => 8,
+            _ => return Err(LexerError::InvalidToken(Some(escaped))),
+        };
+
+        let mut digits = String::new();
+        while digits.len() < max_digits {
+            match self.current_char() {
+                Some(digit) if digit.is_ascii_hexdigit() => {
+                    digits.push(digit);
+                    self.increment_position(false);
+                }
+                _ => break,
             }
         }
 
-        Ok(None)
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or(LexerError::InvalidToken(self.current_char()))
     }
 
     fn take_all_base8_digits(&mut self) -> String {
@@ -734,7 +940,7 @@ impl<'a> Lexer<'a> {
                 string.push(self.current_char().unwrap());
                 self.increment_position(false);
             }
-            return Some(Token::Comment(string));
+            return Some(Token::Comment(string, CommentMarker::Hash));
         }
 
         if self.matches("//") {
@@ -745,7 +951,7 @@ impl<'a> Lexer<'a> {
                 string.push(self.current_char().unwrap());
                 self.increment_position(false);
             }
-            return Some(Token::Comment(string));
+            return Some(Token::Comment(string, CommentMarker::Slashes));
         }
         if self.matches("/*") {
             self.increment_position(false);
@@ -764,35 +970,42 @@ impl<'a> Lexer<'a> {
         None
     }
 
+    /// Strings keep their escape sequences as written, so that they are printed
+    /// back the same way.
     fn string(&mut self) -> Result<Option<Token>, LexerError> {
         if self.is_current_char('"') {
             self.increment_position(false);
             let mut string = String::new();
             loop {
-                if !self.is_not_eof() {
-                    return Err(LexerError::UnterminatedString);
-                }
-
-                if self.matches("\\\"") {
-                    self.increment_position(false);
-                    self.increment_position(false);
-                    string.push('\\');
-                    string.push('"');
-                } else if self.matches("\"") {
-                    self.increment_position(false);
-                    break;
-                } else {
-                    if self.is_current_char('\n') {
-                        return Err(LexerError::UnterminatedString);
+                match self.current_char() {
+                    None | Some('\n') => return Err(LexerError::UnterminatedString),
+                    Some('"') => {
+                        self.increment_position(false);
+                        break;
                     }
+                    Some('\\') => {
+                        string.push('\\');
+                        self.increment_position(false);
 
-                    string.push(self.current_char().unwrap());
-                    self.increment_position(false);
+                        match self.current_char() {
+                            None | Some('\n') => return Err(LexerError::UnterminatedString),
+                            Some(escaped) => {
+                                string.push(escaped);
+                                self.increment_position(false);
+                            }
+                        }
+                    }
+                    Some(current_char) => {
+                        string.push(current_char);
+                        self.increment_position(false);
+                    }
                 }
             }
             return Ok(Some(Token::String(string)));
         }
 
+        // Backslashes are taken literally in verbatim strings, a quote being
+        // escaped by doubling it
         if self.matches("@\"") {
             self.increment_position(false);
             self.increment_position(false);
@@ -802,11 +1015,10 @@ impl<'a> Lexer<'a> {
                     return Err(LexerError::UnterminatedString);
                 }
 
-                if self.matches("\\\"") {
+                if self.matches("\"\"") {
                     self.increment_position(false);
                     self.increment_position(false);
-                    string.push('\\');
-                    string.push('"');
+                    string.push_str("\"\"");
                 } else if self.matches("\"") {
                     self.increment_position(false);
                     break;
@@ -906,6 +1118,10 @@ impl<'a> Lexer<'a> {
                     self.increment_position(false);
                     return Some(Token::Operator(Operator::DivideEqual));
                 }
+                if self.is_current_char('>') {
+                    self.increment_position(false);
+                    return Some(Token::AttributesClose);
+                }
                 Some(Token::Operator(Operator::Divide))
             }
             '*' => {
@@ -929,7 +1145,9 @@ impl<'a> Lexer<'a> {
                 if self.is_current_char('=') {
                     self.increment_position(false);
 
-                    if self.is_current_char('>') {
+                    if self.is_current_char('>')
+                        && self.dialect.supports(Feature::ThreeWayComparison)
+                    {
                         self.increment_position(false);
                         return Some(Token::Operator(Operator::ThreeWayComparison));
                     }
@@ -943,6 +1161,10 @@ impl<'a> Lexer<'a> {
                     self.increment_position(false);
                     return Some(Token::Operator(Operator::LeftShift));
                 }
+                if self.is_current_char('/') {
+                    self.increment_position(false);
+                    return Some(Token::AttributesOpen);
+                }
                 Some(Token::Operator(Operator::Less))
             }
             '>' => {
@@ -962,6 +1184,27 @@ impl<'a> Lexer<'a> {
                 }
                 Some(Token::Operator(Operator::Greater))
             }
+            '?' => {
+                let null_propagation = self.dialect.supports(Feature::NullPropagation);
+
+                let token = if self.matches("??") && self.dialect.supports(Feature::NullCoalescing)
+                {
+                    Token::Operator(Operator::NullCoalesce)
+                } else if self.matches("?.") && null_propagation {
+                    Token::QuestionDot
+                } else if self.matches("?[") && null_propagation {
+                    Token::QuestionLeftBracket
+                } else if self.matches("?(") && null_propagation {
+                    Token::QuestionLeftParenthesis
+                } else {
+                    // A lone `?` of the ternary operator is punctuation
+                    return None;
+                };
+
+                self.increment_position(false);
+                self.increment_position(false);
+                Some(token)
+            }
             _ => None,
         }
     }
@@ -993,7 +1236,7 @@ impl<'a> Lexer<'a> {
                 return Some(Token::Operator(Operator::Typeof));
             }
 
-            if let Ok(keyword) = Keyword::try_from(identifier_name.as_str()) {
+            if let Ok(keyword) = Keyword::try_from((identifier_name.as_str(), self.dialect)) {
                 return Some(Token::Keyword(keyword));
             }
 
@@ -1320,23 +1563,27 @@ mod tests {
 
     #[test]
     fn test_multiline_string_with_escapes() {
-        let input = "@\"Hello\\\"world\"";
+        let input = "@\"Hello\"\"world\\\"";
         let mut lexer = Lexer::new(input, false);
         assert_eq!(
             lexer.next().unwrap().token,
-            Token::MultiLineString("Hello\\\"world".to_string())
+            Token::MultiLineString("Hello\"\"world\\".to_string())
         );
         assert_eq!(lexer.next().unwrap().token, Token::EOF);
     }
 
     #[test]
     fn test_string_with_escapes() {
-        let input = "\"Hello\\\"world\"";
-        let mut lexer = Lexer::new(input, false);
+        let input = "\"Hello\\\"world\" \"a\\\\\"";
+        let mut lexer = Lexer::new(input, true);
         assert_eq!(
             lexer.next().unwrap().token,
             Token::String("Hello\\\"world".to_string())
         );
+        assert_eq!(
+            lexer.next().unwrap().token,
+            Token::String("a\\\\".to_string())
+        );
         assert_eq!(lexer.next().unwrap().token, Token::EOF);
     }
 
@@ -1347,31 +1594,35 @@ mod tests {
 
         assert_eq!(
             lexer.next().unwrap().token,
-            Token::Comment(" Hello world".to_string())
+            Token::Comment(" Hello world".to_string(), CommentMarker::Slashes)
         );
 
         assert_eq!(lexer.next().unwrap().token, Token::Newline);
 
         assert_eq!(
             lexer.next().unwrap().token,
-            Token::Comment(" Hello world".to_string())
+            Token::Comment(" Hello world".to_string(), CommentMarker::Hash)
         );
 
         assert_eq!(lexer.next().unwrap().token, Token::Newline);
 
         assert_eq!(
             lexer.next().unwrap().token,
-            Token::Comment(" Hello world".to_string())
+            Token::Comment(" Hello world".to_string(), CommentMarker::Slashes)
         );
 
         assert_eq!(lexer.next().unwrap().token, Token::Newline);
 
         assert_eq!(
             lexer.next().unwrap().token,
-            Token::Comment(" Hello world".to_string())
+            Token::Comment(" Hello world".to_string(), CommentMarker::Hash)
         );
 
         assert_eq!(lexer.next().unwrap().token, Token::EOF);
+        assert_eq!(
+            Token::Comment(" Hello world".to_string(), CommentMarker::Hash).to_source_string(),
+            "# Hello world"
+        );
     }
 
     #[test]
@@ -1415,39 +1666,190 @@ mod tests {
 
         const EPSILON: f64 = 1e-10;
 
-        assert_eq!(lexer.next().unwrap().token, Token::Integer(0));
+        assert_eq!(
+            lexer.next().unwrap().token,
+            Token::Integer(0, "0".to_string())
+        );
         match lexer.next().unwrap().token {
-            Token::Float(f) => assert!((f - 0.0).abs() < EPSILON),
+            Token::Float(f, _) => assert!((f - 0.0).abs() < EPSILON),
             _ => panic!("Expected float"),
         }
-        assert_eq!(lexer.next().unwrap().token, Token::Integer(123));
+        assert_eq!(
+            lexer.next().unwrap().token,
+            Token::Integer(123, "123".to_string())
+        );
         match lexer.next().unwrap().token {
-            Token::Float(f) => assert!((f - 12.12).abs() < EPSILON),
+            Token::Float(f, _) => assert!((f - 12.12).abs() < EPSILON),
             _ => panic!("Expected float"),
         }
         match lexer.next().unwrap().token {
-            Token::Float(f) => assert!((f - 123.0).abs() < EPSILON),
+            Token::Float(f, _) => assert!((f - 123.0).abs() < EPSILON),
             _ => panic!("Expected float"),
         }
         match lexer.next().unwrap().token {
-            Token::Float(f) => assert!((f - 123e123).abs() < EPSILON * 123e123_f64.abs().max(1.0)),
+            Token::Float(f, _) => {
+                assert!((f - 123e123).abs() < EPSILON * 123e123_f64.abs().max(1.0))
+            }
             _ => panic!("Expected float"),
         }
         match lexer.next().unwrap().token {
-            Token::Float(f) => assert!((f - 123e123).abs() < EPSILON * 123e123_f64.abs().max(1.0)),
+            Token::Float(f, _) => {
+                assert!((f - 123e123).abs() < EPSILON * 123e123_f64.abs().max(1.0))
+            }
             _ => panic!("Expected float"),
         }
         match lexer.next().unwrap().token {
-            Token::Float(f) => assert!((f - 123e-12).abs() < EPSILON),
+            Token::Float(f, _) => assert!((f - 123e-12).abs() < EPSILON),
             _ => panic!("Expected float"),
         }
-        assert_eq!(lexer.next().unwrap().token, Token::Integer(0x123));
-        assert_eq!(lexer.next().unwrap().token, Token::Integer(0o123));
-        assert_eq!(lexer.next().unwrap().token, Token::Integer(97));
-        assert_eq!(lexer.next().unwrap().token, Token::Integer(98));
+        assert_eq!(
+            lexer.next().unwrap().token,
+            Token::Integer(0x123, "0x123".to_string())
+        );
+        assert_eq!(
+            lexer.next().unwrap().token,
+            Token::Integer(0o123, "0123".to_string())
+        );
+        assert_eq!(
+            lexer.next().unwrap().token,
+            Token::Character('a', "a".to_string())
+        );
+        assert_eq!(
+            lexer.next().unwrap().token,
+            Token::Character('b', "b".to_string())
+        );
         assert_eq!(lexer.next().unwrap().token, Token::EOF);
     }
 
+    #[test]
+    fn test_number_forms() {
+        let input = "1e3 1.5e-3 0X1F 0xFFFFFFFFFFFFFFFF";
+        let mut lexer = Lexer::new(input, true);
+
+        for (expected, source) in [
+            (Token::Float(1e3, "1e3".to_string()), "1e3"),
+            (Token::Float(1.5e-3, "1.5e-3".to_string()), "1.5e-3"),
+            (Token::Integer(0x1F, "0X1F".to_string()), "0X1F"),
+            (
+                Token::Integer(-1, "0xFFFFFFFFFFFFFFFF".to_string()),
+                "0xFFFFFFFFFFFFFFFF",
+            ),
+        ] {
+            let token = lexer.next().unwrap().token;
+            assert_eq!(token, expected);
+            assert_eq!(token.to_source_string(), source);
+        }
+
+        let mut lexer = Lexer::new("99999999999999999999", false);
+        assert!(matches!(
+            lexer.next().unwrap_err().error,
+            LexerError::InvalidToken(_)
+        ));
+    }
+
+    #[test]
+    fn test_character_escapes() {
+        let input = r"'\n' '\'' '\\' '\x41' '\0'";
+        let mut lexer = Lexer::new(input, true);
+
+        for (expected, source) in [
+            ('\n', r"\n"),
+            ('\'', r"\'"),
+            ('\\', r"\\"),
+            ('A', r"\x41"),
+            ('\0', r"\0"),
+        ] {
+            let token = lexer.next().unwrap().token;
+            assert_eq!(token, Token::Character(expected, source.to_string()));
+            assert_eq!(token.to_source_string(), format!("'{}'", source));
+        }
+
+        assert!(Lexer::new("''", false).next().is_err());
+        assert!(Lexer::new("'ab'", false).next().is_err());
+        assert!(Lexer::new(r"'\q'", false).next().is_err());
+    }
+
+    #[test]
+    fn test_attribute_tokens() {
+        let input = "</ a = 1 /> x </y a/b";
+        let mut lexer = Lexer::new(input, false);
+
+        for expected in [
+            Token::AttributesOpen,
+            Token::Identifier("a".to_string()),
+            Token::Operator(Operator::Assign),
+            Token::Integer(1, "1".to_string()),
+            Token::AttributesClose,
+            Token::Identifier("x".to_string()),
+            Token::AttributesOpen,
+            Token::Identifier("y".to_string()),
+            Token::Identifier("a".to_string()),
+            Token::Operator(Operator::Divide),
+            Token::Identifier("b".to_string()),
+        ] {
+            assert_eq!(lexer.next().unwrap().token, expected);
+        }
+    }
+
+    #[test]
+    fn test_dialect_tokens() {
+        let input = "let a?.b ?? c?[0] <=> d?(1) ? e : f";
+        let mut lexer = Lexer::new(input, false).with_dialect(Dialect::Quirrel);
+
+        for expected in [
+            Token::Keyword(Keyword::Let),
+            Token::Identifier("a".to_string()),
+            Token::QuestionDot,
+            Token::Identifier("b".to_string()),
+            Token::Operator(Operator::NullCoalesce),
+            Token::Identifier("c".to_string()),
+            Token::QuestionLeftBracket,
+            Token::Integer(0, "0".to_string()),
+            Token::RightBracket,
+            Token::Operator(Operator::ThreeWayComparison),
+            Token::Identifier("d".to_string()),
+            Token::QuestionLeftParenthesis,
+            Token::Integer(1, "1".to_string()),
+            Token::RightParenthesis,
+            Token::QuestionMark,
+            Token::Identifier("e".to_string()),
+        ] {
+            assert_eq!(lexer.next().unwrap().token, expected);
+        }
+
+        let mut lexer = Lexer::new(input, false);
+        assert_eq!(
+            lexer.next().unwrap().token,
+            Token::Identifier("let".to_string())
+        );
+        lexer.next().unwrap();
+        assert_eq!(lexer.next().unwrap().token, Token::QuestionMark);
+        assert_eq!(lexer.next().unwrap().token, Token::Dot);
+    }
+
+    #[test]
+    fn test_squirrel2_keywords() {
+        let input = "static rawcall __LINE__ a <=> b";
+        let mut lexer = Lexer::new(input, false).with_dialect(Dialect::Squirrel2);
+
+        for expected in [
+            Token::Identifier("static".to_string()),
+            Token::Identifier("rawcall".to_string()),
+            Token::Identifier("__LINE__".to_string()),
+            Token::Identifier("a".to_string()),
+            Token::Operator(Operator::LessEqual),
+            Token::Operator(Operator::Greater),
+        ] {
+            assert_eq!(lexer.next().unwrap().token, expected);
+        }
+
+        assert_eq!(
+            Keyword::try_from("static"),
+            Ok(Keyword::Static),
+            "Squirrel 3 is the default dialect"
+        );
+    }
+
     #[test]
     fn sanity_test_sample_code() {
         let input = "
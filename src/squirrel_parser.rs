@@ -1,17 +1,22 @@
 use std::fmt::Display;
 
 use crate::{
+    dialect::{Dialect, Feature},
     grammar::expressions::*,
     grammar::statements::*,
+    grammar::trivia::*,
     squirrel_lexer::{
         Keyword, Lexer, LexerError, LexerErrorWithLocation, Location, Operator, Token,
         TokenWithLocation,
     },
 };
 
+/// Recursive descent parser building the statements of a script, see [`Parser::parse`].
 #[derive(Debug, Clone)]
 pub struct Parser<'a> {
     pub lexer: Lexer<'a>,
+    /// Comments of the input, in order, collected once it was parsed
+    pub comments: Vec<Comment>,
 }
 
 #[derive(Debug)]
@@ -24,6 +29,8 @@ pub enum ParserError {
     UnexpectedToken(Token),
     ExpectedTokenGot(Token, Token),
     ExpectedOneOfGot(Vec<Token>, Token),
+    /// A construct the selected dialect doesn't have
+    UnavailableInDialect(Feature),
 }
 
 #[derive(Debug)]
@@ -51,6 +58,9 @@ impl Display for ParserErrorWithLocation {
             ParserError::ExpectedOneOfGot(expected, got) => {
                 return write!(f, "Expected one of {:?} but got {:?}", expected, got);
             }
+            ParserError::UnavailableInDialect(feature) => {
+                return write!(f, "Unavailable in this dialect: {}", feature);
+            }
         };
 
         write!(f, "{}", error)
@@ -61,9 +71,15 @@ impl<'a> Parser<'a> {
     pub fn new(input: &'a str) -> Self {
         Self {
             lexer: Lexer::new(input, true),
+            comments: Vec::new(),
         }
     }
 
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.lexer = self.lexer.with_dialect(dialect);
+        self
+    }
+
     pub fn parse(&mut self) -> Result<Statements, ParserErrorWithLocation> {
         self.skip_newlines()?;
 
@@ -72,9 +88,32 @@ impl<'a> Parser<'a> {
         self.skip_newlines()?;
         self.expect_token(Token::EOF)?;
 
+        self.comments = self.collect_comments();
+
         Ok(result)
     }
 
+    /// The lexer skips comments while parsing, so they are gathered by lexing
+    /// the input once more. Collecting them while parsing would copy them each
+    /// time the parser backtracks.
+    fn collect_comments(&self) -> Vec<Comment> {
+        let input = std::str::from_utf8(self.lexer.input).unwrap_or_default();
+        let mut lexer = Lexer::new(input, false).with_dialect(self.lexer.dialect);
+        let mut comments = Vec::new();
+
+        while let Ok(token) = lexer.next() {
+            match token.token {
+                Token::EOF => break,
+                Token::Comment(..) | Token::MultiLineComment(_) => {
+                    comments.push(Comment::new(token.token, token.from, token.to, input));
+                }
+                _ => {}
+            }
+        }
+
+        comments
+    }
+
     fn skip_newlines(&mut self) -> Result<(), ParserErrorWithLocation> {
         loop {
             let next_token = self.peek_token()?;
@@ -167,12 +206,24 @@ impl<'a> Parser<'a> {
 
                 Ok(Some(if_statement))
             }
-            Token::Keyword(Keyword::Local) => {
+            Token::Keyword(Keyword::Local) | Token::Keyword(Keyword::Let) => {
                 let local_statement = scoped.parse_local_statement()?;
                 self.lexer = scoped.lexer;
 
                 Ok(Some(local_statement))
             }
+            Token::Keyword(Keyword::Import) => {
+                let import_statement = scoped.parse_import_statement()?;
+                self.lexer = scoped.lexer;
+
+                Ok(Some(import_statement))
+            }
+            Token::Identifier(word) if word == "from" && scoped.is_from_import()? => {
+                let import_statement = scoped.parse_import_statement()?;
+                self.lexer = scoped.lexer;
+
+                Ok(Some(import_statement))
+            }
             Token::Keyword(Keyword::Foreach) => {
                 let foreach_statement = scoped.parse_foreach_statement()?;
                 self.lexer = scoped.lexer;
@@ -276,6 +327,8 @@ impl<'a> Parser<'a> {
                 Ok(Some(enum_statement))
             }
             _ => {
+                scoped.check_unavailable_statement()?;
+
                 let expression = scoped.parse_expression(false)?;
 
                 if expression.is_none() {
@@ -500,30 +553,8 @@ impl<'a> Parser<'a> {
             self.skip_newlines()?;
             let next_token = self.peek_token()?;
 
-            if next_token.token == Token::Operator(Operator::Comma)
-                || next_token.token == Token::Newline
-                || next_token.token == Token::RightBrace
-            {
-                let enumeration = Enumeration {
-                    name: Expression::identifier(
-                        enumeration_name.token,
-                        enumeration_name.from,
-                        enumeration_name.to,
-                    ),
-                    value: None,
-                    from,
-                    to: self.lexer.current_location(),
-                };
-
-                enumerations.push(enumeration);
-
-                if next_token.token == Token::RightBrace {
-                    break;
-                } else {
-                    self.next_token()?;
-                }
-            } else {
-                self.expect_token(Token::Operator(Operator::Assign))?;
+            let enumeration_value = if next_token.token == Token::Operator(Operator::Assign) {
+                self.next_token()?;
 
                 let enumeration_value = self.parse_literal_expressions()?;
 
@@ -536,20 +567,26 @@ impl<'a> Parser<'a> {
                     });
                 }
 
-                let enumeration_value = enumeration_value.unwrap();
+                enumeration_value
+            } else {
+                None
+            };
 
-                let enumeration = Enumeration {
-                    name: Expression::identifier(
-                        enumeration_name.token,
-                        enumeration_name.from,
-                        enumeration_name.to,
-                    ),
-                    value: Some(enumeration_value),
-                    from,
-                    to: self.lexer.current_location(),
-                };
+            enumerations.push(Enumeration {
+                name: Expression::identifier(
+                    enumeration_name.token,
+                    enumeration_name.from,
+                    enumeration_name.to,
+                ),
+                value: enumeration_value,
+                from,
+                to: self.lexer.current_location(),
+            });
 
-                enumerations.push(enumeration);
+            // Members are separated by an optional comma
+            self.skip_newlines()?;
+            if self.peek_token()?.token == Token::Operator(Operator::Comma) {
+                self.next_token()?;
             }
         }
 
@@ -616,6 +653,8 @@ impl<'a> Parser<'a> {
             extends = extends_expr;
         }
 
+        let attributes = self.parse_attributes()?;
+
         self.skip_newlines()?;
         self.expect_token(Token::LeftBrace)?;
 
@@ -628,6 +667,7 @@ impl<'a> Parser<'a> {
             name: class_name,
             extends,
             members: declarations,
+            attributes,
             from,
             to: self.lexer.current_location(),
         })
@@ -657,9 +697,25 @@ impl<'a> Parser<'a> {
                 Ok(())
             };
 
+            let attributes = self.parse_attributes()?;
+
             self.skip_newlines()?;
             let next_token = self.peek_token()?;
 
+            if next_token.token == Token::Identifier("static".to_string())
+                && !self.lexer.dialect.supports(Feature::StaticMembers)
+            {
+                let [_, after] = self.peek_two_tokens()?;
+
+                if after.token != Token::Operator(Operator::Assign) {
+                    return Err(self.unavailable(
+                        Feature::StaticMembers,
+                        next_token.from,
+                        next_token.to,
+                    ));
+                }
+            }
+
             let is_static = {
                 if next_token.token == Token::Keyword(Keyword::Static) {
                     self.next_token()?;
@@ -683,9 +739,8 @@ impl<'a> Parser<'a> {
                     if value.is_none() {
                         return Err(ParserErrorWithLocation {
                             error: ParserError::ExpectedExpression,
-                            details: format!(
-                                "Expected expression after '=' in class field declaration"
-                            ),
+                            details: "Expected expression after '=' in class field declaration"
+                                .to_string(),
                             from,
                             to: self.lexer.current_location(),
                         });
@@ -699,6 +754,7 @@ impl<'a> Parser<'a> {
                         Expression::identifier(next_token.token, next_token.from, next_token.to),
                         value,
                         is_static,
+                        attributes,
                         from,
                         self.lexer.current_location(),
                     ));
@@ -706,6 +762,7 @@ impl<'a> Parser<'a> {
                 Token::Keyword(Keyword::Function) => {
                     let mut declaration = self.parse_function_declaration(false)?;
                     declaration.is_static = is_static;
+                    declaration.attributes = attributes;
 
                     require_separator?;
 
@@ -731,7 +788,7 @@ impl<'a> Parser<'a> {
                         if parameter.is_none() {
                             return Err(ParserErrorWithLocation {
                                 error: ParserError::ExpectedExpression,
-                                details: format!("Expected expression as constructor parameter"),
+                                details: "Expected expression as constructor parameter".to_string(),
                                 from,
                                 to: self.lexer.current_location(),
                             });
@@ -758,7 +815,7 @@ impl<'a> Parser<'a> {
                     if stat.is_none() {
                         return Err(ParserErrorWithLocation {
                             error: ParserError::ExpectedStatement,
-                            details: format!("Expected statement after constructor declaration"),
+                            details: "Expected statement after constructor declaration".to_string(),
                             from,
                             to: self.lexer.current_location(),
                         });
@@ -772,6 +829,7 @@ impl<'a> Parser<'a> {
                         parameters,
                         stat,
                         is_static,
+                        attributes,
                         from,
                         self.lexer.current_location(),
                     ));
@@ -785,7 +843,7 @@ impl<'a> Parser<'a> {
                     if expr.is_none() {
                         return Err(ParserErrorWithLocation {
                             error: ParserError::ExpectedExpression,
-                            details: format!("Expected expression after '['"),
+                            details: "Expected expression after '['".to_string(),
                             from,
                             to: self.lexer.current_location(),
                         });
@@ -801,7 +859,7 @@ impl<'a> Parser<'a> {
                     if value.is_none() {
                         return Err(ParserErrorWithLocation {
                             error: ParserError::ExpectedExpression,
-                            details: format!("Expected expression after '='"),
+                            details: "Expected expression after '='".to_string(),
                             from,
                             to: self.lexer.current_location(),
                         });
@@ -815,10 +873,19 @@ impl<'a> Parser<'a> {
                         expr,
                         value,
                         is_static,
+                        attributes,
                         from,
                         self.lexer.current_location(),
                     ));
                 }
+                _ if attributes.is_some() => {
+                    return Err(ParserErrorWithLocation {
+                        error: ParserError::ExpectedStatement,
+                        details: "Expected a class member after attributes".to_string(),
+                        from: next_token.from,
+                        to: next_token.to,
+                    });
+                }
                 _ => break,
             }
         }
@@ -836,16 +903,23 @@ impl<'a> Parser<'a> {
         let from = self.lexer.current_location();
         let name = self.expect_identifier()?;
 
-        let current_token = self.peek_token()?;
+        let mut scope = Vec::new();
+        let mut accessor = name.token.to_string();
 
-        if current_token.token == Token::DoubleColon {
+        while self.peek_token()?.token == Token::DoubleColon {
             self.next_token()?;
 
             let after_scope = self.expect_identifier()?;
+            scope.push(std::mem::replace(
+                &mut accessor,
+                after_scope.token.to_string(),
+            ));
+        }
 
+        if !scope.is_empty() {
             return Ok(Expression::scope_resolution(
-                Some(name.token.to_string()),
-                after_scope.token.to_string(),
+                scope,
+                accessor,
                 from,
                 self.lexer.current_location(),
             ));
@@ -872,6 +946,37 @@ impl<'a> Parser<'a> {
             Some(name)
         };
 
+        let parameters = self.parse_parameters()?;
+
+        self.skip_newlines()?;
+
+        let statement = self.parse_statement()?;
+
+        if statement.is_none() {
+            return Err(ParserErrorWithLocation {
+                error: ParserError::ExpectedStatement,
+                details: "Expected statement after function declaration".to_string(),
+                from: self.lexer.current_location(),
+                to: self.lexer.current_location(),
+            });
+        }
+
+        let statement = statement.unwrap();
+
+        Ok(FunctionDefinition {
+            name,
+            parameters,
+            statement,
+            is_static: false,
+            is_local: false,
+            is_lambda: false,
+            attributes: None,
+            from,
+            to: self.lexer.current_location(),
+        })
+    }
+
+    fn parse_parameters(&mut self) -> Result<Vec<Expression>, ParserErrorWithLocation> {
         self.skip_newlines()?;
         self.expect_token(Token::LeftParenthesis)?;
 
@@ -889,7 +994,7 @@ impl<'a> Parser<'a> {
             if parameter.is_none() {
                 return Err(ParserErrorWithLocation {
                     error: ParserError::ExpectedExpression,
-                    details: format!("Expected expression as function parameter"),
+                    details: "Expected expression as function parameter".to_string(),
                     from: self.lexer.current_location(),
                     to: self.lexer.current_location(),
                 });
@@ -911,29 +1016,40 @@ impl<'a> Parser<'a> {
 
         self.expect_token(Token::RightParenthesis)?;
 
+        Ok(parameters)
+    }
+
+    /// Lambda, `@(parameters) expression`, returning the expression.
+    fn parse_lambda_expression(&mut self) -> Result<Option<Expression>, ParserErrorWithLocation> {
         self.skip_newlines()?;
+        let from = self.lexer.current_location();
+        self.expect_token(Token::At)?;
 
-        let statement = self.parse_statement()?;
+        let parameters = self.parse_parameters()?;
 
-        if statement.is_none() {
+        let expression_from = self.lexer.current_location();
+        let Some(expression) = self.parse_expression(true)? else {
             return Err(ParserErrorWithLocation {
-                error: ParserError::ExpectedStatement,
-                details: format!("Expected statement after function declaration"),
-                from: self.lexer.current_location(),
+                error: ParserError::ExpectedExpression,
+                details: "Expected expression as the body of a lambda".to_string(),
+                from,
                 to: self.lexer.current_location(),
             });
-        }
+        };
 
-        let statement = statement.unwrap();
+        let to = self.lexer.current_location();
 
-        Ok(FunctionDefinition {
-            name,
+        Ok(Some(Expression::Function(Box::new(FunctionDefinition {
+            name: None,
             parameters,
-            statement,
+            statement: Statement::new_return(Some(expression), expression_from, to.clone()),
             is_static: false,
+            is_local: false,
+            is_lambda: true,
+            attributes: None,
             from,
-            to: self.lexer.current_location(),
-        })
+            to,
+        }))))
     }
 
     fn parse_function_statement(&mut self) -> Result<Statement, ParserErrorWithLocation> {
@@ -1053,7 +1169,43 @@ impl<'a> Parser<'a> {
         self.skip_newlines()?;
         let from = self.lexer.current_location();
 
-        self.expect_token(Token::Keyword(Keyword::Local))?;
+        let is_let = self.peek_token()?.token == Token::Keyword(Keyword::Let);
+        if is_let {
+            self.expect_token(Token::Keyword(Keyword::Let))?;
+        } else {
+            self.expect_token(Token::Keyword(Keyword::Local))?;
+        }
+
+        let next_token = self.peek_token()?;
+        if next_token.token == Token::LeftBrace || next_token.token == Token::LeftBracket {
+            if !self.lexer.dialect.supports(Feature::Destructuring) {
+                return Err(self.unavailable(
+                    Feature::Destructuring,
+                    next_token.from,
+                    next_token.to,
+                ));
+            }
+
+            return self.parse_destructuring_statement(is_let, from);
+        }
+
+        if !is_let && next_token.token == Token::Keyword(Keyword::Function) {
+            let mut declaration = self.parse_function_declaration(false)?;
+
+            if let Some(Expression::ScopeResolution(name)) = &declaration.name {
+                return Err(ParserErrorWithLocation {
+                    error: ParserError::ExpectedIdentifier,
+                    details: "Expected identifier as the name of a local function".to_string(),
+                    from: name.from.clone(),
+                    to: name.to.clone(),
+                });
+            }
+
+            declaration.is_local = true;
+            declaration.from = from;
+
+            return Ok(Statement::FunctionDefinition(Box::new(declaration)));
+        }
 
         let mut initializations = Vec::new();
 
@@ -1104,11 +1256,252 @@ impl<'a> Parser<'a> {
 
         Ok(Statement::new_local(
             initializations,
+            is_let,
+            from,
+            self.lexer.current_location(),
+        ))
+    }
+
+    /// `{a, b = 1} = table` or `[a, b] = array` following `local` or `let`.
+    fn parse_destructuring_statement(
+        &mut self,
+        is_let: bool,
+        from: Location,
+    ) -> Result<Statement, ParserErrorWithLocation> {
+        let (kind, closing) = match self.next_token()?.token {
+            Token::LeftBrace => (DestructuringKind::Table, Token::RightBrace),
+            _ => (DestructuringKind::Array, Token::RightBracket),
+        };
+
+        let mut bindings = Vec::new();
+
+        loop {
+            self.skip_newlines()?;
+            if self.peek_token()?.token == closing {
+                break;
+            }
+
+            let name = self.expect_identifier()?;
+
+            let mut expression = None;
+            if self.peek_token()?.token == Token::Operator(Operator::Assign) {
+                self.next_token()?;
+
+                expression = self.parse_expression(true)?;
+
+                if expression.is_none() {
+                    return Err(ParserErrorWithLocation {
+                        error: ParserError::ExpectedExpression,
+                        details: "Expected default value after '='".to_string(),
+                        from: name.from,
+                        to: self.lexer.current_location(),
+                    });
+                }
+            }
+
+            bindings.push(Initialization {
+                name: name.token.to_string(),
+                expression,
+                from: name.from,
+                to: self.lexer.current_location(),
+            });
+
+            self.skip_newlines()?;
+            if self.peek_token()?.token == Token::Operator(Operator::Comma) {
+                self.next_token()?;
+            }
+        }
+
+        self.expect_token(closing)?;
+
+        if bindings.is_empty() {
+            return Err(ParserErrorWithLocation {
+                error: ParserError::ExpectedIdentifier,
+                details: "Expected at least one name to destructure".to_string(),
+                from,
+                to: self.lexer.current_location(),
+            });
+        }
+
+        self.expect_token(Token::Operator(Operator::Assign))?;
+
+        let expression = self.parse_expression(true)?;
+
+        let Some(expression) = expression else {
+            return Err(ParserErrorWithLocation {
+                error: ParserError::ExpectedExpression,
+                details: "Expected expression to destructure after '='".to_string(),
+                from,
+                to: self.lexer.current_location(),
+            });
+        };
+
+        Ok(Statement::new_destructuring(
+            kind,
+            bindings,
+            expression,
+            is_let,
+            from,
+            self.lexer.current_location(),
+        ))
+    }
+
+    /// `import "module" as name` or `from "module" import a, b as c`.
+    fn parse_import_statement(&mut self) -> Result<Statement, ParserErrorWithLocation> {
+        self.skip_newlines()?;
+        let from = self.lexer.current_location();
+
+        if self.next_token()?.token == Token::Keyword(Keyword::Import) {
+            let module = self.expect_module()?;
+
+            let mut alias = None;
+            if self.peek_token()?.token == Token::Identifier("as".to_string()) {
+                self.next_token()?;
+                let name = self.expect_identifier()?;
+                alias = Some(Expression::identifier(name.token, name.from, name.to));
+            }
+
+            return Ok(Statement::new_import(
+                module,
+                alias,
+                Vec::new(),
+                from,
+                self.lexer.current_location(),
+            ));
+        }
+
+        let module = self.expect_module()?;
+        self.expect_token(Token::Keyword(Keyword::Import))?;
+
+        let mut names = Vec::new();
+
+        loop {
+            let name = self.expect_identifier()?;
+
+            let mut alias = None;
+            if self.peek_token()?.token == Token::Identifier("as".to_string()) {
+                self.next_token()?;
+                alias = Some(self.expect_identifier()?.token.to_string());
+            }
+
+            names.push(ImportedName {
+                name: name.token.to_string(),
+                alias,
+                from: name.from,
+                to: self.lexer.current_location(),
+            });
+
+            if self.peek_token()?.token != Token::Operator(Operator::Comma) {
+                break;
+            }
+
+            self.next_token()?;
+        }
+
+        Ok(Statement::new_import(
+            module,
+            None,
+            names,
             from,
             self.lexer.current_location(),
         ))
     }
 
+    fn expect_module(&mut self) -> Result<String, ParserErrorWithLocation> {
+        let next_token = self.next_token()?;
+
+        match next_token.token {
+            Token::String(module) => Ok(module),
+            token => Err(ParserErrorWithLocation {
+                error: ParserError::UnexpectedToken(token.clone()),
+                details: format!("Expected the path of the module as a string got {}", token),
+                from: next_token.from,
+                to: next_token.to,
+            }),
+        }
+    }
+
+    /// Whether the statement starts with `from "module"`, the contextual
+    /// keyword of imports binding slots of a module.
+    fn is_from_import(&self) -> Result<bool, ParserErrorWithLocation> {
+        if !self.lexer.dialect.supports(Feature::Import) {
+            return Ok(false);
+        }
+
+        let [_, module] = self.peek_two_tokens()?;
+
+        Ok(matches!(module.token, Token::String(_)))
+    }
+
+    /// Reports statements of other dialects whose keywords this dialect lexes
+    /// as identifiers, which would otherwise fail with a confusing error.
+    fn check_unavailable_statement(&self) -> Result<(), ParserErrorWithLocation> {
+        let [first, second] = self.peek_two_tokens()?;
+
+        let Token::Identifier(word) = &first.token else {
+            return Ok(());
+        };
+
+        let feature = match (word.as_str(), &second.token) {
+            ("let", Token::Identifier(_) | Token::LeftBrace) => Feature::Let,
+            ("import" | "from", Token::String(_)) => Feature::Import,
+            _ => return Ok(()),
+        };
+
+        if self.lexer.dialect.supports(feature) {
+            return Ok(());
+        }
+
+        Err(self.unavailable(feature, first.from, second.to))
+    }
+
+    fn peek_two_tokens(&self) -> Result<[TokenWithLocation; 2], ParserErrorWithLocation> {
+        let mut lexer = self.lexer.clone();
+        let first = lexer.next().map_err(Self::map_lexer_error)?;
+        let second = lexer.next().map_err(Self::map_lexer_error)?;
+
+        Ok([first, second])
+    }
+
+    /// Span of the next two tokens when they follow each other without any
+    /// space, e.g. the `?` and `.` a dialect without `?.` lexes it as.
+    fn peek_joined_tokens(
+        &self,
+        first: Token,
+        second: Token,
+    ) -> Result<Option<(Location, Location)>, ParserErrorWithLocation> {
+        let [next, after] = self.peek_two_tokens()?;
+
+        if next.token != first || after.token != second || next.to.index != after.from.index {
+            return Ok(None);
+        }
+
+        Ok(Some((next.from, after.to)))
+    }
+
+    fn unavailable(
+        &self,
+        feature: Feature,
+        from: Location,
+        to: Location,
+    ) -> ParserErrorWithLocation {
+        let dialects = Dialect::supporting(feature)
+            .iter()
+            .map(Dialect::to_string)
+            .collect::<Vec<_>>()
+            .join(" or ");
+
+        ParserErrorWithLocation {
+            error: ParserError::UnavailableInDialect(feature),
+            details: format!(
+                "{} doesn't support {}, switch the dialect to {}",
+                self.lexer.dialect, feature, dialects
+            ),
+            from,
+            to,
+        }
+    }
+
     fn parse_do_while_statement(&mut self) -> Result<Statement, ParserErrorWithLocation> {
         let from = self.lexer.current_location();
 
@@ -1355,6 +1748,15 @@ impl<'a> Parser<'a> {
             return Ok(Some(condition));
         }
 
+        for (second, feature) in [
+            (Token::QuestionMark, Feature::NullCoalescing),
+            (Token::Dot, Feature::NullPropagation),
+        ] {
+            if let Some((from, to)) = self.peek_joined_tokens(Token::QuestionMark, second)? {
+                return Err(self.unavailable(feature, from, to));
+            }
+        }
+
         self.next_token()?;
 
         let true_expression = self.parse_ternary_operator(no_comma)?;
@@ -1394,7 +1796,7 @@ impl<'a> Parser<'a> {
         )))
     }
 
-    fn operator_precedence(index: usize) -> Option<Vec<Operator>> {
+    pub(crate) fn operator_precedence(index: usize) -> Option<Vec<Operator>> {
         // -, ~, !, typeof , ++, --	highest
         // /, *, %	…
         // +, -
@@ -1403,7 +1805,7 @@ impl<'a> Parser<'a> {
         // ==, !=, <=>
         // &
         // ^
-        // &&, in
+        // &&, ||, in, ??
         // +=, =, -=, /=, *=, %=
         // ,
         match index {
@@ -1417,7 +1819,12 @@ impl<'a> Parser<'a> {
                 Operator::MultiplyEqual,
                 Operator::ModuloEqual,
             ]),
-            2 => Some(vec![Operator::And, Operator::Or, Operator::In]),
+            2 => Some(vec![
+                Operator::And,
+                Operator::Or,
+                Operator::In,
+                Operator::NullCoalesce,
+            ]),
             3 => Some(vec![Operator::BitwiseXor]),
             4 => Some(vec![Operator::BitwiseAnd]),
             5 => Some(vec![Operator::BitwiseOr]),
@@ -1472,6 +1879,18 @@ impl<'a> Parser<'a> {
 
         loop {
             let next_token = self.peek_token()?;
+
+            if next_token.token == Token::Operator(Operator::LessEqual)
+                && !self.lexer.dialect.supports(Feature::ThreeWayComparison)
+            {
+                if let Some((from, to)) = self.peek_joined_tokens(
+                    Token::Operator(Operator::LessEqual),
+                    Token::Operator(Operator::Greater),
+                )? {
+                    return Err(self.unavailable(Feature::ThreeWayComparison, from, to));
+                }
+            }
+
             if let Token::Operator(operator) = next_token.token {
                 if !Self::operator_precedence(operator_index)
                     .unwrap()
@@ -1682,9 +2101,16 @@ impl<'a> Parser<'a> {
             if let Some(res) = self.try_parse(|parser| {
                 parser.skip_newlines()?;
                 let next_token = parser.peek_token()?;
+                let null_safe = matches!(
+                    next_token.token,
+                    Token::QuestionDot
+                        | Token::QuestionLeftBracket
+                        | Token::QuestionLeftParenthesis
+                );
+
                 match next_token.token {
                     // Property access
-                    Token::Dot => {
+                    Token::Dot | Token::QuestionDot => {
                         parser.next_token()?;
                         let next_token = parser.next_token()?;
 
@@ -1692,6 +2118,7 @@ impl<'a> Parser<'a> {
                             Token::Identifier(identifier) => Ok(Some(Expression::member_access(
                                 left.clone(),
                                 identifier,
+                                null_safe,
                                 from.clone(),
                                 parser.lexer.current_location(),
                             ))),
@@ -1699,6 +2126,7 @@ impl<'a> Parser<'a> {
                                 Ok(Some(Expression::member_access(
                                     left.clone(),
                                     "constructor".to_string(),
+                                    null_safe,
                                     from.clone(),
                                     parser.lexer.current_location(),
                                 )))
@@ -1712,7 +2140,7 @@ impl<'a> Parser<'a> {
                         }
                     }
                     // function invocation
-                    Token::LeftParenthesis => {
+                    Token::LeftParenthesis | Token::QuestionLeftParenthesis => {
                         parser.next_token()?;
 
                         let mut arguments = Vec::new();
@@ -1753,12 +2181,13 @@ impl<'a> Parser<'a> {
                         Ok(Some(Expression::function_call(
                             left.clone(),
                             arguments,
+                            null_safe,
                             from.clone(),
                             parser.lexer.current_location(),
                         )))
                     }
                     // Index access
-                    Token::LeftBracket => {
+                    Token::LeftBracket | Token::QuestionLeftBracket => {
                         parser.next_token()?;
                         let index = parser.parse_expression(true)?;
 
@@ -1778,6 +2207,7 @@ impl<'a> Parser<'a> {
                         Ok(Some(Expression::array_access(
                             left.clone(),
                             index.unwrap(),
+                            null_safe,
                             from.clone(),
                             parser.lexer.current_location(),
                         )))
@@ -1807,7 +2237,7 @@ impl<'a> Parser<'a> {
                 let next_token = self.next_token()?;
                 left = match &next_token.token {
                     Token::Identifier(val) => Ok(Some(Expression::scope_resolution(
-                        Some(ident.token.to_string()),
+                        vec![ident.token.to_string()],
                         val.clone(),
                         from,
                         next_token.to.clone(),
@@ -1823,7 +2253,7 @@ impl<'a> Parser<'a> {
                 let next_token = self.next_token()?;
                 left = match &next_token.token {
                     Token::Identifier(val) => Ok(Some(Expression::scope_resolution(
-                        None,
+                        Vec::new(),
                         val.clone(),
                         from,
                         next_token.to.clone(),
@@ -1863,18 +2293,50 @@ impl<'a> Parser<'a> {
                     next_token.to,
                 )))
             }
-            Token::Integer(value) => {
+            Token::Integer(value, source) => {
                 self.next_token()?;
                 Ok(Some(Expression::integer_literal(
                     value,
+                    source,
                     from.clone(),
                     next_token.to,
                 )))
             }
-            Token::Float(value) => {
+            Token::Character(value, source) => {
+                self.next_token()?;
+                Ok(Some(Expression::character_literal(
+                    value,
+                    source,
+                    from.clone(),
+                    next_token.to,
+                )))
+            }
+            Token::Keyword(Keyword::Line) => {
+                self.next_token()?;
+                Ok(Some(Expression::line_literal(from.clone(), next_token.to)))
+            }
+            Token::Keyword(Keyword::File) => {
+                self.next_token()?;
+                Ok(Some(Expression::file_literal(from.clone(), next_token.to)))
+            }
+            Token::Keyword(Keyword::Rawcall) => {
+                self.next_token()?;
+
+                // Arguments are written just like parameters, `(function, this, ...)`
+                let arguments = self.parse_parameters()?;
+
+                Ok(Some(Expression::raw_call(
+                    arguments,
+                    from.clone(),
+                    self.lexer.current_location(),
+                )))
+            }
+            Token::At => self.parse_lambda_expression(),
+            Token::Float(value, source) => {
                 self.next_token()?;
                 Ok(Some(Expression::float_literal(
                     value,
+                    source,
                     from.clone(),
                     next_token.to,
                 )))
@@ -2023,6 +2485,40 @@ impl<'a> Parser<'a> {
         self.skip_newlines()?;
         let from = self.lexer.current_location();
         self.expect_token(Token::LeftBrace)?;
+        let properties = self.parse_table_entries(Token::RightBrace)?;
+
+        Ok(Some(Expression::table(
+            properties,
+            from,
+            self.lexer.current_location(),
+        )))
+    }
+
+    /// Attributes of a class or class member, `</ key = value, ... />`, parsed
+    /// like the slots of a table.
+    fn parse_attributes(&mut self) -> Result<Option<TableExpression>, ParserErrorWithLocation> {
+        self.skip_newlines()?;
+        let open = self.peek_token()?;
+
+        if open.token != Token::AttributesOpen {
+            return Ok(None);
+        }
+
+        self.next_token()?;
+        let entries = self.parse_table_entries(Token::AttributesClose)?;
+
+        Ok(Some(TableExpression {
+            entries,
+            from: open.from,
+            to: self.lexer.current_location(),
+        }))
+    }
+
+    /// Slots of a table up to and including the closing token.
+    fn parse_table_entries(
+        &mut self,
+        close: Token,
+    ) -> Result<Vec<TableEntry>, ParserErrorWithLocation> {
         let mut properties = Vec::new();
 
         loop {
@@ -2048,11 +2544,11 @@ impl<'a> Parser<'a> {
 
             self.next_token()?;
 
-            if next_token.token == Token::RightBrace {
+            if next_token.token == close {
                 break;
             }
 
-            let (id, id_expr) = if let Token::Identifier(_) = &next_token.token {
+            let (id, id_expr, string_key) = if let Token::Identifier(_) = &next_token.token {
                 (
                     Some(Expression::identifier(
                         next_token.token.clone(),
@@ -2060,6 +2556,7 @@ impl<'a> Parser<'a> {
                         next_token.to.clone(),
                     )),
                     None,
+                    None,
                 )
             } else if next_token.token == Token::LeftBracket {
                 let from = self.lexer.current_location();
@@ -2076,11 +2573,25 @@ impl<'a> Parser<'a> {
 
                 self.expect_token(Token::RightBracket)?;
 
-                (None, Some(id_exp.unwrap()))
+                (None, Some(id_exp.unwrap()), None)
+            } else if let Token::String(key) = &next_token.token {
+                (
+                    None,
+                    None,
+                    Some(Expression::string_literal(
+                        key.clone(),
+                        next_token.from.clone(),
+                        next_token.to.clone(),
+                    )),
+                )
             } else {
                 return Err(ParserErrorWithLocation {
                     error: ParserError::ExpectedOneOfGot(
-                        vec![Token::Identifier(String::new()), Token::LeftBracket],
+                        vec![
+                            Token::Identifier(String::new()),
+                            Token::LeftBracket,
+                            Token::String(String::new()),
+                        ],
                         next_token.token,
                     ),
                     details: "Expected identifier or expression as a key of a table".to_string(),
@@ -2089,7 +2600,12 @@ impl<'a> Parser<'a> {
                 });
             };
 
-            self.expect_token(Token::Operator(Operator::Assign))?;
+            // JSON style slots, `"key": value`
+            if string_key.is_some() {
+                self.expect_token(Token::Colon)?;
+            } else {
+                self.expect_token(Token::Operator(Operator::Assign))?;
+            }
 
             let value = self.parse_expression(true)?;
             if value.is_none() {
@@ -2102,7 +2618,16 @@ impl<'a> Parser<'a> {
             }
             let value = value.unwrap();
 
-            if id_expr.is_some() {
+            if let Some(key) = string_key {
+                properties.push(TableEntry::FieldWithStringKey(
+                    TableEntryFieldWithStringKey {
+                        key,
+                        expression: value,
+                        from,
+                        to: self.lexer.current_location(),
+                    },
+                ));
+            } else if id_expr.is_some() {
                 properties.push(TableEntry::FieldWithExpressionKey(
                     TableEntryFieldWithExpressionKey {
                         key: id_expr.unwrap(),
@@ -2123,7 +2648,7 @@ impl<'a> Parser<'a> {
             let next_token = self.peek_token()?;
 
             match next_token.token {
-                Token::RightBrace => {
+                token if token == close => {
                     self.next_token()?;
                     break;
                 }
@@ -2137,11 +2662,7 @@ impl<'a> Parser<'a> {
             }
         }
 
-        Ok(Some(Expression::table(
-            properties,
-            from,
-            self.lexer.current_location(),
-        )))
+        Ok(properties)
     }
 
     fn parse_block_statement(&mut self) -> Result<Statement, ParserErrorWithLocation> {
@@ -2336,6 +2857,303 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_enum_separators() {
+        let input = "
+enum Color {
+    Red, Green = 5, Blue
+    Alpha
+    Beta = \"b\"
+}";
+        let mut parser = Parser::new(input);
+        let statements = parser.parse().unwrap();
+
+        match &statements.statements[0] {
+            Statement::Enum(statement) => {
+                assert_eq!(statement.enumerations.len(), 5);
+                assert!(statement.enumerations[1].value.is_some());
+                assert!(statement.enumerations[2].value.is_none());
+                assert!(statement.enumerations[4].value.is_some());
+            }
+            _ => panic!("Expected first statement to be an enum"),
+        };
+    }
+
+    #[test]
+    fn test_local_function_and_nested_namespaces() {
+        let input = "local function a() {}\nfunction b::c::d() {}";
+        let mut parser = Parser::new(input);
+        let statements = parser.parse().unwrap();
+
+        match &statements.statements[0] {
+            Statement::FunctionDefinition(function) => {
+                assert!(function.is_local);
+                assert_eq!(function.from.index, 0);
+            }
+            _ => panic!("Expected first statement to be a function"),
+        };
+
+        match &statements.statements[1] {
+            Statement::FunctionDefinition(function) => match &function.name {
+                Some(Expression::ScopeResolution(name)) => {
+                    assert_eq!(name.scope, vec!["b", "c"]);
+                    assert_eq!(name.accessor, "d");
+                }
+                _ => panic!("Expected function name to be a scope resolution"),
+            },
+            _ => panic!("Expected second statement to be a function"),
+        };
+
+        assert!(Parser::new("local function a::b() {}").parse().is_err());
+    }
+
+    #[test]
+    fn test_special_expressions() {
+        let input = "local a = [__LINE__, __FILE__, rawcall(f, this, 1), 'x', @(x) x]";
+        let mut parser = Parser::new(input);
+        let statements = parser.parse().unwrap();
+
+        let Statement::Local(local) = &statements.statements[0] else {
+            panic!("Expected first statement to be a local variable declaration");
+        };
+        let Some(Expression::Array(array)) = &local.initializations[0].expression else {
+            panic!("Expected initializer to be an array");
+        };
+
+        assert!(matches!(array.elements[0], Expression::LineLiteral(_)));
+        assert!(matches!(array.elements[1], Expression::FileLiteral(_)));
+        match &array.elements[2] {
+            Expression::RawCall(call) => assert_eq!(call.arguments.len(), 3),
+            _ => panic!("Expected a rawcall"),
+        }
+        match &array.elements[3] {
+            Expression::CharacterLiteral(literal) => assert_eq!(literal.value, 'x'),
+            _ => panic!("Expected a character literal"),
+        }
+        match &array.elements[4] {
+            Expression::Function(function) => {
+                assert!(function.is_lambda);
+                assert!(matches!(function.statement, Statement::Return(_)));
+            }
+            _ => panic!("Expected a lambda"),
+        }
+    }
+
+    #[test]
+    fn test_table_string_keys() {
+        let input = "local a = { \"key\": 1, b = 2 }";
+        let mut parser = Parser::new(input);
+        let statements = parser.parse().unwrap();
+
+        let Statement::Local(local) = &statements.statements[0] else {
+            panic!("Expected first statement to be a local variable declaration");
+        };
+        let Some(Expression::Table(table)) = &local.initializations[0].expression else {
+            panic!("Expected initializer to be a table");
+        };
+
+        match &table.entries[0] {
+            TableEntry::FieldWithStringKey(field) => match &field.key {
+                Expression::StringLiteral(key) => assert_eq!(key.value, "key"),
+                _ => panic!("Expected key to be a string"),
+            },
+            _ => panic!("Expected a JSON style slot"),
+        }
+        assert!(matches!(table.entries[1], TableEntry::Field(_)));
+    }
+
+    #[test]
+    fn test_class_attributes() {
+        let input = "
+class Foo </ version = 2 /> {
+    </ editable = true, label = \"Name\" />
+    name = null;
+    </ deprecated = true /> static function bar() {}
+    </ hidden = true /> constructor() {}
+    baz = 1;
+}";
+        let mut parser = Parser::new(input);
+        let statements = parser.parse().unwrap();
+
+        let Statement::Class(class) = &statements.statements[0] else {
+            panic!("Expected first statement to be a class");
+        };
+        assert_eq!(class.attributes.as_ref().unwrap().entries.len(), 1);
+
+        let attributes = class
+            .members
+            .iter()
+            .map(|member| match member {
+                ClassMemberDefinition::Field(field) => field.attributes.as_ref(),
+                ClassMemberDefinition::Method(method)
+                | ClassMemberDefinition::Constructor(method) => method.attributes.as_ref(),
+            })
+            .map(|attributes| attributes.map(|attributes| attributes.entries.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(attributes, vec![Some(2), Some(1), Some(1), None]);
+
+        let ClassMemberDefinition::Method(method) = &class.members[1] else {
+            panic!("Expected second member to be a method");
+        };
+        assert!(method.is_static);
+
+        assert!(Parser::new("class Foo { </ a = 1 /> }").parse().is_err());
+        assert!(Parser::new("class Foo { </ a = 1 }").parse().is_err());
+    }
+
+    #[test]
+    fn test_quirrel_syntax() {
+        let input = "
+let {a, b = 1} = foo?.bar
+local [c, d] = baz?[0]?(a) ?? []
+import \"math\" as math
+from \"string\" import format, strip as trim";
+        let statements = Parser::new(input)
+            .with_dialect(Dialect::Quirrel)
+            .parse()
+            .unwrap();
+
+        let Statement::Destructuring(table) = &statements.statements[0] else {
+            panic!("Expected first statement to destructure a table");
+        };
+        assert_eq!(table.kind, DestructuringKind::Table);
+        assert!(table.is_let);
+        assert!(table.bindings[1].expression.is_some());
+        assert!(matches!(
+            &table.expression,
+            Expression::MemberAccess(access) if access.null_safe
+        ));
+
+        let Statement::Destructuring(array) = &statements.statements[1] else {
+            panic!("Expected second statement to destructure an array");
+        };
+        assert_eq!(array.kind, DestructuringKind::Array);
+        let Expression::BinaryOperator(coalesce) = &array.expression else {
+            panic!("Expected '??' to be the outermost operator");
+        };
+        assert_eq!(coalesce.operator, Operator::NullCoalesce);
+        let Expression::FunctionCall(call) = &coalesce.left else {
+            panic!("Expected a null safe call");
+        };
+        assert!(call.null_safe);
+        assert!(matches!(&call.function, Expression::ArrayAccess(access) if access.null_safe));
+
+        let Statement::Import(module) = &statements.statements[2] else {
+            panic!("Expected third statement to import a module");
+        };
+        assert_eq!(module.module, "math");
+        assert!(module.alias.is_some());
+
+        let Statement::Import(names) = &statements.statements[3] else {
+            panic!("Expected fourth statement to import names");
+        };
+        let bindings = names
+            .names
+            .iter()
+            .map(ImportedName::binding)
+            .collect::<Vec<_>>();
+        assert_eq!(bindings, vec!["format", "trim"]);
+
+        assert!(Parser::new("let local function f() {}")
+            .with_dialect(Dialect::Quirrel)
+            .parse()
+            .is_err());
+    }
+
+    #[test]
+    fn test_unavailable_in_dialect() {
+        let error = |input: &str, dialect: Dialect| {
+            Parser::new(input)
+                .with_dialect(dialect)
+                .parse()
+                .unwrap_err()
+                .error
+        };
+
+        for (input, feature) in [
+            ("let a = 1", Feature::Let),
+            ("local {a} = t", Feature::Destructuring),
+            ("local [a] = t", Feature::Destructuring),
+            ("import \"a\"", Feature::Import),
+            ("from \"a\" import b", Feature::Import),
+            ("a?.b", Feature::NullPropagation),
+            ("a ?? b", Feature::NullCoalescing),
+        ] {
+            assert!(
+                matches!(
+                    error(input, Dialect::Squirrel3),
+                    ParserError::UnavailableInDialect(unavailable) if unavailable == feature
+                ),
+                "{}",
+                input
+            );
+        }
+
+        for (input, feature) in [
+            ("a <=> b", Feature::ThreeWayComparison),
+            ("class A { static b = 1 }", Feature::StaticMembers),
+        ] {
+            assert!(
+                matches!(
+                    error(input, Dialect::Squirrel2),
+                    ParserError::UnavailableInDialect(unavailable) if unavailable == feature
+                ),
+                "{}",
+                input
+            );
+        }
+
+        // Names that are only reserved in other dialects stay usable
+        for input in [
+            "local let = 1\nlet = a ? b : c",
+            "local from = 1\nimport(from)",
+        ] {
+            assert!(Parser::new(input).parse().is_ok(), "{}", input);
+        }
+        assert!(Parser::new("class A { static = 1 }")
+            .with_dialect(Dialect::Squirrel2)
+            .parse()
+            .is_ok());
+
+        let details = Parser::new("a ?? b").parse().unwrap_err().details;
+        assert_eq!(
+            details,
+            "Squirrel 3 doesn't support the '??' operator, switch the dialect to Quirrel"
+        );
+    }
+
+    #[test]
+    fn test_parse_collects_comments() {
+        let input = "// above\nlocal a = 1 // after\nfoo(/* inline */ a)\n";
+        let mut parser = Parser::new(input);
+        parser.parse().unwrap();
+
+        let comments = parser
+            .comments
+            .iter()
+            .map(|comment| (comment.text(), comment.placement, comment.from.line))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            comments,
+            vec![
+                ("above", CommentPlacement::OwnLine, 0),
+                ("after", CommentPlacement::EndOfLine, 1),
+                ("inline", CommentPlacement::Inline, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_trailing_unterminated_comment() {
+        let mut parser = Parser::new("foo()\n/* x");
+        parser.parse().unwrap();
+
+        assert_eq!(parser.comments.len(), 1);
+        assert_eq!(parser.comments[0].placement, CommentPlacement::OwnLine);
+        assert!(Parser::new("/* x").parse().is_ok());
+    }
+
     #[test]
     fn run_test_cases() {
         let paths = fs::read_dir("./test_cases").unwrap();
@@ -2350,18 +3168,7 @@ mod tests {
 
                 let contents = fs::read_to_string(path.unwrap().path()).unwrap();
 
-                let mut lexer = Lexer::new(&contents, false);
-                let mut tokens = Vec::new();
-
-                loop {
-                    let token = lexer.next().unwrap();
-
-                    if token.token == crate::squirrel_lexer::Token::EOF {
-                        break;
-                    }
-
-                    tokens.push(token);
-                }
+                let tokens = Lexer::new(&contents, false).tokens().unwrap();
 
                 let json_path = "./test_cases/".to_string() + file_name + ".tokens.json";
 
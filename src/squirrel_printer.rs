@@ -1,101 +1,557 @@
 use crate::{
     grammar::expressions::*,
     grammar::statements::*,
-    squirrel_lexer::{Keyword, Operator, Token},
+    grammar::trivia::*,
+    pretty::{layout, Doc},
+    squirrel_formatter::{BraceStyle, FormatterOptions},
+    squirrel_lexer::{Keyword, Location, Operator, Token},
 };
 
-pub enum PrintInstruction {
-    EmitToken(Token),
-    IncrementIndentation { id: usize },
-    DecrementIndentation { id: usize },
-}
+/// Comments enclosing a region of statements printed as is
+const IGNORE_FORMATTING_START: &str = "<sqlsp:ignore_formatting>";
+const IGNORE_FORMATTING_END: &str = "</sqlsp:ignore_formatting>";
 
 pub struct Printer {
-    buffer: Vec<PrintInstruction>,
+    buffer: Vec<Doc>,
     current_indentation: usize,
+    options: FormatterOptions,
+    source: String,
+    comments: Vec<Comment>,
+    next_comment: usize,
+    /// Last line of the source printed so far, to tell how many blank lines
+    /// precede the next statement
+    last_line: usize,
+    /// Index in the source past the last token printed, to attach the
+    /// comments following a token to it
+    cursor: usize,
 }
 
 impl Printer {
-    pub fn new(buffer_capacity: usize) -> Self {
+    pub fn new(buffer_capacity: usize, options: &FormatterOptions) -> Self {
         Self {
             buffer: Vec::with_capacity(buffer_capacity),
             current_indentation: 0,
+            options: options.clone(),
+            source: String::new(),
+            comments: Vec::new(),
+            next_comment: 0,
+            last_line: 0,
+            cursor: 0,
         }
     }
 
-    pub fn print(&mut self, ast: &Statements) -> Vec<PrintInstruction> {
-        self.print_statements(ast);
+    /// Prints the comments of the source the AST was parsed from along with it.
+    pub fn with_comments(mut self, source: &str, comments: Vec<Comment>) -> Self {
+        self.source = source.to_string();
+        self.comments = comments;
+        self
+    }
+
+    pub fn print(&mut self, ast: &Statements) -> String {
+        self.print_statements(ast, Some(self.source.len()));
 
-        self.buffer.drain(..).collect()
+        // Comments without any code after them to attach to
+        self.print_leading_comments(usize::MAX);
+
+        let doc = Doc::Concat(self.buffer.drain(..).collect());
+
+        layout(&doc, &self.options)
     }
 
     fn print_token(&mut self, token: Token) {
-        if let Token::Identifier(ident) = token {
-            if ident == "constructor" {
-                self.buffer.push(PrintInstruction::EmitToken(Token::Keyword(
-                    Keyword::Constructor,
-                )));
-            } else {
-                self.buffer
-                    .push(PrintInstruction::EmitToken(Token::Identifier(ident)));
+        let token = match token {
+            Token::Identifier(ident) if ident == "constructor" => {
+                Token::Keyword(Keyword::Constructor)
             }
-        } else {
-            self.buffer.push(PrintInstruction::EmitToken(token));
+            token => token,
+        };
+
+        match &token {
+            Token::Space
+            | Token::Newline
+            | Token::Tab
+            | Token::Indent(_)
+            | Token::Dummy
+            | Token::EOF
+            | Token::Comment(..)
+            | Token::MultiLineComment(_) => self.buffer.push(Doc::Token(token)),
+            // Comments after an opening bracket lead what follows it
+            Token::LeftParenthesis | Token::LeftBracket | Token::LeftBrace => {
+                self.skip_source_token(&token);
+                self.buffer.push(Doc::Token(token));
+            }
+            _ => {
+                let matched = self.skip_source_token(&token);
+                self.buffer.push(Doc::Token(token));
+                if matched {
+                    self.print_trailing_comments(self.cursor);
+                }
+            }
+        }
+    }
+
+    /// Moves the cursor past `token` if it is the next code in the source.
+    /// Tokens the printer adds or rewrites don't match and leave it as is.
+    fn skip_source_token(&mut self, token: &Token) -> bool {
+        if self.source.is_empty() {
+            return false;
+        }
+
+        let mut start = self.code_start(self.cursor);
+        let text = token.to_source_string();
+        // Semicolons ending statements are dropped by the printer
+        while text != ";" && self.source[start..].starts_with(';') {
+            start = self.code_start(start + 1);
         }
+        let rest = self.source.get(start..).unwrap_or_default();
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+
+        let matched = !text.is_empty()
+            && rest.starts_with(&text)
+            && !(text.ends_with(is_word) && rest[text.len()..].starts_with(is_word));
+
+        if matched {
+            self.cursor = start + text.len();
+        }
+
+        matched
+    }
+
+    /// Moves the cursor to the code at `index`, past any tokens left unmatched
+    fn seek(&mut self, index: usize) {
+        self.cursor = self.cursor.max(index);
     }
 
     fn print_space(&mut self) {
-        self.buffer.push(PrintInstruction::EmitToken(Token::Space));
+        self.buffer.push(Doc::Token(Token::Space));
     }
 
     fn newline(&mut self) {
-        self.buffer
-            .push(PrintInstruction::EmitToken(Token::Newline));
+        self.buffer.push(Doc::HardLine);
+    }
+
+    /// A space, or a line break if the enclosing group doesn't fit on one line
+    fn line(&mut self) {
+        self.buffer.push(Doc::Line);
+    }
+
+    /// Nothing, or a line break if the enclosing group doesn't fit on one line
+    fn soft_line(&mut self) {
+        self.buffer.push(Doc::SoftLine);
+    }
+
+    /// A line break only if the enclosing group doesn't fit on one line
+    fn print_broken_line(&mut self) {
+        self.buffer.push(Doc::if_break(Doc::HardLine, Doc::nil()));
+    }
+
+    fn begin_group(&mut self) -> usize {
+        self.buffer.len()
+    }
+
+    fn end_group(&mut self, start: usize, force_break: bool) {
+        let doc = Doc::Concat(self.buffer.split_off(start));
+        let force_break = force_break || doc.has_forced_break();
+
+        self.buffer.push(Doc::Group {
+            doc: Box::new(doc),
+            force_break,
+        });
+    }
+
+    fn print_trailing_comma(&mut self) {
+        if self.options.trailing_commas {
+            self.buffer.push(Doc::if_break(
+                Doc::Token(Token::Operator(Operator::Comma)),
+                Doc::nil(),
+            ));
+        }
+    }
+
+    fn ends_with(&self, doc: &Doc) -> bool {
+        self.buffer.last() == Some(doc)
+    }
+
+    /// Opening brace of a block, function, class, enum or switch, placed
+    /// according to the configured brace style
+    fn print_opening_brace(&mut self) {
+        let next_line = match self.options.brace_style {
+            BraceStyle::Preserve => self.next_code_on_new_line("{"),
+            BraceStyle::SameLine => false,
+            BraceStyle::NextLine => true,
+        };
+        if next_line && self.ends_with(&Doc::Token(Token::Space)) {
+            self.buffer.pop();
+            self.newline();
+        }
+        self.print_token(Token::LeftBrace);
+    }
+
+    /// Separates the end of a statement from the `else`, `catch` or `while` that follows it
+    fn print_continuation(&mut self, keyword: Keyword) {
+        let next_line = match self.options.brace_style {
+            BraceStyle::Preserve => {
+                self.next_code_on_new_line(&Token::Keyword(keyword).to_source_string())
+            }
+            BraceStyle::SameLine => false,
+            BraceStyle::NextLine => true,
+        };
+        if !next_line {
+            self.print_space();
+        } else if !self.ends_with(&Doc::HardLine) {
+            self.newline();
+        }
+    }
+
+    /// Whether `code` comes next in the source, on a new line
+    fn next_code_on_new_line(&self, code: &str) -> bool {
+        let start = self.code_start(self.cursor);
+        let rest = self.source.get(start..).unwrap_or_default();
+        let between = self.source.get(self.cursor..start).unwrap_or_default();
+
+        rest.starts_with(code) && between.contains('\n')
+    }
+
+    fn print_opening_parenthesis(&mut self, empty: bool) {
+        self.print_token(Token::LeftParenthesis);
+        if self.options.space_inside_parentheses && !empty {
+            self.print_space();
+        }
+    }
+
+    fn print_closing_parenthesis(&mut self, empty: bool) {
+        if self.options.space_inside_parentheses && !empty {
+            self.print_space();
+        }
+        self.print_token(Token::RightParenthesis);
+    }
+
+    /// Line break between a bracket and the list within it, padded with a
+    /// space when flat if the list is parenthesized and padding is configured
+    fn print_list_padding(&mut self, parenthesized: bool) {
+        match parenthesized && self.options.space_inside_parentheses {
+            true => self.line(),
+            false => self.soft_line(),
+        }
+    }
+
+    /// Argument or parameter list, kept on one line when it fits
+    fn print_parenthesized_list(&mut self, items: &[Expression]) {
+        if items.is_empty() {
+            self.print_opening_parenthesis(true);
+            self.print_enclosed_comments(false);
+            self.print_closing_parenthesis(true);
+            return;
+        }
+
+        // A sole table, array or function hugs the parentheses, e.g. `foo({`
+        if let [item @ (Expression::Table(_) | Expression::Array(_) | Expression::Function(_))] =
+            items
+        {
+            self.print_opening_parenthesis(false);
+            self.print_expression(item);
+            self.print_closing_parenthesis(false);
+            return;
+        }
+
+        let group = self.begin_group();
+        self.print_token(Token::LeftParenthesis);
+        let id = self.increment_indentation();
+        self.print_list_padding(true);
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                self.print_token(Token::Operator(Operator::Comma));
+                self.line();
+            }
+            self.print_expression(item);
+        }
+        self.decrement_indentation(id);
+        self.print_list_padding(true);
+        self.print_token(Token::RightParenthesis);
+        self.end_group(group, false);
+    }
+
+    fn peek_comment(&self) -> Option<&Comment> {
+        self.comments.get(self.next_comment)
+    }
+
+    /// Index of the first code at or after `index`, skipping whitespace and comments
+    fn code_start(&self, mut index: usize) -> usize {
+        loop {
+            let rest = self.source.get(index..).unwrap_or_default();
+            index += rest.len() - rest.trim_start().len();
+
+            match self
+                .comments
+                .binary_search_by_key(&index, |comment| comment.from.index)
+            {
+                Ok(comment) => index = self.comments[comment].to.index,
+                Err(_) => return index,
+            }
+        }
+    }
+
+    fn has_comments_before(&self, index: usize) -> bool {
+        self.peek_comment()
+            .is_some_and(|comment| comment.from.index < self.code_start(index))
+    }
+
+    /// Line break before the next line of a statement list, which starts at
+    /// `line` in the source, keeping up to `max_blank_lines` blank lines before it
+    fn print_separator(&mut self, first: &mut bool, line: usize) {
+        if std::mem::take(first) {
+            return;
+        }
+
+        let blank_lines = line
+            .saturating_sub(self.last_line + 1)
+            .min(self.options.max_blank_lines);
+
+        for _ in 0..=blank_lines {
+            self.newline();
+        }
+    }
+
+    fn print_comment(&mut self, comment: &Comment) {
+        self.print_token(comment.token.clone());
+        self.last_line = comment.to.line;
+    }
+
+    /// Prints the comments on their own lines before the code at `index` as
+    /// lines of a statement list.
+    /// Returns where a region excluded from formatting ends if one was printed,
+    /// so that the statements within it are skipped.
+    fn print_own_line_comments(&mut self, index: usize, first: &mut bool) -> Option<usize> {
+        let code = self.code_start(index);
+
+        while let Some(comment) = self
+            .peek_comment()
+            .filter(|comment| {
+                comment.placement == CommentPlacement::OwnLine && comment.from.index < code
+            })
+            .cloned()
+        {
+            self.print_separator(first, comment.from.line);
+            self.buffer.push(Doc::BreakParent);
+            self.next_comment += 1;
+
+            if comment.text() == IGNORE_FORMATTING_START {
+                let end = self.comments[self.next_comment..]
+                    .iter()
+                    .position(|end| end.text() == IGNORE_FORMATTING_END);
+
+                if let Some(end) = end {
+                    let end = self.comments[self.next_comment + end].clone();
+                    let region = &self.source[comment.from.index..end.to.index];
+
+                    self.buffer.push(Doc::Text(region.to_string()));
+                    self.next_comment += self.comments[self.next_comment..]
+                        .iter()
+                        .take_while(|comment| comment.from.index < end.to.index)
+                        .count();
+                    self.last_line = end.to.line;
+
+                    return Some(end.to.index);
+                }
+            }
+
+            self.print_comment(&comment);
+        }
+
+        None
+    }
+
+    /// Prints the comments before the code at `index` in front of it
+    fn print_leading_comments(&mut self, index: usize) {
+        if !self.has_comments_before(index) {
+            return;
+        }
+
+        let code = self.code_start(index);
+
+        while let Some(comment) = self
+            .peek_comment()
+            .filter(|comment| comment.from.index < code)
+            .cloned()
+        {
+            self.next_comment += 1;
+
+            let separated = matches!(
+                self.buffer.last(),
+                None | Some(
+                    Doc::HardLine
+                        | Doc::Line
+                        | Doc::SoftLine
+                        | Doc::Token(Token::Space | Token::LeftParenthesis | Token::LeftBracket)
+                )
+            );
+            if !separated {
+                self.print_space();
+            }
+
+            self.print_comment(&comment);
+
+            if comment.is_line_comment() || comment.placement == CommentPlacement::OwnLine {
+                self.buffer.push(Doc::BreakParent);
+                self.newline();
+            } else {
+                self.print_space();
+            }
+        }
+    }
+
+    /// Prints the comments following the code that ends at `index` on the same line.
+    /// Comments ending the line are deferred to its end, e.g. past a comma that
+    /// separates the code from the next list element.
+    fn print_trailing_comments(&mut self, index: usize) {
+        let mut position = index;
+
+        while let Some(comment) = self.peek_comment().cloned() {
+            let separators: &[char] = match comment.placement {
+                CommentPlacement::OwnLine => break,
+                CommentPlacement::EndOfLine => &[' ', '\t', ',', ';'],
+                CommentPlacement::Inline => &[' ', '\t'],
+            };
+
+            let on_same_line = self
+                .source
+                .get(position..comment.from.index)
+                .is_some_and(|between| between.chars().all(|c| separators.contains(&c)));
+
+            if !on_same_line {
+                break;
+            }
+
+            self.next_comment += 1;
+            position = comment.to.index;
+
+            match comment.placement {
+                CommentPlacement::EndOfLine => {
+                    self.buffer.push(Doc::line_suffix(Doc::Concat(vec![
+                        Doc::Token(Token::Space),
+                        Doc::Token(comment.token.clone()),
+                    ])));
+                    if comment.is_line_comment() {
+                        self.buffer.push(Doc::BreakParent);
+                    }
+                    self.last_line = comment.to.line;
+                }
+                _ => {
+                    self.print_space();
+                    self.print_comment(&comment);
+                }
+            }
+        }
+    }
+
+    /// Prints the comments within an empty list right after its opening bracket,
+    /// e.g. `foo(/* none */)`, `padded` with spaces when they fit on its line.
+    /// Line comments and comments on lines of their own break the list.
+    fn print_enclosed_comments(&mut self, padded: bool) {
+        let closing = self.code_start(self.cursor);
+        let start = self.next_comment;
+        while self
+            .peek_comment()
+            .is_some_and(|comment| comment.from.index < closing)
+        {
+            self.next_comment += 1;
+        }
+        let comments = self.comments[start..self.next_comment].to_vec();
+
+        if comments.is_empty() {
+            return;
+        }
+
+        let broken = comments.iter().any(|comment| {
+            comment.is_line_comment() || comment.placement == CommentPlacement::OwnLine
+        });
+
+        if broken {
+            self.buffer.push(Doc::BreakParent);
+            let id = self.increment_indentation();
+            for comment in &comments {
+                self.newline();
+                self.print_comment(comment);
+            }
+            self.decrement_indentation(id);
+            self.newline();
+            return;
+        }
+
+        for (i, comment) in comments.iter().enumerate() {
+            if i > 0 || padded {
+                self.print_space();
+            }
+            self.print_comment(comment);
+        }
+        if padded {
+            self.print_space();
+        }
+    }
+
+    /// Prints the comments on their own lines before the closing brace or
+    /// bracket at `index` of a list, `empty` telling whether the list has no entries
+    fn print_dangling_comments(&mut self, index: usize, empty: bool) {
+        let mut first = empty;
+        self.print_own_line_comments(index, &mut first);
     }
 
     fn print_multi_line_string_literal(&mut self, expr: &MutliLineStringLiteralExpression) {
         self.buffer
-            .push(PrintInstruction::EmitToken(Token::MultiLineString(
-                expr.value.clone(),
-            )));
+            .push(Doc::Token(Token::MultiLineString(expr.value.clone())));
     }
 
     fn print_string_literal(&mut self, expr: &StringLiteralExpression) {
-        self.buffer.push(PrintInstruction::EmitToken(Token::String(
-            expr.value.clone(),
-        )));
+        self.buffer
+            .push(Doc::Token(Token::String(expr.value.clone())));
     }
 
     fn increment_indentation(&mut self) -> usize {
-        let id = self.buffer.len();
-        self.current_indentation = self.current_indentation + 1;
-
-        self.buffer
-            .push(PrintInstruction::IncrementIndentation { id });
+        self.current_indentation += 1;
 
-        id
+        self.buffer.len()
     }
 
-    fn decrement_indentation(&mut self, id: usize) {
-        self.current_indentation = self.current_indentation - 1;
+    fn decrement_indentation(&mut self, start: usize) {
+        self.current_indentation -= 1;
 
-        self.buffer
-            .push(PrintInstruction::DecrementIndentation { id });
+        let docs = self.buffer.split_off(start);
+        self.buffer.push(Doc::indent(Doc::Concat(docs)));
     }
 
-    fn print_statements(&mut self, ast: &Statements) {
-        for (i, statement) in ast.statements.iter().enumerate() {
-            self.print_statement(statement);
-            if i < ast.statements.len() - 1 {
-                let line_difference = (ast.statements[i + 1].get_from().line
-                    - statement.get_from().line)
-                    .max(1)
-                    .min(2);
-
-                for _ in 0..line_difference {
-                    self.newline();
-                }
+    /// Prints the statements on lines of their own, along with the comments
+    /// between them and those before `end`, if given
+    fn print_statements(&mut self, ast: &Statements, end: Option<usize>) {
+        let mut first = true;
+        let mut statements = ast.statements.iter().peekable();
+
+        loop {
+            let next = match statements.peek() {
+                Some(statement) => statement.get_from().index,
+                None => match end {
+                    Some(end) => end,
+                    None => break,
+                },
+            };
+
+            if let Some(region_end) = self.print_own_line_comments(next, &mut first) {
+                while statements
+                    .next_if(|statement| statement.get_from().index < region_end)
+                    .is_some()
+                {}
+                continue;
             }
+
+            let Some(statement) = statements.next() else {
+                break;
+            };
+
+            self.print_separator(&mut first, statement.get_from().line);
+            self.print_leading_comments(statement.get_from().index);
+            self.seek(statement.get_from().index);
+            self.print_statement(statement);
+            self.last_line = statement.get_to().line;
+            self.print_trailing_comments(statement.get_to().index);
         }
     }
 
@@ -117,6 +573,8 @@ impl Printer {
             Statement::Expression(stat) => self.print_expression_statement(stat),
             Statement::Const(stat) => self.print_const(stat),
             Statement::Local(stat) => self.print_local(stat),
+            Statement::Destructuring(stat) => self.print_destructuring(stat),
+            Statement::Import(stat) => self.print_import(stat),
             Statement::FunctionDefinition(stat) => self.print_function_declaration(stat),
             Statement::Class(stat) => self.print_class_definition(stat),
             Statement::Enum(stat) => self.print_enum(stat),
@@ -124,32 +582,36 @@ impl Printer {
     }
 
     fn print_block(&mut self, stat: &BlockStatement) {
-        self.print_token(Token::LeftBrace);
+        self.print_leading_comments(stat.from.index);
+        self.print_opening_brace();
+        self.print_trailing_comments(self.code_start(stat.from.index) + 1);
+        let closing = stat.to.index.saturating_sub(1);
         let id = self.increment_indentation();
-        if !stat.statements.statements.is_empty() {
+        if !stat.statements.statements.is_empty() || self.has_comments_before(closing) {
             self.newline();
-            self.print_statements(&stat.statements);
+            self.print_statements(&stat.statements, Some(closing));
         } else {
             self.print_token(Token::Dummy);
         }
         self.decrement_indentation(id);
         self.newline();
         self.print_token(Token::RightBrace);
+        self.print_trailing_comments(stat.to.index);
     }
 
     fn print_if(&mut self, stat: &IfStatement) {
         self.print_token(Token::Keyword(Keyword::If));
         self.print_space();
-        self.print_token(Token::LeftParenthesis);
+        self.print_opening_parenthesis(false);
         let id = self.increment_indentation();
         self.print_expression(&stat.condition);
         self.decrement_indentation(id);
-        self.print_token(Token::RightParenthesis);
+        self.print_closing_parenthesis(false);
         self.print_space();
         if let Statement::Block(block) = &stat.if_true {
             self.print_block(block);
             if stat.if_false.is_some() {
-                self.print_space();
+                self.print_continuation(Keyword::Else);
             }
         } else {
             let id = self.increment_indentation();
@@ -180,11 +642,11 @@ impl Printer {
     fn print_while(&mut self, stat: &WhileStatement) {
         self.print_token(Token::Keyword(Keyword::While));
         self.print_space();
-        self.print_token(Token::LeftParenthesis);
+        self.print_opening_parenthesis(false);
         let id = self.increment_indentation();
         self.print_expression(&stat.condition);
         self.decrement_indentation(id);
-        self.print_token(Token::RightParenthesis);
+        self.print_closing_parenthesis(false);
         self.print_space();
         if let Statement::Block(block) = &stat.statement {
             self.print_block(block);
@@ -208,49 +670,60 @@ impl Printer {
             self.decrement_indentation(id);
             self.newline();
         }
-        self.print_space();
+        self.print_continuation(Keyword::While);
         self.print_token(Token::Keyword(Keyword::While));
         self.print_space();
-        self.print_token(Token::LeftParenthesis);
+        self.print_opening_parenthesis(false);
         let id = self.increment_indentation();
         self.print_expression(&stat.condition);
         self.decrement_indentation(id);
-        self.print_token(Token::RightParenthesis);
+        self.print_closing_parenthesis(false);
     }
 
     fn print_switch(&mut self, stat: &SwitchStatement) {
         self.print_token(Token::Keyword(Keyword::Switch));
         self.print_space();
-        self.print_token(Token::LeftParenthesis);
+        self.print_opening_parenthesis(false);
         let id = self.increment_indentation();
         self.print_expression(&stat.expression);
         self.decrement_indentation(id);
-        self.print_token(Token::RightParenthesis);
+        self.print_closing_parenthesis(false);
         self.print_space();
-        self.print_token(Token::LeftBrace);
+        let closing_parenthesis = self.code_start(stat.expression.get_to().index);
+        let brace = self.code_start(closing_parenthesis + 1);
+        self.print_leading_comments(brace);
+        self.print_opening_brace();
+        self.print_trailing_comments(brace + 1);
         let id = self.increment_indentation();
         self.newline();
-        for (i, case) in stat.cases.iter().enumerate() {
+        let mut first = true;
+        for case in &stat.cases {
+            self.print_own_line_comments(case.from.index, &mut first);
+            self.print_separator(&mut first, case.from.line);
+            self.print_leading_comments(case.from.index);
             self.print_token(Token::Keyword(Keyword::Case));
             self.print_space();
             self.print_expression(&case.expression);
             self.print_token(Token::Colon);
             let id = self.increment_indentation();
             self.newline();
-            self.print_statements(&case.statements);
+            // Comments after the statements are left to the next case
+            self.print_statements(&case.statements, None);
             self.decrement_indentation(id);
-            if i < stat.cases.len() - 1 || stat.default.is_some() {
-                self.newline();
-            }
         }
         if let Some(default) = &stat.default {
+            self.print_own_line_comments(default.from.index, &mut first);
+            self.print_separator(&mut first, default.from.line);
             self.print_token(Token::Keyword(Keyword::Default));
+            // The statements of the default case start right after its colon
+            self.print_leading_comments(default.from.index.saturating_sub(1));
             self.print_token(Token::Colon);
             let id = self.increment_indentation();
             self.newline();
-            self.print_statements(&default);
+            self.print_statements(default, None);
             self.decrement_indentation(id);
         }
+        self.print_dangling_comments(stat.to.index.saturating_sub(1), first);
         self.decrement_indentation(id);
         self.newline();
         self.print_token(Token::RightBrace);
@@ -259,7 +732,7 @@ impl Printer {
     fn print_for(&mut self, stat: &ForStatement) {
         self.print_token(Token::Keyword(Keyword::For));
         self.print_space();
-        self.print_token(Token::LeftParenthesis);
+        self.print_opening_parenthesis(false);
 
         let id = self.increment_indentation();
 
@@ -279,7 +752,7 @@ impl Printer {
 
         self.decrement_indentation(id);
 
-        self.print_token(Token::RightParenthesis);
+        self.print_closing_parenthesis(false);
         self.print_space();
         if let Statement::Block(block) = &stat.statement {
             self.print_block(block);
@@ -294,7 +767,7 @@ impl Printer {
     fn print_for_each(&mut self, stat: &ForEachStatement) {
         self.print_token(Token::Keyword(Keyword::Foreach));
         self.print_space();
-        self.print_token(Token::LeftParenthesis);
+        self.print_opening_parenthesis(false);
         let id = self.increment_indentation();
         if let Some(key) = &stat.key {
             self.print_expression(key);
@@ -307,7 +780,7 @@ impl Printer {
         self.print_space();
         self.print_expression(&stat.iterable);
         self.decrement_indentation(id);
-        self.print_token(Token::RightParenthesis);
+        self.print_closing_parenthesis(false);
         self.print_space();
         if let Statement::Block(block) = &stat.statement {
             self.print_block(block);
@@ -324,7 +797,10 @@ impl Printer {
         self.print_space();
         self.print_expression(&stat.name);
         self.print_space();
-        self.print_token(Token::LeftBrace);
+        let brace = self.code_start(stat.name.get_to().index);
+        self.print_leading_comments(brace);
+        self.print_opening_brace();
+        self.print_trailing_comments(brace + 1);
         let id = self.increment_indentation();
         self.newline();
         for (i, enumeration) in stat.enumerations.iter().enumerate() {
@@ -335,12 +811,18 @@ impl Printer {
                 self.print_space();
                 self.print_expression(value);
             }
+            self.last_line = enumeration.to.line;
+            self.print_trailing_comments(enumeration.to.index);
 
             if i < stat.enumerations.len() - 1 {
                 self.print_token(Token::Operator(Operator::Comma));
                 self.newline();
             }
         }
+        self.print_dangling_comments(
+            stat.to.index.saturating_sub(1),
+            stat.enumerations.is_empty(),
+        );
         self.decrement_indentation(id);
         self.newline();
         self.print_token(Token::RightBrace);
@@ -358,14 +840,14 @@ impl Printer {
             self.decrement_indentation(id);
             self.newline();
         }
-        self.print_space();
+        self.print_continuation(Keyword::Catch);
         self.print_token(Token::Keyword(Keyword::Catch));
         self.print_space();
-        self.print_token(Token::LeftParenthesis);
+        self.print_opening_parenthesis(false);
         let id = self.increment_indentation();
         self.print_expression(&stat.catch_variable);
         self.decrement_indentation(id);
-        self.print_token(Token::RightParenthesis);
+        self.print_closing_parenthesis(false);
         self.print_space();
         if let Statement::Block(block) = &stat.catch_statement {
             self.print_block(block);
@@ -424,10 +906,23 @@ impl Printer {
     }
 
     fn print_local(&mut self, stat: &LocalStatement) {
-        self.print_token(Token::Keyword(Keyword::Local));
-        self.print_space();
+        self.print_local_keyword(stat.is_let);
         let id = self.increment_indentation();
-        for (i, init) in stat.initializations.iter().enumerate() {
+        self.print_initializations(&stat.initializations);
+        self.decrement_indentation(id);
+    }
+
+    fn print_local_keyword(&mut self, is_let: bool) {
+        if is_let {
+            self.print_token(Token::Keyword(Keyword::Let));
+        } else {
+            self.print_token(Token::Keyword(Keyword::Local));
+        }
+        self.print_space();
+    }
+
+    fn print_initializations(&mut self, initializations: &[Initialization]) {
+        for (i, init) in initializations.iter().enumerate() {
             if i > 0 {
                 self.print_token(Token::Operator(Operator::Comma));
                 self.print_space();
@@ -440,10 +935,69 @@ impl Printer {
                 self.print_expression(expr);
             }
         }
+    }
+
+    fn print_destructuring(&mut self, stat: &DestructuringStatement) {
+        self.print_local_keyword(stat.is_let);
+        let id = self.increment_indentation();
+        match stat.kind {
+            DestructuringKind::Table => self.print_token(Token::LeftBrace),
+            DestructuringKind::Array => self.print_token(Token::LeftBracket),
+        }
+        self.print_initializations(&stat.bindings);
+        match stat.kind {
+            DestructuringKind::Table => self.print_token(Token::RightBrace),
+            DestructuringKind::Array => self.print_token(Token::RightBracket),
+        }
+        self.print_space();
+        self.print_token(Token::Operator(Operator::Assign));
+        self.print_space();
+        self.print_expression(&stat.expression);
         self.decrement_indentation(id);
     }
 
+    fn print_import(&mut self, stat: &ImportStatement) {
+        let module = Token::String(stat.module.clone());
+        let as_keyword = Token::Identifier("as".to_string());
+
+        if stat.names.is_empty() {
+            self.print_token(Token::Keyword(Keyword::Import));
+            self.print_space();
+            self.print_token(module);
+            if let Some(alias) = &stat.alias {
+                self.print_space();
+                self.print_token(as_keyword);
+                self.print_space();
+                self.print_expression(alias);
+            }
+            return;
+        }
+
+        self.print_token(Token::Identifier("from".to_string()));
+        self.print_space();
+        self.print_token(module);
+        self.print_space();
+        self.print_token(Token::Keyword(Keyword::Import));
+        self.print_space();
+        for (i, name) in stat.names.iter().enumerate() {
+            if i > 0 {
+                self.print_token(Token::Operator(Operator::Comma));
+                self.print_space();
+            }
+            self.print_token(Token::Identifier(name.name.clone()));
+            if let Some(alias) = &name.alias {
+                self.print_space();
+                self.print_token(as_keyword.clone());
+                self.print_space();
+                self.print_token(Token::Identifier(alias.clone()));
+            }
+        }
+    }
+
     fn print_expression(&mut self, expression: &Expression) {
+        self.print_leading_comments(expression.get_from().index);
+        self.seek(expression.get_from().index);
+
         match expression {
             Expression::UnaryOperator(expr) => self.print_unary_operator(expr.as_ref()),
             Expression::PostfixUnaryOperator(expr) => {
@@ -457,6 +1011,7 @@ impl Printer {
             }
             Expression::FloatLiteral(expr) => self.print_float_literal(expr.as_ref()),
             Expression::IntegerLiteral(expr) => self.print_integer_literal(expr.as_ref()),
+            Expression::CharacterLiteral(expr) => self.print_character_literal(expr.as_ref()),
             Expression::Table(expr) => self.print_table(expr.as_ref()),
             Expression::Clone(expr) => self.print_clone(expr.as_ref()),
             Expression::Resume(expr) => self.print_resume(expr.as_ref()),
@@ -467,36 +1022,49 @@ impl Printer {
             Expression::Identifier(expr) => self.print_identifier(expr.as_ref()),
             Expression::NullLiteral(expr) => self.print_null_literal(expr.as_ref()),
             Expression::BooleanLiteral(expr) => self.print_boolean_literal(expr.as_ref()),
+            Expression::LineLiteral(_) => self.print_token(Token::Keyword(Keyword::Line)),
+            Expression::FileLiteral(_) => self.print_token(Token::Keyword(Keyword::File)),
+            Expression::FunctionCall(_) | Expression::MemberAccess(_)
+                if self.print_call_chain(expression) => {}
             Expression::FunctionCall(expr) => self.print_function_call(expr.as_ref()),
+            Expression::RawCall(expr) => self.print_raw_call(expr.as_ref()),
             Expression::ScopeResolution(expr) => self.print_scope_resolution(expr.as_ref()),
             Expression::MemberAccess(expr) => self.print_member_access(expr.as_ref()),
             Expression::Class(expr) => self.print_class_definition(expr.as_ref()),
             Expression::TernaryOperator(expr) => self.print_ternary_operator(expr.as_ref()),
             Expression::Function(expr) => self.print_function_declaration(expr.as_ref()),
         }
+
+        self.last_line = self.last_line.max(expression.get_to().line);
+        self.seek(expression.get_to().index);
+        self.print_trailing_comments(expression.get_to().index);
     }
 
     fn print_function_declaration(&mut self, expr: &FunctionDefinition) {
+        if let (true, Statement::Return(statement)) = (expr.is_lambda, &expr.statement) {
+            if let Some(expression) = &statement.expression {
+                self.print_token(Token::At);
+                self.print_parenthesized_list(&expr.parameters);
+                self.print_space();
+                self.print_expression(expression);
+                return;
+            }
+        }
+
         if expr.is_static {
             self.print_token(Token::Keyword(Keyword::Static));
             self.print_space();
         }
+        if expr.is_local {
+            self.print_token(Token::Keyword(Keyword::Local));
+            self.print_space();
+        }
         self.print_token(Token::Keyword(Keyword::Function));
         self.print_space();
         if let Some(name) = &expr.name {
             self.print_expression(name);
         }
-        self.print_token(Token::LeftParenthesis);
-        let id = self.increment_indentation();
-        for (i, param) in expr.parameters.iter().enumerate() {
-            if i > 0 {
-                self.print_token(Token::Operator(Operator::Comma));
-                self.print_space();
-            }
-            self.print_expression(param);
-        }
-        self.decrement_indentation(id);
-        self.print_token(Token::RightParenthesis);
+        self.print_parenthesized_list(&expr.parameters);
         self.print_space();
         if let Statement::Block(block) = &expr.statement {
             self.print_block(block);
@@ -546,20 +1114,44 @@ impl Printer {
             self.decrement_indentation(id);
             self.print_space();
         }
-        self.print_token(Token::LeftBrace);
+        if let Some(attributes) = &expr.attributes {
+            self.print_attributes(attributes);
+            self.print_space();
+        }
+        let header = expr.extends.as_ref().or(expr.name.as_ref());
+        let brace = match &expr.attributes {
+            Some(attributes) => Some(self.code_start(attributes.to.index)),
+            None => header.map(|header| self.code_start(header.get_to().index)),
+        };
+        if let Some(brace) = brace {
+            self.print_leading_comments(brace);
+        }
+        self.print_opening_brace();
+        if let Some(brace) = brace {
+            self.print_trailing_comments(brace + 1);
+        }
         let id = self.increment_indentation();
-        self.newline();
+        let closing = expr.to.index.saturating_sub(1);
+        if !expr.members.is_empty() || self.has_comments_before(closing) {
+            self.newline();
+        }
         let mut newline_after_last = false;
         for (i, member) in expr.members.iter().enumerate() {
             if i > 0 {
                 self.newline();
             }
+            let (from, to) = match member {
+                ClassMemberDefinition::Field(field) => (&field.from, &field.to),
+                ClassMemberDefinition::Method(method)
+                | ClassMemberDefinition::Constructor(method) => (&method.from, &method.to),
+            };
             match member {
                 ClassMemberDefinition::Field(field) => {
                     if newline_after_last {
                         self.newline();
                     }
                     newline_after_last = false;
+                    self.print_member_attributes(&field.attributes, from);
 
                     if field.is_static {
                         self.print_token(Token::Keyword(Keyword::Static));
@@ -579,6 +1171,7 @@ impl Printer {
                         self.newline();
                     }
                     newline_after_last = true;
+                    self.print_member_attributes(&method.attributes, from);
 
                     self.print_function_declaration(method);
                 }
@@ -587,19 +1180,10 @@ impl Printer {
                         self.newline();
                     }
                     newline_after_last = true;
+                    self.print_member_attributes(&constructor.attributes, from);
 
                     self.print_token(Token::Keyword(Keyword::Constructor));
-                    self.print_token(Token::LeftParenthesis);
-                    let id = self.increment_indentation();
-                    for (i, param) in constructor.parameters.iter().enumerate() {
-                        if i > 0 {
-                            self.print_token(Token::Operator(Operator::Comma));
-                            self.print_space();
-                        }
-                        self.print_expression(param);
-                    }
-                    self.decrement_indentation(id);
-                    self.print_token(Token::RightParenthesis);
+                    self.print_parenthesized_list(&constructor.parameters);
                     self.print_space();
                     if let Statement::Block(block) = &constructor.statement {
                         self.print_block(block);
@@ -611,7 +1195,10 @@ impl Printer {
                     }
                 }
             }
+            self.last_line = to.line;
+            self.print_trailing_comments(to.index);
         }
+        self.print_dangling_comments(closing, expr.members.is_empty());
         self.decrement_indentation(id);
         self.newline();
         self.print_token(Token::RightBrace);
@@ -620,32 +1207,111 @@ impl Printer {
     fn print_member_access(&mut self, expr: &MemberAccessExpression) {
         self.print_expression(&expr.expression);
         let id = self.increment_indentation();
-        self.print_token(Token::Dot);
+        self.print_member_separator(expr.null_safe);
         self.print_token(Token::Identifier(expr.member.to_string()));
         self.decrement_indentation(id);
     }
 
+    fn print_member_separator(&mut self, null_safe: bool) {
+        if null_safe {
+            self.print_token(Token::QuestionDot);
+        } else {
+            self.print_token(Token::Dot);
+        }
+    }
+
+    /// The `?` of `a?(b)`, the list printing the parenthesis.
+    fn print_call_null_safety(&mut self, null_safe: bool) {
+        if null_safe {
+            self.print_token(Token::QuestionMark);
+        }
+    }
+
     fn print_scope_resolution(&mut self, expr: &ScopeResolutionExpression) {
-        if let Some(scope) = &expr.scope {
+        for scope in &expr.scope {
             self.print_token(Token::Identifier(scope.to_string()));
+            self.print_token(Token::DoubleColon);
+        }
+        if expr.scope.is_empty() {
+            self.print_token(Token::DoubleColon);
         }
-        self.print_token(Token::DoubleColon);
         self.print_token(Token::Identifier(expr.accessor.to_string()));
     }
 
     fn print_function_call(&mut self, expr: &FunctionCallExpression) {
         self.print_expression(&expr.function);
-        self.print_token(Token::LeftParenthesis);
+        self.print_call_null_safety(expr.null_safe);
+        self.print_parenthesized_list(&expr.arguments);
+    }
+
+    fn print_raw_call(&mut self, expr: &RawCallExpression) {
+        self.print_token(Token::Keyword(Keyword::Rawcall));
+        self.print_parenthesized_list(&expr.arguments);
+    }
+
+    /// Prints chains with at least two method calls, e.g. `a.filter(f).map(g)`,
+    /// with every call on its own line when the chain doesn't fit on one or
+    /// functions are passed to it.
+    /// Returns false for any other expression.
+    fn print_call_chain(&mut self, expression: &Expression) -> bool {
+        let mut links = Vec::new();
+        let mut head = expression;
+
+        loop {
+            match head {
+                Expression::FunctionCall(call) => match &call.function {
+                    Expression::MemberAccess(access) => {
+                        links.push((access.as_ref(), Some(call.as_ref())));
+                        head = &access.expression;
+                    }
+                    _ => break,
+                },
+                Expression::MemberAccess(access) => {
+                    links.push((access.as_ref(), None));
+                    head = &access.expression;
+                }
+                _ => break,
+            }
+        }
+
+        links.reverse();
+
+        if links.iter().filter(|(_, call)| call.is_some()).count() < 2 {
+            return false;
+        }
+
+        // Function literals span several lines, so the calls they are passed to
+        // are put on their own lines instead of trailing the previous function body
+        let force_break = links.iter().any(|(_, call)| {
+            call.is_some_and(|call| {
+                call.arguments
+                    .iter()
+                    .any(|argument| matches!(argument, Expression::Function(_)))
+            })
+        });
+
+        let group = self.begin_group();
+        self.print_expression(head);
         let id = self.increment_indentation();
-        for (i, arg) in expr.arguments.iter().enumerate() {
-            if i > 0 {
-                self.print_token(Token::Operator(Operator::Comma));
-                self.print_space();
+        let mut called = false;
+        for (access, call) in links {
+            if call.is_some() {
+                called = true;
+                self.soft_line();
+            } else if called {
+                self.soft_line();
+            }
+            self.print_member_separator(access.null_safe);
+            self.print_token(Token::Identifier(access.member.to_string()));
+            if let Some(call) = call {
+                self.print_call_null_safety(call.null_safe);
+                self.print_parenthesized_list(&call.arguments);
             }
-            self.print_expression(arg);
         }
         self.decrement_indentation(id);
-        self.print_token(Token::RightParenthesis);
+        self.end_group(group, force_break);
+
+        true
     }
 
     fn print_boolean_literal(&mut self, expr: &BooleanLiteralExpression) {
@@ -665,18 +1331,23 @@ impl Printer {
     }
 
     fn print_grouping(&mut self, expr: &GroupingExpression) {
-        self.print_token(Token::LeftParenthesis);
+        self.print_opening_parenthesis(expr.expression.is_none());
         let id = self.increment_indentation();
-        if let Some(expr) = &expr.expression {
-            self.print_expression(expr);
+        match &expr.expression {
+            Some(expr) => self.print_expression(expr),
+            None => self.print_enclosed_comments(false),
         }
         self.decrement_indentation(id);
-        self.print_token(Token::RightParenthesis);
+        self.print_closing_parenthesis(expr.expression.is_none());
     }
 
     fn print_array_access(&mut self, expr: &ArrayAccessExpression) {
         self.print_expression(&expr.array);
-        self.print_token(Token::LeftBracket);
+        if expr.null_safe {
+            self.print_token(Token::QuestionLeftBracket);
+        } else {
+            self.print_token(Token::LeftBracket);
+        }
         let id = self.increment_indentation();
         self.print_expression(&expr.index);
         self.decrement_indentation(id);
@@ -684,21 +1355,57 @@ impl Printer {
     }
 
     fn print_array(&mut self, expr: &ArrayExpression) {
+        if expr.elements.is_empty() {
+            self.print_token(Token::LeftBracket);
+            self.print_enclosed_comments(false);
+            self.print_token(Token::RightBracket);
+            return;
+        }
+
+        let group = self.begin_group();
         self.print_token(Token::LeftBracket);
+        self.print_trailing_comments(self.code_start(expr.from.index) + 1);
         let id = self.increment_indentation();
-        for (i, element) in expr.elements.iter().enumerate() {
-            if i > 0 {
-                self.print_token(Token::Operator(Operator::Comma));
+        self.soft_line();
+
+        let numeric = expr.elements.iter().all(|element| {
+            matches!(
+                element,
+                Expression::IntegerLiteral(_) | Expression::FloatLiteral(_)
+            )
+        });
+
+        if numeric {
+            // Numbers are filled into as few lines as possible instead of one per line
+            let mut parts = Vec::new();
+            for (i, element) in expr.elements.iter().enumerate() {
+                let start = self.buffer.len();
+                self.print_expression(element);
+                if i < expr.elements.len() - 1 {
+                    self.print_token(Token::Operator(Operator::Comma));
+                }
+                parts.push(Doc::Concat(self.buffer.split_off(start)));
+                if i < expr.elements.len() - 1 {
+                    parts.push(Doc::Line);
+                }
+            }
+            self.buffer.push(Doc::Fill(parts));
+        } else {
+            for (i, element) in expr.elements.iter().enumerate() {
+                if i > 0 {
+                    self.print_token(Token::Operator(Operator::Comma));
+                    self.line();
+                }
+                self.print_expression(element);
             }
-
-            self.newline();
-            self.print_expression(element);
         }
+
+        self.print_trailing_comma();
+        self.print_dangling_comments(expr.to.index.saturating_sub(1), false);
         self.decrement_indentation(id);
-        if !expr.elements.is_empty() {
-            self.newline();
-        }
+        self.soft_line();
         self.print_token(Token::RightBracket);
+        self.end_group(group, false);
     }
 
     fn print_delete(&mut self, expr: &DeleteExpression) {
@@ -726,7 +1433,30 @@ impl Printer {
     }
 
     fn print_table(&mut self, expr: &TableExpression) {
+        if expr.entries.is_empty() {
+            self.print_token(Token::LeftBrace);
+            self.print_enclosed_comments(true);
+            self.print_token(Token::RightBrace);
+            return;
+        }
+
+        // Tables with methods, or written with their first entry on a new line,
+        // keep every entry on its own line
+        let first_entry_line = match &expr.entries[0] {
+            TableEntry::Field(f) => f.name.get_from().line,
+            TableEntry::Function(f) => f.from.line,
+            TableEntry::FieldWithExpressionKey(f) => f.key.get_from().line,
+            TableEntry::FieldWithStringKey(f) => f.key.get_from().line,
+        };
+        let force_break = first_entry_line > expr.from.line
+            || expr
+                .entries
+                .iter()
+                .any(|entry| matches!(entry, TableEntry::Function(_)));
+
+        let group = self.begin_group();
         self.print_token(Token::LeftBrace);
+        self.print_trailing_comments(self.code_start(expr.from.index) + 1);
         let id = self.increment_indentation();
 
         let mut newline_after_last = false;
@@ -734,20 +1464,14 @@ impl Printer {
             if i > 0 {
                 self.print_token(Token::Operator(Operator::Comma));
             }
-            self.newline();
+            self.line();
 
             match elm {
-                TableEntry::Field(f) => {
+                TableEntry::Field(_) | TableEntry::FieldWithStringKey(_) => {
                     if newline_after_last {
-                        self.newline();
+                        self.print_broken_line();
                     }
                     newline_after_last = false;
-
-                    self.print_expression(&f.name);
-                    self.print_space();
-                    self.print_token(Token::Operator(Operator::Assign));
-                    self.print_space();
-                    self.print_expression(&f.expression);
                 }
                 TableEntry::Function(f) => {
                     if i > 0 {
@@ -755,37 +1479,109 @@ impl Printer {
                     }
                     newline_after_last = true;
 
-                    self.print_function_declaration(&f.function);
+                    self.print_leading_comments(f.from.index);
                 }
                 TableEntry::FieldWithExpressionKey(f) => {
                     if i > 0 {
-                        self.newline();
+                        self.print_broken_line();
                     }
                     newline_after_last = true;
 
-                    self.print_token(Token::LeftBracket);
-                    self.print_expression(&f.key);
-                    self.print_token(Token::RightBracket);
-                    self.print_space();
-                    self.print_token(Token::Operator(Operator::Assign));
-                    self.print_space();
-                    self.print_expression(&f.expression);
+                    self.print_leading_comments(f.from.index);
                 }
             }
+            self.print_table_entry(elm);
+            if let TableEntry::Function(f) = elm {
+                self.print_trailing_comments(f.to.index);
+            }
         }
+        self.print_trailing_comma();
+        self.print_dangling_comments(expr.to.index.saturating_sub(1), false);
         self.decrement_indentation(id);
-        if !expr.entries.is_empty() {
-            self.newline();
-        }
+        self.line();
         self.print_token(Token::RightBrace);
+        self.end_group(group, force_break);
+    }
+
+    fn print_table_entry(&mut self, entry: &TableEntry) {
+        match entry {
+            TableEntry::Field(f) => {
+                self.print_expression(&f.name);
+                self.print_space();
+                self.print_token(Token::Operator(Operator::Assign));
+                self.print_space();
+                self.print_expression(&f.expression);
+            }
+            TableEntry::FieldWithStringKey(f) => {
+                self.print_expression(&f.key);
+                self.print_token(Token::Colon);
+                self.print_space();
+                self.print_expression(&f.expression);
+            }
+            TableEntry::Function(f) => self.print_function_declaration(&f.function),
+            TableEntry::FieldWithExpressionKey(f) => {
+                self.print_token(Token::LeftBracket);
+                self.print_expression(&f.key);
+                self.print_token(Token::RightBracket);
+                self.print_space();
+                self.print_token(Token::Operator(Operator::Assign));
+                self.print_space();
+                self.print_expression(&f.expression);
+            }
+        }
+    }
+
+    /// `</ key = value />`, breaking one slot per line when too long
+    fn print_attributes(&mut self, attributes: &TableExpression) {
+        let group = self.begin_group();
+        self.print_token(Token::AttributesOpen);
+        let id = self.increment_indentation();
+        for (i, entry) in attributes.entries.iter().enumerate() {
+            if i > 0 {
+                self.print_token(Token::Operator(Operator::Comma));
+            }
+            self.line();
+            self.print_table_entry(entry);
+        }
+        self.decrement_indentation(id);
+        self.line();
+        self.print_token(Token::AttributesClose);
+        self.end_group(group, false);
+    }
+
+    /// Attributes of a class member, on their own line when written that way
+    fn print_member_attributes(&mut self, attributes: &Option<TableExpression>, from: &Location) {
+        if let Some(attributes) = attributes {
+            self.print_leading_comments(attributes.from.index);
+            self.print_attributes(attributes);
+            if attributes.to.line < from.line {
+                self.newline();
+            } else {
+                self.print_space();
+            }
+        }
+        self.print_leading_comments(from.index);
+    }
+
+    /// Prints an attribute list on its own, as shown in hovers and symbols.
+    pub fn print_attribute_list(&mut self, attributes: &TableExpression) -> String {
+        self.print_attributes(attributes);
+
+        let doc = Doc::Concat(self.buffer.drain(..).collect());
+
+        layout(&doc, &self.options)
     }
 
     fn print_integer_literal(&mut self, expr: &IntegerLiteralExpression) {
-        self.print_token(Token::Integer(expr.value));
+        self.print_token(Token::Integer(expr.value, expr.source.clone()));
+    }
+
+    fn print_character_literal(&mut self, expr: &CharacterLiteralExpression) {
+        self.print_token(Token::Character(expr.value, expr.source.clone()));
     }
 
     fn print_float_literal(&mut self, expr: &FloatLiteralExpression) {
-        self.print_token(Token::Float(expr.value));
+        self.print_token(Token::Float(expr.value, expr.source.clone()));
     }
 
     fn print_spread(&mut self, _expr: &SpreadExpression) {
@@ -0,0 +1,932 @@
+use std::{cmp::Reverse, fmt::Display, ops::Range, rc::Rc};
+
+use crate::{
+    ast_visitor::{visit, AstVisitor, AstVisitorResult},
+    dialect::Dialect,
+    grammar::{expressions::Expression, statements::*, trivia::Comment},
+    squirrel_lexer::{location_at, Lexer, Token},
+    squirrel_parser::{Parser, ParserErrorWithLocation},
+};
+
+/// Kind of a node or token of the syntax tree. Nodes mirror the statements and
+/// expressions of the AST, tokens cover every byte of the source in between.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyntaxKind {
+    Root,
+    BlockStatement,
+    IfStatement,
+    WhileStatement,
+    DoWhileStatement,
+    SwitchStatement,
+    /// The statements of a `case` or of the `default` of a switch
+    SwitchCase,
+    ForStatement,
+    ForEachStatement,
+    TryCatchStatement,
+    BreakStatement,
+    ContinueStatement,
+    ReturnStatement,
+    YieldStatement,
+    ThrowStatement,
+    ExpressionStatement,
+    ConstStatement,
+    LocalStatement,
+    DestructuringStatement,
+    ImportStatement,
+    FunctionDefinition,
+    ClassDefinition,
+    EnumStatement,
+    UnaryOperatorExpression,
+    PostfixUnaryOperatorExpression,
+    BinaryOperatorExpression,
+    SpreadExpression,
+    StringLiteralExpression,
+    FloatLiteralExpression,
+    IntegerLiteralExpression,
+    CharacterLiteralExpression,
+    TableExpression,
+    CloneExpression,
+    ResumeExpression,
+    DeleteExpression,
+    ArrayExpression,
+    ArrayAccessExpression,
+    GroupingExpression,
+    IdentifierExpression,
+    NullLiteralExpression,
+    BooleanLiteralExpression,
+    LineLiteralExpression,
+    FileLiteralExpression,
+    FunctionCallExpression,
+    RawCallExpression,
+    ScopeResolutionExpression,
+    MemberAccessExpression,
+    TernaryOperatorExpression,
+    Whitespace,
+    Newline,
+    Comment,
+    Identifier,
+    Keyword,
+    Literal,
+    Operator,
+    Punctuation,
+}
+
+impl SyntaxKind {
+    fn of_statement(statement: &Statement) -> Self {
+        match statement {
+            Statement::Block(_) => SyntaxKind::BlockStatement,
+            Statement::If(_) => SyntaxKind::IfStatement,
+            Statement::While(_) => SyntaxKind::WhileStatement,
+            Statement::DoWhile(_) => SyntaxKind::DoWhileStatement,
+            Statement::Switch(_) => SyntaxKind::SwitchStatement,
+            Statement::For(_) => SyntaxKind::ForStatement,
+            Statement::ForEach(_) => SyntaxKind::ForEachStatement,
+            Statement::TryCatch(_) => SyntaxKind::TryCatchStatement,
+            Statement::Break(_) => SyntaxKind::BreakStatement,
+            Statement::Continue(_) => SyntaxKind::ContinueStatement,
+            Statement::Return(_) => SyntaxKind::ReturnStatement,
+            Statement::Yield(_) => SyntaxKind::YieldStatement,
+            Statement::Throw(_) => SyntaxKind::ThrowStatement,
+            Statement::Expression(_) => SyntaxKind::ExpressionStatement,
+            Statement::Const(_) => SyntaxKind::ConstStatement,
+            Statement::Local(_) => SyntaxKind::LocalStatement,
+            Statement::Destructuring(_) => SyntaxKind::DestructuringStatement,
+            Statement::Import(_) => SyntaxKind::ImportStatement,
+            Statement::FunctionDefinition(_) => SyntaxKind::FunctionDefinition,
+            Statement::Class(_) => SyntaxKind::ClassDefinition,
+            Statement::Enum(_) => SyntaxKind::EnumStatement,
+        }
+    }
+
+    fn of_expression(expression: &Expression) -> Self {
+        match expression {
+            Expression::UnaryOperator(_) => SyntaxKind::UnaryOperatorExpression,
+            Expression::PostfixUnaryOperator(_) => SyntaxKind::PostfixUnaryOperatorExpression,
+            Expression::BinaryOperator(_) => SyntaxKind::BinaryOperatorExpression,
+            Expression::Spread(_) => SyntaxKind::SpreadExpression,
+            Expression::StringLiteral(_) | Expression::MutliLineStringLiteral(_) => {
+                SyntaxKind::StringLiteralExpression
+            }
+            Expression::FloatLiteral(_) => SyntaxKind::FloatLiteralExpression,
+            Expression::IntegerLiteral(_) => SyntaxKind::IntegerLiteralExpression,
+            Expression::CharacterLiteral(_) => SyntaxKind::CharacterLiteralExpression,
+            Expression::Table(_) => SyntaxKind::TableExpression,
+            Expression::Clone(_) => SyntaxKind::CloneExpression,
+            Expression::Resume(_) => SyntaxKind::ResumeExpression,
+            Expression::Delete(_) => SyntaxKind::DeleteExpression,
+            Expression::Array(_) => SyntaxKind::ArrayExpression,
+            Expression::ArrayAccess(_) => SyntaxKind::ArrayAccessExpression,
+            Expression::Grouping(_) => SyntaxKind::GroupingExpression,
+            Expression::Identifier(_) => SyntaxKind::IdentifierExpression,
+            Expression::NullLiteral(_) => SyntaxKind::NullLiteralExpression,
+            Expression::BooleanLiteral(_) => SyntaxKind::BooleanLiteralExpression,
+            Expression::LineLiteral(_) => SyntaxKind::LineLiteralExpression,
+            Expression::FileLiteral(_) => SyntaxKind::FileLiteralExpression,
+            Expression::FunctionCall(_) => SyntaxKind::FunctionCallExpression,
+            Expression::RawCall(_) => SyntaxKind::RawCallExpression,
+            Expression::ScopeResolution(_) => SyntaxKind::ScopeResolutionExpression,
+            Expression::MemberAccess(_) => SyntaxKind::MemberAccessExpression,
+            Expression::Class(_) => SyntaxKind::ClassDefinition,
+            Expression::TernaryOperator(_) => SyntaxKind::TernaryOperatorExpression,
+            Expression::Function(_) => SyntaxKind::FunctionDefinition,
+        }
+    }
+
+    fn of_token(token: &Token) -> Self {
+        match token {
+            Token::Identifier(_) => SyntaxKind::Identifier,
+            Token::Newline => SyntaxKind::Newline,
+            Token::Comment(..) | Token::MultiLineComment(_) => SyntaxKind::Comment,
+            Token::String(_)
+            | Token::MultiLineString(_)
+            | Token::Integer(..)
+            | Token::Character(..)
+            | Token::Float(..) => SyntaxKind::Literal,
+            Token::Operator(_) => SyntaxKind::Operator,
+            Token::Keyword(_) => SyntaxKind::Keyword,
+            Token::Space | Token::Tab | Token::Indent(_) | Token::EOF | Token::Dummy => {
+                SyntaxKind::Whitespace
+            }
+            _ => SyntaxKind::Punctuation,
+        }
+    }
+
+    pub fn is_statement(self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::BlockStatement
+                | SyntaxKind::IfStatement
+                | SyntaxKind::WhileStatement
+                | SyntaxKind::DoWhileStatement
+                | SyntaxKind::SwitchStatement
+                | SyntaxKind::ForStatement
+                | SyntaxKind::ForEachStatement
+                | SyntaxKind::TryCatchStatement
+                | SyntaxKind::BreakStatement
+                | SyntaxKind::ContinueStatement
+                | SyntaxKind::ReturnStatement
+                | SyntaxKind::YieldStatement
+                | SyntaxKind::ThrowStatement
+                | SyntaxKind::ExpressionStatement
+                | SyntaxKind::ConstStatement
+                | SyntaxKind::LocalStatement
+                | SyntaxKind::DestructuringStatement
+                | SyntaxKind::ImportStatement
+                | SyntaxKind::FunctionDefinition
+                | SyntaxKind::ClassDefinition
+                | SyntaxKind::EnumStatement
+        )
+    }
+
+    /// Whether nodes of this kind hold a list of statements
+    pub fn is_statement_list(self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::Root | SyntaxKind::BlockStatement | SyntaxKind::SwitchCase
+        )
+    }
+
+    /// Whether tokens of this kind carry no meaning for the parser
+    pub fn is_trivia(self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::Whitespace | SyntaxKind::Newline | SyntaxKind::Comment
+        )
+    }
+}
+
+/// Immutable token holding its exact source text, shared between trees.
+#[derive(Debug, PartialEq, Eq)]
+pub struct GreenToken {
+    kind: SyntaxKind,
+    text: String,
+}
+
+/// Immutable node knowing only its width, so it can be shared between trees
+/// wherever it appears.
+#[derive(Debug, PartialEq, Eq)]
+pub struct GreenNode {
+    kind: SyntaxKind,
+    width: usize,
+    children: Vec<GreenElement>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenElement {
+    fn width(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.width,
+            GreenElement::Token(token) => token.text.len(),
+        }
+    }
+}
+
+impl GreenNode {
+    fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> Self {
+        Self {
+            kind,
+            width: children.iter().map(GreenElement::width).sum(),
+            children,
+        }
+    }
+}
+
+struct NodeData {
+    green: Rc<GreenNode>,
+    offset: usize,
+    parent: Option<SyntaxNode>,
+}
+
+/// Node positioned within its tree, built on demand while walking down from
+/// the root.
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+#[derive(Clone)]
+pub struct SyntaxToken {
+    green: Rc<GreenToken>,
+    offset: usize,
+    parent: SyntaxNode,
+}
+
+#[derive(Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    fn new_root(green: Rc<GreenNode>) -> Self {
+        Self(Rc::new(NodeData {
+            green,
+            offset: 0,
+            parent: None,
+        }))
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind
+    }
+
+    pub fn text_range(&self) -> Range<usize> {
+        self.0.offset..self.0.offset + self.0.green.width
+    }
+
+    pub fn parent(&self) -> Option<SyntaxNode> {
+        self.0.parent.clone()
+    }
+
+    /// The node itself followed by all nodes containing it, up to the root
+    pub fn ancestors(&self) -> impl Iterator<Item = SyntaxNode> {
+        std::iter::successors(Some(self.clone()), SyntaxNode::parent)
+    }
+
+    /// Child nodes and tokens, in source order
+    pub fn children_with_tokens(&self) -> Vec<SyntaxElement> {
+        self.elements()
+            .map(|(offset, element)| match element {
+                GreenElement::Node(green) => SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                    green: green.clone(),
+                    offset,
+                    parent: Some(self.clone()),
+                }))),
+                GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+                    green: green.clone(),
+                    offset,
+                    parent: self.clone(),
+                }),
+            })
+            .collect()
+    }
+
+    pub fn children(&self) -> Vec<SyntaxNode> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(|element| match element {
+                SyntaxElement::Node(node) => Some(node),
+                SyntaxElement::Token(_) => None,
+            })
+            .collect()
+    }
+
+    /// All tokens within the node, in source order
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        self.children_with_tokens()
+            .into_iter()
+            .flat_map(|element| match element {
+                SyntaxElement::Node(node) => node.tokens(),
+                SyntaxElement::Token(token) => vec![token],
+            })
+            .collect()
+    }
+
+    /// The token covering the offset, or the last one at the end of the node
+    pub fn token_at_offset(&self, offset: usize) -> Option<SyntaxToken> {
+        let mut last = None;
+
+        for (start, element) in self.elements() {
+            if offset < start {
+                break;
+            }
+
+            let within = offset < start + element.width();
+            last = Some((start, element));
+
+            if within {
+                break;
+            }
+        }
+
+        match last? {
+            (start, GreenElement::Node(green)) => SyntaxNode(Rc::new(NodeData {
+                green: green.clone(),
+                offset: start,
+                parent: Some(self.clone()),
+            }))
+            .token_at_offset(offset),
+            (start, GreenElement::Token(green)) => Some(SyntaxToken {
+                green: green.clone(),
+                offset: start,
+                parent: self.clone(),
+            }),
+        }
+    }
+
+    fn elements(&self) -> impl Iterator<Item = (usize, &GreenElement)> {
+        self.0
+            .green
+            .children
+            .iter()
+            .scan(self.0.offset, |offset, element| {
+                let start = *offset;
+                *offset += element.width();
+                Some((start, element))
+            })
+    }
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> SyntaxKind {
+        self.green.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.green.text
+    }
+
+    pub fn text_range(&self) -> Range<usize> {
+        self.offset..self.offset + self.green.text.len()
+    }
+
+    pub fn parent(&self) -> SyntaxNode {
+        self.parent.clone()
+    }
+}
+
+/// Byte span of a statement or expression as found in the AST.
+struct Span {
+    kind: SyntaxKind,
+    from: usize,
+    to: usize,
+}
+
+struct SpanCollector {
+    spans: Vec<Span>,
+}
+
+impl SpanCollector {
+    fn push(&mut self, kind: SyntaxKind, from: usize, to: usize) {
+        self.spans.push(Span { kind, from, to });
+    }
+
+    /// Methods are only seen as function or class definitions, while the
+    /// others were already added as a statement or expression.
+    fn push_definition(&mut self, kind: SyntaxKind, from: usize, to: usize) {
+        let added = self
+            .spans
+            .last()
+            .is_some_and(|span| span.kind == kind && span.from == from && span.to == to);
+
+        if !added {
+            self.push(kind, from, to);
+        }
+    }
+}
+
+impl AstVisitor for SpanCollector {
+    fn enter_statement(&mut self, statement: &Statement) -> AstVisitorResult {
+        self.push(
+            SyntaxKind::of_statement(statement),
+            statement.get_from().index,
+            statement.get_to().index,
+        );
+        AstVisitorResult::Continue
+    }
+
+    fn enter_expression(&mut self, expression: &Expression) -> AstVisitorResult {
+        self.push(
+            SyntaxKind::of_expression(expression),
+            expression.get_from().index,
+            expression.get_to().index,
+        );
+        AstVisitorResult::Continue
+    }
+
+    fn enter_switch_statement(&mut self, statement: &SwitchStatement) -> AstVisitorResult {
+        for case in &statement.cases {
+            self.push(SyntaxKind::SwitchCase, case.from.index, case.to.index);
+        }
+        if let Some(default) = &statement.default {
+            self.push(SyntaxKind::SwitchCase, default.from.index, default.to.index);
+        }
+        AstVisitorResult::Continue
+    }
+
+    fn enter_function_declaration(&mut self, statement: &FunctionDefinition) -> AstVisitorResult {
+        self.push_definition(
+            SyntaxKind::FunctionDefinition,
+            statement.from.index,
+            statement.to.index,
+        );
+        AstVisitorResult::Continue
+    }
+
+    fn enter_class_definition(&mut self, statement: &ClassDefinition) -> AstVisitorResult {
+        self.push_definition(
+            SyntaxKind::ClassDefinition,
+            statement.from.index,
+            statement.to.index,
+        );
+        AstVisitorResult::Continue
+    }
+}
+
+/// Splits source text between tokens into whitespace and newline tokens.
+fn push_whitespace(tokens: &mut Vec<GreenToken>, text: &str) {
+    for line in text.split_inclusive('\n') {
+        let (spaces, newline) = match line.strip_suffix('\n') {
+            Some(spaces) => match spaces.strip_suffix('\r') {
+                Some(spaces) => (spaces, "\r\n"),
+                None => (spaces, "\n"),
+            },
+            None => (line, ""),
+        };
+
+        for (kind, text) in [
+            (SyntaxKind::Whitespace, spaces),
+            (SyntaxKind::Newline, newline),
+        ] {
+            if !text.is_empty() {
+                tokens.push(GreenToken {
+                    kind,
+                    text: text.to_string(),
+                });
+            }
+        }
+    }
+}
+
+/// Every token of the source, the text between the lexer's tokens becoming
+/// whitespace and newline tokens so that nothing is lost.
+fn lex(text: &str, dialect: Dialect) -> Vec<GreenToken> {
+    let mut lexer = Lexer::new(text, false).with_dialect(dialect);
+    let mut tokens = Vec::new();
+    let mut position = 0;
+
+    while let Ok(token) = lexer.next() {
+        match token.token {
+            Token::EOF => break,
+            Token::Newline | Token::Space | Token::Tab | Token::Indent(_) => continue,
+            _ => {}
+        }
+
+        let from = token.from.index.max(position);
+        push_whitespace(&mut tokens, &text[position..from]);
+        tokens.push(GreenToken {
+            kind: SyntaxKind::of_token(&token.token),
+            text: text[from..token.to.index].to_string(),
+        });
+        position = token.to.index;
+    }
+
+    push_whitespace(&mut tokens, &text[position..]);
+    tokens
+}
+
+/// Node being built, holding the tokens up to `end` (exclusive).
+struct Frame {
+    kind: SyntaxKind,
+    end: usize,
+    children: Vec<GreenElement>,
+}
+
+/// Index of a token among all tokens, with its byte range.
+type IndexedToken = (usize, Range<usize>);
+
+/// Byte ranges of the tokens the parser sees, in source order, used to trim
+/// AST spans down to the tokens of their nodes.
+struct SignificantTokens {
+    tokens: Vec<IndexedToken>,
+}
+
+impl SignificantTokens {
+    fn new(tokens: &[GreenToken]) -> Self {
+        let mut offset = 0;
+        let mut significant = Vec::new();
+
+        for (index, token) in tokens.iter().enumerate() {
+            let range = offset..offset + token.text.len();
+            offset = range.end;
+
+            if !token.kind.is_trivia() {
+                significant.push((index, range));
+            }
+        }
+
+        Self {
+            tokens: significant,
+        }
+    }
+
+    /// First and last significant tokens within an AST span. AST locations
+    /// may include surrounding whitespace, which nodes leave to their parent.
+    fn within(&self, from: usize, to: usize) -> Option<(&IndexedToken, &IndexedToken)> {
+        let first = self.tokens.partition_point(|(_, range)| range.start < from);
+        let end = self.tokens.partition_point(|(_, range)| range.end <= to);
+
+        (first < end).then(|| (&self.tokens[first], &self.tokens[end - 1]))
+    }
+}
+
+/// Why a document has no [`SyntaxTree`].
+#[derive(Debug)]
+pub enum SyntaxTreeError {
+    /// The document doesn't parse
+    Syntax(Box<ParserErrorWithLocation>),
+    /// A statement or expression of the AST, starting at the given byte
+    /// offset, ends past the end of the node it starts in. Nesting it would
+    /// leave tokens out of one of the nodes.
+    CrossingNode(SyntaxKind, usize),
+}
+
+impl Display for SyntaxTreeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyntaxTreeError::Syntax(error) => write!(f, "{}", error),
+            SyntaxTreeError::CrossingNode(kind, offset) => {
+                write!(f, "{:?} at {} crosses the end of its parent", kind, offset)
+            }
+        }
+    }
+}
+
+/// Groups the tokens into nodes for the spans, given as the kind along with
+/// the index of their first token and the index past their last one.
+fn build(
+    tokens: Vec<GreenToken>,
+    mut spans: Vec<(SyntaxKind, usize, usize)>,
+) -> Result<GreenNode, SyntaxTreeError> {
+    // Outer nodes first when starting at the same token, the sort being
+    // stable keeps the visiting order for nodes with the same tokens
+    spans.sort_by_key(|(_, first, end)| (*first, Reverse(*end)));
+
+    let mut stack = vec![Frame {
+        kind: SyntaxKind::Root,
+        end: tokens.len(),
+        children: Vec::new(),
+    }];
+    let mut spans = spans.into_iter().peekable();
+    let mut offset = 0;
+
+    for (index, token) in tokens.into_iter().enumerate() {
+        while let Some((kind, _, end)) = spans.next_if(|(_, first, _)| *first == index) {
+            if stack.last().is_some_and(|frame| frame.end < end) {
+                return Err(SyntaxTreeError::CrossingNode(kind, offset));
+            }
+
+            stack.push(Frame {
+                kind,
+                end,
+                children: Vec::new(),
+            });
+        }
+
+        offset += token.text.len();
+        if let Some(frame) = stack.last_mut() {
+            frame.children.push(GreenElement::Token(Rc::new(token)));
+        }
+
+        while stack.len() > 1 && stack.last().is_some_and(|frame| frame.end == index + 1) {
+            if let Some(frame) = stack.pop() {
+                let node = GreenNode::new(frame.kind, frame.children);
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(GreenElement::Node(Rc::new(node)));
+                }
+            }
+        }
+    }
+
+    Ok(stack.pop().map_or_else(
+        || GreenNode::new(SyntaxKind::Root, Vec::new()),
+        |frame| GreenNode::new(SyntaxKind::Root, frame.children),
+    ))
+}
+
+/// Lossless syntax tree of a document: every byte of the source, comments and
+/// whitespace included, is held by a token, and tokens are grouped into nodes
+/// for the statements and expressions of the AST. The AST of statement nodes
+/// is derived back from their tokens.
+pub struct SyntaxTree {
+    text: String,
+    root: SyntaxNode,
+    dialect: Dialect,
+}
+
+impl SyntaxTree {
+    /// Builds the tree of a document written in `dialect`.
+    pub fn parse(text: &str, dialect: Dialect) -> Result<Self, SyntaxTreeError> {
+        let ast = Parser::new(text)
+            .with_dialect(dialect)
+            .parse()
+            .map_err(|error| SyntaxTreeError::Syntax(Box::new(error)))?;
+
+        let mut collector = SpanCollector { spans: Vec::new() };
+        visit(&ast, &mut collector);
+
+        let tokens = lex(text, dialect);
+        let significant = SignificantTokens::new(&tokens);
+
+        let spans = collector
+            .spans
+            .iter()
+            .filter_map(|span| {
+                let ((first, _), (last, _)) = significant.within(span.from, span.to)?;
+                Some((span.kind, *first, last + 1))
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Self {
+            text: text.to_string(),
+            root: SyntaxNode::new_root(Rc::new(build(tokens, spans)?)),
+            dialect,
+        })
+    }
+
+    pub fn root(&self) -> SyntaxNode {
+        self.root.clone()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Statements of the outermost statement nodes between two offsets,
+    /// parsed from the tokens of the nodes with locations relative to the
+    /// whole document. `None` when a node doesn't parse into a statement of
+    /// its kind on its own, like a method of a class.
+    pub fn statements(&self, from: usize, to: usize) -> Option<Statements> {
+        let mut nodes = Vec::new();
+        outermost_statements(&self.root, from..to, &mut nodes);
+
+        let statements = nodes
+            .into_iter()
+            .map(|(kind, range)| self.parse_node(kind, range))
+            .collect::<Option<Vec<_>>>()?;
+
+        let from = statements.first()?.get_from();
+        let to = statements.last()?.get_to();
+
+        Some(Statements {
+            statements,
+            from,
+            to,
+        })
+    }
+
+    /// Parses the text of a statement node, starting the parser where the
+    /// node starts so that locations stay the same.
+    fn parse_node(&self, kind: SyntaxKind, range: Range<usize>) -> Option<Statement> {
+        let mut parser = Parser::new(&self.text[..range.end]).with_dialect(self.dialect);
+        parser.lexer.position = range.start;
+        parser.lexer.location = location_at(&self.text, range.start);
+
+        let mut statements = parser.parse().ok()?.statements;
+        match statements.as_slice() {
+            [statement] if SyntaxKind::of_statement(statement) == kind => statements.pop(),
+            _ => None,
+        }
+    }
+
+    /// AST statement a statement node was built from.
+    pub fn statement(&self, node: &SyntaxNode) -> Option<Statement> {
+        let range = node.text_range();
+
+        self.statements(range.start, range.end)
+            .and_then(|statements| statements.statements.into_iter().next())
+    }
+
+    /// Comments between two offsets, placed like the parser would.
+    pub fn comments(&self, from: usize, to: usize) -> Vec<Comment> {
+        self.root
+            .tokens()
+            .into_iter()
+            .filter(|token| token.kind() == SyntaxKind::Comment)
+            .filter(|token| from <= token.text_range().start && token.text_range().end <= to)
+            .filter_map(|token| {
+                let mut lexer = Lexer::new(token.text(), false);
+                lexer.location = location_at(&self.text, token.text_range().start);
+
+                let comment = lexer.next().ok()?;
+                Some(Comment::new(
+                    comment.token,
+                    comment.from,
+                    comment.to,
+                    &self.text,
+                ))
+            })
+            .collect()
+    }
+}
+
+/// Statement nodes within the range that aren't within another one.
+fn outermost_statements(
+    node: &SyntaxNode,
+    range: Range<usize>,
+    found: &mut Vec<(SyntaxKind, Range<usize>)>,
+) {
+    for child in node.children() {
+        let child_range = child.text_range();
+        if child_range.end <= range.start || range.end <= child_range.start {
+            continue;
+        }
+
+        if child.kind().is_statement()
+            && range.start <= child_range.start
+            && child_range.end <= range.end
+        {
+            found.push((child.kind(), child_range));
+        } else {
+            outermost_statements(&child, range.clone(), found);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{squirrel_formatter::FormatterOptions, squirrel_printer::Printer};
+
+    use super::*;
+
+    fn print(statements: &Statements) -> String {
+        Printer::new(64, &FormatterOptions::default()).print(statements)
+    }
+
+    #[test]
+    fn test_tree_keeps_every_byte_of_test_cases() {
+        for path in fs::read_dir("./test_cases").unwrap() {
+            let path = path.unwrap().path();
+            if path.extension().unwrap() != "nut" {
+                continue;
+            }
+
+            let contents = fs::read_to_string(&path).unwrap();
+            let tree = SyntaxTree::parse(&contents, Dialect::default()).unwrap();
+
+            let text = tree
+                .root()
+                .tokens()
+                .iter()
+                .map(SyntaxToken::text)
+                .collect::<String>();
+            assert_eq!(text, contents, "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn test_statements_are_derived_from_nodes() {
+        let text = "local a = 1 // one\nfunction foo(b) {\n  return b * 2\n}\nswitch (a) {\n  case 1:\n    foo(a)\n    break\n}\n";
+        let tree = SyntaxTree::parse(text, Dialect::default()).unwrap();
+        let statements = Parser::new(text).parse().unwrap();
+
+        let nodes = tree.root().children();
+        assert_eq!(nodes.len(), statements.statements.len());
+
+        for (node, statement) in nodes.iter().zip(&statements.statements) {
+            let range = node.text_range();
+            let derived = tree.statements(range.start, range.end).unwrap();
+
+            assert_eq!(derived.statements.len(), 1);
+            assert_eq!(derived.statements[0].get_to(), statement.get_to());
+            assert_eq!(
+                print(&derived),
+                print(&Statements {
+                    statements: vec![statement.clone()],
+                    from: statement.get_from(),
+                    to: statement.get_to(),
+                })
+            );
+        }
+
+        let comments = tree.comments(0, text.len());
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0].text(), "one");
+
+        let function = &nodes[1];
+        assert!(matches!(
+            tree.statement(function),
+            Some(Statement::FunctionDefinition(_))
+        ));
+
+        // Nested statements come from the block holding them
+        let body = text.find("return").unwrap();
+        let nested = tree.statements(body, body + "return b * 2".len()).unwrap();
+        assert!(matches!(nested.statements[..], [Statement::Return(_)]));
+        assert_eq!(nested.from.line, 2);
+    }
+
+    #[test]
+    fn test_nodes_crossing_their_parent_are_an_error() {
+        let tokens = lex("a + b", Dialect::default());
+
+        // `a +` and `+ b` share a token without one holding the other
+        let spans = vec![
+            (SyntaxKind::BinaryOperatorExpression, 0, 3),
+            (SyntaxKind::UnaryOperatorExpression, 2, 5),
+        ];
+
+        assert!(matches!(
+            build(tokens, spans),
+            Err(SyntaxTreeError::CrossingNode(
+                SyntaxKind::UnaryOperatorExpression,
+                2
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_methods_are_no_statements_on_their_own() {
+        let text = "class A {\n  constructor(a) {\n    x = a\n  }\n}\n";
+        let tree = SyntaxTree::parse(text, Dialect::default()).unwrap();
+
+        let constructor = text.find("constructor").unwrap();
+        let end = text.rfind("  }").unwrap() + 3;
+        assert!(tree.statements(constructor, end).is_none());
+
+        let assignment = text.find("x = a").unwrap();
+        let nested = tree.statements(assignment, assignment + 5).unwrap();
+        assert!(matches!(nested.statements[..], [Statement::Expression(_)]));
+    }
+
+    #[test]
+    fn test_large_documents_build_in_linear_time() {
+        let function = "function f(a, b) {\n  local x = a + b * 2 // sum\n  if (x > 2) {\n    foo(x, [1, 2, {y = 3}])\n  }\n  return x\n}\n";
+        let text = function.repeat(1000);
+
+        let started = std::time::Instant::now();
+        let tree = SyntaxTree::parse(&text, Dialect::default()).unwrap();
+        let elapsed = started.elapsed();
+
+        assert_eq!(tree.root().text_range(), 0..text.len());
+        assert_eq!(tree.root().children().len(), 1000);
+        assert_eq!(tree.comments(0, text.len()).len(), 1000);
+
+        // Mapping every span by scanning all tokens took minutes here
+        assert!(elapsed.as_secs() < 20, "took {:?}", elapsed);
+    }
+
+    #[test]
+    fn test_token_at_offset_finds_enclosing_nodes() {
+        let text = "if (a) {\n  foo(/* b */ c)\n}\n";
+        let tree = SyntaxTree::parse(text, Dialect::default()).unwrap();
+
+        let comment = tree
+            .root()
+            .token_at_offset(text.find("b */").unwrap())
+            .unwrap();
+        assert_eq!(comment.kind(), SyntaxKind::Comment);
+        assert_eq!(comment.text(), "/* b */");
+
+        let kinds = comment
+            .parent()
+            .ancestors()
+            .map(|node| node.kind())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                SyntaxKind::FunctionCallExpression,
+                SyntaxKind::ExpressionStatement,
+                SyntaxKind::BlockStatement,
+                SyntaxKind::IfStatement,
+                SyntaxKind::Root,
+            ]
+        );
+    }
+}
@@ -0,0 +1,704 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    ast_visitor::{visit, AstVisitor, AstVisitorResult},
+    diagnostics::{report, Diagnostic, Severity},
+    doc_comments::DocComments,
+    grammar::expressions::*,
+    grammar::statements::*,
+    scope_table::{DeclarationType, ScopeTable, VariableDeclarationKind, VariableUsage},
+    squirrel_lexer::{Location, Operator},
+};
+
+/// Inference follows declarations through their initializers, this bounds how deep
+/// it goes so self-referencing initializers like `local a = a;` terminate.
+const MAX_INFERENCE_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SquirrelType {
+    Null,
+    Integer,
+    Float,
+    Bool,
+    String,
+    Table,
+    Array,
+    Function,
+    Class,
+    Instance,
+    Unknown,
+}
+
+impl SquirrelType {
+    /// Maps a type name used in doc comment annotations. Names the checker can't
+    /// reason about (user classes, `any`, ...) return `None`.
+    pub fn from_annotation(name: &str) -> Option<Vec<SquirrelType>> {
+        let types = match name {
+            "null" => vec![SquirrelType::Null],
+            "integer" | "int" => vec![SquirrelType::Integer],
+            "float" => vec![SquirrelType::Float],
+            "number" => vec![SquirrelType::Integer, SquirrelType::Float],
+            "bool" | "boolean" => vec![SquirrelType::Bool],
+            "string" => vec![SquirrelType::String],
+            "table" => vec![SquirrelType::Table],
+            "array" => vec![SquirrelType::Array],
+            "function" => vec![SquirrelType::Function],
+            "class" => vec![SquirrelType::Class],
+            "instance" => vec![SquirrelType::Instance],
+            _ => return None,
+        };
+
+        Some(types)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SquirrelType::Null => "null",
+            SquirrelType::Integer => "integer",
+            SquirrelType::Float => "float",
+            SquirrelType::Bool => "bool",
+            SquirrelType::String => "string",
+            SquirrelType::Table => "table",
+            SquirrelType::Array => "array",
+            SquirrelType::Function => "function",
+            SquirrelType::Class => "class",
+            SquirrelType::Instance => "instance",
+            SquirrelType::Unknown => "unknown",
+        }
+    }
+
+    fn supports_arithmetic(&self) -> bool {
+        matches!(
+            self,
+            SquirrelType::Integer
+                | SquirrelType::Float
+                | SquirrelType::Instance
+                | SquirrelType::Unknown
+        )
+    }
+
+    fn is_numeric(&self) -> bool {
+        matches!(self, SquirrelType::Integer | SquirrelType::Float)
+    }
+}
+
+pub struct TypeChecker<'a> {
+    scope_table: &'a ScopeTable,
+    doc_comments: DocComments,
+    severity: Option<Severity>,
+    facts: DeclarationFacts<'a>,
+    parameter_types: HashMap<usize, Vec<SquirrelType>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Resolves identifiers to the variables they refer to and knows which of
+/// them get assigned after their declaration, whose declared value can't be
+/// relied on.
+pub(crate) struct DeclarationFacts<'a> {
+    usages: HashMap<usize, &'a VariableUsage>,
+    reassigned: HashSet<(usize, usize)>,
+}
+
+impl<'a> DeclarationFacts<'a> {
+    pub(crate) fn new(scope_table: &'a ScopeTable) -> Self {
+        Self {
+            usages: scope_table.usages_by_index(),
+            reassigned: HashSet::new(),
+        }
+    }
+
+    /// Collects the reassignments in `statements`
+    pub(crate) fn collect(&mut self, statements: &Statements) {
+        visit(statements, self);
+    }
+
+    /// Scope and sequence number of the variable the identifier refers to
+    pub(crate) fn declaration_of(
+        &self,
+        identifier: &IdentifierExpression,
+    ) -> Option<(usize, usize)> {
+        match self.usages.get(&identifier.from.index)?.declaration {
+            Some(DeclarationType::Variable(scope, sequence_number)) => {
+                Some((scope, sequence_number))
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn is_reassigned(&self, declaration: (usize, usize)) -> bool {
+        self.reassigned.contains(&declaration)
+    }
+}
+
+impl AstVisitor for DeclarationFacts<'_> {
+    fn enter_binary_operator_expression(
+        &mut self,
+        expression: &BinaryOperatorExpression,
+    ) -> AstVisitorResult {
+        if !expression.operator.is_assignment() {
+            return AstVisitorResult::Continue;
+        }
+
+        if let Expression::Identifier(identifier) = &expression.left {
+            if let Some(declaration) = self.declaration_of(identifier) {
+                self.reassigned.insert(declaration);
+            }
+        }
+
+        AstVisitorResult::Continue
+    }
+}
+
+/// Collects the types of parameters given by `@param` annotations.
+struct ParameterAnnotations<'a, 'b> {
+    checker: &'b mut TypeChecker<'a>,
+}
+
+impl AstVisitor for ParameterAnnotations<'_, '_> {
+    fn enter_function_declaration(&mut self, function: &FunctionDefinition) -> AstVisitorResult {
+        for annotation in self
+            .checker
+            .doc_comments
+            .param_annotations(function.from.line)
+        {
+            let types = annotation
+                .types
+                .iter()
+                .map(|t| SquirrelType::from_annotation(t))
+                .collect::<Option<Vec<_>>>();
+
+            let (Some(types), Some(parameter)) =
+                (types, find_parameter(function, &annotation.name))
+            else {
+                continue;
+            };
+
+            self.checker
+                .parameter_types
+                .insert(parameter.from.index, types.concat());
+        }
+
+        AstVisitorResult::Continue
+    }
+}
+
+impl<'a> TypeChecker<'a> {
+    pub fn new(input: &str, scope_table: &'a ScopeTable, severity: Option<Severity>) -> Self {
+        Self {
+            scope_table,
+            doc_comments: DocComments::new(input),
+            severity,
+            facts: DeclarationFacts::new(scope_table),
+            parameter_types: HashMap::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    pub fn check(mut self, statements: &Statements) -> Vec<Diagnostic> {
+        if self.severity.is_none() {
+            return Vec::new();
+        }
+
+        self.facts.collect(statements);
+        visit(statements, &mut ParameterAnnotations { checker: &mut self });
+        visit(statements, &mut self);
+
+        self.diagnostics
+    }
+
+    pub fn infer(&self, expression: &Expression) -> SquirrelType {
+        self.infer_with_depth(expression, 0)
+    }
+
+    fn infer_with_depth(&self, expression: &Expression, depth: usize) -> SquirrelType {
+        if depth > MAX_INFERENCE_DEPTH {
+            return SquirrelType::Unknown;
+        }
+
+        match expression {
+            Expression::NullLiteral(_) => SquirrelType::Null,
+            Expression::BooleanLiteral(_) => SquirrelType::Bool,
+            Expression::IntegerLiteral(_)
+            | Expression::CharacterLiteral(_)
+            | Expression::LineLiteral(_) => SquirrelType::Integer,
+            Expression::FloatLiteral(_) => SquirrelType::Float,
+            Expression::StringLiteral(_)
+            | Expression::MutliLineStringLiteral(_)
+            | Expression::FileLiteral(_) => SquirrelType::String,
+            Expression::Table(_) => SquirrelType::Table,
+            Expression::Array(_) => SquirrelType::Array,
+            Expression::Function(_) => SquirrelType::Function,
+            Expression::Class(_) => SquirrelType::Class,
+            Expression::Grouping(grouping) => match &grouping.expression {
+                Some(expression) => self.infer_with_depth(expression, depth + 1),
+                None => SquirrelType::Unknown,
+            },
+            Expression::Identifier(identifier) => self.infer_identifier(identifier, depth),
+            Expression::FunctionCall(call) => {
+                match self.infer_with_depth(&call.function, depth + 1) {
+                    SquirrelType::Class => SquirrelType::Instance,
+                    _ => SquirrelType::Unknown,
+                }
+            }
+            Expression::Clone(clone) => match self.infer_with_depth(&clone.expression, depth + 1) {
+                t @ (SquirrelType::Table | SquirrelType::Array | SquirrelType::Instance) => t,
+                _ => SquirrelType::Unknown,
+            },
+            Expression::UnaryOperator(unary) => {
+                let operand = self.infer_with_depth(&unary.expression, depth + 1);
+
+                match unary.operator {
+                    Operator::Not => SquirrelType::Bool,
+                    Operator::Typeof => SquirrelType::String,
+                    Operator::Minus if operand.is_numeric() => operand,
+                    Operator::BitwiseNot if operand == SquirrelType::Integer => operand,
+                    _ => SquirrelType::Unknown,
+                }
+            }
+            Expression::BinaryOperator(binary) => self.infer_binary(binary, depth),
+            Expression::TernaryOperator(ternary) => {
+                let if_true = self.infer_with_depth(&ternary.if_true, depth + 1);
+                let if_false = self.infer_with_depth(&ternary.if_false, depth + 1);
+
+                if if_true == if_false {
+                    if_true
+                } else {
+                    SquirrelType::Unknown
+                }
+            }
+            _ => SquirrelType::Unknown,
+        }
+    }
+
+    fn infer_binary(&self, binary: &BinaryOperatorExpression, depth: usize) -> SquirrelType {
+        let left = || self.infer_with_depth(&binary.left, depth + 1);
+        let right = || self.infer_with_depth(&binary.right, depth + 1);
+
+        match binary.operator {
+            Operator::Equal
+            | Operator::NotEqual
+            | Operator::Greater
+            | Operator::GreaterEqual
+            | Operator::Less
+            | Operator::LessEqual
+            | Operator::In
+            | Operator::InstanceOf => SquirrelType::Bool,
+            Operator::ThreeWayComparison => SquirrelType::Integer,
+            Operator::Assign | Operator::LeftArrow | Operator::Comma => right(),
+            Operator::NullCoalesce => match left() {
+                SquirrelType::Null => right(),
+                SquirrelType::Unknown => SquirrelType::Unknown,
+                left => left,
+            },
+            Operator::Plus => {
+                let (left, right) = (left(), right());
+
+                if left == SquirrelType::String || right == SquirrelType::String {
+                    SquirrelType::String
+                } else {
+                    numeric_result(left, right)
+                }
+            }
+            Operator::Minus | Operator::Multiply | Operator::Divide | Operator::Modulo => {
+                numeric_result(left(), right())
+            }
+            Operator::BitwiseAnd
+            | Operator::BitwiseOr
+            | Operator::BitwiseXor
+            | Operator::LeftShift
+            | Operator::RightShift
+            | Operator::UnsignedRightShift => {
+                if left() == SquirrelType::Integer && right() == SquirrelType::Integer {
+                    SquirrelType::Integer
+                } else {
+                    SquirrelType::Unknown
+                }
+            }
+            _ => SquirrelType::Unknown,
+        }
+    }
+
+    fn infer_identifier(&self, identifier: &IdentifierExpression, depth: usize) -> SquirrelType {
+        let Some(declaration) = self.facts.declaration_of(identifier) else {
+            return SquirrelType::Unknown;
+        };
+
+        if self.facts.is_reassigned(declaration) {
+            return SquirrelType::Unknown;
+        }
+
+        let declaration = &self.scope_table.scopes()[declaration.0].variables[declaration.1];
+
+        match declaration.kind {
+            VariableDeclarationKind::Function => SquirrelType::Function,
+            VariableDeclarationKind::Parameter => {
+                match self.parameter_types.get(&declaration.from.index) {
+                    Some(types) if types.len() == 1 => types[0],
+                    _ => SquirrelType::Unknown,
+                }
+            }
+            VariableDeclarationKind::Local | VariableDeclarationKind::Const => {
+                match &declaration.value {
+                    Some(value) => self.infer_with_depth(value, depth + 1),
+                    None => SquirrelType::Unknown,
+                }
+            }
+            VariableDeclarationKind::CatchVariable
+            | VariableDeclarationKind::ClassMember
+            | VariableDeclarationKind::Enum
+            | VariableDeclarationKind::EnumMember
+            | VariableDeclarationKind::Import => SquirrelType::Unknown,
+        }
+    }
+
+    /// Resolves the definition of a called function when the callee is a plain
+    /// identifier that is never reassigned.
+    fn called_function(&self, callee: &Expression) -> Option<&'a FunctionDefinition> {
+        let Expression::Identifier(identifier) = callee else {
+            return None;
+        };
+
+        let declaration = self.facts.declaration_of(identifier)?;
+
+        if self.facts.is_reassigned(declaration) {
+            return None;
+        }
+
+        let scope_table: &'a ScopeTable = self.scope_table;
+
+        match &scope_table.scopes()[declaration.0].variables[declaration.1].value {
+            Some(Expression::Function(function)) => Some(function.as_ref()),
+            _ => None,
+        }
+    }
+
+    fn report(&mut self, from: &Location, to: &Location, message: String) {
+        report(
+            &mut self.diagnostics,
+            self.severity,
+            "type-mismatch",
+            from,
+            to,
+            message,
+        );
+    }
+}
+
+impl AstVisitor for TypeChecker<'_> {
+    fn enter_binary_operator_expression(
+        &mut self,
+        expression: &BinaryOperatorExpression,
+    ) -> AstVisitorResult {
+        let operator: &str = (&expression.operator).into();
+
+        match expression.operator {
+            Operator::Plus
+            | Operator::PlusEqual
+            | Operator::Minus
+            | Operator::MinusEqual
+            | Operator::Multiply
+            | Operator::MultiplyEqual
+            | Operator::Divide
+            | Operator::DivideEqual
+            | Operator::Modulo
+            | Operator::ModuloEqual => {
+                let left = self.infer(&expression.left);
+                let right = self.infer(&expression.right);
+
+                let is_concatenation =
+                    matches!(expression.operator, Operator::Plus | Operator::PlusEqual)
+                        && (left == SquirrelType::String || right == SquirrelType::String);
+
+                if !is_concatenation
+                    && (!left.supports_arithmetic() || !right.supports_arithmetic())
+                {
+                    self.report(
+                        &expression.from,
+                        &expression.to,
+                        format!(
+                            "Arithmetic operator '{}' cannot be applied to '{}' and '{}'",
+                            operator,
+                            left.name(),
+                            right.name()
+                        ),
+                    );
+                }
+            }
+            Operator::In => {
+                let right = self.infer(&expression.right);
+
+                if matches!(
+                    right,
+                    SquirrelType::Null
+                        | SquirrelType::Integer
+                        | SquirrelType::Float
+                        | SquirrelType::Bool
+                        | SquirrelType::String
+                        | SquirrelType::Function
+                ) {
+                    self.report(
+                        &expression.right.get_from(),
+                        &expression.right.get_to(),
+                        format!(
+                            "Operator 'in' expects a table, array, class or instance, found '{}'",
+                            right.name()
+                        ),
+                    );
+                }
+            }
+            Operator::InstanceOf => {
+                let right = self.infer(&expression.right);
+
+                if right != SquirrelType::Class && right != SquirrelType::Unknown {
+                    self.report(
+                        &expression.right.get_from(),
+                        &expression.right.get_to(),
+                        format!(
+                            "Operator 'instanceof' expects a class, found '{}'",
+                            right.name()
+                        ),
+                    );
+                }
+            }
+            _ => {}
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_function_call_expression(
+        &mut self,
+        expression: &FunctionCallExpression,
+    ) -> AstVisitorResult {
+        let callee = self.infer(&expression.function);
+
+        if matches!(
+            callee,
+            SquirrelType::Null
+                | SquirrelType::Integer
+                | SquirrelType::Float
+                | SquirrelType::Bool
+                | SquirrelType::String
+                | SquirrelType::Array
+        ) {
+            self.report(
+                &expression.function.get_from(),
+                &expression.function.get_to(),
+                format!("Value of type '{}' is not callable", callee.name()),
+            );
+
+            return AstVisitorResult::Continue;
+        }
+
+        let Some(function) = self.called_function(&expression.function) else {
+            return AstVisitorResult::Continue;
+        };
+
+        for (parameter, argument) in function.parameters.iter().zip(&expression.arguments) {
+            let Some(parameter) = parameter_identifier(parameter) else {
+                break;
+            };
+
+            let Some(expected) = self.parameter_types.get(&parameter.from.index).cloned() else {
+                continue;
+            };
+
+            let actual = self.infer(argument);
+
+            let matches = actual == SquirrelType::Unknown
+                || expected.contains(&actual)
+                || (actual == SquirrelType::Integer && expected.contains(&SquirrelType::Float));
+
+            if !matches {
+                let expected = expected
+                    .iter()
+                    .map(|t| t.name())
+                    .collect::<Vec<_>>()
+                    .join("|");
+
+                self.report(
+                    &argument.get_from(),
+                    &argument.get_to(),
+                    format!(
+                        "Argument of type '{}' does not match type '{}' of parameter '{}'",
+                        actual.name(),
+                        expected,
+                        parameter.token
+                    ),
+                );
+            }
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_array_access_expression(
+        &mut self,
+        expression: &ArrayAccessExpression,
+    ) -> AstVisitorResult {
+        let array = self.infer(&expression.array);
+        let index = self.infer(&expression.index);
+
+        let indexable = match array {
+            SquirrelType::Null => false,
+            // Numbers and bools only expose their default delegate, e.g. `1["tofloat"]`
+            SquirrelType::Integer | SquirrelType::Float | SquirrelType::Bool => {
+                index == SquirrelType::String
+            }
+            _ => true,
+        };
+
+        if !indexable {
+            self.report(
+                &expression.from,
+                &expression.to,
+                format!("Value of type '{}' cannot be indexed", array.name()),
+            );
+        }
+
+        AstVisitorResult::Continue
+    }
+}
+
+fn numeric_result(left: SquirrelType, right: SquirrelType) -> SquirrelType {
+    match (left, right) {
+        (SquirrelType::Integer, SquirrelType::Integer) => SquirrelType::Integer,
+        (l, r) if l.is_numeric() && r.is_numeric() => SquirrelType::Float,
+        _ => SquirrelType::Unknown,
+    }
+}
+
+fn parameter_identifier(parameter: &Expression) -> Option<&IdentifierExpression> {
+    match parameter {
+        Expression::Identifier(identifier) => Some(identifier),
+        Expression::BinaryOperator(op) if op.operator == Operator::Assign => match &op.left {
+            Expression::Identifier(identifier) => Some(identifier),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn find_parameter<'f>(
+    function: &'f FunctionDefinition,
+    name: &str,
+) -> Option<&'f IdentifierExpression> {
+    function
+        .parameters
+        .iter()
+        .filter_map(parameter_identifier)
+        .find(|parameter| parameter.token.to_string() == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::{Diagnostic, Severity};
+
+    use crate::{scope_table::ScopeTable, squirrel_parser::Parser};
+
+    use super::TypeChecker;
+
+    fn check(input: &str) -> Vec<Diagnostic> {
+        let mut parser = Parser::new(input);
+        let statements = parser.parse().unwrap();
+        let scope_table = ScopeTable::new(&statements);
+
+        TypeChecker::new(input, &scope_table, Some(Severity::Warning)).check(&statements)
+    }
+
+    #[test]
+    fn test_arithmetic_on_string_and_table() {
+        let input = "
+local a = \"text\";
+local b = {};
+local c = a - b;
+local d = a + b;
+";
+        let diagnostics = check(input);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "Arithmetic operator '-' cannot be applied to 'string' and 'table'"
+        );
+    }
+
+    #[test]
+    fn test_reassigned_variables_are_not_inferred() {
+        let input = "
+local a = \"text\";
+a = 10;
+local b = a - 1;
+";
+        assert_eq!(check(input).len(), 0);
+    }
+
+    #[test]
+    fn test_in_and_instanceof_operands() {
+        let input = "
+class Foo {}
+local foo = Foo();
+local a = \"x\" in 10;
+local b = \"x\" in {};
+local c = foo instanceof Foo;
+local d = foo instanceof foo;
+";
+        let diagnostics = check(input);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            diagnostics[0].message,
+            "Operator 'in' expects a table, array, class or instance, found 'integer'"
+        );
+        assert_eq!(
+            diagnostics[1].message,
+            "Operator 'instanceof' expects a class, found 'instance'"
+        );
+    }
+
+    #[test]
+    fn test_calling_and_indexing_non_containers() {
+        let input = "
+local a = 10;
+a();
+a[0];
+a[\"tofloat\"]();
+";
+        let diagnostics = check(input);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            diagnostics[0].message,
+            "Value of type 'integer' is not callable"
+        );
+        assert_eq!(
+            diagnostics[1].message,
+            "Value of type 'integer' cannot be indexed"
+        );
+    }
+
+    #[test]
+    fn test_param_annotations() {
+        let input = "
+// @param {string} name
+// @param {number} count
+function foo(name, count) {
+    return name - count;
+}
+
+foo(\"a\", 1.5);
+foo(1, 1);
+";
+        let diagnostics = check(input);
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            diagnostics[0].message,
+            "Arithmetic operator '-' cannot be applied to 'string' and 'unknown'"
+        );
+        assert_eq!(
+            diagnostics[1].message,
+            "Argument of type 'integer' does not match type 'string' of parameter 'name'"
+        );
+    }
+}
@@ -0,0 +1,458 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    ast_visitor::{visit, AstVisitor, AstVisitorResult},
+    code_actions::FixData,
+    config::DiagnosticsConfig,
+    diagnostics::{report, Diagnostic, Severity},
+    grammar::expressions::*,
+    grammar::statements::*,
+    scope_table::{DeclarationType, ScopeTable, VariableDeclaration, VariableDeclarationKind},
+    squirrel_lexer::{Keyword, Location, Token},
+};
+
+/// Reports declarations that are never used and can't be reached from other
+/// files: parameters, local functions and classes, private class members and
+/// `catch` variables. Unused locals and consts are reported by
+/// [`crate::diagnostics::validate_variables`].
+pub struct UnusedChecker<'a> {
+    scope_table: &'a ScopeTable,
+    config: &'a DiagnosticsConfig,
+    definitions: LocalDefinitions,
+    /// Number of usages of each variable by scope and sequence number, leaving
+    /// out usages within the declaration itself
+    usage_counts: HashMap<(usize, usize), usize>,
+    classes: Vec<PrivateMembers>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Finds functions and classes that are only visible within the function
+/// declaring them, and `local` functions. Other declarations at the top level
+/// end up in the root table and may be used by other scripts.
+#[derive(Default)]
+pub(crate) struct LocalDefinitions {
+    function_depth: usize,
+    members: HashSet<usize>,
+    functions: HashSet<usize>,
+    classes: HashSet<usize>,
+}
+
+impl LocalDefinitions {
+    pub(crate) fn new(statements: &Statements) -> Self {
+        let mut definitions = Self::default();
+        visit(statements, &mut definitions);
+        definitions
+    }
+
+    pub(crate) fn is_local_function(&self, function: &FunctionDefinition) -> bool {
+        self.functions.contains(&function.from.index)
+    }
+}
+
+impl AstVisitor for LocalDefinitions {
+    fn enter_table_expression(&mut self, table: &TableExpression) -> AstVisitorResult {
+        for entry in &table.entries {
+            if let TableEntry::Function(function) = entry {
+                self.members.insert(function.function.from.index);
+            }
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_class_definition(&mut self, class: &ClassDefinition) -> AstVisitorResult {
+        for member in &class.members {
+            match member {
+                ClassMemberDefinition::Method(method) => {
+                    self.members.insert(method.from.index);
+                }
+                ClassMemberDefinition::Constructor(constructor) => {
+                    self.members.insert(constructor.from.index);
+                }
+                ClassMemberDefinition::Field(_) => {}
+            }
+        }
+
+        if class.name.is_none() || self.function_depth > 0 {
+            self.classes.insert(class.from.index);
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_function_declaration(&mut self, function: &FunctionDefinition) -> AstVisitorResult {
+        let nested = self.function_depth > 0 && !self.members.contains(&function.from.index);
+
+        if function.name.is_some() && (function.is_local || nested) {
+            self.functions.insert(function.from.index);
+        }
+
+        self.function_depth += 1;
+        AstVisitorResult::Continue
+    }
+
+    fn leave_function_declaration(&mut self, _function: &FunctionDefinition) -> AstVisitorResult {
+        self.function_depth -= 1;
+        AstVisitorResult::Continue
+    }
+}
+
+/// Members of one class whose names start with `_`, along with whether they
+/// were accessed anywhere within the class.
+struct PrivateMembers {
+    members: Vec<(String, Location, Location, bool)>,
+    declarations: HashSet<usize>,
+}
+
+impl PrivateMembers {
+    fn new(class: &ClassDefinition) -> Self {
+        let mut members = Vec::new();
+        let mut declarations = HashSet::new();
+
+        for member in &class.members {
+            let name = match member {
+                ClassMemberDefinition::Field(field) => &field.name,
+                ClassMemberDefinition::Method(method) => match &method.name {
+                    Some(name) => name,
+                    None => continue,
+                },
+                ClassMemberDefinition::Constructor(_) => continue,
+            };
+
+            let Expression::Identifier(identifier) = name else {
+                continue;
+            };
+
+            declarations.insert(identifier.from.index);
+
+            if let Token::Identifier(name) = &identifier.token {
+                if name.starts_with('_') {
+                    members.push((
+                        name.clone(),
+                        identifier.from.clone(),
+                        identifier.to.clone(),
+                        false,
+                    ));
+                }
+            }
+        }
+
+        Self {
+            members,
+            declarations,
+        }
+    }
+
+    fn mark_used(&mut self, name: &str) {
+        for member in &mut self.members {
+            if member.0 == name {
+                member.3 = true;
+            }
+        }
+    }
+}
+
+/// Counts the usages resolving to each variable. Usages within the declaration
+/// itself, like its own name or a recursive call, don't count.
+fn usage_counts(scope_table: &ScopeTable) -> HashMap<(usize, usize), usize> {
+    let mut counts = HashMap::new();
+
+    for usage in scope_table
+        .scopes()
+        .iter()
+        .flat_map(|scope| &scope.variable_usage)
+    {
+        let Some(DeclarationType::Variable(scope, sequence_number)) = usage.declaration else {
+            continue;
+        };
+
+        let variable = &scope_table.scopes()[scope].variables[sequence_number];
+        let within_declaration = variable.from.index <= usage.from.index
+            && usage.from.index < variable.to.index.max(variable.from.index + 1);
+
+        if !within_declaration {
+            *counts.entry((scope, sequence_number)).or_insert(0) += 1;
+        }
+    }
+
+    counts
+}
+
+impl<'a> UnusedChecker<'a> {
+    pub fn new(scope_table: &'a ScopeTable, config: &'a DiagnosticsConfig) -> Self {
+        Self {
+            scope_table,
+            config,
+            definitions: LocalDefinitions::default(),
+            usage_counts: usage_counts(scope_table),
+            classes: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    pub fn check(mut self, statements: &Statements) -> Vec<Diagnostic> {
+        self.definitions = LocalDefinitions::new(statements);
+
+        self.check_declarations();
+        visit(statements, &mut self);
+
+        self.diagnostics
+    }
+
+    fn check_declarations(&mut self) {
+        for scope in self.scope_table.scopes() {
+            for variable in &scope.variables {
+                if variable.name.starts_with('_') || self.is_used(variable) {
+                    continue;
+                }
+
+                match (&variable.kind, &variable.value) {
+                    (VariableDeclarationKind::Parameter, _) => self.report(
+                        self.config.unused_parameter.to_severity(),
+                        "unused-parameter",
+                        &variable.from,
+                        &variable.to,
+                        format!("Unused parameter '{}'", variable.name),
+                        Some(FixData::Unused {
+                            name: variable.name.clone(),
+                            removal: None,
+                        }),
+                    ),
+                    (VariableDeclarationKind::CatchVariable, _) => self.report(
+                        self.config.unused_catch_variable.to_severity(),
+                        "unused-catch-variable",
+                        &variable.from,
+                        &variable.to,
+                        format!("Unused catch variable '{}'", variable.name),
+                        Some(FixData::Unused {
+                            name: variable.name.clone(),
+                            removal: None,
+                        }),
+                    ),
+                    (
+                        VariableDeclarationKind::Function | VariableDeclarationKind::Local,
+                        Some(Expression::Function(function)),
+                    ) if self.definitions.is_local_function(function) => {
+                        let Some(name) = &function.name else {
+                            continue;
+                        };
+
+                        self.report(
+                            self.config.unused_function.to_severity(),
+                            "unused-function",
+                            &name.get_from(),
+                            &name.get_to(),
+                            format!("Unused function '{}'", variable.name),
+                            None,
+                        );
+                    }
+                    (VariableDeclarationKind::Local, Some(Expression::Class(class)))
+                        if self.definitions.classes.contains(&class.from.index) =>
+                    {
+                        let (from, to) = match &class.name {
+                            Some(name) => (name.get_from(), name.get_to()),
+                            None => (variable.from.clone(), variable.to.clone()),
+                        };
+
+                        self.report(
+                            self.config.unused_class.to_severity(),
+                            "unused-class",
+                            &from,
+                            &to,
+                            format!("Unused class '{}'", variable.name),
+                            None,
+                        );
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn is_used(&self, variable: &VariableDeclaration) -> bool {
+        self.usage_counts
+            .contains_key(&(variable.scope, variable.sequence_number))
+    }
+
+    fn report(
+        &mut self,
+        severity: Option<Severity>,
+        code: &str,
+        from: &Location,
+        to: &Location,
+        message: String,
+        fix: Option<FixData>,
+    ) {
+        if let Some(diagnostic) = report(&mut self.diagnostics, severity, code, from, to, message) {
+            diagnostic.unnecessary = true;
+            diagnostic.fix = fix;
+        }
+    }
+}
+
+impl AstVisitor for UnusedChecker<'_> {
+    fn enter_class_definition(&mut self, class: &ClassDefinition) -> AstVisitorResult {
+        self.classes.push(PrivateMembers::new(class));
+        AstVisitorResult::Continue
+    }
+
+    fn leave_class_definition(&mut self, _class: &ClassDefinition) -> AstVisitorResult {
+        let Some(class) = self.classes.pop() else {
+            return AstVisitorResult::Continue;
+        };
+
+        for (name, from, to, used) in class.members {
+            if !used {
+                self.report(
+                    self.config.unused_private_member.to_severity(),
+                    "unused-private-member",
+                    &from,
+                    &to,
+                    format!("Unused private member '{}'", name),
+                    None,
+                );
+            }
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_member_access_expression(
+        &mut self,
+        expression: &MemberAccessExpression,
+    ) -> AstVisitorResult {
+        if let Expression::Identifier(identifier) = &expression.expression {
+            if identifier.token == Token::Keyword(Keyword::This) {
+                if let Some(class) = self.classes.last_mut() {
+                    class.mark_used(&expression.member);
+                }
+            }
+        }
+
+        AstVisitorResult::Continue
+    }
+
+    fn enter_identifier_expression(
+        &mut self,
+        identifier: &IdentifierExpression,
+    ) -> AstVisitorResult {
+        // Members can also be accessed without `this.` from within methods
+        if let Token::Identifier(name) = &identifier.token {
+            if let Some(class) = self.classes.last_mut() {
+                if !class.declarations.contains(&identifier.from.index) {
+                    class.mark_used(name);
+                }
+            }
+        }
+
+        AstVisitorResult::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        config::DiagnosticsConfig, diagnostics::Diagnostic, scope_table::ScopeTable,
+        squirrel_parser::Parser,
+    };
+
+    use super::UnusedChecker;
+
+    fn check(input: &str) -> Vec<Diagnostic> {
+        let mut parser = Parser::new(input);
+        let statements = parser.parse().unwrap();
+        let scope_table = ScopeTable::new(&statements);
+        let config = DiagnosticsConfig::default();
+
+        UnusedChecker::new(&scope_table, &config).check(&statements)
+    }
+
+    fn messages(input: &str) -> Vec<String> {
+        check(input)
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect()
+    }
+
+    #[test]
+    fn test_unused_parameters_and_catch_variables() {
+        let input = "
+function foo(a, b, _c) {
+    try {
+        return a;
+    } catch (e) {
+        return null;
+    }
+}
+
+function bar(a) {
+    return bar(a - 1);
+}
+";
+        assert_eq!(
+            messages(input),
+            vec![
+                "Unused parameter 'b'".to_string(),
+                "Unused catch variable 'e'".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unused_local_functions_and_classes() {
+        let input = "
+function exported() {}
+class Exported {}
+local function helper() {}
+local function used_helper() {}
+used_helper();
+
+function outer() {
+    function used() {}
+    function unused() {
+        unused();
+    }
+    class Unused {}
+    local Helper = class {};
+    local table = {
+        function member() {}
+    };
+
+    return used() + table;
+}
+";
+        assert_eq!(
+            messages(input),
+            vec![
+                "Unused function 'helper'".to_string(),
+                "Unused function 'unused'".to_string(),
+                "Unused class 'Unused'".to_string(),
+                "Unused class 'Helper'".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unused_private_members() {
+        let input = "
+class Foo {
+    _used = 1;
+    _implicit = 2;
+    _unused = 3;
+    public = 4;
+
+    function _helper() {
+        return this._used + _implicit;
+    }
+
+    function get() {
+        return this._helper();
+    }
+}
+";
+        assert_eq!(
+            messages(input),
+            vec!["Unused private member '_unused'".to_string()]
+        );
+    }
+}
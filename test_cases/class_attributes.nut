@@ -0,0 +1,19 @@
+class Entity </ description = "Base entity", version = 2 /> {
+    </ type = "string", editable = true />
+    name = null;
+
+    </ deprecated = true />
+    static function create(name) {
+        return Entity(name);
+    }
+
+    </ hidden = true /> constructor(name) {
+        this.name = name;
+    }
+
+    id = 0;
+}
+
+class Player extends Entity </ tags = ["controllable", "visible"] /> {
+    </ min = 0, max = 100 /> health = 100;
+}
@@ -0,0 +1,13 @@
+// End of line comments after headers without braces
+if (a) // after if
+  foo();
+while (b) // after while
+  foo();
+foreach (x in y) // after foreach
+  foo();
+
+// Inline comments after keywords and names
+local /* b */ x /* c */ = 1;
+if /* b */ (true) {
+  foo();
+}
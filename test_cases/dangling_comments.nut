@@ -0,0 +1,10 @@
+// Comments within empty lists and bodies
+foo(/* none */);
+{ /* empty */ }
+function bar() { /* empty */ }
+class C { /* only */ }
+class D {
+  // only
+}
+local t = { /* empty */ };
+local a = [ /* empty */ ];
@@ -0,0 +1,11 @@
+local function helper(a, b = 2) {
+  return a + b
+}
+
+function Outer::Inner::method(x) {
+  return ::print(x)
+}
+
+local double = @(x) x * 2
+local sum = @(a, b) a + b
+local nested = @() @(y) y
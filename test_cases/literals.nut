@@ -0,0 +1,6 @@
+# Hash comments are single line comments too
+local integers = [0, 123, 0x1F, 0XFF, 0777, 'a', '\n', '\'', '\\', '\x41', '\u0041']
+local floats = [0.5, 1.5, 1e3, 1.5e-3, 2.E+2]
+local strings = ["tab\there", "quote \" inside", "backslash \\"]
+local verbatim = @"C:\path\to ""quoted""
+second line"
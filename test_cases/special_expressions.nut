@@ -0,0 +1,10 @@
+local line = __LINE__
+local file = __FILE__
+local result = rawcall(table.method, table, 1, 2)
+
+class Counter {
+  static instances = 0
+  static function create() {
+    return Counter()
+  }
+}
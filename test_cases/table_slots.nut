@@ -0,0 +1,9 @@
+local table = {
+  name = "value",
+  ["computed" + 1] = 2,
+  "json key": 3,
+  "other": @(x) x,
+  function method() {
+    return this.name
+  }
+}